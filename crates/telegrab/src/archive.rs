@@ -0,0 +1,235 @@
+use crate::metrics::metrics;
+use crate::model::entity::doc::ComicInfo;
+use crate::storage::{Storage, StorageObject, join_key};
+use crate::worker::format_page_filename;
+use crate::{Error, Result};
+use quick_xml::events::{BytesEnd, BytesStart, BytesText, Event};
+use std::collections::HashMap;
+use std::io::{Read, Seek, Write};
+use std::path::{Path, PathBuf};
+use uuid::Uuid;
+use zip::write::SimpleFileOptions;
+
+const COMIC_INFO: &str = "ComicInfo.xml";
+
+/// `[writer]title.cbz` when both are set, else the page title, else the url segment
+pub fn cbz_filename(
+    writer: Option<&str>,
+    title: Option<&str>,
+    page_title: Option<&str>,
+    segment: &str,
+) -> String {
+    let name = match (writer, title, page_title) {
+        (Some(writer), Some(title), _) => format!("[{}]{}", writer, title),
+        (_, None, Some(page_title)) => page_title.to_string(),
+        _ => segment.to_string(),
+    };
+    format!("{}.cbz", name)
+}
+
+/// where a pic is stored, pages are numbered so they sort in reading order
+pub fn pic_key(segment: &str, seq: usize, total: usize, url: &str) -> String {
+    join_key(segment, &pic_file_name(seq, total, url))
+}
+
+/// the file name part of [`pic_key`], the extension is taken from the url
+pub fn pic_file_name(seq: usize, total: usize, url: &str) -> String {
+    let ext = url.split('.').next_back().unwrap_or("jpg");
+    format_page_filename(seq, total, ext)
+}
+
+pub fn comic_info_xml(info: &ComicInfo) -> String {
+    let mut xml = String::new();
+    quick_xml::se::to_writer(&mut xml, info).expect("Failed to serialize ComicInfo Xml");
+    format!(r#"<?xml version="1.0" encoding="utf-8"?>{}"#, xml)
+}
+
+/// Builds the archive in the temp dir from pics of the storage, the caller moves it
+/// where it belongs. `None` when the zip could not be written, the reason is logged.
+pub async fn build_cbz(
+    info: &ComicInfo,
+    pics: &dyn Storage,
+    files: &[StorageObject],
+) -> Result<Option<PathBuf>> {
+    let zip_file_path = std::env::temp_dir().join(format!("telegrab-{}.cbz", Uuid::new_v4()));
+    match write_cbz(&zip_file_path, &comic_info_xml(info), pics, files).await {
+        Ok(true) => {
+            if let Ok(meta) = tokio::fs::metadata(&zip_file_path).await {
+                metrics().cbz_built(meta.len());
+            }
+            Ok(Some(zip_file_path))
+        }
+        Ok(false) => {
+            let _ = tokio::fs::remove_file(&zip_file_path).await;
+            Ok(None)
+        }
+        Err(err) => {
+            let _ = tokio::fs::remove_file(&zip_file_path).await;
+            Err(err)
+        }
+    }
+}
+
+async fn write_cbz(
+    zip_file_path: &Path,
+    xml_with_decl: &str,
+    pics: &dyn Storage,
+    files: &[StorageObject],
+) -> Result<bool> {
+    let zip_file = std::fs::File::create(zip_file_path)?;
+    let mut zip_writer = zip::ZipWriter::new(zip_file);
+    let r = zip_writer.start_file(COMIC_INFO, SimpleFileOptions::default());
+    if let Err(err) = r {
+        tracing::warn!("Start file {} in zip failed: {}", COMIC_INFO, err);
+        return Ok(false);
+    }
+    let r = zip_writer.write_all(xml_with_decl.as_bytes());
+    if let Err(err) = r {
+        tracing::warn!("Write file {} in zip failed: {}", COMIC_INFO, err);
+        return Ok(false);
+    }
+
+    let simple_options = SimpleFileOptions::default();
+    for file in files {
+        let filename = file.key.rsplit('/').next().unwrap_or(&file.key).to_string();
+        let r = zip_writer.start_file(&filename, simple_options);
+        if let Err(err) = r {
+            tracing::warn!("Add file {} to zip failed: {}", filename, err);
+            return Ok(false);
+        }
+        let img = pics.get(&file.key).await?;
+        let r = zip_writer.write_all(&img);
+        if let Err(err) = r {
+            tracing::warn!("Write file {} in zip failed: {}", filename, err);
+            return Ok(false);
+        }
+    }
+    let r = zip_writer.finish();
+    if let Err(err) = r {
+        tracing::warn!("Finish zip file failed: {}", err);
+        return Ok(false);
+    }
+    Ok(true)
+}
+
+/// `ComicInfo.xml` of an archive, `None` when it has none. Blocking.
+pub fn read_comic_info<R: Read + Seek>(reader: R) -> Result<Option<String>> {
+    let mut archive = zip::ZipArchive::new(reader).map_err(Error::wrap)?;
+    let Some(index) = comic_info_index(&mut archive) else {
+        return Ok(None);
+    };
+    let mut xml = String::new();
+    archive
+        .by_index(index)
+        .map_err(Error::wrap)?
+        .read_to_string(&mut xml)?;
+    Ok(Some(xml))
+}
+
+fn comic_info_index<R: Read + Seek>(archive: &mut zip::ZipArchive<R>) -> Option<usize> {
+    archive
+        .file_names()
+        .position(|name| name.eq_ignore_ascii_case(COMIC_INFO))
+}
+
+/// Sets the text of top level elements, `None` removes the element. Other
+/// elements and their order are kept, new ones are appended.
+pub fn set_comic_info_fields(xml: &str, fields: &[(String, Option<String>)]) -> Result<String> {
+    let mut pending: HashMap<&str, Option<&str>> = fields
+        .iter()
+        .map(|(name, value)| (name.as_str(), value.as_deref()))
+        .collect();
+    let mut reader = quick_xml::Reader::from_str(xml);
+    let mut writer = quick_xml::Writer::new(Vec::new());
+    let mut depth = 0;
+    // inside an element that is replaced, its old content is dropped
+    let mut skipping = false;
+    loop {
+        let event = reader.read_event().map_err(Error::wrap)?;
+        match &event {
+            Event::Eof => break,
+            Event::Start(start) => {
+                depth += 1;
+                if skipping {
+                    continue;
+                }
+                let name = String::from_utf8_lossy(start.name().as_ref()).into_owned();
+                if depth == 2
+                    && let Some(value) = pending.remove(name.as_str())
+                {
+                    if let Some(value) = value {
+                        write_element(&mut writer, &name, value)?;
+                    }
+                    skipping = true;
+                    continue;
+                }
+            }
+            Event::Empty(start) if depth == 1 && !skipping => {
+                let name = String::from_utf8_lossy(start.name().as_ref()).into_owned();
+                if let Some(value) = pending.remove(name.as_str()) {
+                    if let Some(value) = value {
+                        write_element(&mut writer, &name, value)?;
+                    }
+                    continue;
+                }
+            }
+            Event::End(_) => {
+                depth -= 1;
+                if skipping {
+                    skipping = depth > 1;
+                    continue;
+                }
+                if depth == 0 {
+                    for (name, value) in fields {
+                        if let (Some(_), Some(value)) = (pending.remove(name.as_str()), value) {
+                            write_element(&mut writer, name, value)?;
+                        }
+                    }
+                }
+            }
+            _ if skipping => continue,
+            _ => {}
+        }
+        writer.write_event(event).map_err(Error::wrap)?;
+    }
+    String::from_utf8(writer.into_inner()).map_err(Error::from)
+}
+
+fn write_element(writer: &mut quick_xml::Writer<Vec<u8>>, name: &str, value: &str) -> Result<()> {
+    writer
+        .write_event(Event::Start(BytesStart::new(name)))
+        .map_err(Error::wrap)?;
+    writer
+        .write_event(Event::Text(BytesText::new(value)))
+        .map_err(Error::wrap)?;
+    writer
+        .write_event(Event::End(BytesEnd::new(name)))
+        .map_err(Error::wrap)
+}
+
+/// Writes the archive again with a new `ComicInfo.xml`, pages are copied without
+/// recompressing. Replaces the file only once the new one is complete. Blocking.
+pub fn replace_comic_info(path: &Path, xml: &str) -> Result<()> {
+    let mut archive = zip::ZipArchive::new(std::fs::File::open(path)?).map_err(Error::wrap)?;
+    let skip = comic_info_index(&mut archive);
+    let file_name = path.file_name().unwrap_or_default().to_string_lossy();
+    let tmp = path.with_file_name(format!(".{}.{}.tmp", file_name, Uuid::new_v4()));
+    let result = (|| {
+        let mut zip_writer = zip::ZipWriter::new(std::fs::File::create(&tmp)?);
+        zip_writer
+            .start_file(COMIC_INFO, SimpleFileOptions::default())
+            .map_err(Error::wrap)?;
+        zip_writer.write_all(xml.as_bytes())?;
+        for i in (0..archive.len()).filter(|i| Some(*i) != skip) {
+            let entry = archive.by_index_raw(i).map_err(Error::wrap)?;
+            zip_writer.raw_copy_file(entry).map_err(Error::wrap)?;
+        }
+        zip_writer.finish().map_err(Error::wrap)?;
+        std::fs::rename(&tmp, path)?;
+        Ok(())
+    })();
+    if result.is_err() {
+        let _ = std::fs::remove_file(&tmp);
+    }
+    result
+}
@@ -0,0 +1,56 @@
+use crate::archive;
+use crate::{Error, Result};
+use clap::Args;
+use std::path::PathBuf;
+
+const EMPTY_COMIC_INFO: &str = r#"<?xml version="1.0" encoding="utf-8"?><ComicInfo></ComicInfo>"#;
+
+#[derive(Debug, Args)]
+pub struct ComicInfoArgs {
+    pub cbz: PathBuf,
+    /// set an element, e.g. `--set Writer=Someone`, repeatable
+    #[arg(long, value_parser = parse_field)]
+    pub set: Vec<(String, String)>,
+    /// remove an element, repeatable
+    #[arg(long)]
+    pub unset: Vec<String>,
+}
+
+fn parse_field(s: &str) -> std::result::Result<(String, String), String> {
+    let (name, value) = s
+        .split_once('=')
+        .ok_or_else(|| format!("expected Name=value, got `{}`", s))?;
+    if name.is_empty() || !name.chars().all(|c| c.is_ascii_alphanumeric()) {
+        return Err(format!("invalid element name `{}`", name));
+    }
+    Ok((name.to_string(), value.to_string()))
+}
+
+/// Prints `ComicInfo.xml` of a cbz, or edits it when fields are given.
+pub async fn run(args: ComicInfoArgs) -> Result<()> {
+    tokio::task::spawn_blocking(move || {
+        let file = std::fs::File::open(&args.cbz)?;
+        let xml = archive::read_comic_info(file)?;
+        if args.set.is_empty() && args.unset.is_empty() {
+            let xml = xml.ok_or_else(|| {
+                Error::Message(format!("{} has no ComicInfo.xml", args.cbz.display()))
+            })?;
+            println!("{}", xml);
+            return Ok(());
+        }
+        let fields: Vec<(String, Option<String>)> = args
+            .set
+            .into_iter()
+            .map(|(name, value)| (name, Some(value)))
+            .chain(args.unset.into_iter().map(|name| (name, None)))
+            .collect();
+        let xml = archive::set_comic_info_fields(
+            xml.as_deref().unwrap_or(EMPTY_COMIC_INFO),
+            &fields,
+        )?;
+        archive::replace_comic_info(&args.cbz, &xml)?;
+        println!("{}", xml);
+        Ok(())
+    })
+    .await?
+}
@@ -0,0 +1,98 @@
+use crate::archive;
+use crate::configuration::Settings;
+use crate::http_client::HttpClientManager;
+use crate::link::canonicalize;
+use crate::model::entity::doc::{ComicInfo, PageInfo, Pages};
+use crate::storage::{LocalStorage, Storage};
+use crate::worker::url_last_segment;
+use crate::{Error, Result};
+use clap::Args;
+use std::io::Write;
+use std::path::PathBuf;
+use uuid::Uuid;
+
+#[derive(Debug, Args)]
+pub struct GrabArgs {
+    /// telegraph pages to archive
+    #[arg(required = true)]
+    pub urls: Vec<String>,
+    /// where the cbz files are written
+    #[arg(long, default_value = ".")]
+    pub out: PathBuf,
+}
+
+/// Parses, downloads and archives every url without the database or the queue.
+pub async fn run(args: GrabArgs, configuration: &Settings) -> Result<()> {
+    let http_client = HttpClientManager::new(Some(configuration.http_client.clone()));
+    let out = LocalStorage::new(&args.out);
+    let mut failed = 0;
+    for url in &args.urls {
+        match grab(&http_client, &out, url).await {
+            Ok(filename) => println!("{} -> {}", url, args.out.join(filename).display()),
+            Err(err) => {
+                failed += 1;
+                eprintln!("{} failed: {}", url, err);
+            }
+        }
+    }
+    if failed > 0 {
+        return Err(Error::Message(format!(
+            "{} of {} urls failed",
+            failed,
+            args.urls.len()
+        )));
+    }
+    Ok(())
+}
+
+async fn grab(http_client: &HttpClientManager, out: &LocalStorage, url: &str) -> Result<String> {
+    let url = canonicalize(url).map_err(Error::Message)?;
+    let post = http_client.parse_telegraph_post(&url).await?;
+    let segment = url_last_segment(&url);
+    // pics only live until the archive is built
+    let pic_dir = std::env::temp_dir().join(format!("telegrab-{}", Uuid::new_v4()));
+    let pics = LocalStorage::new(&pic_dir);
+    let result = async {
+        let total = post.image_urls.len();
+        let downloads = async {
+            progress(&segment, 0, total);
+            for (seq, image_url) in post.image_urls.iter().enumerate() {
+                let key = archive::pic_key(&segment, seq, total, image_url);
+                http_client
+                    .download_file(image_url, &pics, &key)
+                    .await
+                    .map_err(|err| {
+                        Error::Message(format!("download pic {} failed: {}", image_url, err))
+                    })?;
+                progress(&segment, seq + 1, total);
+            }
+            Ok::<_, Error>(())
+        }
+        .await;
+        eprintln!();
+        downloads?;
+        let files = pics.list(&segment).await?;
+        let comic_info = ComicInfo {
+            web: Some(url.clone()),
+            page_count: Some(total.to_string()),
+            pages: Pages {
+                page: PageInfo::with_count(total),
+            },
+            ..ComicInfo::default()
+        };
+        let zip_file_path = archive::build_cbz(&comic_info, &pics, &files)
+            .await?
+            .ok_or_else(|| Error::string("writing the cbz failed"))?;
+        let filename = archive::cbz_filename(None, None, Some(&post.title), &segment);
+        out.put_file(&filename, &zip_file_path).await?;
+        Ok(filename)
+    }
+    .await;
+    let _ = tokio::fs::remove_dir_all(&pic_dir).await;
+    result
+}
+
+fn progress(segment: &str, downloaded: usize, total: usize) {
+    eprint!("\r{} {}/{} pics", segment, downloaded, total);
+    let _ = std::io::stderr().flush();
+}
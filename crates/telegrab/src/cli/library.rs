@@ -0,0 +1,64 @@
+use crate::configuration::Settings;
+use crate::model::entity::task::Task;
+use crate::service;
+use crate::state::AppState;
+use crate::storage::join_key;
+use crate::worker::TaskWorker;
+use crate::{Error, Result};
+
+/// a worker outside of the pool, tasks run one after another in this process
+async fn worker(configuration: &Settings) -> (AppState, TaskWorker) {
+    let state = AppState::build(configuration).await;
+    let worker = TaskWorker::new(&state, configuration.clone(), 0).with_actor("cli");
+    (state, worker)
+}
+
+/// Registers cbz files of the storage that are not in the database yet.
+pub async fn scan(configuration: &Settings) -> Result<()> {
+    let (state, worker) = worker(configuration).await;
+    let result = worker.run_task(&Task::new_scan_dir_task()).await?;
+    println!(
+        "Scanned {}: {}",
+        state.storage.cbz,
+        result.unwrap_or_default()
+    );
+    Ok(())
+}
+
+/// Parses the doc if needed, downloads missing pics and builds its cbz.
+pub async fn archive(doc_id: i32, configuration: &Settings) -> Result<()> {
+    let (state, worker) = worker(configuration).await;
+    let doc = match service::doc::get_doc_by_id(&state.db_pool, doc_id).await {
+        Err(sqlx::Error::RowNotFound) => {
+            return Err(Error::Message(format!("No doc #{}", doc_id)));
+        }
+        doc => doc?,
+    };
+    if doc.page_title.is_none() {
+        println!("Parsing {}", doc.url);
+        worker.run_task(&Task::new_html_parse_task(doc_id)).await?;
+    }
+    println!("Downloading pics of doc #{}", doc_id);
+    if let Some(result) = worker.run_task(&Task::new_doc_download_task(doc_id)).await? {
+        println!("{}", result);
+    }
+    if service::pic::has_status_0_pics_by_doc_id(&state.db_pool, doc_id).await? {
+        return Err(Error::Message(format!(
+            "Some pics of doc #{} failed to download, cbz not built",
+            doc_id
+        )));
+    }
+    println!("Archiving doc #{}", doc_id);
+    worker.run_task(&Task::new_cbz_archive_task(doc_id)).await?;
+    let doc = service::doc::get_doc_by_id(&state.db_pool, doc_id).await?;
+    let cbz_id = doc
+        .cbz_id
+        .ok_or_else(|| Error::Message(format!("No cbz built for doc #{}", doc_id)))?;
+    let cbz = service::cbz::get_cbz_by_id(&state.db_pool, cbz_id).await?;
+    println!(
+        "Archived doc #{} to {}",
+        doc_id,
+        join_key(&state.storage.cbz.to_string(), &cbz.path)
+    );
+    Ok(())
+}
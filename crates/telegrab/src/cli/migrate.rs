@@ -0,0 +1,77 @@
+use crate::Result;
+use crate::cli::connect_db;
+use crate::configuration::Settings;
+use crate::db::DbPool;
+use crate::migration::{self, MigrationState};
+use clap::Subcommand;
+use time::format_description::well_known::Rfc3339;
+
+#[derive(Debug, Subcommand)]
+pub enum MigrateCommand {
+    /// Apply all pending migrations
+    Up,
+    /// List applied and pending migrations
+    Status,
+    /// Revert the latest migration, or every migration after --to
+    Down {
+        #[arg(long)]
+        to: Option<i64>,
+    },
+    /// Mark migrations as applied without running them, for databases set up by hand
+    Baseline {
+        #[arg(long)]
+        to: Option<i64>,
+    },
+}
+
+pub async fn run(command: MigrateCommand, configuration: &Settings) -> Result<()> {
+    let pool = connect_db(configuration).await?;
+    match command {
+        MigrateCommand::Up => {
+            let pending = count_pending(&pool).await?;
+            migration::up(&pool).await?;
+            println!("Applied {} migrations", pending);
+        }
+        MigrateCommand::Status => {
+            for s in migration::status(&pool).await? {
+                let state = match s.state {
+                    MigrationState::Applied(at) => {
+                        format!("applied {}", at.format(&Rfc3339).unwrap_or_default())
+                    }
+                    MigrationState::Pending => "pending".to_string(),
+                    MigrationState::Failed => "failed".to_string(),
+                    MigrationState::Modified => "applied, file modified since".to_string(),
+                    MigrationState::Unknown => "applied, unknown to this binary".to_string(),
+                };
+                println!("{}\t{}\t{}", s.version, s.description, state);
+            }
+        }
+        MigrateCommand::Down { to } => {
+            let reverted = migration::down(&pool, to).await?;
+            if reverted.is_empty() {
+                println!("Nothing to revert");
+            }
+            for version in reverted {
+                println!("Reverted {}", version);
+            }
+        }
+        MigrateCommand::Baseline { to } => {
+            let recorded = migration::baseline(&pool, to).await?;
+            if recorded.is_empty() {
+                println!("Nothing to record");
+            }
+            for version in recorded {
+                println!("Recorded {} as applied", version);
+            }
+        }
+    }
+    Ok(())
+}
+
+async fn count_pending(pool: &DbPool) -> Result<usize> {
+    Ok(migration::status(pool)
+        .await?
+        .iter()
+        .filter(|s| s.state == MigrationState::Pending)
+        .count())
+}
@@ -0,0 +1,42 @@
+use crate::configuration::Settings;
+use clap::{Parser, Subcommand};
+use crate::db::DbPool;
+use std::time::Duration;
+
+pub mod comicinfo;
+pub mod grab;
+pub mod library;
+pub mod migrate;
+pub mod token;
+
+#[derive(Debug, Parser)]
+#[command(name = "telegrab", version, about)]
+pub struct Cli {
+    #[command(subcommand)]
+    pub command: Option<Command>,
+}
+
+#[derive(Debug, Subcommand)]
+pub enum Command {
+    /// Start the api server and background workers (default)
+    Serve,
+    /// Manage api tokens
+    #[command(subcommand)]
+    Token(token::TokenCommand),
+    /// Apply, list or revert database migrations
+    #[command(subcommand)]
+    Migrate(migrate::MigrateCommand),
+    /// Parse, download and archive pages into cbz files, without the database
+    Grab(grab::GrabArgs),
+    /// Register cbz files of the storage that are not in the database yet
+    Scan,
+    /// Download the pics of a doc and build its cbz
+    Archive { doc_id: i32 },
+    /// Print or edit ComicInfo.xml of a cbz file
+    #[command(name = "comicinfo")]
+    ComicInfo(comicinfo::ComicInfoArgs),
+}
+
+pub async fn connect_db(configuration: &Settings) -> crate::Result<DbPool> {
+    DbPool::connect(&configuration.database, Duration::from_secs(5)).await
+}
@@ -0,0 +1,69 @@
+use crate::Result;
+use crate::cli::connect_db;
+use crate::configuration::Settings;
+use crate::model::entity::api_token::ApiScope;
+use crate::service;
+use clap::Subcommand;
+
+#[derive(Debug, Subcommand)]
+pub enum TokenCommand {
+    /// Create a token, the plain token is only printed once
+    Create {
+        #[arg(long)]
+        name: String,
+        /// comma separated: read, write, admin
+        #[arg(long, value_delimiter = ',', default_value = "read")]
+        scope: Vec<ApiScope>,
+    },
+    /// Revoke a token by id
+    Revoke { id: i32 },
+    /// List all tokens
+    List,
+}
+
+pub async fn run(command: TokenCommand, configuration: &Settings) -> Result<()> {
+    let pool = connect_db(configuration).await?;
+    match command {
+        TokenCommand::Create { name, scope } => {
+            let created = service::api_token::create_api_token(&pool, name, &scope).await?;
+            println!(
+                "Created token #{} `{}` with scopes [{}]",
+                created.api_token.id,
+                created.api_token.name,
+                created.api_token.scopes.join(",")
+            );
+            println!("{}", created.token);
+            println!("Store it now, it can not be shown again.");
+        }
+        TokenCommand::Revoke { id } => {
+            let count = service::api_token::revoke_api_token(&pool, id).await?;
+            if count == 0 {
+                println!("No active token #{} found", id);
+            } else {
+                println!("Revoked token #{}", id);
+            }
+        }
+        TokenCommand::List => {
+            let tokens = service::api_token::get_api_tokens(&pool).await?;
+            for t in tokens {
+                let state = if t.revoked_at.is_some() {
+                    "revoked"
+                } else {
+                    "active"
+                };
+                println!(
+                    "#{}\t{}\t{}...\t[{}]\t{}\tlast used: {}",
+                    t.id,
+                    t.name,
+                    t.token_prefix,
+                    t.scopes.join(","),
+                    state,
+                    t.last_used_at
+                        .map(|d| d.to_string())
+                        .unwrap_or_else(|| "never".to_string())
+                );
+            }
+        }
+    }
+    Ok(())
+}
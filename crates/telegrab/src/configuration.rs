@@ -1,6 +1,7 @@
 use crate::telemetry;
+use crate::model::entity::task::TaskType;
 use secrecy::{ExposeSecret, SecretString};
-use serde::Deserialize;
+use serde::{Deserialize, Serialize};
 use serde_aux::field_attributes::deserialize_number_from_string;
 use sqlx_postgres::{PgConnectOptions, PgSslMode};
 
@@ -63,18 +64,173 @@ pub struct Settings {
     pub http_client: HttpClientSettings,
     pub worker: WorkerSettings,
     pub logger: LoggerSettings,
+    pub auth: AuthSettings,
+    #[serde(default)]
+    pub scheduler: SchedulerSettings,
+    #[serde(default)]
+    pub disk: DiskSettings,
+    #[serde(default)]
+    pub storage: StorageSettings,
+    #[serde(default)]
+    pub health: HealthSettings,
+    #[serde(default)]
+    pub webhook: WebhookSettings,
     pub redis_uri: SecretString,
     pub pic_dir: String,
     pub cbz_dir: String,
+    /// garbage collection moves orphaned files here instead of deleting them
+    #[serde(default = "default_quarantine_dir")]
+    pub quarantine_dir: String,
 }
 
+fn default_quarantine_dir() -> String {
+    "data/quarantine".to_string()
+}
+
+#[derive(Deserialize, Debug, Clone, Copy, Default, Eq, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum StorageBackend {
+    /// `pic_dir`, `cbz_dir` and `quarantine_dir`
+    #[default]
+    Local,
+    S3,
+}
+
+#[derive(Deserialize, Debug, Clone, Default)]
+pub struct StorageSettings {
+    #[serde(default)]
+    pub backend: StorageBackend,
+    #[serde(default)]
+    pub s3: Option<S3Settings>,
+}
+
+/// any S3 compatible service, e.g. MinIO; credentials fall back to the `AWS_*` environment
+#[derive(Deserialize, Debug, Clone)]
+pub struct S3Settings {
+    pub bucket: String,
+    /// e.g. `http://127.0.0.1:9000` for MinIO, AWS when not set
+    #[serde(default)]
+    pub endpoint: Option<String>,
+    #[serde(default = "default_s3_region")]
+    pub region: String,
+    #[serde(default)]
+    pub access_key_id: Option<String>,
+    #[serde(default)]
+    pub secret_access_key: Option<SecretString>,
+    /// needed for an `http://` endpoint
+    #[serde(default)]
+    pub allow_http: bool,
+    #[serde(default = "default_pic_prefix")]
+    pub pic_prefix: String,
+    #[serde(default = "default_cbz_prefix")]
+    pub cbz_prefix: String,
+    #[serde(default = "default_quarantine_prefix")]
+    pub quarantine_prefix: String,
+    /// `/resource` redirects to presigned urls valid this long, 0 streams through telegrab instead
+    #[serde(default = "default_presign_expiry_secs")]
+    pub presign_expiry_secs: u64,
+}
+
+fn default_s3_region() -> String {
+    "us-east-1".to_string()
+}
+
+fn default_pic_prefix() -> String {
+    "pic".to_string()
+}
+
+fn default_cbz_prefix() -> String {
+    "cbz".to_string()
+}
+
+fn default_quarantine_prefix() -> String {
+    "quarantine".to_string()
+}
+
+fn default_presign_expiry_secs() -> u64 {
+    3600
+}
+
+/// Downloads pause while the file system of `pic_dir` has less than `low_watermark_mb`
+/// available or `pic_dir` holds `pic_quota_mb`, archives likewise for `cbz_dir`.
+/// They resume once more than `high_watermark_mb` is available and the dir is below its quota.
+#[derive(Deserialize, Debug, Clone)]
+pub struct DiskSettings {
+    pub check_interval_secs: u64,
+    pub low_watermark_mb: u64,
+    pub high_watermark_mb: u64,
+    #[serde(default)]
+    pub pic_quota_mb: Option<u64>,
+    #[serde(default)]
+    pub cbz_quota_mb: Option<u64>,
+}
+
+impl Default for DiskSettings {
+    fn default() -> Self {
+        Self {
+            check_interval_secs: 30,
+            low_watermark_mb: 512,
+            high_watermark_mb: 1024,
+            pic_quota_mb: None,
+            cbz_quota_mb: None,
+        }
+    }
+}
+
+/// Limits of `/api/health/ready`.
 #[derive(Deserialize, Debug, Clone)]
+pub struct HealthSettings {
+    /// the database ping fails when it takes longer
+    pub db_timeout_ms: u64,
+    /// a worker without a task that has not looked at the queue for this long is dead
+    pub worker_stale_secs: u64,
+}
+
+impl Default for HealthSettings {
+    fn default() -> Self {
+        Self {
+            db_timeout_ms: 2000,
+            worker_stale_secs: 30,
+        }
+    }
+}
+
+/// Delivery of the webhook payloads, a failed attempt is retried after
+/// `retry_base_secs`, doubled on every further attempt up to `retry_max_secs` (a week at most).
+#[derive(Deserialize, Debug, Clone)]
+pub struct WebhookSettings {
+    /// how often due retries are looked for
+    pub check_interval_secs: u64,
+    pub timeout_secs: u64,
+    /// a delivery is given up after this many attempts
+    pub max_attempts: i32,
+    pub retry_base_secs: u64,
+    pub retry_max_secs: u64,
+}
+
+impl Default for WebhookSettings {
+    fn default() -> Self {
+        Self {
+            check_interval_secs: 5,
+            timeout_secs: 10,
+            max_attempts: 8,
+            retry_base_secs: 10,
+            retry_max_secs: 3600,
+        }
+    }
+}
+
+#[derive(Deserialize, Serialize, Debug, Clone)]
 pub struct HttpClientSettings {
     pub connect_timeout_secs: u64,
     pub timeout_secs: u64,
     pub max_connections: usize,
     pub pool_enabled: bool,
     pub user_agent: String,
+    #[serde(default)]
+    pub rate_limit: RateLimitSettings,
+    #[serde(default)]
+    pub proxy: Option<ProxySettings>,
 }
 
 impl Default for HttpClientSettings {
@@ -85,18 +241,199 @@ impl Default for HttpClientSettings {
             max_connections: 100,
             pool_enabled: true,
             user_agent: "telegraph/0.1.0".into(),
+            rate_limit: RateLimitSettings::default(),
+            proxy: None,
+        }
+    }
+}
+
+#[derive(Deserialize, Serialize, Debug, Clone)]
+pub struct ProxySettings {
+    /// `http://`, `https://`, `socks5://` or `socks5h://` (dns resolved by the proxy)
+    pub url: String,
+    #[serde(default)]
+    pub username: Option<String>,
+    /// never serialized, an update without password keeps the current one
+    #[serde(default, skip_serializing)]
+    pub password: Option<SecretString>,
+    /// hosts sent through the proxy, empty means every host; subdomains are included
+    #[serde(default)]
+    pub hosts: Vec<String>,
+    /// hosts always connected directly, wins over `hosts`
+    #[serde(default)]
+    pub bypass: Vec<String>,
+}
+
+#[derive(Deserialize, Serialize, Debug, Clone)]
+pub struct RateLimitSettings {
+    /// applied to every host without an entry in `hosts`
+    pub default: HostPolicy,
+    #[serde(default)]
+    pub hosts: Vec<HostRateLimit>,
+    /// how often a request answered by 429/503 is retried
+    pub max_retries: u32,
+    /// upper bound of a `Retry-After` we are willing to wait for
+    pub max_retry_after_secs: u64,
+}
+
+impl Default for RateLimitSettings {
+    fn default() -> Self {
+        Self {
+            default: HostPolicy::default(),
+            hosts: vec![],
+            max_retries: 3,
+            max_retry_after_secs: 300,
+        }
+    }
+}
+
+impl HttpClientSettings {
+    /// checks what `reqwest` would silently accept, proxy urls are checked when the client is built
+    pub fn validate(&self) -> Result<(), String> {
+        if self.connect_timeout_secs == 0 || self.timeout_secs == 0 {
+            return Err("timeouts must be greater than 0".to_string());
+        }
+        if self.connect_timeout_secs > self.timeout_secs {
+            return Err("connect_timeout_secs must not exceed timeout_secs".to_string());
+        }
+        if self.user_agent.trim().is_empty() {
+            return Err("user_agent must not be empty".to_string());
+        }
+        self.rate_limit.validate()
+    }
+}
+
+impl RateLimitSettings {
+    fn validate(&self) -> Result<(), String> {
+        self.default
+            .validate()
+            .map_err(|e| format!("rate_limit.default: {}", e))?;
+        let mut seen = std::collections::HashSet::new();
+        for h in &self.hosts {
+            if h.host.trim().is_empty() {
+                return Err("rate_limit.hosts: host must not be empty".to_string());
+            }
+            if !seen.insert(h.host.as_str()) {
+                return Err(format!("rate_limit.hosts: {} is listed twice", h.host));
+            }
+            h.policy()
+                .validate()
+                .map_err(|e| format!("rate_limit.hosts.{}: {}", h.host, e))?;
+        }
+        Ok(())
+    }
+
+    /// exact host first, then the closest parent domain, then the default
+    pub fn policy_for(&self, host: &str) -> HostPolicy {
+        let mut candidate = host;
+        loop {
+            if let Some(h) = self.hosts.iter().find(|h| h.host == candidate) {
+                return h.policy();
+            }
+            match candidate.split_once('.') {
+                Some((_, parent)) if parent.contains('.') => candidate = parent,
+                _ => return self.default.clone(),
+            }
+        }
+    }
+}
+
+#[derive(Deserialize, Serialize, Debug, Clone)]
+pub struct HostPolicy {
+    pub requests_per_second: f64,
+    pub max_concurrency: usize,
+}
+
+impl HostPolicy {
+    /// `requests_per_second` of 0 disables pacing
+    fn validate(&self) -> Result<(), String> {
+        if !self.requests_per_second.is_finite() || self.requests_per_second < 0.0 {
+            return Err("requests_per_second must be a number >= 0".to_string());
+        }
+        if self.max_concurrency == 0 {
+            return Err("max_concurrency must be at least 1".to_string());
+        }
+        Ok(())
+    }
+}
+
+impl Default for HostPolicy {
+    fn default() -> Self {
+        Self {
+            requests_per_second: 2.0,
+            max_concurrency: 4,
+        }
+    }
+}
+
+#[derive(Deserialize, Serialize, Debug, Clone)]
+pub struct HostRateLimit {
+    pub host: String,
+    pub requests_per_second: f64,
+    pub max_concurrency: usize,
+}
+
+impl HostRateLimit {
+    pub fn policy(&self) -> HostPolicy {
+        HostPolicy {
+            requests_per_second: self.requests_per_second,
+            max_concurrency: self.max_concurrency,
         }
     }
 }
 
+#[derive(Deserialize, Debug, Clone)]
+pub struct AuthSettings {
+    pub enabled: bool,
+}
+
+impl Default for AuthSettings {
+    fn default() -> Self {
+        Self { enabled: true }
+    }
+}
+
+#[derive(Deserialize, Debug, Clone)]
+pub struct SchedulerSettings {
+    /// how often the schedule table is checked for due entries
+    #[serde(deserialize_with = "deserialize_number_from_string")]
+    pub check_interval_secs: u64,
+    /// upserted by name on startup, `enabled` is only taken for new entries
+    #[serde(default)]
+    pub schedules: Vec<ScheduleConfig>,
+}
+
+impl Default for SchedulerSettings {
+    fn default() -> Self {
+        Self {
+            check_interval_secs: 5,
+            schedules: vec![],
+        }
+    }
+}
+
+#[derive(Deserialize, Debug, Clone)]
+pub struct ScheduleConfig {
+    pub name: String,
+    /// utc, five fields or six with leading seconds
+    pub cron: String,
+    /// a serialized `TaskType`, e.g. `scanDir` or `{pollFeed: {id: 1}}`
+    pub task: TaskType,
+    #[serde(default = "default_true")]
+    pub enabled: bool,
+}
+
+fn default_true() -> bool {
+    true
+}
+
 #[derive(Deserialize, Debug, Clone)]
 pub struct WorkerSettings {
     #[serde(deserialize_with = "deserialize_number_from_string")]
     pub count: usize,
+    /// completed tasks kept in memory by the `cleanupCompleted` task
     #[serde(deserialize_with = "deserialize_number_from_string")]
     pub max_completed_tasks: usize,
-    #[serde(deserialize_with = "deserialize_number_from_string")]
-    pub auto_cleanup_interval_secs: u64,
 }
 
 impl Default for WorkerSettings {
@@ -104,7 +441,6 @@ impl Default for WorkerSettings {
         Self {
             count: 4,
             max_completed_tasks: 100,
-            auto_cleanup_interval_secs: 60,
         }
     }
 }
@@ -146,8 +482,21 @@ impl Default for ApplicationSettings {
         }
     }
 }
+#[derive(Deserialize, Debug, Clone, Copy, Default, Eq, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum DatabaseBackend {
+    #[default]
+    Postgres,
+    /// a single file at `sqlite_path`, needs the `sqlite` cargo feature
+    Sqlite,
+}
+
 #[derive(Deserialize, Debug, Clone)]
 pub struct DatabaseSettings {
+    #[serde(default)]
+    pub backend: DatabaseBackend,
+    #[serde(default = "default_sqlite_path")]
+    pub sqlite_path: String,
     pub username: String,
     pub password: SecretString,
     #[serde(deserialize_with = "deserialize_number_from_string")]
@@ -155,6 +504,13 @@ pub struct DatabaseSettings {
     pub host: String,
     pub database_name: String,
     pub require_ssl: bool,
+    /// applies pending migrations on startup, `telegrab migrate up` does it by hand
+    #[serde(default)]
+    pub auto_migrate: bool,
+}
+
+fn default_sqlite_path() -> String {
+    "data/telegrab.db".to_string()
 }
 
 impl DatabaseSettings {
@@ -186,4 +542,26 @@ pub struct LoggerSettings {
     pub pretty_backtrace: bool,
     pub level: telemetry::LogLevel,
     pub format: telemetry::Format,
+    /// exports spans to an OpenTelemetry collector, off when not set
+    #[serde(default)]
+    pub otlp: Option<OtlpSettings>,
+}
+
+#[derive(Deserialize, Debug, Clone)]
+pub struct OtlpSettings {
+    /// OTLP over http, e.g. `http://localhost:4318/v1/traces`
+    pub endpoint: String,
+    #[serde(default = "default_otlp_service_name")]
+    pub service_name: String,
+    /// share of traces kept, 1.0 keeps all of them
+    #[serde(default = "default_otlp_sample_ratio")]
+    pub sample_ratio: f64,
+}
+
+fn default_otlp_service_name() -> String {
+    "telegrab".to_string()
+}
+
+fn default_otlp_sample_ratio() -> f64 {
+    1.0
 }
@@ -0,0 +1,39 @@
+use crate::Result;
+use crate::configuration::HttpClientSettings;
+use crate::errors::{Error, ErrorDetail};
+use crate::format;
+use crate::state::AppState;
+use axum::extract::State;
+use axum::http::StatusCode;
+use axum::response::Response;
+use axum::routing::get;
+use axum::{Json, Router};
+
+pub fn routers() -> Router<AppState> {
+    Router::new().route(
+        "/http-client",
+        get(get_http_client_settings_handler).put(update_http_client_settings_handler),
+    )
+}
+
+async fn get_http_client_settings_handler(State(state): State<AppState>) -> Result<Response> {
+    format::json(state.http_client.config().await)
+}
+
+/// takes the same shape as `http_client` in the configuration file
+async fn update_http_client_settings_handler(
+    State(state): State<AppState>,
+    Json(settings): Json<HttpClientSettings>,
+) -> Result<Response> {
+    state
+        .http_client
+        .update_config(settings)
+        .await
+        .map_err(|e| {
+            Error::CustomError(
+                StatusCode::UNPROCESSABLE_ENTITY,
+                ErrorDetail::new("invalid_settings".to_string(), e),
+            )
+        })?;
+    format::json(state.http_client.config().await)
+}
@@ -1,9 +1,57 @@
 use crate::state::AppState;
+use crate::storage::Storage;
+use crate::{Error, Result};
 use axum::Router;
+use axum::body::Body;
+use axum::extract::{Path, State};
+use axum::http::header;
+use axum::response::{IntoResponse, Redirect, Response};
+use axum::routing::get;
+use std::sync::Arc;
 use tower_http::services::ServeDir;
 
 pub fn routers(state: &AppState) -> Router<AppState> {
-    Router::new()
-        .nest_service("/pic", ServeDir::new(state.pic_dir.as_str()))
-        .nest_service("/cbz", ServeDir::new(state.cbz_dir.as_str()))
+    let router = Router::new();
+    // local dirs keep range requests and caching headers of ServeDir
+    let router = match state.storage.pic.local_path("") {
+        Some(dir) => router.nest_service("/pic", ServeDir::new(dir)),
+        None => router.route("/pic/{*key}", get(get_pic_handler)),
+    };
+    match state.storage.cbz.local_path("") {
+        Some(dir) => router.nest_service("/cbz", ServeDir::new(dir)),
+        None => router.route("/cbz/{*key}", get(get_cbz_handler)),
+    }
+}
+
+async fn get_pic_handler(
+    State(state): State<AppState>,
+    Path(key): Path<String>,
+) -> Result<Response> {
+    serve(&state.storage.pic, state.storage.presign_expiry, &key).await
+}
+
+async fn get_cbz_handler(
+    State(state): State<AppState>,
+    Path(key): Path<String>,
+) -> Result<Response> {
+    serve(&state.storage.cbz, state.storage.presign_expiry, &key).await
+}
+
+/// redirects to a presigned url when the storage can sign one, streams the object otherwise
+async fn serve(
+    storage: &Arc<dyn Storage>,
+    presign_expiry: Option<std::time::Duration>,
+    key: &str,
+) -> Result<Response> {
+    if storage.head(key).await?.is_none() {
+        return Err(Error::NotFound);
+    }
+    if let Some(expires_in) = presign_expiry
+        && let Some(url) = storage.presign(key, expires_in).await?
+    {
+        return Ok(Redirect::temporary(url.as_str()).into_response());
+    }
+    let content_type = mime_guess::from_path(key).first_or_octet_stream();
+    let body = Body::from_stream(storage.stream(key).await?);
+    Ok(([(header::CONTENT_TYPE, content_type.to_string())], body).into_response())
 }
@@ -0,0 +1,26 @@
+use crate::Result;
+use crate::model::dto::audit::AuditFilter;
+use crate::model::dto::pagination::PaginationQuery;
+use crate::service;
+use crate::state::AppState;
+use axum::extract::{Query, State};
+use axum::http::HeaderMap;
+use axum::response::{IntoResponse, Response};
+use axum::routing::get;
+use axum::{Json, Router};
+
+pub fn routers() -> Router<AppState> {
+    Router::new().route("/", get(get_events_handler))
+}
+
+/// newest first, `?_start=&_end=` pages and every other param filters, e.g. `?entity=doc&entityId=3`
+async fn get_events_handler(
+    State(state): State<AppState>,
+    Query(pagination): Query<PaginationQuery>,
+    Query(filter): Query<AuditFilter>,
+) -> Result<Response> {
+    let events = service::audit::get_events(&state.db_pool, &filter, &pagination).await?;
+    let mut headers = HeaderMap::new();
+    headers.insert("x-total-count", events.total.to_string().parse()?);
+    Ok((headers, Json(events.data)).into_response())
+}
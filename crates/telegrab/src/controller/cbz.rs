@@ -93,6 +93,6 @@ pub async fn update_cbz_handler(
     Path(id): Path<i32>,
     Json(params): Json<UpdateCbzReq>,
 ) -> Result<Response> {
-    let cbz = service::cbz::update_cbz(&state.db_pool, id, params.doc_id).await?;
+    let cbz = service::cbz::update_cbz(&state.db_pool, &state.queue_state, id, params.doc_id).await?;
     format::json(cbz)
 }
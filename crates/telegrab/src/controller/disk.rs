@@ -0,0 +1,28 @@
+use crate::Result;
+use crate::disk;
+use crate::format;
+use crate::state::AppState;
+use axum::extract::State;
+use axum::response::Response;
+use axum::routing::{get, post};
+use axum::Router;
+
+pub fn routers() -> Router<AppState> {
+    Router::new()
+        .route("/", get(get_disk_usage_handler))
+        .route("/check", post(check_disk_handler))
+}
+
+/// the last check of the disk monitor
+async fn get_disk_usage_handler(State(state): State<AppState>) -> Result<Response> {
+    match state.queue_state.get_disk_usage().await {
+        Some(usage) => format::json(usage),
+        None => check_disk_handler(State(state)).await,
+    }
+}
+
+/// measures right away, e.g. after freeing space, instead of waiting for the next check
+async fn check_disk_handler(State(state): State<AppState>) -> Result<Response> {
+    let usage = disk::check(&state.queue_state, &state.disk, &state.storage).await?;
+    format::json(usage)
+}
@@ -1,15 +1,18 @@
 use crate::Result;
 use crate::errors;
 use crate::format;
+use crate::link::{self, LinkSource};
+use crate::middleware::{AuditChanges, AuditEntity};
 use crate::model::dto;
 use crate::model::dto::AffectedRows;
 use crate::model::dto::doc::UpdateDocReq;
+use crate::model::dto::import::ImportQuery;
 use crate::model::dto::pagination::PaginationQuery;
 use crate::model::entity::task::{EnqueueResponse, Task};
 use crate::service;
 use crate::state::AppState;
-use axum::extract::{Path, Query, State};
-use axum::http::{HeaderMap, StatusCode};
+use axum::extract::{DefaultBodyLimit, Path, Query, State};
+use axum::http::{HeaderMap, StatusCode, header};
 use axum::response::{IntoResponse, Response};
 use axum::routing::{delete, get, patch, post};
 use axum::{Json, Router};
@@ -19,14 +22,23 @@ pub fn routers() -> Router<AppState> {
     Router::new()
         .route("/parsed", get(get_parsed_docs_handler))
         .route("/parse_all", post(parse_all_doc_handler))
+        .route("/merge_duplicates", post(merge_duplicates_handler))
+        .route(
+            "/import",
+            post(import_docs_handler).layer(DefaultBodyLimit::max(IMPORT_BODY_LIMIT)),
+        )
         .route("/", get(get_docs_handler))
         .route("/", post(create_doc_handler))
         .route("/{id}", get(get_doc_handler))
         .route("/{id}/pics", get(get_pics_by_doc_id_handler))
+        .route("/{id}/refresh", post(refresh_doc_handler))
         .route("/{id}", patch(update_doc_handler))
         .route("/{id}", delete(delete_doc_handler))
 }
 
+/// telegram exports of a busy chat easily exceed the default 2MB
+const IMPORT_BODY_LIMIT: usize = 64 * 1024 * 1024;
+
 #[derive(Deserialize)]
 pub struct NewDocData {
     pub url: String,
@@ -36,11 +48,49 @@ async fn create_doc_handler(
     Json(params): Json<NewDocData>,
 ) -> Result<Response> {
     let new_doc = params.try_into()?;
-    let doc = service::doc::create_doc(&state.db_pool, new_doc).await?;
-    let response = (StatusCode::CREATED, Json(doc)).into_response();
+    let doc = service::doc::create_doc(&state.db_pool, &state.queue_state, new_doc).await?;
+    let mut response = (StatusCode::CREATED, Json(&doc)).into_response();
+    response.extensions_mut().insert(AuditEntity(doc.id.to_string()));
     Ok(response)
 }
 
+/// The body is pasted text, bookmark html or a telegram `result.json`;
+/// without `?source=` it is guessed from `Content-Type` and the content itself.
+async fn import_docs_handler(
+    State(state): State<AppState>,
+    Query(params): Query<ImportQuery>,
+    headers: HeaderMap,
+    body: String,
+) -> Result<Response> {
+    if params.enqueue && state.shutdown.is_shutting_down().await {
+        return Err(errors::Error::CustomError(
+            StatusCode::SERVICE_UNAVAILABLE,
+            errors::ErrorDetail::new(
+                "shutting_down",
+                "Server is shutting down, no new tasks accepted",
+            ),
+        ));
+    }
+    let source = params.source.unwrap_or_else(|| {
+        let content_type = headers
+            .get(header::CONTENT_TYPE)
+            .and_then(|v| v.to_str().ok())
+            .unwrap_or_default();
+        if content_type.starts_with("text/html") {
+            LinkSource::Html
+        } else if content_type.starts_with("application/json") {
+            LinkSource::Telegram
+        } else {
+            LinkSource::Auto
+        }
+    });
+    let mut report = service::import::import_links(&state.db_pool, &state.queue_state, &body, source).await?;
+    if params.enqueue {
+        service::import::enqueue_created(&state.queue_state, &mut report).await;
+    }
+    format::json(report)
+}
+
 async fn parse_all_doc_handler(State(state): State<AppState>) -> impl IntoResponse {
     if state.shutdown.is_shutting_down().await {
         return (
@@ -76,6 +126,79 @@ async fn parse_all_doc_handler(State(state): State<AppState>) -> impl IntoRespon
     };
     (StatusCode::CREATED, Json(response))
 }
+async fn merge_duplicates_handler(State(state): State<AppState>) -> impl IntoResponse {
+    if state.shutdown.is_shutting_down().await {
+        return (
+            StatusCode::SERVICE_UNAVAILABLE,
+            Json(EnqueueResponse {
+                task_id: "".to_string(),
+                task_type: "".to_string(),
+                message: "Server is shutting down, no new tasks accepted".to_string(),
+                queue_size: 0,
+            }),
+        );
+    }
+    if state.queue_state.is_merge_duplicates_pending().await {
+        return (
+            StatusCode::CONFLICT,
+            Json(EnqueueResponse {
+                task_id: "".to_string(),
+                task_type: "".to_string(),
+                message: "MergeDuplicateDocs is already queued".to_string(),
+                queue_size: 0,
+            }),
+        );
+    }
+    let task = Task::new_merge_duplicate_docs_task();
+    state.queue_state.enqueue(task.clone()).await;
+    let queue_size = state.queue_state.size().await;
+
+    let response = EnqueueResponse {
+        task_id: task.id.clone(),
+        task_type: task.task_type.into(),
+        message: "Task added to queue".to_string(),
+        queue_size,
+    };
+    (StatusCode::CREATED, Json(response))
+}
+#[derive(Deserialize)]
+pub struct RefreshQuery {
+    #[serde(default)]
+    pub rebuild: bool,
+}
+/// refetches the page, `?rebuild=true` also downloads what changed and rebuilds the cbz
+async fn refresh_doc_handler(
+    State(state): State<AppState>,
+    Path(id): Path<i32>,
+    Query(params): Query<RefreshQuery>,
+) -> Result<Response> {
+    if state.shutdown.is_shutting_down().await {
+        return Err(errors::Error::CustomError(
+            StatusCode::SERVICE_UNAVAILABLE,
+            errors::ErrorDetail::new(
+                "shutting_down",
+                "Server is shutting down, no new tasks accepted",
+            ),
+        ));
+    }
+    let doc = service::doc::get_doc_by_id(&state.db_pool, id).await?;
+    if state.queue_state.is_doc_refresh_pending(doc.id).await {
+        return Err(errors::Error::CustomError(
+            StatusCode::CONFLICT,
+            errors::ErrorDetail::new("conflict", "Doc refresh is already queued"),
+        ));
+    }
+    let task = Task::new_refresh_doc_task(doc.id, params.rebuild);
+    state.queue_state.enqueue(task.clone()).await;
+    let queue_size = state.queue_state.size().await;
+    let response = EnqueueResponse {
+        task_id: task.id.clone(),
+        task_type: task.task_type.into(),
+        message: "Task added to queue".to_string(),
+        queue_size,
+    };
+    Ok((StatusCode::CREATED, Json(response)).into_response())
+}
 async fn get_parsed_docs_handler(State(state): State<AppState>) -> Result<Response> {
     let docs = service::doc::get_parsed_docs(&state.db_pool).await?;
     format::json(docs)
@@ -108,14 +231,20 @@ async fn update_doc_handler(
     Path(id): Path<i32>,
     Json(params): Json<UpdateDocReq>,
 ) -> Result<Response> {
-    let doc = service::doc::update_doc(&state.db_pool, id, params).await?;
-    format::json(doc)
+    let before = service::doc::get_doc_by_id(&state.db_pool, id).await?;
+    let doc = service::doc::update_doc(&state.db_pool, &state.queue_state, id, params).await?;
+    let changes = service::audit::diff(&before, &doc);
+    let mut response = format::json(doc)?;
+    if let Some(changes) = changes {
+        response.extensions_mut().insert(AuditChanges(changes));
+    }
+    Ok(response)
 }
 async fn delete_doc_handler(
     State(state): State<AppState>,
     Path(id): Path<i32>,
 ) -> Result<Response> {
-    let count = service::doc::delete_doc_by_id(&state.db_pool, id).await?;
+    let count = service::doc::delete_doc_by_id(&state.db_pool, &state.queue_state, id).await?;
     let affected_rows = AffectedRows::new(count);
     format::json(affected_rows)
 }
@@ -124,13 +253,7 @@ impl TryFrom<NewDocData> for dto::doc::CreateDocReq {
     type Error = errors::Error;
 
     fn try_from(value: NewDocData) -> Result<Self, Self::Error> {
-        if validator::ValidateUrl::validate_url(&value.url) {
-            Ok(Self { url: value.url })
-        } else {
-            Err(errors::Error::Message(format!(
-                "Invalid url: {}",
-                value.url
-            )))
-        }
+        let url = link::canonicalize(&value.url).map_err(errors::Error::Message)?;
+        Ok(Self { url })
     }
 }
@@ -0,0 +1,108 @@
+use crate::Result;
+use crate::errors::{Error, ErrorDetail};
+use crate::format;
+use crate::middleware::AuditEntity;
+use crate::model::dto::AffectedRows;
+use crate::model::dto::feed::{CreateFeedReq, UpdateFeedReq};
+use crate::model::entity::task::{EnqueueResponse, Task};
+use crate::service;
+use crate::state::AppState;
+use axum::extract::{Path, State};
+use axum::http::StatusCode;
+use axum::response::{IntoResponse, Response};
+use axum::routing::{get, post};
+use axum::{Json, Router};
+
+pub fn routers() -> Router<AppState> {
+    Router::new()
+        .route("/", get(get_feeds_handler).post(create_feed_handler))
+        .route(
+            "/{id}",
+            get(get_feed_handler)
+                .patch(update_feed_handler)
+                .delete(delete_feed_handler),
+        )
+        .route("/{id}/items", get(get_feed_items_handler))
+        .route("/{id}/poll", post(poll_feed_handler))
+}
+
+fn invalid_feed(msg: String) -> Error {
+    Error::CustomError(
+        StatusCode::BAD_REQUEST,
+        ErrorDetail::new("invalid_feed".to_string(), msg),
+    )
+}
+
+async fn get_feeds_handler(State(state): State<AppState>) -> Result<Response> {
+    let feeds = service::feed::get_feeds(&state.db_pool).await?;
+    format::json(feeds)
+}
+
+async fn create_feed_handler(
+    State(state): State<AppState>,
+    Json(params): Json<CreateFeedReq>,
+) -> Result<Response> {
+    params.validate().map_err(invalid_feed)?;
+    let feed = service::feed::create_feed(&state.db_pool, params).await?;
+    let mut response = (StatusCode::CREATED, Json(&feed)).into_response();
+    response.extensions_mut().insert(AuditEntity(feed.id.to_string()));
+    Ok(response)
+}
+
+async fn get_feed_handler(
+    State(state): State<AppState>,
+    Path(id): Path<i32>,
+) -> Result<Response> {
+    let feed = service::feed::get_feed_by_id(&state.db_pool, id).await?;
+    format::json(feed)
+}
+
+async fn update_feed_handler(
+    State(state): State<AppState>,
+    Path(id): Path<i32>,
+    Json(params): Json<UpdateFeedReq>,
+) -> Result<Response> {
+    params.validate().map_err(invalid_feed)?;
+    let feed = service::feed::update_feed(&state.db_pool, id, params).await?;
+    format::json(feed)
+}
+
+async fn delete_feed_handler(
+    State(state): State<AppState>,
+    Path(id): Path<i32>,
+) -> Result<Response> {
+    let count = service::feed::delete_feed_by_id(&state.db_pool, id).await?;
+    format::json(AffectedRows::new(count))
+}
+
+async fn get_feed_items_handler(
+    State(state): State<AppState>,
+    Path(id): Path<i32>,
+) -> Result<Response> {
+    let items = service::feed::get_feed_items(&state.db_pool, id).await?;
+    format::json(items)
+}
+
+/// polls right away instead of waiting for the interval
+async fn poll_feed_handler(
+    State(state): State<AppState>,
+    Path(id): Path<i32>,
+) -> Result<Response> {
+    let feed = service::feed::get_feed_by_id(&state.db_pool, id).await?;
+    if state.queue_state.is_feed_poll_pending(feed.id).await {
+        return Err(Error::CustomError(
+            StatusCode::CONFLICT,
+            ErrorDetail::new("conflict", "Feed poll is already queued"),
+        ));
+    }
+    let task = Task::new_poll_feed_task(feed.id);
+    state.queue_state.enqueue(task.clone()).await;
+    let queue_size = state.queue_state.size().await;
+    let response = EnqueueResponse {
+        task_id: task.id.clone(),
+        task_type: task.task_type.into(),
+        message: "Task added to queue".to_string(),
+        queue_size,
+    };
+    Ok((StatusCode::CREATED, Json(response)).into_response())
+}
@@ -1,25 +1,29 @@
+use crate::middleware::{extract_token, resolve_identity, TeleGrabRequestId};
+use crate::model::entity::api_token::ApiIdentity;
 use crate::schema::{create_schema, GallerySchema};
 use crate::state::AppState;
-use async_graphql::http::GraphiQLSource;
-use async_graphql_axum::{GraphQL, GraphQLSubscription};
+use async_graphql::http::{GraphiQLSource, ALL_WEBSOCKET_PROTOCOLS};
+use async_graphql::Data;
+use async_graphql_axum::{GraphQLProtocol, GraphQLRequest, GraphQLResponse, GraphQLWebSocket};
 use axum::{
-    response::{self, IntoResponse},
-    routing::{get, post_service},
-    Router,
+    response::{self, IntoResponse, Response},
+    routing::get,
+    Extension, Router,
 };
-use axum::extract::State;
+use axum::extract::{State, WebSocketUpgrade};
 use axum::http::header;
 
 pub fn routers(state: &AppState) -> Router<AppState> {
-    let schema = create_schema(state.db_pool.clone(), state.queue_state.clone());
-    let graphql_service = GraphQL::new(schema.clone());
-    let subscription_service = GraphQLSubscription::new(schema.clone());
+    let schema = create_schema(
+        state.db_pool.clone(),
+        state.queue_state.clone(),
+        state.http_client.clone(),
+    );
     Router::new()
-        .route("/", get(graphiql))
-        .route("/", post_service(graphql_service))
-        .route_service("/ws", subscription_service)
+        .route("/", get(graphiql).post(graphql_handler))
+        .route("/ws", get(graphql_ws_handler))
         .route("/schema", get(export_schema))
-        .with_state(schema)
+        .layer(Extension(schema))
 }
 async fn graphiql() -> impl IntoResponse {
     response::Html(
@@ -29,7 +33,56 @@ async fn graphiql() -> impl IntoResponse {
             .finish(),
     )
 }
-async fn export_schema(State(schema):State<GallerySchema>)-> impl IntoResponse{
+async fn graphql_handler(
+    Extension(schema): Extension<GallerySchema>,
+    Extension(identity): Extension<ApiIdentity>,
+    Extension(request_id): Extension<TeleGrabRequestId>,
+    req: GraphQLRequest,
+) -> GraphQLResponse {
+    schema
+        .execute(req.into_inner().data(identity).data(request_id))
+        .await
+        .into()
+}
+async fn graphql_ws_handler(
+    State(state): State<AppState>,
+    Extension(schema): Extension<GallerySchema>,
+    protocol: GraphQLProtocol,
+    upgrade: WebSocketUpgrade,
+) -> Response {
+    upgrade
+        .protocols(ALL_WEBSOCKET_PROTOCOLS)
+        .on_upgrade(move |stream| {
+            GraphQLWebSocket::new(stream, schema, protocol)
+                .on_connection_init(move |payload| async move {
+                    let token = payload_token(&payload);
+                    let identity = resolve_identity(&state, token.as_deref())
+                        .await
+                        .map_err(|e| async_graphql::Error::new(e.to_string()))?;
+                    let mut data = Data::default();
+                    data.insert(identity);
+                    Ok(data)
+                })
+                .serve()
+        })
+}
+/// accepts `{"token": ".."}`, `{"authToken": ".."}` or `{"Authorization": "Bearer .."}`
+fn payload_token(payload: &serde_json::Value) -> Option<String> {
+    let mut headers = header::HeaderMap::new();
+    for key in ["Authorization", "authorization"] {
+        if let Some(v) = payload.get(key).and_then(|v| v.as_str())
+            && let Ok(v) = v.parse()
+        {
+            headers.insert(header::AUTHORIZATION, v);
+        }
+    }
+    ["token", "authToken"]
+        .iter()
+        .find_map(|key| payload.get(key).and_then(|v| v.as_str()))
+        .map(|t| t.to_string())
+        .or_else(|| extract_token(&headers, None))
+}
+async fn export_schema(Extension(schema): Extension<GallerySchema>) -> impl IntoResponse {
     let sdl_content = schema.sdl();
     let response_headers = [
         (header::CONTENT_TYPE, "application/graphql; charset=utf-8"),
@@ -42,4 +95,4 @@ async fn export_schema(State(schema):State<GallerySchema>)-> impl IntoResponse{
 
     // 组合「响应头 + SDL内容」返回
     (response_headers, sdl_content)
-}
\ No newline at end of file
+}
@@ -0,0 +1,112 @@
+use crate::Result;
+use crate::errors::{Error, ErrorDetail};
+use crate::format;
+use crate::model::entity::gc::GcAction;
+use crate::model::entity::task::{EnqueueResponse, Task};
+use crate::service;
+use crate::state::AppState;
+use axum::extract::{Path, Query, State};
+use axum::http::StatusCode;
+use axum::response::{IntoResponse, Response};
+use axum::routing::{get, post};
+use axum::{Json, Router};
+use serde::Deserialize;
+
+pub fn routers() -> Router<AppState> {
+    Router::new()
+        .route("/run", post(collect_garbage_handler))
+        .route("/reports", get(get_reports_handler))
+        .route("/reports/latest", get(get_latest_report_handler))
+        .route("/reports/{id}", get(get_report_handler))
+        .route("/reports/{id}/apply", post(apply_report_handler))
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CollectGarbageQuery {
+    /// only a report unless set to `false`
+    #[serde(default = "default_dry_run")]
+    pub dry_run: bool,
+    #[serde(default)]
+    pub action: GcAction,
+}
+
+fn default_dry_run() -> bool {
+    true
+}
+
+#[derive(Debug, Deserialize)]
+pub struct ApplyReportQuery {
+    #[serde(default)]
+    pub action: GcAction,
+}
+
+async fn enqueue(state: &AppState, task: Task) -> Result<Response> {
+    if state.shutdown.is_shutting_down().await {
+        return Err(Error::CustomError(
+            StatusCode::SERVICE_UNAVAILABLE,
+            ErrorDetail::new("shutting_down", "Server is shutting down, no new tasks accepted"),
+        ));
+    }
+    if state.queue_state.is_gc_pending().await {
+        return Err(Error::CustomError(
+            StatusCode::CONFLICT,
+            ErrorDetail::new("conflict", "Garbage collection is already queued"),
+        ));
+    }
+    state.queue_state.enqueue(task.clone()).await;
+    let queue_size = state.queue_state.size().await;
+    let response = EnqueueResponse {
+        task_id: task.id.clone(),
+        task_type: task.task_type.into(),
+        message: "Task added to queue".to_string(),
+        queue_size,
+    };
+    Ok((StatusCode::CREATED, Json(response)).into_response())
+}
+
+/// `?dryRun=false&action=delete` removes right away, the default only stores a report
+async fn collect_garbage_handler(
+    State(state): State<AppState>,
+    Query(params): Query<CollectGarbageQuery>,
+) -> Result<Response> {
+    let task = Task::new_collect_garbage_task(params.dry_run, params.action);
+    enqueue(&state, task).await
+}
+
+async fn get_reports_handler(State(state): State<AppState>) -> Result<Response> {
+    let reports = service::gc::get_reports(&state.db_pool).await?;
+    format::json(reports)
+}
+
+async fn get_latest_report_handler(State(state): State<AppState>) -> Result<Response> {
+    let report = service::gc::get_latest_report(&state.db_pool)
+        .await?
+        .ok_or(Error::NotFound)?;
+    format::json(report)
+}
+
+async fn get_report_handler(
+    State(state): State<AppState>,
+    Path(id): Path<i32>,
+) -> Result<Response> {
+    let report = service::gc::get_report_by_id(&state.db_pool, id).await?;
+    format::json(report)
+}
+
+/// the confirmation of a dry run, items a doc took back since are skipped
+async fn apply_report_handler(
+    State(state): State<AppState>,
+    Path(id): Path<i32>,
+    Query(params): Query<ApplyReportQuery>,
+) -> Result<Response> {
+    let report = service::gc::get_report_by_id(&state.db_pool, id).await?;
+    if report.applied_at.is_some() {
+        return Err(Error::CustomError(
+            StatusCode::CONFLICT,
+            ErrorDetail::new("conflict", "Report was already applied"),
+        ));
+    }
+    let task = Task::new_apply_gc_report_task(report.id, params.action);
+    enqueue(&state, task).await
+}
@@ -1,5 +1,38 @@
-use axum::response::IntoResponse;
+use crate::format;
+use crate::health;
+use crate::model::entity::health::CheckStatus;
+use crate::state::AppState;
+use crate::Result;
+use axum::extract::State;
+use axum::http::StatusCode;
+use axum::response::{IntoResponse, Response};
+use axum::Json;
 
 pub async fn health() -> impl IntoResponse {
     "OK"
 }
+
+/// the process is up and serving, nothing else is checked
+pub async fn live() -> Result<Response> {
+    format::json(serde_json::json!({ "live": true }))
+}
+
+/// 503 when a dependency is down, so systemd and load balancers hold traffic back
+pub async fn ready(State(state): State<AppState>) -> Response {
+    let readiness = health::check_ready(&state).await;
+    if !readiness.ready {
+        let failed: Vec<&str> = readiness
+            .checks
+            .iter()
+            .filter(|c| c.status == CheckStatus::Fail)
+            .map(|c| c.name.as_str())
+            .collect();
+        tracing::warn!("Not ready, failed checks: {}", failed.join(", "));
+    }
+    let status = if readiness.ready {
+        StatusCode::OK
+    } else {
+        StatusCode::SERVICE_UNAVAILABLE
+    };
+    (status, Json(readiness)).into_response()
+}
@@ -0,0 +1,116 @@
+use crate::Result;
+use crate::errors::{Error, ErrorDetail};
+use crate::format;
+use crate::library;
+use crate::model::entity::library::RepairAction;
+use crate::model::entity::task::{EnqueueResponse, Task, TaskType};
+use crate::service;
+use crate::state::AppState;
+use axum::extract::{Path, State};
+use axum::http::StatusCode;
+use axum::response::{IntoResponse, Response};
+use axum::routing::{get, post};
+use axum::{Json, Router};
+
+pub fn routers() -> Router<AppState> {
+    Router::new()
+        .route("/verify", post(verify_library_handler))
+        .route("/reports", get(get_reports_handler))
+        .route("/reports/latest", get(get_latest_report_handler))
+        .route("/reports/{id}", get(get_report_handler))
+        .route("/reports/{id}/repair", post(repair_report_handler))
+        .route("/repair", post(repair_handler))
+}
+
+async fn ensure_accepting_tasks(state: &AppState) -> Result<()> {
+    if state.shutdown.is_shutting_down().await {
+        return Err(Error::CustomError(
+            StatusCode::SERVICE_UNAVAILABLE,
+            ErrorDetail::new("shutting_down", "Server is shutting down, no new tasks accepted"),
+        ));
+    }
+    Ok(())
+}
+
+fn enqueued(tasks: Vec<Task>, queue_size: usize) -> Vec<EnqueueResponse> {
+    tasks
+        .into_iter()
+        .map(|task| EnqueueResponse {
+            task_id: task.id,
+            task_type: task.task_type.into(),
+            message: "Task added to queue".to_string(),
+            queue_size,
+        })
+        .collect()
+}
+
+async fn verify_library_handler(State(state): State<AppState>) -> Result<Response> {
+    ensure_accepting_tasks(&state).await?;
+    if state
+        .queue_state
+        .is_task_pending(&TaskType::VerifyLibrary)
+        .await
+    {
+        return Err(Error::CustomError(
+            StatusCode::CONFLICT,
+            ErrorDetail::new("conflict", "VerifyLibrary is already queued"),
+        ));
+    }
+    let task = Task::new_verify_library_task();
+    state.queue_state.enqueue(task.clone()).await;
+    let queue_size = state.queue_state.size().await;
+    let response = enqueued(vec![task], queue_size).remove(0);
+    Ok((StatusCode::CREATED, Json(response)).into_response())
+}
+
+async fn get_reports_handler(State(state): State<AppState>) -> Result<Response> {
+    let reports = service::library::get_reports(&state.db_pool).await?;
+    format::json(reports)
+}
+
+async fn get_latest_report_handler(State(state): State<AppState>) -> Result<Response> {
+    let report = service::library::get_latest_report(&state.db_pool)
+        .await?
+        .ok_or(Error::NotFound)?;
+    format::json(report)
+}
+
+async fn get_report_handler(
+    State(state): State<AppState>,
+    Path(id): Path<i32>,
+) -> Result<Response> {
+    let report = service::library::get_report_by_id(&state.db_pool, id).await?;
+    format::json(report)
+}
+
+/// queues the repair of every issue in the report
+async fn repair_report_handler(
+    State(state): State<AppState>,
+    Path(id): Path<i32>,
+) -> Result<Response> {
+    ensure_accepting_tasks(&state).await?;
+    let report = service::library::get_report_by_id(&state.db_pool, id).await?;
+    let tasks = library::repair_all(&state.db_pool, &state.queue_state, &report.issues).await?;
+    let queue_size = state.queue_state.size().await;
+    Ok((StatusCode::CREATED, Json(enqueued(tasks, queue_size))).into_response())
+}
+
+/// the `repair` of a single issue, e.g. `{"kind": "rearchive", "id": 12}`
+async fn repair_handler(
+    State(state): State<AppState>,
+    Json(action): Json<RepairAction>,
+) -> Result<Response> {
+    ensure_accepting_tasks(&state).await?;
+    let task = library::repair(&state.db_pool, &state.queue_state, action)
+        .await
+        .map_err(|err| match err {
+            Error::Message(msg) => Error::CustomError(
+                StatusCode::BAD_REQUEST,
+                ErrorDetail::new("invalid_repair".to_string(), msg),
+            ),
+            err => err,
+        })?;
+    let queue_size = state.queue_state.size().await;
+    let response = enqueued(vec![task], queue_size).remove(0);
+    Ok((StatusCode::CREATED, Json(response)).into_response())
+}
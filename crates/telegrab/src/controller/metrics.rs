@@ -0,0 +1,12 @@
+use crate::metrics::metrics;
+use crate::state::AppState;
+use axum::extract::State;
+use axum::http::header;
+use axum::response::{IntoResponse, Response};
+use prometheus::TEXT_FORMAT;
+
+/// everything in the Prometheus text format
+pub async fn metrics_handler(State(state): State<AppState>) -> Response {
+    let body = metrics().render(&state.queue_state, &state.db_pool).await;
+    ([(header::CONTENT_TYPE, TEXT_FORMAT)], body).into_response()
+}
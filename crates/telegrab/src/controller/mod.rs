@@ -1,7 +1,16 @@
+pub mod admin;
 pub mod assets;
+pub mod audit;
 pub mod cbz;
+pub mod disk;
 pub mod doc;
+pub mod feed;
+pub mod gc;
 pub mod health_check;
+pub mod library;
+pub mod metrics;
 pub mod pic;
+pub mod schedule;
 pub mod task;
+pub mod webhook;
 pub mod gallery;
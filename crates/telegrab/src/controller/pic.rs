@@ -1,4 +1,5 @@
 use crate::Result;
+use crate::middleware::AuditEntity;
 use crate::model::dto::AffectedRows;
 use crate::model::dto::pagination::PaginationQuery;
 use crate::model::dto::pic::MutatePicReq;
@@ -46,7 +47,8 @@ async fn create_pic_handler(
     Json(params): Json<MutatePicReq>,
 ) -> Result<Response> {
     let pic = service::pic::create_pic(&state.db_pool, params).await?;
-    let response = (StatusCode::CREATED, Json(pic)).into_response();
+    let mut response = (StatusCode::CREATED, Json(&pic)).into_response();
+    response.extensions_mut().insert(AuditEntity(pic.id.to_string()));
     Ok(response)
 }
 
@@ -0,0 +1,125 @@
+use crate::Result;
+use crate::errors::{Error, ErrorDetail};
+use crate::format;
+use crate::middleware::AuditEntity;
+use crate::model::dto::AffectedRows;
+use crate::model::dto::schedule::{CreateScheduleReq, UpdateScheduleReq};
+use crate::scheduler;
+use crate::service;
+use crate::state::AppState;
+use axum::extract::{Path, State};
+use axum::http::StatusCode;
+use axum::response::{IntoResponse, Response};
+use axum::routing::{get, post};
+use axum::{Json, Router};
+use time::OffsetDateTime;
+
+pub fn routers() -> Router<AppState> {
+    Router::new()
+        .route("/", get(get_schedules_handler).post(create_schedule_handler))
+        .route(
+            "/{id}",
+            get(get_schedule_handler)
+                .patch(update_schedule_handler)
+                .delete(delete_schedule_handler),
+        )
+        .route("/{id}/runs", get(get_schedule_runs_handler))
+        .route("/{id}/run", post(run_schedule_handler))
+}
+
+fn invalid_schedule(msg: String) -> Error {
+    Error::CustomError(
+        StatusCode::BAD_REQUEST,
+        ErrorDetail::new("invalid_schedule".to_string(), msg),
+    )
+}
+
+async fn get_schedules_handler(State(state): State<AppState>) -> Result<Response> {
+    let schedules = service::schedule::get_schedules(&state.db_pool).await?;
+    format::json(schedules)
+}
+
+async fn create_schedule_handler(
+    State(state): State<AppState>,
+    Json(params): Json<CreateScheduleReq>,
+) -> Result<Response> {
+    params.validate().map_err(invalid_schedule)?;
+    let schedule = match scheduler::create_schedule(&state.db_pool, params).await {
+        Err(Error::Sqlx(err))
+            if err.as_database_error().is_some_and(|e| e.is_unique_violation()) =>
+        {
+            return Err(Error::CustomError(
+                StatusCode::CONFLICT,
+                ErrorDetail::new("conflict", "A schedule with this name already exists"),
+            ));
+        }
+        result => result?,
+    };
+    let mut response = (StatusCode::CREATED, Json(&schedule)).into_response();
+    response.extensions_mut().insert(AuditEntity(schedule.id.to_string()));
+    Ok(response)
+}
+
+async fn get_schedule_handler(
+    State(state): State<AppState>,
+    Path(id): Path<i32>,
+) -> Result<Response> {
+    let schedule = service::schedule::get_schedule_by_id(&state.db_pool, id).await?;
+    format::json(schedule)
+}
+
+/// enable/disable or change the cron expression
+async fn update_schedule_handler(
+    State(state): State<AppState>,
+    Path(id): Path<i32>,
+    Json(params): Json<UpdateScheduleReq>,
+) -> Result<Response> {
+    params.validate().map_err(invalid_schedule)?;
+    let schedule = scheduler::update_schedule(&state.db_pool, id, params).await?;
+    format::json(schedule)
+}
+
+async fn delete_schedule_handler(
+    State(state): State<AppState>,
+    Path(id): Path<i32>,
+) -> Result<Response> {
+    let count = service::schedule::delete_schedule_by_id(&state.db_pool, id).await?;
+    format::json(AffectedRows::new(count))
+}
+
+async fn get_schedule_runs_handler(
+    State(state): State<AppState>,
+    Path(id): Path<i32>,
+) -> Result<Response> {
+    let runs = service::schedule::get_schedule_runs(&state.db_pool, id).await?;
+    format::json(runs)
+}
+
+/// runs right away, the next scheduled run stays as it is
+async fn run_schedule_handler(
+    State(state): State<AppState>,
+    Path(id): Path<i32>,
+) -> Result<Response> {
+    if state.shutdown.is_shutting_down().await {
+        return Err(Error::CustomError(
+            StatusCode::SERVICE_UNAVAILABLE,
+            ErrorDetail::new("shutting_down", "Server is shutting down, no new tasks accepted"),
+        ));
+    }
+    let schedule = service::schedule::get_schedule_by_id(&state.db_pool, id).await?;
+    let run = scheduler::run_schedule(
+        &state.db_pool,
+        &state.queue_state,
+        &schedule,
+        OffsetDateTime::now_utc(),
+        None,
+    )
+    .await?;
+    if run.skipped {
+        return Err(Error::CustomError(
+            StatusCode::CONFLICT,
+            ErrorDetail::new("conflict", "The task of this schedule is already queued"),
+        ));
+    }
+    Ok((StatusCode::CREATED, Json(run)).into_response())
+}
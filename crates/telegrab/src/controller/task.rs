@@ -1,20 +1,26 @@
+use crate::model::entity::doc::DocStatus;
 use crate::model::entity::task::{
     ActiveTaskResponse, CleanupRequest, CleanupResponse, EnqueueRequest, EnqueueResponse,
-    QueueInfo, QueueStats, Task, TaskStatus,
+    EventFilter, NumberedEvent, QueueEvent, QueueInfo, QueueSnapshot, QueueStats, Task,
+    TaskStatus,
 };
 use crate::service;
-use crate::state::AppState;
-use axum::extract::State;
-use axum::http::StatusCode;
+use crate::state::{AppState, QueueState};
+use axum::extract::{Query, State};
+use axum::http::{HeaderMap, StatusCode};
 use axum::response::sse::{Event, KeepAlive};
 use axum::response::{IntoResponse, Sse};
 use axum::routing::{get, post};
 use axum::{Json, Router};
 use futures_util::stream::StreamExt;
 use futures_util::{Stream, stream};
+use serde_json::json;
 use std::convert::Infallible;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicU64, Ordering};
 use std::time::Duration;
 use tokio_stream::wrappers::BroadcastStream;
+use tokio_stream::wrappers::errors::BroadcastStreamRecvError;
 
 pub fn routers() -> Router<AppState> {
     Router::new()
@@ -102,22 +108,13 @@ async fn enqueue_task(
             );
         }
     };
-    let doc_status = doc.status;
-    let task = match doc_status {
-        0 => Task::new_html_parse_task(payload.id),
-        1 => Task::new_doc_download_task(payload.id),
-        2 | 3 => Task::new_cbz_archive_task(payload.id),
-        _ => {
-            return (
-                StatusCode::BAD_REQUEST,
-                Json(EnqueueResponse {
-                    task_id: "".to_string(),
-                    task_type: "".to_string(),
-                    message: "Invalid task status".to_string(),
-                    queue_size: 0,
-                }),
-            );
-        }
+    let task = match doc.status {
+        DocStatus::New => Task::new_html_parse_task(payload.id),
+        DocStatus::Parsed | DocStatus::Stale => Task::new_doc_download_task(payload.id),
+        DocStatus::Downloaded | DocStatus::Archived => Task::new_cbz_archive_task(payload.id),
+        // retries whatever gave up
+        DocStatus::Failed if doc.page_title.is_none() => Task::new_html_parse_task(payload.id),
+        DocStatus::Failed => Task::new_doc_download_task(payload.id),
     };
     state.queue_state.enqueue(task.clone()).await;
     let queue_size = state.queue_state.size().await;
@@ -130,33 +127,69 @@ async fn enqueue_task(
     };
     (StatusCode::CREATED, Json(response))
 }
+/// Every queue event, numbered in `id:`. A client reconnecting with `Last-Event-ID`
+/// gets the events it missed, or a `snapshot` event when they are no longer kept;
+/// a client that falls behind gets a `snapshot` as well. Filtered by [`EventFilter`].
 pub async fn sse_handler(
     State(state): State<AppState>,
+    Query(filter): Query<EventFilter>,
+    headers: HeaderMap,
 ) -> Sse<impl Stream<Item = Result<Event, Infallible>>> {
+    let last_event_id = headers
+        .get("last-event-id")
+        .and_then(|v| v.to_str().ok())
+        .and_then(|v| v.trim().parse::<u64>().ok());
+    // subscribed before the journal is read, no event falls in between
     let rx = state.queue_state.sender.subscribe();
     let mut shutdown_rx = state.shutdown.get_shutdown_rx().await;
-    let sse_stream = StreamExt::chain(
-        StreamExt::filter_map(
-            BroadcastStream::new(rx).take_until(async move {
-                let _ = shutdown_rx.recv().await;
-                tracing::info!("Shutdown signal received. Closing SSE stream.");
-            }),
-            |event| async move {
+    let queue_state = state.queue_state.clone();
+    let connected = json!({
+        "message": "Connected to SSE stream",
+        "lastEventId": queue_state.last_event_id(),
+    });
+    let mut head = vec![Event::default().event("connected").data(connected.to_string())];
+    // live events up to here were replayed or are part of the snapshot
+    let mut seen = 0;
+    if let Some(last_event_id) = last_event_id {
+        match queue_state.events_since(last_event_id) {
+            Some(events) => {
+                seen = events.last().map_or(last_event_id, |e| e.id);
+                for numbered in &events {
+                    head.extend(sse_event(&queue_state, &filter, numbered).await);
+                }
+            }
+            None => {
+                let snapshot = queue_state.snapshot().await;
+                seen = snapshot.last_event_id;
+                head.push(snapshot_event(&snapshot));
+            }
+        }
+    }
+    let seen = Arc::new(AtomicU64::new(seen));
+    let live = BroadcastStream::new(rx)
+        .take_until(async move {
+            let _ = shutdown_rx.recv().await;
+            tracing::info!("Shutdown signal received. Closing SSE stream.");
+        })
+        .then(move |event| {
+            let queue_state = queue_state.clone();
+            let filter = filter.clone();
+            let seen = seen.clone();
+            async move {
                 match event {
-                    Ok(event) => {
-                        let json = serde_json::to_string(&event).unwrap();
-                        Some(Ok(Event::default().data(json)))
+                    Ok(numbered) if numbered.id <= seen.load(Ordering::Relaxed) => None,
+                    Ok(numbered) => sse_event(&queue_state, &filter, &numbered).await,
+                    Err(BroadcastStreamRecvError::Lagged(missed)) => {
+                        tracing::warn!("SSE client fell behind, {} events missed, resync", missed);
+                        let snapshot = queue_state.snapshot().await;
+                        seen.fetch_max(snapshot.last_event_id, Ordering::Relaxed);
+                        Some(snapshot_event(&snapshot))
                     }
-                    Err(_) => None,
                 }
-            },
-        ),
-        stream::once(async {
-            Ok(Event::default()
-                .event("connected")
-                .data("{\"message\":\"Connected to SSE stream\"}"))
-        }),
-    );
+            }
+        })
+        .filter_map(|event| async move { event.map(Ok) });
+    let sse_stream = stream::iter(head.into_iter().map(Ok)).chain(live);
 
     Sse::new(sse_stream).keep_alive(
         KeepAlive::new()
@@ -165,6 +198,35 @@ pub async fn sse_handler(
     )
 }
 
+/// `None` when the filter drops it
+async fn sse_event(
+    queue_state: &QueueState,
+    filter: &EventFilter,
+    numbered: &NumberedEvent,
+) -> Option<Event> {
+    let task = match &numbered.event {
+        QueueEvent::TaskRemoved(task_id) | QueueEvent::TaskProgress(task_id, _)
+            if filter.has_task_filter() =>
+        {
+            queue_state.get_task(task_id).await
+        }
+        _ => None,
+    };
+    if !filter.wants(&numbered.event, task.as_ref()) {
+        return None;
+    }
+    let json = serde_json::to_string(&numbered.event).unwrap();
+    Some(Event::default().id(numbered.id.to_string()).data(json))
+}
+
+fn snapshot_event(snapshot: &QueueSnapshot) -> Event {
+    let json = serde_json::to_string(snapshot).unwrap();
+    Event::default()
+        .event("snapshot")
+        .id(snapshot.last_event_id.to_string())
+        .data(json)
+}
+
 async fn cleanup_completed_tasks(
     State(state): State<AppState>,
     Json(payload): Json<CleanupRequest>,
@@ -0,0 +1,102 @@
+use crate::Result;
+use crate::errors::{Error, ErrorDetail};
+use crate::format;
+use crate::middleware::AuditEntity;
+use crate::model::dto::AffectedRows;
+use crate::model::dto::pagination::PaginationQuery;
+use crate::model::dto::webhook::{CreateWebhookReq, UpdateWebhookReq};
+use crate::service;
+use crate::state::AppState;
+use crate::webhook;
+use axum::extract::{Path, Query, State};
+use axum::http::{HeaderMap, StatusCode};
+use axum::response::{IntoResponse, Response};
+use axum::routing::{get, post};
+use axum::{Json, Router};
+
+pub fn routers() -> Router<AppState> {
+    Router::new()
+        .route("/", get(get_webhooks_handler).post(create_webhook_handler))
+        .route(
+            "/{id}",
+            get(get_webhook_handler)
+                .patch(update_webhook_handler)
+                .delete(delete_webhook_handler),
+        )
+        .route("/{id}/deliveries", get(get_deliveries_handler))
+        .route("/{id}/test", post(test_webhook_handler))
+}
+
+fn invalid_webhook(msg: String) -> Error {
+    Error::CustomError(
+        StatusCode::BAD_REQUEST,
+        ErrorDetail::new("invalid_webhook".to_string(), msg),
+    )
+}
+
+async fn get_webhooks_handler(State(state): State<AppState>) -> Result<Response> {
+    let webhooks = service::webhook::get_webhooks(&state.db_pool).await?;
+    format::json(webhooks)
+}
+
+async fn create_webhook_handler(
+    State(state): State<AppState>,
+    Json(params): Json<CreateWebhookReq>,
+) -> Result<Response> {
+    params.validate().map_err(invalid_webhook)?;
+    let webhook = service::webhook::create_webhook(&state.db_pool, params).await?;
+    state.webhooks.invalidate().await;
+    let mut response = (StatusCode::CREATED, Json(&webhook)).into_response();
+    response.extensions_mut().insert(AuditEntity(webhook.id.to_string()));
+    Ok(response)
+}
+
+async fn get_webhook_handler(
+    State(state): State<AppState>,
+    Path(id): Path<i32>,
+) -> Result<Response> {
+    let webhook = service::webhook::get_webhook_by_id(&state.db_pool, id).await?;
+    format::json(webhook)
+}
+
+async fn update_webhook_handler(
+    State(state): State<AppState>,
+    Path(id): Path<i32>,
+    Json(params): Json<UpdateWebhookReq>,
+) -> Result<Response> {
+    params.validate().map_err(invalid_webhook)?;
+    let webhook = service::webhook::update_webhook(&state.db_pool, id, params).await?;
+    state.webhooks.invalidate().await;
+    format::json(webhook)
+}
+
+async fn delete_webhook_handler(
+    State(state): State<AppState>,
+    Path(id): Path<i32>,
+) -> Result<Response> {
+    let count = service::webhook::delete_webhook_by_id(&state.db_pool, id).await?;
+    state.webhooks.invalidate().await;
+    format::json(AffectedRows::new(count))
+}
+
+/// newest first, `?_start=&_end=` pages
+async fn get_deliveries_handler(
+    State(state): State<AppState>,
+    Path(id): Path<i32>,
+    Query(pagination): Query<PaginationQuery>,
+) -> Result<Response> {
+    let deliveries = service::webhook::get_deliveries(&state.db_pool, id, &pagination).await?;
+    let mut headers = HeaderMap::new();
+    headers.insert("x-total-count", deliveries.total.to_string().parse()?);
+    Ok((headers, Json(deliveries.data)).into_response())
+}
+
+/// sends a `ping` right away and returns the delivery with the outcome of the attempt
+async fn test_webhook_handler(
+    State(state): State<AppState>,
+    Path(id): Path<i32>,
+) -> Result<Response> {
+    let webhook = service::webhook::get_webhook_by_id(&state.db_pool, id).await?;
+    let delivery = webhook::test_fire(&state.db_pool, &state.webhook, &webhook).await?;
+    format::json(delivery)
+}
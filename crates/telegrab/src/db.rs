@@ -0,0 +1,115 @@
+use crate::configuration::{DatabaseBackend, DatabaseSettings};
+use crate::Result;
+use sqlx_postgres::{PgPool, PgPoolOptions};
+#[cfg(feature = "sqlite")]
+use sqlx_sqlite::{SqliteConnectOptions, SqliteJournalMode, SqlitePool, SqlitePoolOptions};
+use std::time::Duration;
+
+/// The database of an install, chosen by `database.backend`.
+/// Services take it as `&DbPool` and run the same sql on either backend
+/// through [`with_pool!`], dialect differences are handled where they come up.
+#[derive(Debug, Clone)]
+pub enum DbPool {
+    Postgres(PgPool),
+    #[cfg(feature = "sqlite")]
+    Sqlite(SqlitePool),
+}
+
+/// Runs `$body` with `$p` bound to the pool of the configured backend.
+macro_rules! with_pool {
+    ($pool:expr, $p:ident => $body:expr) => {
+        match $pool {
+            $crate::db::DbPool::Postgres($p) => $body,
+            #[cfg(feature = "sqlite")]
+            $crate::db::DbPool::Sqlite($p) => $body,
+        }
+    };
+}
+pub(crate) use with_pool;
+
+impl DbPool {
+    /// Connects on first use, so the server starts while the database is down.
+    pub fn connect_lazy(settings: &DatabaseSettings, acquire_timeout: Duration) -> Result<Self> {
+        match settings.backend {
+            DatabaseBackend::Postgres => Ok(DbPool::Postgres(
+                PgPoolOptions::new()
+                    .acquire_timeout(acquire_timeout)
+                    .connect_lazy_with(settings.with_db()),
+            )),
+            #[cfg(feature = "sqlite")]
+            DatabaseBackend::Sqlite => Ok(DbPool::Sqlite(
+                SqlitePoolOptions::new()
+                    .acquire_timeout(acquire_timeout)
+                    .connect_lazy_with(sqlite_options(settings)?),
+            )),
+            #[cfg(not(feature = "sqlite"))]
+            DatabaseBackend::Sqlite => Err(sqlite_disabled()),
+        }
+    }
+
+    /// Connects right away, for commands that fail early without a database.
+    pub async fn connect(settings: &DatabaseSettings, acquire_timeout: Duration) -> Result<Self> {
+        let pool = Self::connect_lazy(settings, acquire_timeout)?;
+        with_pool!(&pool, p => drop(p.acquire().await?));
+        Ok(pool)
+    }
+
+    /// `db.system` of the OpenTelemetry conventions
+    pub fn system(&self) -> &'static str {
+        match self {
+            DbPool::Postgres(_) => "postgresql",
+            #[cfg(feature = "sqlite")]
+            DbPool::Sqlite(_) => "sqlite",
+        }
+    }
+
+    /// the current time in sql, sqlite stores it as rfc3339 text like its column defaults
+    pub fn now(&self) -> &'static str {
+        match self {
+            DbPool::Postgres(_) => "now()",
+            #[cfg(feature = "sqlite")]
+            DbPool::Sqlite(_) => "strftime('%Y-%m-%dT%H:%M:%fZ', 'now')",
+        }
+    }
+
+    /// picks the statement written for the backend, where the dialects part ways
+    pub fn dialect<'a>(&self, postgres: &'a str, _sqlite: &'a str) -> &'a str {
+        match self {
+            DbPool::Postgres(_) => postgres,
+            #[cfg(feature = "sqlite")]
+            DbPool::Sqlite(_) => _sqlite,
+        }
+    }
+
+    /// open connections, idle or in use
+    pub fn size(&self) -> u32 {
+        with_pool!(self, p => p.size())
+    }
+
+    pub fn num_idle(&self) -> usize {
+        with_pool!(self, p => p.num_idle())
+    }
+
+    pub fn max_connections(&self) -> u32 {
+        with_pool!(self, p => p.options().get_max_connections())
+    }
+}
+
+#[cfg(feature = "sqlite")]
+fn sqlite_options(settings: &DatabaseSettings) -> Result<SqliteConnectOptions> {
+    let path = std::path::Path::new(&settings.sqlite_path);
+    if let Some(parent) = path.parent().filter(|p| !p.as_os_str().is_empty()) {
+        std::fs::create_dir_all(parent)?;
+    }
+    Ok(SqliteConnectOptions::new()
+        .filename(path)
+        .create_if_missing(true)
+        .foreign_keys(true)
+        // readers do not wait for the workers writing
+        .journal_mode(SqliteJournalMode::Wal))
+}
+
+#[cfg(not(feature = "sqlite"))]
+fn sqlite_disabled() -> crate::Error {
+    crate::Error::string("database.backend is sqlite, but telegrab was built without the sqlite feature")
+}
@@ -0,0 +1,97 @@
+use crate::Result;
+use crate::configuration::{DiskSettings, Settings};
+use crate::model::entity::disk::{DirUsage, DiskUsage};
+use crate::state::{AppState, QueueState};
+use crate::storage::{Storage, Storages};
+use std::time::Duration;
+use time::OffsetDateTime;
+
+const MB: u64 = 1024 * 1024;
+
+/// Lists the whole storage to sum up its size.
+async fn storage_usage(storage: &dyn Storage, quota_mb: Option<u64>) -> Result<DirUsage> {
+    let capacity = storage.capacity().await?;
+    let used_bytes = storage.list("").await?.iter().map(|o| o.size).sum();
+    Ok(DirUsage {
+        path: storage.to_string(),
+        used_bytes,
+        available_bytes: capacity.map(|c| c.available),
+        total_bytes: capacity.map(|c| c.total),
+        quota_bytes: quota_mb.map(|mb| mb * MB),
+    })
+}
+
+/// Why tasks writing to the dir have to wait, `None` when they may run.
+/// Paused ones need the high watermark to resume so they do not flap around the low one.
+fn pause_reason(usage: &DirUsage, paused: bool, settings: &DiskSettings) -> Option<String> {
+    if let Some(quota) = usage.quota_bytes
+        && usage.used_bytes >= quota
+    {
+        return Some(format!(
+            "{} holds {} MB, quota is {} MB",
+            usage.path,
+            usage.used_bytes / MB,
+            quota / MB
+        ));
+    }
+    let watermark = if paused {
+        settings.high_watermark_mb.max(settings.low_watermark_mb)
+    } else {
+        settings.low_watermark_mb
+    };
+    // storages without a fixed size are only limited by their quota
+    let available_bytes = usage.available_bytes?;
+    (available_bytes < watermark * MB).then(|| {
+        format!(
+            "{} MB available for {}, watermark is {} MB",
+            available_bytes / MB,
+            usage.path,
+            watermark
+        )
+    })
+}
+
+/// measures both storages and pauses or resumes downloads and archives accordingly
+pub async fn check(
+    queue_state: &QueueState,
+    settings: &DiskSettings,
+    storage: &Storages,
+) -> Result<DiskUsage> {
+    let pic = storage_usage(storage.pic.as_ref(), settings.pic_quota_mb).await?;
+    let cbz = storage_usage(storage.cbz.as_ref(), settings.cbz_quota_mb).await?;
+    let previous = queue_state.get_disk_usage().await;
+    let (downloads_paused, archives_paused) = previous
+        .as_ref()
+        .map(|u| (u.downloads_paused.is_some(), u.archives_paused.is_some()))
+        .unwrap_or_default();
+    let usage = DiskUsage {
+        downloads_paused: pause_reason(&pic, downloads_paused, settings),
+        archives_paused: pause_reason(&cbz, archives_paused, settings),
+        pic,
+        cbz,
+        checked_at: OffsetDateTime::now_utc(),
+    };
+    queue_state.set_disk_usage(usage.clone()).await;
+    Ok(usage)
+}
+
+pub async fn start_disk_monitor(state: AppState, configuration: Settings) {
+    let settings = configuration.disk;
+    let check_interval = settings.check_interval_secs.max(1);
+    tokio::spawn(async move {
+        let mut shutdown_rx = state.shutdown.get_shutdown_rx().await;
+        tracing::info!("Start disk monitor, check disk space in every {}s", check_interval);
+        loop {
+            if let Err(err) = check(&state.queue_state, &settings, &state.storage).await {
+                tracing::warn!("Disk monitor check failed: {}", err);
+            }
+            tokio::select! {
+                _ = shutdown_rx.recv() => {
+                    tracing::info!("Disk monitor received shutdown signal, stop.");
+                    break;
+                }
+                _ = tokio::time::sleep(Duration::from_secs(check_interval)) => {}
+            }
+        }
+    });
+}
@@ -26,6 +26,8 @@ pub enum Error {
     #[error(transparent)]
     Sqlx(#[from] sqlx::Error),
     #[error(transparent)]
+    Migrate(#[from] sqlx::migrate::MigrateError),
+    #[error(transparent)]
     Reqwest(#[from] reqwest::Error),
     #[error(transparent)]
     FromUtf8(#[from] string::FromUtf8Error),
@@ -49,6 +51,8 @@ pub enum Error {
     BadRequest(String),
     #[error("{0}")]
     Unauthorized(String),
+    #[error("{0}")]
+    Forbidden(String),
     #[error("internal server error")]
     InternalServerError,
     #[error("")]
@@ -168,6 +172,16 @@ impl IntoResponse for Error {
                     ),
                 )
             }
+            Self::Forbidden(err) => {
+                tracing::warn!(err);
+                (
+                    StatusCode::FORBIDDEN,
+                    ErrorDetail::new(
+                        "forbidden",
+                        "Your token does not have the scope required by this resource",
+                    ),
+                )
+            }
             Self::InternalServerError => (
                 StatusCode::INTERNAL_SERVER_ERROR,
                 ErrorDetail::new("internal_server_error", "Internal Server Error"),
@@ -0,0 +1,57 @@
+use crate::link::{self, LinkSource};
+use regex::Regex;
+use std::collections::HashSet;
+
+/// per feed include/exclude patterns, matched against entry title and link
+#[derive(Debug, Clone, Default)]
+pub struct FeedFilter {
+    include: Option<Regex>,
+    exclude: Option<Regex>,
+}
+
+impl FeedFilter {
+    pub fn new(include: Option<&str>, exclude: Option<&str>) -> Result<Self, regex::Error> {
+        let compile = |p: Option<&str>| p.filter(|p| !p.is_empty()).map(Regex::new).transpose();
+        Ok(Self {
+            include: compile(include)?,
+            exclude: compile(exclude)?,
+        })
+    }
+    fn accepts(&self, title: &str, url: &str) -> bool {
+        let matches = |re: &Regex| re.is_match(title) || re.is_match(url);
+        self.include.as_ref().is_none_or(matches) && !self.exclude.as_ref().is_some_and(matches)
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct ParsedFeed {
+    pub title: Option<String>,
+    /// canonical urls of supported pages, in feed order
+    pub links: Vec<String>,
+}
+
+/// RSS, Atom and JSON Feed; links are taken from the entry links and its html content
+pub fn parse_feed(body: &[u8], filter: &FeedFilter) -> Result<ParsedFeed, String> {
+    let feed = feed_rs::parser::parse(body).map_err(|e| format!("Invalid feed: {}", e))?;
+    let mut seen = HashSet::new();
+    let mut links = vec![];
+    for entry in feed.entries {
+        let title = entry.title.map(|t| t.content).unwrap_or_default();
+        let mut candidates: Vec<String> = entry.links.into_iter().map(|l| l.href).collect();
+        if let Some(body) = entry.content.and_then(|c| c.body) {
+            candidates.extend(link::extract_links(&body, LinkSource::Html));
+        }
+        if let Some(summary) = entry.summary {
+            candidates.extend(link::extract_links(&summary.content, LinkSource::Html));
+        }
+        for url in candidates.iter().filter_map(|c| link::canonicalize(c).ok()) {
+            if filter.accepts(&title, &url) && seen.insert(url.clone()) {
+                links.push(url);
+            }
+        }
+    }
+    Ok(ParsedFeed {
+        title: feed.title.map(|t| t.content),
+        links,
+    })
+}
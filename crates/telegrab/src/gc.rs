@@ -0,0 +1,177 @@
+use crate::archive;
+use crate::link;
+use crate::model::entity::gc::{GcAction, GcItem, GcItemKind};
+use crate::service;
+use crate::state::QueueState;
+use crate::storage::{Storage, Storages, join_key, move_key};
+use crate::worker::url_last_segment;
+use crate::Result;
+use serde::Deserialize;
+use crate::db::DbPool;
+use std::collections::{HashMap, HashSet};
+use std::io::Cursor;
+
+#[derive(Debug, Deserialize)]
+struct ComicInfoWeb {
+    #[serde(rename = "Web")]
+    web: Option<String>,
+}
+
+/// Everything in the pic storage and every cbz row nothing refers to any more:
+/// pic dirs of deleted docs, files that are not the file of a live pic
+/// (also ones left behind by a refresh), and cbz rows that lost their doc.
+/// A cbz row without a doc is only collected when its file is gone or was
+/// written by telegrab for a page that is no longer a doc, cbz files dropped
+/// into the cbz storage by hand stay.
+pub async fn find_orphans(pool: &DbPool, storage: &Storages) -> Result<Vec<GcItem>> {
+    let docs = service::doc::get_all_docs(pool).await?;
+    let pics = service::pic::get_live_pics(pool).await?;
+    let cbzs = service::cbz::get_cbzs(pool).await?;
+    let mut pics_by_doc: HashMap<i32, Vec<_>> = HashMap::new();
+    for pic in &pics {
+        pics_by_doc.entry(pic.doc_id).or_default().push(pic);
+    }
+    // pages are named with the width of the total at download time,
+    // which was either the page count or the number of pics
+    let mut expected: HashMap<String, HashSet<String>> = HashMap::new();
+    for doc in &docs {
+        let names = expected.entry(url_last_segment(&doc.url)).or_default();
+        let doc_pics = pics_by_doc.get(&doc.id).map(Vec::as_slice).unwrap_or_default();
+        let totals = [
+            doc.page_count.map(|n| n as usize).unwrap_or(1),
+            doc_pics.len(),
+        ];
+        for pic in doc_pics {
+            for total in totals {
+                names.insert(archive::pic_file_name(pic.seq as usize, total, &pic.url));
+            }
+        }
+    }
+    let mut items = vec![];
+    // keys are sorted, so the files of a dir are next to each other
+    let mut orphan_dirs: Vec<GcItem> = vec![];
+    for object in storage.pic.list("").await? {
+        let Some((dir, name)) = object.key.split_once('/') else {
+            items.push(GcItem {
+                kind: GcItemKind::OrphanPicFile,
+                bytes: object.size,
+                path: object.key,
+                cbz_id: None,
+            });
+            continue;
+        };
+        let Some(names) = expected.get(dir) else {
+            match orphan_dirs.last_mut() {
+                Some(item) if item.path == dir => item.bytes += object.size,
+                _ => orphan_dirs.push(GcItem {
+                    kind: GcItemKind::OrphanPicDir,
+                    bytes: object.size,
+                    path: dir.to_string(),
+                    cbz_id: None,
+                }),
+            }
+            continue;
+        };
+        if !names.contains(name) {
+            items.push(GcItem {
+                kind: GcItemKind::OrphanPicFile,
+                bytes: object.size,
+                path: object.key,
+                cbz_id: None,
+            });
+        }
+    }
+    items.extend(orphan_dirs);
+    let doc_urls: HashSet<&str> = docs.iter().map(|d| d.url.as_str()).collect();
+    for cbz in cbzs.iter().filter(|c| c.doc_id.is_none()) {
+        let head = storage.cbz.head(&cbz.path).await?;
+        let orphan = if head.is_some() {
+            comic_info_web(storage.cbz.as_ref(), &cbz.path)
+                .await
+                .and_then(|web| link::canonicalize(&web).ok())
+                .is_some_and(|url| !doc_urls.contains(url.as_str()))
+        } else {
+            true
+        };
+        if orphan {
+            items.push(GcItem {
+                kind: GcItemKind::OrphanCbz,
+                bytes: head.map(|h| h.size).unwrap_or(0),
+                path: cbz.path.clone(),
+                cbz_id: Some(cbz.id),
+            });
+        }
+    }
+    items.sort_by(|a, b| a.path.cmp(&b.path));
+    Ok(items)
+}
+
+/// `Web` of the `ComicInfo.xml` in the archive, set on every cbz telegrab builds
+async fn comic_info_web(storage: &dyn Storage, key: &str) -> Option<String> {
+    let xml = match storage.local_path(key) {
+        Some(path) => {
+            tokio::task::spawn_blocking(move || {
+                archive::read_comic_info(std::fs::File::open(path).ok()?).ok()?
+            })
+            .await
+            .ok()?
+        }
+        None => {
+            let bytes = storage.get(key).await.ok()?;
+            tokio::task::spawn_blocking(move || archive::read_comic_info(Cursor::new(bytes)).ok()?)
+                .await
+                .ok()?
+        }
+    }?;
+    quick_xml::de::from_str::<ComicInfoWeb>(&xml).ok()?.web
+}
+
+/// Deletes or quarantines the items of a report that are still orphaned now,
+/// anything a doc picked up again since the report was made is left alone.
+/// Quarantined items go to `gc-<report id>/{pic,cbz}/` in the quarantine storage.
+/// Returns the bytes freed, or moved.
+pub async fn apply(
+    pool: &DbPool,
+    queue_state: &QueueState,
+    report_id: i32,
+    items: &[GcItem],
+    action: GcAction,
+    storage: &Storages,
+) -> Result<u64> {
+    let current: HashSet<(GcItemKind, String)> = find_orphans(pool, storage)
+        .await?
+        .into_iter()
+        .map(|i| (i.kind, i.path))
+        .collect();
+    let quarantine_prefix = format!("gc-{}", report_id);
+    let mut bytes = 0;
+    for item in items {
+        if !current.contains(&(item.kind, item.path.clone())) {
+            tracing::info!("Gc report {} skips {}, no longer orphaned", report_id, item.path);
+            continue;
+        }
+        let (source, kind) = match item.kind {
+            GcItemKind::OrphanPicDir | GcItemKind::OrphanPicFile => (&storage.pic, "pic"),
+            GcItemKind::OrphanCbz => (&storage.cbz, "cbz"),
+        };
+        let objects = match item.kind {
+            GcItemKind::OrphanPicDir => source.list(&item.path).await?,
+            _ => source.head(&item.path).await?.into_iter().collect(),
+        };
+        for object in objects {
+            match action {
+                GcAction::Delete => source.delete(&object.key).await?,
+                GcAction::Quarantine => {
+                    let target = join_key(&join_key(&quarantine_prefix, kind), &object.key);
+                    move_key(source.as_ref(), &object.key, storage.quarantine.as_ref(), &target)
+                        .await?
+                }
+            }
+            bytes += object.size;
+        }
+        if let Some(cbz_id) = item.cbz_id {
+            service::cbz::remove_cbz_by_id(pool, queue_state, cbz_id).await?;
+        }
+    }
+    Ok(bytes)
+}
@@ -0,0 +1,136 @@
+use crate::db::with_pool;
+use crate::model::entity::health::{CheckStatus, HealthCheck, Readiness};
+use crate::state::AppState;
+use crate::storage::Storage;
+use bytes::Bytes;
+use std::collections::HashSet;
+use std::future::Future;
+use std::time::{Duration, Instant};
+use time::OffsetDateTime;
+use uuid::Uuid;
+
+/// written and removed again to see that a storage takes writes, a dot file so scans skip it;
+/// every probe gets its own name so concurrent checks do not remove each other's
+const PROBE_PREFIX: &str = ".health-probe-";
+
+async fn timed<F>(name: &str, check: F) -> HealthCheck
+where
+    F: Future<Output = (CheckStatus, Option<String>)>,
+{
+    let start = Instant::now();
+    let (status, detail) = check.await;
+    HealthCheck {
+        name: name.to_string(),
+        status,
+        latency_ms: start.elapsed().as_secs_f64() * 1000.0,
+        detail,
+    }
+}
+
+async fn check_database(state: &AppState) -> (CheckStatus, Option<String>) {
+    let timeout = Duration::from_millis(state.health.db_timeout_ms);
+    let ping = async {
+        with_pool!(&*state.db_pool, db => sqlx::query("SELECT 1").execute(db).await.map(|_| ()))
+    };
+    match tokio::time::timeout(timeout, ping).await {
+        Ok(Ok(_)) => (CheckStatus::Ok, None),
+        Ok(Err(err)) => (CheckStatus::Fail, Some(err.to_string())),
+        Err(_) => (
+            CheckStatus::Fail,
+            Some(format!("no answer within {}ms", state.health.db_timeout_ms)),
+        ),
+    }
+}
+
+async fn check_storage(storage: &dyn Storage) -> (CheckStatus, Option<String>) {
+    let key = format!("{}{}", PROBE_PREFIX, Uuid::new_v4());
+    if let Err(err) = storage.put(&key, Bytes::from_static(b"ok")).await {
+        return (
+            CheckStatus::Fail,
+            Some(format!("{} is not writable: {}", storage, err)),
+        );
+    }
+    match storage.delete(&key).await {
+        Ok(()) => (CheckStatus::Ok, Some(storage.to_string())),
+        Err(err) => (
+            CheckStatus::Fail,
+            Some(format!("{} kept probe {}: {}", storage, key, err)),
+        ),
+    }
+}
+
+/// a worker is alive while it runs a task or polled the queue recently
+async fn check_workers(state: &AppState) -> (CheckStatus, Option<String>) {
+    if state.worker_count == 0 {
+        return (CheckStatus::Skipped, Some("no workers configured".to_string()));
+    }
+    let heartbeats = state.queue_state.get_heartbeats().await;
+    let busy: HashSet<usize> = state
+        .queue_state
+        .get_active_tasks()
+        .await
+        .iter()
+        .map(|t| t.worker_id)
+        .collect();
+    let now = OffsetDateTime::now_utc();
+    let stale = Duration::from_secs(state.health.worker_stale_secs);
+    let dead: Vec<String> = (0..state.worker_count)
+        .filter(|id| !busy.contains(id))
+        .filter_map(|id| match heartbeats.get(&id) {
+            None => Some(format!("worker {} never polled", id)),
+            Some(seen) if now - *seen > stale => Some(format!(
+                "worker {} last polled {}s ago",
+                id,
+                (now - *seen).whole_seconds()
+            )),
+            Some(_) => None,
+        })
+        .collect();
+    if dead.is_empty() {
+        (
+            CheckStatus::Ok,
+            Some(format!("{} workers alive", state.worker_count)),
+        )
+    } else {
+        (CheckStatus::Fail, Some(dead.join(", ")))
+    }
+}
+
+async fn check_fs_watcher(state: &AppState) -> (CheckStatus, Option<String>) {
+    if state.storage.cbz.local_path("").is_none() {
+        return (
+            CheckStatus::Skipped,
+            Some(format!("nothing to watch on {}", state.storage.cbz)),
+        );
+    }
+    if state.fs_watcher.lock().await.is_some() {
+        (CheckStatus::Ok, None)
+    } else {
+        (CheckStatus::Fail, Some("fs watcher is not running".to_string()))
+    }
+}
+
+async fn check_shutdown(state: &AppState) -> (CheckStatus, Option<String>) {
+    if state.shutdown.is_shutting_down().await {
+        (CheckStatus::Fail, Some("shutting down".to_string()))
+    } else {
+        (CheckStatus::Ok, None)
+    }
+}
+
+/// Everything telegrab needs to do its work, a failed check makes it not ready.
+pub async fn check_ready(state: &AppState) -> Readiness {
+    let checks = vec![
+        timed("shutdown", check_shutdown(state)).await,
+        timed("database", check_database(state)).await,
+        timed("picStorage", check_storage(state.storage.pic.as_ref())).await,
+        timed("cbzStorage", check_storage(state.storage.cbz.as_ref())).await,
+        timed("workers", check_workers(state)).await,
+        timed("fsWatcher", check_fs_watcher(state)).await,
+    ];
+    Readiness {
+        ready: checks.iter().all(|c| c.status != CheckStatus::Fail),
+        checks,
+        checked_at: OffsetDateTime::now_utc(),
+    }
+}
@@ -0,0 +1,177 @@
+use crate::configuration::{HostPolicy, RateLimitSettings};
+use reqwest::header::{HeaderMap, RETRY_AFTER};
+use std::collections::HashMap;
+use std::sync::Arc;
+use std::time::Duration;
+use time::OffsetDateTime;
+use time::format_description::well_known::Rfc2822;
+use tokio::sync::{Mutex, OwnedSemaphorePermit, RwLock, Semaphore};
+use tokio::time::Instant;
+
+/// Per host pacing and concurrency, shared by page fetches and image downloads
+#[derive(Debug)]
+pub struct HostLimiter {
+    settings: RwLock<RateLimitSettings>,
+    hosts: Mutex<HashMap<String, Arc<HostState>>>,
+}
+
+#[derive(Debug)]
+struct HostState {
+    semaphore: Arc<Semaphore>,
+    limits: std::sync::Mutex<Limits>,
+    next_slot: Mutex<Instant>,
+    blocked_until: Mutex<Option<Instant>>,
+}
+
+#[derive(Debug)]
+struct Limits {
+    interval: Duration,
+    max_concurrency: usize,
+    /// permits to forget as they come back, the limit was lowered while they were held
+    shrink_pending: usize,
+}
+
+/// Holds one of the host's concurrency slots, keep it until the body is read
+#[derive(Debug)]
+pub struct HostPermit {
+    state: Arc<HostState>,
+    permit: Option<OwnedSemaphorePermit>,
+}
+
+impl Drop for HostPermit {
+    fn drop(&mut self) {
+        let Some(permit) = self.permit.take() else {
+            return;
+        };
+        let mut limits = self.state.limits.lock().unwrap();
+        if limits.shrink_pending > 0 {
+            limits.shrink_pending -= 1;
+            permit.forget();
+        }
+    }
+}
+
+fn interval(policy: &HostPolicy) -> Duration {
+    if policy.requests_per_second > 0.0 {
+        Duration::from_secs_f64(1.0 / policy.requests_per_second)
+    } else {
+        Duration::ZERO
+    }
+}
+
+impl HostState {
+    fn new(policy: &HostPolicy) -> Self {
+        let max_concurrency = policy.max_concurrency.max(1);
+        Self {
+            semaphore: Arc::new(Semaphore::new(max_concurrency)),
+            limits: std::sync::Mutex::new(Limits {
+                interval: interval(policy),
+                max_concurrency,
+                shrink_pending: 0,
+            }),
+            next_slot: Mutex::new(Instant::now()),
+            blocked_until: Mutex::new(None),
+        }
+    }
+    /// resizes the semaphore to the new `max_concurrency`, held permits count against it
+    fn apply(&self, policy: &HostPolicy) {
+        let mut limits = self.limits.lock().unwrap();
+        limits.interval = interval(policy);
+        let target = policy.max_concurrency.max(1);
+        if target > limits.max_concurrency {
+            let grow = target - limits.max_concurrency;
+            let cancelled = grow.min(limits.shrink_pending);
+            limits.shrink_pending -= cancelled;
+            self.semaphore.add_permits(grow - cancelled);
+        } else if target < limits.max_concurrency {
+            let shrink = limits.max_concurrency - target;
+            let forgotten = self.semaphore.forget_permits(shrink);
+            limits.shrink_pending += shrink - forgotten;
+        }
+        limits.max_concurrency = target;
+    }
+    async fn wait_turn(&self) {
+        let blocked_until = *self.blocked_until.lock().await;
+        if let Some(until) = blocked_until {
+            tokio::time::sleep_until(until).await;
+        }
+        let interval = self.limits.lock().unwrap().interval;
+        let slot = {
+            let mut next_slot = self.next_slot.lock().await;
+            let slot = (*next_slot).max(Instant::now());
+            *next_slot = slot + interval;
+            slot
+        };
+        tokio::time::sleep_until(slot).await;
+    }
+}
+
+impl HostLimiter {
+    pub fn new(settings: RateLimitSettings) -> Self {
+        Self {
+            settings: RwLock::new(settings),
+            hosts: Mutex::new(HashMap::new()),
+        }
+    }
+    /// applies the new policies to the hosts seen so far, their back-off and in-flight permits are kept
+    pub async fn update_settings(&self, settings: RateLimitSettings) {
+        // same lock order as `state`
+        let hosts = self.hosts.lock().await;
+        for (host, state) in hosts.iter() {
+            state.apply(&settings.policy_for(host));
+        }
+        *self.settings.write().await = settings;
+    }
+    pub async fn max_retries(&self) -> u32 {
+        self.settings.read().await.max_retries
+    }
+    async fn state(&self, host: &str) -> Arc<HostState> {
+        let mut hosts = self.hosts.lock().await;
+        if let Some(state) = hosts.get(host) {
+            return state.clone();
+        }
+        let policy = self.settings.read().await.policy_for(host);
+        tracing::debug!("rate limit for {}: {:?}", host, policy);
+        let state = Arc::new(HostState::new(&policy));
+        hosts.insert(host.to_string(), state.clone());
+        state
+    }
+    pub async fn acquire(&self, host: &str) -> HostPermit {
+        let state = self.state(host).await;
+        let permit = state
+            .semaphore
+            .clone()
+            .acquire_owned()
+            .await
+            .expect("host semaphore closed");
+        state.wait_turn().await;
+        HostPermit {
+            state,
+            permit: Some(permit),
+        }
+    }
+    /// pause every request to `host` for `delay`, capped by `max_retry_after_secs`
+    pub async fn back_off(&self, host: &str, delay: Duration) -> Duration {
+        let cap = Duration::from_secs(self.settings.read().await.max_retry_after_secs);
+        let delay = delay.min(cap);
+        let state = self.state(host).await;
+        let until = Instant::now() + delay;
+        let mut blocked_until = state.blocked_until.lock().await;
+        if blocked_until.is_none_or(|b| b < until) {
+            *blocked_until = Some(until);
+        }
+        tracing::warn!("back off {} for {:?}", host, delay);
+        delay
+    }
+}
+
+/// `Retry-After` is either delay-seconds or an HTTP-date
+pub fn retry_after(headers: &HeaderMap) -> Option<Duration> {
+    let value = headers.get(RETRY_AFTER)?.to_str().ok()?.trim();
+    if let Ok(secs) = value.parse::<u64>() {
+        return Some(Duration::from_secs(secs));
+    }
+    let date = OffsetDateTime::parse(value, &Rfc2822).ok()?;
+    let secs = (date - OffsetDateTime::now_utc()).whole_seconds().max(0);
+    Some(Duration::from_secs(secs as u64))
+}
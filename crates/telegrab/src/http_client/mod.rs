@@ -0,0 +1,395 @@
+use crate::configuration::HttpClientSettings;
+use crate::metrics::metrics;
+use crate::model::entity::doc::TelegraphPost;
+use crate::storage::Storage;
+use arc_swap::ArcSwap;
+use reqwest::{Client, RequestBuilder, Response, StatusCode, header};
+use scraper::{Html, Selector};
+use std::collections::HashSet;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use tokio::sync::RwLock;
+use tracing::Instrument;
+
+mod limiter;
+mod proxy;
+
+pub use limiter::{HostLimiter, HostPermit, retry_after};
+
+fn build_client(config: &HttpClientSettings) -> Result<Client, String> {
+    let mut client_builder = Client::builder()
+        .connect_timeout(Duration::from_secs(config.connect_timeout_secs))
+        .timeout(Duration::from_secs(config.timeout_secs))
+        .tcp_nodelay(true)
+        .tcp_keepalive(Duration::from_secs(60))
+        .http2_adaptive_window(true)
+        .http2_initial_stream_window_size(Some(2 * 1024 * 1024))
+        .http2_initial_connection_window_size(Some(4 * 1024 * 1024))
+        .user_agent(&config.user_agent);
+    if let Some(proxy) = &config.proxy {
+        client_builder = client_builder.proxy(proxy::build_proxy(proxy)?);
+    }
+    if config.pool_enabled {
+        client_builder
+            .pool_max_idle_per_host(config.max_connections)
+            .build()
+            .map_err(|e| format!("Failed to create HTTP client with connection pool: {}", e))
+    } else {
+        client_builder
+            .build()
+            .map_err(|e| format!("Failed to create HTTP client: {}", e))
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct HttpClientManager {
+    client: Arc<ArcSwap<Client>>,
+    config: Arc<RwLock<HttpClientSettings>>,
+    limiter: Arc<HostLimiter>,
+}
+
+impl HttpClientManager {
+    pub fn new(config: Option<HttpClientSettings>) -> Self {
+        let config = config.unwrap_or_default();
+        tracing::debug!("Creating HTTP client with settings: {:?}", config);
+        let client = build_client(&config).unwrap_or_else(|e| {
+            tracing::error!("{}, fallback to default client", e);
+            reqwest::Client::new()
+        });
+        Self {
+            client: Arc::new(ArcSwap::from_pointee(client)),
+            limiter: Arc::new(HostLimiter::new(config.rate_limit.clone())),
+            config: Arc::new(RwLock::new(config)),
+        }
+    }
+
+    /// the current client, requests started on it keep it alive after a swap
+    pub fn client(&self) -> Arc<Client> {
+        self.client.load_full()
+    }
+    pub async fn config(&self) -> HttpClientSettings {
+        let config = self.config.read().await;
+        config.clone()
+    }
+    /// Validates and applies new settings; the client is swapped atomically, so in-flight
+    /// requests finish on the old one while new requests use the new one.
+    pub async fn update_config(&self, mut new_config: HttpClientSettings) -> Result<(), String> {
+        new_config.validate()?;
+        let mut config = self.config.write().await;
+        // the password is never returned to clients, so an update usually comes without it
+        if let (Some(new_proxy), Some(old_proxy)) = (new_config.proxy.as_mut(), config.proxy.as_ref())
+            && new_proxy.password.is_none()
+            && new_proxy.url == old_proxy.url
+            && new_proxy.username == old_proxy.username
+        {
+            new_proxy.password = old_proxy.password.clone();
+        }
+        tracing::info!("Updating HTTP client config to: {:?}", new_config);
+        let client = build_client(&new_config)?;
+        self.client.store(Arc::new(client));
+        self.limiter
+            .update_settings(new_config.rate_limit.clone())
+            .await;
+        *config = new_config;
+        tracing::info!("HTTP client config updated");
+        Ok(())
+    }
+    /// Sends through the per host limiter, 429/503 pause the host and get retried.
+    /// The permit holds a concurrency slot of the host, drop it after reading the body.
+    pub async fn send(&self, request: RequestBuilder) -> reqwest::Result<(Response, HostPermit)> {
+        let request = request.build()?;
+        let host = request.url().host_str().unwrap_or_default().to_string();
+        let max_retries = self.limiter.max_retries().await;
+        let mut attempt = 0;
+        loop {
+            let permit = self.limiter.acquire(&host).await;
+            let cloned = request
+                .try_clone()
+                .expect("only requests without streaming body are sent");
+            let span = tracing::info_span!(
+                "upstream request",
+                "otel.kind" = "client",
+                "http.request.method" = %request.method(),
+                "url.full" = %request.url(),
+                "server.address" = %host,
+                "http.request.resend_count" = attempt,
+                "http.response.status_code" = tracing::field::Empty,
+            );
+            let response = self.client().execute(cloned).instrument(span.clone()).await?;
+            let status = response.status();
+            span.record("http.response.status_code", status.as_u16());
+            metrics().upstream_response(&host, status.as_u16());
+            if status != StatusCode::TOO_MANY_REQUESTS && status != StatusCode::SERVICE_UNAVAILABLE
+            {
+                return Ok((response, permit));
+            }
+            let delay = retry_after(response.headers())
+                .unwrap_or_else(|| Duration::from_secs(2u64.pow(attempt + 1)));
+            self.limiter.back_off(&host, delay).await;
+            if attempt >= max_retries {
+                return Ok((response, permit));
+            }
+            drop(permit);
+            attempt += 1;
+            tracing::info!(
+                "{} answered {}, retry {}/{}",
+                request.url(),
+                status,
+                attempt,
+                max_retries
+            );
+        }
+    }
+    pub async fn download_file(
+        &self,
+        url: &str,
+        storage: &dyn Storage,
+        key: &str,
+    ) -> Result<DownloadResult, DownloadError> {
+        tracing::info!("Downloading file: {} -> {}/{}", url, storage, key);
+        let start_time = Instant::now();
+
+        let (response, _permit) = self
+            .send(self.client().get(url).header("Referer", "https://telegra.ph/"))
+            .await
+            .map_err(|e| DownloadError::NetworkError(format!("Request failed: {}", e)))?;
+        let status = response.status();
+        if !status.is_success() {
+            return Err(DownloadError::HTTPError(
+                status.as_u16(),
+                status.to_string(),
+            ));
+        }
+        let content_length = response.content_length().unwrap_or(0);
+        tracing::info!("file size: {} bytes", content_length);
+        let bytes = response
+            .bytes()
+            .await
+            .map_err(|e| DownloadError::IOError(format!("Failed to read response bytes: {}", e)))?;
+        let size = bytes.len();
+        let duration = start_time.elapsed();
+        if let Some(host) = url_host(url) {
+            metrics().download(&host, size as u64, duration);
+        }
+        storage
+            .put(key, bytes)
+            .await
+            .map_err(|e| DownloadError::IOError(format!("Failed to write file: {}", e)))?;
+
+        let speed = if duration.as_secs() > 0 {
+            content_length as f64 / duration.as_secs_f64()
+        } else {
+            content_length as f64
+        };
+        tracing::info!(
+            "Downloaded {} bytes in {:?}, speed: {:.2} bytes/sec",
+            size,
+            duration,
+            speed
+        );
+
+        Ok(DownloadResult {
+            url: url.to_string(),
+            size: content_length,
+            save_path: key.to_string(),
+            duration,
+            speed: speed as u64,
+        })
+    }
+    pub async fn parse_telegraph_post(&self, url: &str) -> crate::Result<TelegraphPost> {
+        // 获取网页内容
+        let (response, _permit) = self.send(self.client().get(url)).await?;
+        let html_content = response.text().await?;
+        parse_telegraph_html(url, &html_content).inspect_err(|_| record_parse_failure(url))
+    }
+    /// Conditional GET of a parsed page with the validators stored on the doc.
+    pub async fn fetch_telegraph_post(
+        &self,
+        url: &str,
+        etag: Option<&str>,
+        last_modified: Option<&str>,
+    ) -> crate::Result<PostFetch> {
+        let mut request = self.client().get(url);
+        if let Some(etag) = etag {
+            request = request.header(header::IF_NONE_MATCH, etag);
+        }
+        if let Some(last_modified) = last_modified {
+            request = request.header(header::IF_MODIFIED_SINCE, last_modified);
+        }
+        let (response, _permit) = self.send(request).await?;
+        let status = response.status();
+        if status == StatusCode::NOT_MODIFIED {
+            return Ok(PostFetch::NotModified);
+        }
+        if status == StatusCode::NOT_FOUND || status == StatusCode::GONE {
+            return Ok(PostFetch::Gone(status.as_u16()));
+        }
+        if !status.is_success() {
+            return Err(crate::Error::Message(format!("{} answered {}", url, status)));
+        }
+        let header_value = |name| {
+            response
+                .headers()
+                .get(name)
+                .and_then(|v| v.to_str().ok())
+                .map(|v| v.to_string())
+        };
+        let etag = header_value(header::ETAG);
+        let last_modified = header_value(header::LAST_MODIFIED);
+        let html_content = response.text().await?;
+        Ok(PostFetch::Fetched {
+            post: parse_telegraph_html(url, &html_content)
+                .inspect_err(|_| record_parse_failure(url))?,
+            etag,
+            last_modified,
+        })
+    }
+    /// Conditional GET with the validators of the previous poll, `None` when not modified.
+    pub async fn fetch_feed(
+        &self,
+        url: &str,
+        etag: Option<&str>,
+        last_modified: Option<&str>,
+    ) -> crate::Result<Option<FeedResponse>> {
+        let mut request = self.client().get(url).header(
+            header::ACCEPT,
+            "application/rss+xml, application/atom+xml, application/feed+json, application/json;q=0.9, */*;q=0.8",
+        );
+        if let Some(etag) = etag {
+            request = request.header(header::IF_NONE_MATCH, etag);
+        }
+        if let Some(last_modified) = last_modified {
+            request = request.header(header::IF_MODIFIED_SINCE, last_modified);
+        }
+        let (response, _permit) = self.send(request).await?;
+        let status = response.status();
+        if status == StatusCode::NOT_MODIFIED {
+            return Ok(None);
+        }
+        if !status.is_success() {
+            return Err(crate::Error::Message(format!("{} answered {}", url, status)));
+        }
+        let header_value = |name| {
+            response
+                .headers()
+                .get(name)
+                .and_then(|v| v.to_str().ok())
+                .map(|v| v.to_string())
+        };
+        let etag = header_value(header::ETAG);
+        let last_modified = header_value(header::LAST_MODIFIED);
+        let body = response.bytes().await?.to_vec();
+        Ok(Some(FeedResponse {
+            body,
+            etag,
+            last_modified,
+        }))
+    }
+}
+
+fn url_host(url: &str) -> Option<String> {
+    url::Url::parse(url)
+        .ok()
+        .and_then(|u| u.host_str().map(|h| h.to_string()))
+}
+
+fn record_parse_failure(url: &str) {
+    metrics().parse_failure(&url_host(url).unwrap_or_default());
+}
+
+fn parse_telegraph_html(url: &str, html_content: &str) -> crate::Result<TelegraphPost> {
+    // 解析HTML
+    let document = Html::parse_document(html_content);
+
+    // 提取标题
+    let title_selector = Selector::parse("h1").expect("Failed to parse h1 selector");
+    let title = document
+        .select(&title_selector)
+        .next()
+        .ok_or_else(|| crate::Error::Message(format!("Failed to find title in {}", url)))?
+        .text()
+        .collect::<String>()
+        .trim()
+        .to_string();
+
+    // 提取日期
+    let date_selector = Selector::parse("time").expect("Failed to parse time selector");
+    let date = document.select(&date_selector).next().map(|element| {
+        let s = element.text().collect::<String>().trim().to_string();
+        let l = element.attr("datetime");
+        if let Some(datetime) = l {
+            datetime.to_string()
+        } else {
+            s
+        }
+    });
+
+    // 提取图片URL
+    let img_selector = Selector::parse("img").expect("Failed to parse img selector");
+    let mut image_urls = Vec::new();
+    let mut seen_urls = HashSet::new();
+
+    for img_element in document.select(&img_selector) {
+        if let Some(src) = img_element.value().attr("src") {
+            let full_url = if src.starts_with("http") {
+                src.to_string()
+            } else if src.starts_with("/") {
+                format!("https://telegra.ph{}", src)
+            } else {
+                continue;
+            };
+
+            // 避免重复URL
+            if seen_urls.insert(full_url.clone()) {
+                image_urls.push(full_url);
+            }
+        }
+    }
+
+    Ok(TelegraphPost {
+        url: url.to_string(),
+        title,
+        date,
+        image_urls,
+    })
+}
+
+#[derive(Debug)]
+pub enum PostFetch {
+    NotModified,
+    /// 404 or 410, the page was deleted
+    Gone(u16),
+    Fetched {
+        post: TelegraphPost,
+        etag: Option<String>,
+        last_modified: Option<String>,
+    },
+}
+
+#[derive(Debug, Clone)]
+pub struct FeedResponse {
+    pub body: Vec<u8>,
+    pub etag: Option<String>,
+    pub last_modified: Option<String>,
+}
+
+#[derive(Debug, Clone)]
+pub struct DownloadResult {
+    pub url: String,
+    pub size: u64,
+    pub save_path: String,
+    pub duration: Duration,
+    pub speed: u64,
+}
+
+#[derive(Debug, Clone, thiserror::Error)]
+pub enum DownloadError {
+    #[error("Network error: {0}")]
+    NetworkError(String),
+    #[error("HTTP error: {0}")]
+    HTTPError(u16, String),
+    #[error("IO error: {0}")]
+    IOError(String),
+    #[error("Invalid URL: {0}")]
+    InvalidUrl(String),
+}
@@ -0,0 +1,57 @@
+use crate::configuration::ProxySettings;
+use reqwest::{Proxy, Url};
+use secrecy::ExposeSecret;
+
+const SUPPORTED_SCHEMES: &[&str] = &["http", "https", "socks5", "socks5h"];
+
+/// `pattern` covers the host itself and its subdomains, `*` covers every host
+fn host_matches(pattern: &str, host: &str) -> bool {
+    let pattern = pattern.trim().trim_start_matches("*.");
+    pattern == "*"
+        || host.eq_ignore_ascii_case(pattern)
+        || host
+            .to_ascii_lowercase()
+            .ends_with(&format!(".{}", pattern.to_ascii_lowercase()))
+}
+
+pub fn should_proxy(settings: &ProxySettings, host: &str) -> bool {
+    if settings.bypass.iter().any(|p| host_matches(p, host)) {
+        return false;
+    }
+    settings.hosts.is_empty() || settings.hosts.iter().any(|p| host_matches(p, host))
+}
+
+/// proxy url with the credentials embedded, so it works for http and socks5 alike
+pub fn proxy_url(settings: &ProxySettings) -> Result<Url, String> {
+    let mut url = Url::parse(&settings.url)
+        .map_err(|e| format!("Invalid proxy url {}: {}", settings.url, e))?;
+    if !SUPPORTED_SCHEMES.contains(&url.scheme()) {
+        return Err(format!(
+            "Unsupported proxy scheme {}, use one of {}",
+            url.scheme(),
+            SUPPORTED_SCHEMES.join(", ")
+        ));
+    }
+    if let Some(username) = &settings.username {
+        url.set_username(username)
+            .map_err(|_| format!("Can not set username on proxy url {}", settings.url))?;
+    }
+    if let Some(password) = &settings.password {
+        url.set_password(Some(password.expose_secret()))
+            .map_err(|_| format!("Can not set password on proxy url {}", settings.url))?;
+    }
+    Ok(url)
+}
+
+pub fn build_proxy(settings: &ProxySettings) -> Result<Proxy, String> {
+    let target = proxy_url(settings)?;
+    let settings = settings.clone();
+    Ok(Proxy::custom(move |url| {
+        let host = url.host_str()?;
+        if should_proxy(&settings, host) {
+            Some(target.clone())
+        } else {
+            None
+        }
+    }))
+}
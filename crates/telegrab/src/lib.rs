@@ -1,22 +1,36 @@
 use errors::Error;
 
+pub mod archive;
 pub mod backtrace;
+pub mod cli;
 pub mod configuration;
+pub mod db;
 pub mod controller;
+pub mod disk;
 pub mod errors;
+pub mod feed;
 pub mod format;
+pub mod gc;
 pub mod graceful;
+pub mod health;
 pub mod http_client;
+pub mod library;
+pub mod link;
 pub mod listener;
+pub mod metrics;
+pub mod migration;
 pub mod middleware;
 pub mod model;
 pub mod repository;
+pub mod scheduler;
 pub mod schema;
 pub mod service;
 pub mod shutdown_signal;
 pub mod startup;
 pub mod state;
+pub mod storage;
 pub mod telemetry;
+pub mod webhook;
 pub mod worker;
 
 pub type Result<T, E = Error> = std::result::Result<T, E>;
@@ -0,0 +1,175 @@
+use crate::model::entity::library::{IssueKind, LibraryIssue, RepairAction, RepairKind};
+use crate::model::entity::task::Task;
+use crate::service;
+use crate::state::QueueState;
+use crate::storage::Storage;
+use crate::{Error, Result};
+use serde::Deserialize;
+use crate::db::DbPool;
+use std::collections::HashSet;
+use std::io::{Cursor, Read, Seek};
+
+/// entries counted as pages, everything else in a cbz is ignored
+const IMAGE_EXTENSIONS: &[&str] = &["jpg", "jpeg", "png", "gif", "webp"];
+
+#[derive(Debug, Deserialize)]
+struct ComicInfoCheck {
+    #[serde(rename = "PageCount")]
+    page_count: Option<String>,
+}
+
+/// problems found in one cbz file, without ids
+#[derive(Debug, Clone, Default)]
+pub struct CbzCheck {
+    /// `None` when the zip could not be read at all
+    pub image_count: Option<usize>,
+    pub problems: Vec<(IssueKind, Option<String>, String)>,
+}
+
+impl CbzCheck {
+    fn invalid_zip(err: String) -> Self {
+        Self {
+            image_count: None,
+            problems: vec![(IssueKind::InvalidZip, None, err)],
+        }
+    }
+}
+
+/// Checks a cbz of the storage, local files are read in place and
+/// remote ones are downloaded into memory first.
+pub async fn check_stored_cbz(storage: &dyn Storage, key: &str) -> Result<CbzCheck> {
+    if let Some(path) = storage.local_path(key) {
+        let check = tokio::task::spawn_blocking(move || match std::fs::File::open(path) {
+            Ok(file) => check_cbz(file),
+            Err(err) => CbzCheck::invalid_zip(err.to_string()),
+        })
+        .await?;
+        return Ok(check);
+    }
+    let bytes = storage.get(key).await?;
+    let check = tokio::task::spawn_blocking(move || check_cbz(Cursor::new(bytes))).await?;
+    Ok(check)
+}
+
+/// Opens the archive, reads every entry (which checks the crc), parses
+/// `ComicInfo.xml` and decodes every image. Blocking.
+pub fn check_cbz<R: Read + Seek>(reader: R) -> CbzCheck {
+    let mut check = CbzCheck::default();
+    let mut archive = match zip::ZipArchive::new(reader).map_err(|e| e.to_string()) {
+        Ok(archive) => archive,
+        Err(err) => return CbzCheck::invalid_zip(err),
+    };
+    let mut image_count = 0;
+    let mut comic_info = None;
+    for i in 0..archive.len() {
+        let mut entry = match archive.by_index(i) {
+            Ok(entry) => entry,
+            Err(err) => {
+                check
+                    .problems
+                    .push((IssueKind::InvalidZip, None, format!("entry {}: {}", i, err)));
+                continue;
+            }
+        };
+        if entry.is_dir() {
+            continue;
+        }
+        let name = entry.name().to_string();
+        let mut bytes = vec![];
+        if let Err(err) = entry.read_to_end(&mut bytes) {
+            check
+                .problems
+                .push((IssueKind::InvalidZip, Some(name), err.to_string()));
+            continue;
+        }
+        let ext = name.rsplit('.').next().unwrap_or_default().to_ascii_lowercase();
+        if name.eq_ignore_ascii_case("ComicInfo.xml") {
+            comic_info = Some(String::from_utf8_lossy(&bytes).into_owned());
+        } else if IMAGE_EXTENSIONS.contains(&ext.as_str()) {
+            image_count += 1;
+            if let Err(err) = image::load_from_memory(&bytes) {
+                check
+                    .problems
+                    .push((IssueKind::CorruptImage, Some(name), err.to_string()));
+            }
+        }
+    }
+    match comic_info {
+        None => check.problems.push((
+            IssueKind::MissingComicInfo,
+            None,
+            "ComicInfo.xml not found".to_string(),
+        )),
+        Some(xml) => match quick_xml::de::from_str::<ComicInfoCheck>(&xml) {
+            Err(err) => check.problems.push((
+                IssueKind::InvalidComicInfo,
+                Some("ComicInfo.xml".to_string()),
+                err.to_string(),
+            )),
+            Ok(info) => {
+                if let Some(page_count) = info.page_count
+                    && page_count.trim().parse::<usize>().ok() != Some(image_count)
+                {
+                    check.problems.push((
+                        IssueKind::InvalidComicInfo,
+                        Some("ComicInfo.xml".to_string()),
+                        format!("PageCount {} but {} images", page_count, image_count),
+                    ));
+                }
+            }
+        },
+    }
+    check.image_count = Some(image_count);
+    check
+}
+
+/// Queues the task that fixes an issue, pics are reset so the download is not skipped.
+pub async fn repair(pool: &DbPool, queue_state: &QueueState, action: RepairAction) -> Result<Task> {
+    let id = || {
+        action
+            .id
+            .ok_or_else(|| Error::Message(format!("{:?} needs an id", action.kind)))
+    };
+    let task = match action.kind {
+        RepairKind::RedownloadPic => {
+            let pic = service::pic::update_pic_status_by_id(pool, id()?, 0).await?;
+            Task::new_pic_download_task(pic.id)
+        }
+        RepairKind::Rearchive => {
+            let doc = service::doc::get_doc_by_id(pool, id()?).await?;
+            Task::new_cbz_archive_task(doc.id)
+        }
+        RepairKind::RemoveCbz => {
+            let cbz = service::cbz::get_cbz_by_id(pool, id()?).await?;
+            Task::new_remove_cbz_task(cbz.id)
+        }
+        RepairKind::Rescan => Task::new_scan_dir_task(),
+    };
+    queue_state.enqueue(task.clone()).await;
+    Ok(task)
+}
+
+/// every distinct repair of a report, downloads first so archives are built from complete pics
+pub async fn repair_all(
+    pool: &DbPool,
+    queue_state: &QueueState,
+    issues: &[LibraryIssue],
+) -> Result<Vec<Task>> {
+    let mut seen = HashSet::new();
+    let mut actions: Vec<RepairAction> = issues
+        .iter()
+        .filter_map(|i| i.repair)
+        .filter(|a| seen.insert(*a))
+        .collect();
+    actions.sort_by_key(|a| match a.kind {
+        RepairKind::RedownloadPic => 0,
+        RepairKind::RemoveCbz => 1,
+        RepairKind::Rescan => 2,
+        RepairKind::Rearchive => 3,
+    });
+    let mut tasks = vec![];
+    for action in actions {
+        tasks.push(repair(pool, queue_state, action).await?);
+    }
+    Ok(tasks)
+}
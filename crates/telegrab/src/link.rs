@@ -0,0 +1,198 @@
+use async_graphql::Enum;
+use regex::Regex;
+use scraper::{Html, Selector};
+use serde::Deserialize;
+use std::collections::HashSet;
+use std::sync::OnceLock;
+use url::Url;
+
+/// hosts we know how to parse
+pub const SUPPORTED_HOSTS: &[&str] = &["telegra.ph", "teletype.in"];
+/// mirrors serving the same pages, stored under the host they point to
+const HOST_ALIASES: &[(&str, &str)] = &[("te.legra.ph", "telegra.ph"), ("graph.org", "telegra.ph")];
+/// query parameters added by share buttons and newsletters, `utm_*` is matched by prefix
+const TRACKING_PARAMS: &[&str] = &[
+    "fbclid", "gclid", "dclid", "yclid", "msclkid", "igshid", "mc_cid", "mc_eid", "ref",
+    "ref_src", "_ga", "_gl", "si",
+];
+
+static LINK: OnceLock<Regex> = OnceLock::new();
+
+fn link_regex() -> &'static Regex {
+    LINK.get_or_init(|| {
+        let hosts = SUPPORTED_HOSTS
+            .iter()
+            .chain(HOST_ALIASES.iter().map(|(alias, _)| alias))
+            .map(|h| regex::escape(h))
+            .collect::<Vec<_>>()
+            .join("|");
+        Regex::new(&format!(
+            r#"(?i)\b(?:https?://)?(?:www\.)?(?:{})/[^\s<>"'`\[\]{{}}|\\^]+"#,
+            hosts
+        ))
+        .unwrap()
+    })
+}
+
+/// Normalised form of a supported link, the same page always gives the same string:
+/// https, mirror hosts mapped to their origin, no trailing slash, fragment or tracking
+/// parameters, and percent-encoding only where it is required, in upper case.
+pub fn canonicalize(raw: &str) -> Result<String, String> {
+    let raw = raw.trim();
+    let with_scheme = if raw.contains("://") {
+        raw.to_string()
+    } else {
+        format!("https://{}", raw)
+    };
+    let mut url = Url::parse(&with_scheme).map_err(|e| format!("Invalid url {}: {}", raw, e))?;
+    if !matches!(url.scheme(), "http" | "https") {
+        return Err(format!("Unsupported scheme: {}", url.scheme()));
+    }
+    let host = url
+        .host_str()
+        .map(|h| h.trim_end_matches('.').trim_start_matches("www."))
+        .ok_or_else(|| format!("Missing host: {}", raw))?;
+    let host = HOST_ALIASES
+        .iter()
+        .find(|(alias, _)| *alias == host)
+        .map_or(host, |(_, origin)| origin)
+        .to_string();
+    if !SUPPORTED_HOSTS.contains(&host.as_str()) {
+        return Err(format!("Unsupported host: {}", host));
+    }
+    let path = normalize_percent_encoding(url.path().trim_end_matches('/'));
+    if path.is_empty() {
+        return Err(format!("Missing page path: {}", raw));
+    }
+    let query: Vec<(String, String)> = url
+        .query_pairs()
+        .filter(|(k, _)| !is_tracking_param(k))
+        .map(|(k, v)| (k.into_owned(), v.into_owned()))
+        .collect();
+    url.set_scheme("https")
+        .map_err(|_| format!("Invalid url: {}", raw))?;
+    url.set_host(Some(&host))
+        .map_err(|e| format!("Invalid url {}: {}", raw, e))?;
+    url.set_port(None)
+        .map_err(|_| format!("Invalid url: {}", raw))?;
+    url.set_username("").ok();
+    url.set_password(None).ok();
+    url.set_path(&path);
+    url.set_fragment(None);
+    if query.is_empty() {
+        url.set_query(None);
+    } else {
+        url.query_pairs_mut().clear().extend_pairs(query);
+    }
+    Ok(url.to_string())
+}
+
+fn is_tracking_param(key: &str) -> bool {
+    let key = key.to_ascii_lowercase();
+    key.starts_with("utm_") || TRACKING_PARAMS.contains(&key.as_str())
+}
+
+/// decodes escaped unreserved characters and upper-cases the remaining escapes,
+/// `Url` has already escaped everything outside ascii
+fn normalize_percent_encoding(path: &str) -> String {
+    let bytes = path.as_bytes();
+    let mut out = String::with_capacity(path.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        let escaped = (bytes[i] == b'%')
+            .then(|| bytes.get(i + 1..i + 3))
+            .flatten()
+            .and_then(|hex| std::str::from_utf8(hex).ok())
+            .and_then(|hex| u8::from_str_radix(hex, 16).ok());
+        match escaped {
+            Some(b) if b.is_ascii_alphanumeric() || b"-._~".contains(&b) => {
+                out.push(b as char);
+                i += 3;
+            }
+            Some(b) => {
+                out.push_str(&format!("%{:02X}", b));
+                i += 3;
+            }
+            None => {
+                out.push(bytes[i] as char);
+                i += 1;
+            }
+        }
+    }
+    out
+}
+
+#[derive(Debug, Clone, Copy, Default, Eq, PartialEq, Deserialize, Enum)]
+#[serde(rename_all = "lowercase")]
+pub enum LinkSource {
+    /// html when it looks like markup, telegram when it parses as an export, text otherwise
+    #[default]
+    Auto,
+    Text,
+    Html,
+    /// Telegram Desktop `result.json` export
+    Telegram,
+}
+
+/// Supported links found in `content`, in order of appearance, without duplicates.
+pub fn extract_links(content: &str, source: LinkSource) -> Vec<String> {
+    let source = match source {
+        LinkSource::Auto => detect(content),
+        s => s,
+    };
+    let mut links = vec![];
+    match source {
+        LinkSource::Html => {
+            let document = Html::parse_document(content);
+            let selector = Selector::parse("a[href]").unwrap();
+            for a in document.select(&selector) {
+                if let Some(href) = a.value().attr("href") {
+                    links.extend(find_in_text(href));
+                }
+            }
+            links.extend(find_in_text(&document.root_element().text().collect::<String>()));
+        }
+        LinkSource::Telegram => match serde_json::from_str::<serde_json::Value>(content) {
+            Ok(value) => walk_json(&value, &mut links),
+            Err(e) => {
+                tracing::warn!("not a telegram export ({}), fall back to text", e);
+                links.extend(find_in_text(content));
+            }
+        },
+        _ => links.extend(find_in_text(content)),
+    }
+    let mut seen = HashSet::new();
+    links.retain(|l| seen.insert(l.clone()));
+    links
+}
+
+fn detect(content: &str) -> LinkSource {
+    let head = content.trim_start();
+    if (head.starts_with('{') || head.starts_with('['))
+        && serde_json::from_str::<serde_json::Value>(content).is_ok()
+    {
+        LinkSource::Telegram
+    } else if head.starts_with('<') {
+        LinkSource::Html
+    } else {
+        LinkSource::Text
+    }
+}
+
+fn find_in_text(text: &str) -> Vec<String> {
+    link_regex()
+        .find_iter(text)
+        // sentence punctuation glued to the end of a link
+        .map(|m| m.as_str().trim_end_matches(['.', ',', ';', ':', '!', '?', ')']).to_string())
+        .collect()
+}
+
+/// message text, text entities and `text_link` hrefs are all plain strings somewhere in the tree
+fn walk_json(value: &serde_json::Value, links: &mut Vec<String>) {
+    match value {
+        serde_json::Value::String(s) => links.extend(find_in_text(s)),
+        serde_json::Value::Array(items) => items.iter().for_each(|v| walk_json(v, links)),
+        serde_json::Value::Object(map) => map.values().for_each(|v| walk_json(v, links)),
+        _ => {}
+    }
+}
@@ -1,18 +1,42 @@
 use std::fmt::{self, Display};
 
+use clap::Parser;
+use telegrab::cli::{Cli, Command};
+use telegrab::configuration::Settings;
 use telegrab::state::AppState;
 use telegrab::{
-    Result,
+    Result, cli,
     configuration::get_configuration,
+    disk::start_disk_monitor,
     startup::run_app_until_stopped,
-    telemetry::init,
-    worker::{setup_fs_monitor, start_auto_cleanup_task, start_background_workers},
+    telemetry::{self, init},
+    scheduler::start_scheduler,
+    webhook::start_webhook_dispatcher,
+    worker::{setup_fs_monitor, start_background_workers},
 };
 use tokio::task::JoinError;
 
 #[tokio::main]
 async fn main() -> Result<()> {
+    let cli = Cli::parse();
+    let command = match cli.command.unwrap_or(Command::Serve) {
+        // only touches the given file, works without a configuration
+        Command::ComicInfo(args) => return cli::comicinfo::run(args).await,
+        command => command,
+    };
     let configuration = get_configuration().expect("Failed to read configuration.");
+    match command {
+        Command::Serve => serve(configuration).await,
+        Command::Token(command) => cli::token::run(command, &configuration).await,
+        Command::Migrate(command) => cli::migrate::run(command, &configuration).await,
+        Command::Grab(args) => cli::grab::run(args, &configuration).await,
+        Command::Scan => cli::library::scan(&configuration).await,
+        Command::Archive { doc_id } => cli::library::archive(doc_id, &configuration).await,
+        Command::ComicInfo(_) => unreachable!("handled before the configuration is read"),
+    }
+}
+
+async fn serve(configuration: Settings) -> Result<()> {
     init(&configuration.logger);
     let app_state = AppState::build(&configuration).await;
     let application_task = tokio::spawn(run_app_until_stopped(
@@ -23,14 +47,14 @@ async fn main() -> Result<()> {
         app_state.clone(),
         configuration.clone(),
     ));
-    tokio::spawn(start_auto_cleanup_task(
-        app_state.clone(),
-        configuration.clone(),
-    ));
-    tokio::spawn(setup_fs_monitor(app_state.clone(), configuration.clone()));
+    tokio::spawn(start_scheduler(app_state.clone(), configuration.clone()));
+    tokio::spawn(start_disk_monitor(app_state.clone(), configuration.clone()));
+    tokio::spawn(start_webhook_dispatcher(app_state.clone(), configuration.clone()));
+    tokio::spawn(setup_fs_monitor(app_state.clone()));
     tokio::select! {
         o = application_task => report_exit("API server", o),
     }
+    telemetry::shutdown();
     Ok(())
 }
 
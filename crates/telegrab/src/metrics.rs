@@ -0,0 +1,223 @@
+use crate::model::entity::task::{TaskStatus, TaskType};
+use crate::state::QueueState;
+use prometheus::{
+    Encoder, GaugeVec, Histogram, HistogramOpts, HistogramVec, IntCounterVec, IntGauge,
+    IntGaugeVec, Opts, Registry, TextEncoder, exponential_buckets,
+};
+use serde_variant::to_variant_name;
+use crate::db::DbPool;
+use std::collections::HashMap;
+use std::sync::OnceLock;
+use std::time::Duration;
+
+static METRICS: OnceLock<Metrics> = OnceLock::new();
+
+/// the metrics of this process, registered on first use
+pub fn metrics() -> &'static Metrics {
+    METRICS.get_or_init(Metrics::new)
+}
+
+/// Everything `/metrics` exposes. Counters and histograms are fed where things
+/// happen, queue depth and pool usage are read from their owners on every scrape.
+#[derive(Debug)]
+pub struct Metrics {
+    registry: Registry,
+    queue_tasks: IntGaugeVec,
+    task_duration: HistogramVec,
+    workers: IntGauge,
+    active_workers: IntGauge,
+    download_bytes: IntCounterVec,
+    download_duration: HistogramVec,
+    download_throughput: GaugeVec,
+    upstream_responses: IntCounterVec,
+    parse_failures: IntCounterVec,
+    cbz_size: Histogram,
+    db_pool_connections: IntGaugeVec,
+    http_request_duration: HistogramVec,
+}
+
+impl Metrics {
+    fn new() -> Self {
+        let registry = Registry::new_custom(Some("telegrab".to_string()), None)
+            .expect("valid metrics prefix");
+        let queue_tasks = IntGaugeVec::new(
+            Opts::new("queue_tasks", "Tasks known to the queue by type and status"),
+            &["task_type", "status"],
+        )
+        .unwrap();
+        let task_duration = HistogramVec::new(
+            HistogramOpts::new("task_duration_seconds", "Time a worker spent on a task")
+                .buckets(exponential_buckets(0.01, 4.0, 10).unwrap()),
+            &["task_type", "status"],
+        )
+        .unwrap();
+        let workers = IntGauge::new("workers", "Configured workers").unwrap();
+        let active_workers =
+            IntGauge::new("active_workers", "Workers processing a task right now").unwrap();
+        let download_bytes = IntCounterVec::new(
+            Opts::new("download_bytes_total", "Bytes of downloaded pics by host"),
+            &["host"],
+        )
+        .unwrap();
+        let download_duration = HistogramVec::new(
+            HistogramOpts::new("download_duration_seconds", "Time to download a pic by host")
+                .buckets(exponential_buckets(0.05, 2.0, 10).unwrap()),
+            &["host"],
+        )
+        .unwrap();
+        let download_throughput = GaugeVec::new(
+            Opts::new(
+                "download_throughput_bytes_per_second",
+                "Speed of the last pic download by host",
+            ),
+            &["host"],
+        )
+        .unwrap();
+        let upstream_responses = IntCounterVec::new(
+            Opts::new(
+                "upstream_responses_total",
+                "Responses of upstream hosts by status code, retried ones included",
+            ),
+            &["host", "status"],
+        )
+        .unwrap();
+        let parse_failures = IntCounterVec::new(
+            Opts::new("parse_failures_total", "Pages that could not be parsed by host"),
+            &["host"],
+        )
+        .unwrap();
+        let cbz_size = Histogram::with_opts(
+            HistogramOpts::new("cbz_size_bytes", "Size of built cbz archives")
+                .buckets(exponential_buckets(1024.0 * 1024.0, 2.0, 12).unwrap()),
+        )
+        .unwrap();
+        let db_pool_connections = IntGaugeVec::new(
+            Opts::new("db_pool_connections", "Database connections by state"),
+            &["state"],
+        )
+        .unwrap();
+        let http_request_duration = HistogramVec::new(
+            HistogramOpts::new(
+                "http_request_duration_seconds",
+                "Latency of served requests by route",
+            ),
+            &["method", "route", "status"],
+        )
+        .unwrap();
+        let collectors: Vec<Box<dyn prometheus::core::Collector>> = vec![
+            Box::new(queue_tasks.clone()),
+            Box::new(task_duration.clone()),
+            Box::new(workers.clone()),
+            Box::new(active_workers.clone()),
+            Box::new(download_bytes.clone()),
+            Box::new(download_duration.clone()),
+            Box::new(download_throughput.clone()),
+            Box::new(upstream_responses.clone()),
+            Box::new(parse_failures.clone()),
+            Box::new(cbz_size.clone()),
+            Box::new(db_pool_connections.clone()),
+            Box::new(http_request_duration.clone()),
+        ];
+        for collector in collectors {
+            registry.register(collector).expect("metrics are registered once");
+        }
+        Self {
+            registry,
+            queue_tasks,
+            task_duration,
+            workers,
+            active_workers,
+            download_bytes,
+            download_duration,
+            download_throughput,
+            upstream_responses,
+            parse_failures,
+            cbz_size,
+            db_pool_connections,
+            http_request_duration,
+        }
+    }
+    pub fn set_workers(&self, count: usize) {
+        self.workers.set(count as i64);
+    }
+    pub fn worker_started(&self) {
+        self.active_workers.inc();
+    }
+    pub fn worker_finished(&self, task_type: &TaskType, succeeded: bool, elapsed: Duration) {
+        self.active_workers.dec();
+        let status = if succeeded { "completed" } else { "failed" };
+        self.task_duration
+            .with_label_values(&[task_type_label(task_type), status])
+            .observe(elapsed.as_secs_f64());
+    }
+    pub fn download(&self, host: &str, bytes: u64, elapsed: Duration) {
+        self.download_bytes.with_label_values(&[host]).inc_by(bytes);
+        self.download_duration
+            .with_label_values(&[host])
+            .observe(elapsed.as_secs_f64());
+        if elapsed > Duration::ZERO {
+            self.download_throughput
+                .with_label_values(&[host])
+                .set(bytes as f64 / elapsed.as_secs_f64());
+        }
+    }
+    pub fn upstream_response(&self, host: &str, status: u16) {
+        self.upstream_responses
+            .with_label_values(&[host, &status.to_string()])
+            .inc();
+    }
+    pub fn parse_failure(&self, host: &str) {
+        self.parse_failures.with_label_values(&[host]).inc();
+    }
+    pub fn cbz_built(&self, bytes: u64) {
+        self.cbz_size.observe(bytes as f64);
+    }
+    pub fn http_request(&self, method: &str, route: &str, status: u16, elapsed: Duration) {
+        self.http_request_duration
+            .with_label_values(&[method, route, &status.to_string()])
+            .observe(elapsed.as_secs_f64());
+    }
+    /// samples the queue and the pool, then encodes everything in the text format
+    pub async fn render(&self, queue_state: &QueueState, db_pool: &DbPool) -> String {
+        let mut counts: HashMap<(&'static str, &'static str), i64> = HashMap::new();
+        for task in queue_state.get_tasks().await {
+            let status = match task.status {
+                TaskStatus::Pending => "pending",
+                TaskStatus::Processing => "processing",
+                TaskStatus::Completed => "completed",
+                TaskStatus::Failed => "failed",
+            };
+            *counts
+                .entry((task_type_label(&task.task_type), status))
+                .or_default() += 1;
+        }
+        // types that left the queue must drop to zero instead of keeping their last value
+        self.queue_tasks.reset();
+        for ((task_type, status), count) in counts {
+            self.queue_tasks
+                .with_label_values(&[task_type, status])
+                .set(count);
+        }
+        let idle = db_pool.num_idle() as i64;
+        let size = db_pool.size() as i64;
+        self.db_pool_connections
+            .with_label_values(&["idle"])
+            .set(idle);
+        self.db_pool_connections
+            .with_label_values(&["active"])
+            .set(size - idle);
+        self.db_pool_connections
+            .with_label_values(&["max"])
+            .set(db_pool.max_connections() as i64);
+        let mut buffer = vec![];
+        if let Err(err) = TextEncoder::new().encode(&self.registry.gather(), &mut buffer) {
+            tracing::warn!("Encode metrics failed: {}", err);
+        }
+        String::from_utf8(buffer).unwrap_or_default()
+    }
+}
+
+/// the variant name, without the ids that would make every task its own series
+fn task_type_label(task_type: &TaskType) -> &'static str {
+    to_variant_name(task_type).unwrap_or("unknown")
+}
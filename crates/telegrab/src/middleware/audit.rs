@@ -0,0 +1,90 @@
+use crate::middleware::TeleGrabRequestId;
+use crate::model::dto::audit::NewAuditEvent;
+use crate::model::entity::api_token::ApiIdentity;
+use crate::model::entity::audit::{AuditOutcome, AuditSource};
+use crate::service;
+use crate::state::AppState;
+use axum::extract::{MatchedPath, Request, State};
+use axum::http::Method;
+use axum::middleware::Next;
+use axum::response::Response;
+use serde_json::Value;
+
+/// What a handler changed, put in the response extensions and stored with the event.
+#[derive(Debug, Clone)]
+pub struct AuditChanges(pub Value);
+
+/// The id of what a handler created, for routes without one in the path.
+#[derive(Debug, Clone)]
+pub struct AuditEntity(pub String);
+
+/// Records every `/api` request that may change something, reads are not recorded.
+/// The entity is the first segment of the route after `/api` (and `/admin`),
+/// its id the first path parameter, or the [`AuditEntity`] of the response.
+pub async fn audit_middleware(
+    State(state): State<AppState>,
+    request: Request,
+    next: Next,
+) -> Response {
+    let path = request.uri().path().to_string();
+    if matches!(*request.method(), Method::GET | Method::HEAD | Method::OPTIONS)
+        || !path.starts_with("/api/")
+    {
+        return next.run(request).await;
+    }
+    let route = request
+        .extensions()
+        .get::<MatchedPath>()
+        .map_or_else(|| path.clone(), |p| p.as_str().to_string());
+    let action = format!("{} {}", request.method(), route);
+    let identity = request.extensions().get::<ApiIdentity>().cloned();
+    let request_id = request
+        .extensions()
+        .get::<TeleGrabRequestId>()
+        .map(|r| r.get().to_string());
+    let response = next.run(request).await;
+    let (entity, entity_id) = route_entity(&route, &path);
+    let entity_id =
+        entity_id.or_else(|| response.extensions().get::<AuditEntity>().map(|e| e.0.clone()));
+    let status = response.status();
+    let event = NewAuditEvent {
+        source: AuditSource::Rest,
+        actor: identity
+            .as_ref()
+            .map_or_else(|| "anonymous".to_string(), |i| i.name.clone()),
+        token_id: identity.and_then(|i| i.token_id),
+        request_id,
+        task_id: None,
+        action,
+        entity,
+        entity_id,
+        changes: response.extensions().get::<AuditChanges>().map(|c| c.0.clone()),
+        outcome: if status.is_success() {
+            AuditOutcome::Ok
+        } else {
+            AuditOutcome::Error
+        },
+        detail: Some(status.to_string()),
+    };
+    if let Err(err) = service::audit::record(&state.db_pool, event).await {
+        tracing::warn!("Record audit event for {} failed: {}", path, err);
+    }
+    response
+}
+
+/// `/api/doc/{id}/refresh` with `/api/doc/3/refresh` is `(doc, 3)`
+fn route_entity(route: &str, path: &str) -> (Option<String>, Option<String>) {
+    let route_segments: Vec<&str> = route.split('/').skip(2).collect();
+    let path_segments: Vec<&str> = path.split('/').skip(2).collect();
+    let offset = usize::from(route_segments.first() == Some(&"admin"));
+    let entity = route_segments
+        .get(offset)
+        .filter(|s| !s.is_empty() && !s.starts_with('{'))
+        .map(|s| s.to_string());
+    let entity_id = route_segments
+        .iter()
+        .position(|s| s.starts_with('{'))
+        .and_then(|i| path_segments.get(i))
+        .map(|s| s.to_string());
+    (entity, entity_id)
+}
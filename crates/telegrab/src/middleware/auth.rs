@@ -0,0 +1,96 @@
+use crate::errors::Error;
+use crate::model::entity::api_token::{ApiIdentity, ApiScope};
+use crate::service;
+use crate::state::AppState;
+use axum::extract::{Request, State};
+use axum::http::{HeaderMap, Method, header};
+use axum::middleware::Next;
+use axum::response::Response;
+
+const X_API_KEY: &str = "x-api-key";
+const ACCESS_TOKEN_PARAM: &str = "access_token";
+
+/// paths reachable without a token
+fn is_public(method: &Method, path: &str) -> bool {
+    path == "/api/health"
+        || path.starts_with("/api/health/")
+        // the graphiql page, its requests carry their own headers
+        || (method == Method::GET && path == "/graphql")
+        // websocket clients authenticate in the connection_init payload
+        || path == "/graphql/ws"
+}
+
+fn required_scope(method: &Method, path: &str) -> ApiScope {
+    if path.starts_with("/api/admin") {
+        return ApiScope::Admin;
+    }
+    // graphql mutations are checked by `ScopeGuard`, the endpoint itself only needs read
+    if path == "/graphql" {
+        return ApiScope::Read;
+    }
+    match *method {
+        Method::GET | Method::HEAD | Method::OPTIONS => ApiScope::Read,
+        _ => ApiScope::Write,
+    }
+}
+
+/// reads the token from `Authorization: Bearer`, `X-Api-Key`,
+/// or the `access_token` query param for clients that can not set headers (EventSource, img)
+pub fn extract_token(headers: &HeaderMap, query: Option<&str>) -> Option<String> {
+    let bearer = headers
+        .get(header::AUTHORIZATION)
+        .and_then(|h| h.to_str().ok())
+        .and_then(|h| h.strip_prefix("Bearer "))
+        .map(|t| t.trim().to_string());
+    let api_key = || {
+        headers
+            .get(X_API_KEY)
+            .and_then(|h| h.to_str().ok())
+            .map(|t| t.trim().to_string())
+    };
+    let query_param = || {
+        query.and_then(|q| {
+            url::form_urlencoded::parse(q.as_bytes())
+                .find(|(k, _)| k == ACCESS_TOKEN_PARAM)
+                .map(|(_, v)| v.to_string())
+        })
+    };
+    bearer
+        .or_else(api_key)
+        .or_else(query_param)
+        .filter(|t| !t.is_empty())
+}
+
+pub async fn resolve_identity(state: &AppState, token: Option<&str>) -> Result<ApiIdentity, Error> {
+    if !state.auth_enabled {
+        return Ok(ApiIdentity::unrestricted());
+    }
+    let token = token.ok_or_else(|| Error::Unauthorized("missing api token".to_string()))?;
+    match service::api_token::authenticate(&state.db_pool, token).await? {
+        Some(api_token) => Ok(api_token.into()),
+        None => Err(Error::Unauthorized("invalid or revoked api token".to_string())),
+    }
+}
+
+pub async fn auth_middleware(
+    State(state): State<AppState>,
+    mut request: Request,
+    next: Next,
+) -> Result<Response, Error> {
+    let method = request.method().clone();
+    let path = request.uri().path().to_string();
+    if is_public(&method, &path) {
+        return Ok(next.run(request).await);
+    }
+    let token = extract_token(request.headers(), request.uri().query());
+    let identity = resolve_identity(&state, token.as_deref()).await?;
+    let required = required_scope(&method, &path);
+    if !identity.has_scope(required) {
+        return Err(Error::Forbidden(format!(
+            "token `{}` lacks `{}` scope for {} {}",
+            identity.name, required, method, path
+        )));
+    }
+    request.extensions_mut().insert(identity);
+    Ok(next.run(request).await)
+}
@@ -0,0 +1,19 @@
+use crate::metrics::metrics;
+use axum::extract::{MatchedPath, Request};
+use axum::middleware::Next;
+use axum::response::Response;
+use std::time::Instant;
+
+/// records the latency of every request under its route template, e.g. `/api/doc/{id}`
+pub async fn metrics_middleware(request: Request, next: Next) -> Response {
+    let start = Instant::now();
+    let method = request.method().to_string();
+    // unmatched paths share one series, they are made up by clients
+    let route = request
+        .extensions()
+        .get::<MatchedPath>()
+        .map_or_else(|| "unmatched".to_string(), |p| p.as_str().to_string());
+    let response = next.run(request).await;
+    metrics().http_request(&method, &route, response.status().as_u16(), start.elapsed());
+    response
+}
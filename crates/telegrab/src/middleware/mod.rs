@@ -1,3 +1,9 @@
+mod audit;
+mod auth;
+mod metrics;
 mod request_id;
 
+pub use audit::{AuditChanges, AuditEntity, audit_middleware};
+pub use auth::{auth_middleware, extract_token, resolve_identity};
+pub use metrics::metrics_middleware;
 pub use request_id::{TeleGrabRequestId, request_id_middleware};
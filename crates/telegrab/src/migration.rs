@@ -0,0 +1,151 @@
+use crate::Result;
+use crate::db::{with_pool, DbPool};
+use sqlx::migrate::{Migrate, Migrator};
+use sqlx::query;
+use std::collections::HashMap;
+use time::OffsetDateTime;
+
+/// `migrations/` of the workspace for Postgres, embedded at build time
+pub static MIGRATOR: Migrator = sqlx::migrate!("../../migrations");
+
+/// `migrations/sqlite/`, the same versions written for SQLite
+#[cfg(feature = "sqlite")]
+pub static SQLITE_MIGRATOR: Migrator = sqlx::migrate!("../../migrations/sqlite");
+
+/// the migrations written for the backend of `pool`
+pub fn migrator(pool: &DbPool) -> &'static Migrator {
+    match pool {
+        DbPool::Postgres(_) => &MIGRATOR,
+        #[cfg(feature = "sqlite")]
+        DbPool::Sqlite(_) => &SQLITE_MIGRATOR,
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum MigrationState {
+    Applied(OffsetDateTime),
+    Pending,
+    /// a previous run stopped halfway, the database needs a look by hand
+    Failed,
+    /// applied, but the file changed since
+    Modified,
+    /// applied, but not embedded in this binary, e.g. after a downgrade
+    Unknown,
+}
+
+#[derive(Debug, Clone)]
+pub struct MigrationStatus {
+    pub version: i64,
+    pub description: String,
+    pub state: MigrationState,
+}
+
+#[derive(sqlx::FromRow)]
+struct AppliedRow {
+    version: i64,
+    description: String,
+    installed_on: OffsetDateTime,
+    success: bool,
+    checksum: Vec<u8>,
+}
+
+async fn applied_rows(pool: &DbPool) -> Result<Vec<AppliedRow>> {
+    let sql = r#"
+        SELECT version, description, installed_on, success, checksum
+        FROM _sqlx_migrations
+        ORDER BY version
+    "#;
+    with_pool!(pool, db => {
+        let mut conn = db.acquire().await?;
+        conn.ensure_migrations_table().await?;
+        Ok(sqlx::query_as(sql).fetch_all(&mut *conn).await?)
+    })
+}
+
+/// Applies every pending migration.
+pub async fn up(pool: &DbPool) -> Result<()> {
+    with_pool!(pool, db => migrator(pool).run(db).await?);
+    Ok(())
+}
+
+/// Embedded and applied migrations side by side, ordered by version.
+pub async fn status(pool: &DbPool) -> Result<Vec<MigrationStatus>> {
+    let mut applied: HashMap<i64, AppliedRow> = applied_rows(pool)
+        .await?
+        .into_iter()
+        .map(|row| (row.version, row))
+        .collect();
+    let mut statuses: Vec<MigrationStatus> = migrator(pool)
+        .iter()
+        .filter(|m| m.migration_type.is_up_migration())
+        .map(|m| {
+            let state = match applied.remove(&m.version) {
+                None => MigrationState::Pending,
+                Some(row) if !row.success => MigrationState::Failed,
+                Some(row) if row.checksum != *m.checksum => MigrationState::Modified,
+                Some(row) => MigrationState::Applied(row.installed_on),
+            };
+            MigrationStatus {
+                version: m.version,
+                description: m.description.to_string(),
+                state,
+            }
+        })
+        .collect();
+    statuses.extend(applied.into_values().map(|row| MigrationStatus {
+        version: row.version,
+        description: row.description,
+        state: MigrationState::Unknown,
+    }));
+    statuses.sort_by_key(|s| s.version);
+    Ok(statuses)
+}
+
+/// Reverts the migrations applied after `to`, only the latest one without it.
+/// Returns the reverted versions, newest first.
+pub async fn down(pool: &DbPool, to: Option<i64>) -> Result<Vec<i64>> {
+    let mut applied: Vec<i64> = applied_rows(pool)
+        .await?
+        .into_iter()
+        .map(|row| row.version)
+        .collect();
+    applied.reverse();
+    let target = match to {
+        Some(to) => to,
+        None => applied.get(1).copied().unwrap_or(0),
+    };
+    with_pool!(pool, db => migrator(pool).undo(db, target).await?);
+    Ok(applied.into_iter().take_while(|v| *v > target).collect())
+}
+
+/// Records the embedded migrations up to `to` as applied without running them,
+/// for databases that were set up by hand before migrations were tracked.
+/// Returns the newly recorded versions.
+pub async fn baseline(pool: &DbPool, to: Option<i64>) -> Result<Vec<i64>> {
+    let sql = r#"
+        INSERT INTO _sqlx_migrations (version, description, success, checksum, execution_time)
+        VALUES ($1, $2, TRUE, $3, 0)
+        ON CONFLICT (version) DO NOTHING
+    "#;
+    let mut recorded = vec![];
+    with_pool!(pool, db => {
+        let mut conn = db.acquire().await?;
+        conn.ensure_migrations_table().await?;
+        for migration in migrator(pool)
+            .iter()
+            .filter(|m| m.migration_type.is_up_migration())
+            .filter(|m| to.is_none_or(|to| m.version <= to))
+        {
+            let result = query(sql)
+                .bind(migration.version)
+                .bind(&*migration.description)
+                .bind(&*migration.checksum)
+                .execute(&mut *conn)
+                .await?;
+            if result.rows_affected() > 0 {
+                recorded.push(migration.version);
+            }
+        }
+    });
+    Ok(recorded)
+}
@@ -0,0 +1,39 @@
+use crate::model::entity::audit::{AuditOutcome, AuditSource};
+use serde::Deserialize;
+use serde_json::Value;
+use time::OffsetDateTime;
+use time::serde::rfc3339;
+
+#[derive(Debug, Clone)]
+pub struct NewAuditEvent {
+    pub source: AuditSource,
+    pub actor: String,
+    pub token_id: Option<i32>,
+    pub request_id: Option<String>,
+    pub task_id: Option<String>,
+    pub action: String,
+    pub entity: Option<String>,
+    pub entity_id: Option<String>,
+    pub changes: Option<Value>,
+    pub outcome: AuditOutcome,
+    pub detail: Option<String>,
+}
+
+/// every field that is set must match, `since` and `until` bound `createdAt`
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AuditFilter {
+    pub source: Option<AuditSource>,
+    pub actor: Option<String>,
+    pub token_id: Option<i32>,
+    pub request_id: Option<String>,
+    pub task_id: Option<String>,
+    pub action: Option<String>,
+    pub entity: Option<String>,
+    pub entity_id: Option<String>,
+    pub outcome: Option<AuditOutcome>,
+    #[serde(default, with = "rfc3339::option")]
+    pub since: Option<OffsetDateTime>,
+    #[serde(default, with = "rfc3339::option")]
+    pub until: Option<OffsetDateTime>,
+}
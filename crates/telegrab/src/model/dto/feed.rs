@@ -0,0 +1,80 @@
+use regex::Regex;
+use serde::Deserialize;
+
+/// feeds are not polled more often than this
+pub const MIN_POLL_INTERVAL_SECS: i32 = 60;
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CreateFeedReq {
+    pub url: String,
+    pub title: Option<String>,
+    pub enabled: Option<bool>,
+    pub auto_grab: Option<bool>,
+    pub include_pattern: Option<String>,
+    pub exclude_pattern: Option<String>,
+    pub poll_interval_secs: Option<i32>,
+}
+
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct UpdateFeedReq {
+    pub url: Option<String>,
+    pub title: Option<String>,
+    pub enabled: Option<bool>,
+    pub auto_grab: Option<bool>,
+    /// an empty string removes the pattern
+    pub include_pattern: Option<String>,
+    pub exclude_pattern: Option<String>,
+    pub poll_interval_secs: Option<i32>,
+}
+
+impl CreateFeedReq {
+    pub fn validate(&self) -> Result<(), String> {
+        validate_feed_fields(
+            Some(&self.url),
+            self.include_pattern.as_deref(),
+            self.exclude_pattern.as_deref(),
+            self.poll_interval_secs,
+        )
+    }
+}
+
+impl UpdateFeedReq {
+    pub fn validate(&self) -> Result<(), String> {
+        validate_feed_fields(
+            self.url.as_deref(),
+            self.include_pattern.as_deref(),
+            self.exclude_pattern.as_deref(),
+            self.poll_interval_secs,
+        )
+    }
+}
+
+fn validate_feed_fields(
+    url: Option<&str>,
+    include_pattern: Option<&str>,
+    exclude_pattern: Option<&str>,
+    poll_interval_secs: Option<i32>,
+) -> Result<(), String> {
+    if let Some(url) = url {
+        let parsed = url::Url::parse(url).map_err(|e| format!("Invalid url {}: {}", url, e))?;
+        if !matches!(parsed.scheme(), "http" | "https") {
+            return Err(format!("Unsupported scheme: {}", parsed.scheme()));
+        }
+    }
+    for pattern in [include_pattern, exclude_pattern].into_iter().flatten() {
+        if !pattern.is_empty() {
+            Regex::new(pattern).map_err(|e| format!("Invalid pattern {}: {}", pattern, e))?;
+        }
+    }
+    if let Some(secs) = poll_interval_secs
+        && secs < MIN_POLL_INTERVAL_SECS
+    {
+        return Err(format!(
+            "poll_interval_secs must be at least {}",
+            MIN_POLL_INTERVAL_SECS
+        ));
+    }
+    Ok(())
+}
@@ -0,0 +1,43 @@
+use crate::link::LinkSource;
+use crate::model::entity::doc::Doc;
+use async_graphql::Enum;
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Clone, Copy, Default, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ImportQuery {
+    pub source: Option<LinkSource>,
+    #[serde(default)]
+    pub enqueue: bool,
+}
+
+#[derive(Debug, Clone, Copy, Eq, PartialEq, Serialize, Enum)]
+#[serde(rename_all = "lowercase")]
+pub enum ImportStatus {
+    Created,
+    /// already in `doc`, left untouched
+    Existing,
+    Invalid,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ImportedUrl {
+    pub url: String,
+    pub canonical_url: Option<String>,
+    pub status: ImportStatus,
+    pub doc_id: Option<i32>,
+    pub task_id: Option<String>,
+    pub error: Option<String>,
+    #[serde(skip)]
+    pub doc: Option<Doc>,
+}
+
+#[derive(Debug, Clone, Default, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ImportReport {
+    pub created: usize,
+    pub existing: usize,
+    pub invalid: usize,
+    pub results: Vec<ImportedUrl>,
+}
@@ -1,9 +1,14 @@
 use serde::Serialize;
 
+pub mod audit;
 pub mod cbz;
 pub mod doc;
+pub mod feed;
+pub mod import;
 pub mod pagination;
 pub mod pic;
+pub mod schedule;
+pub mod webhook;
 
 #[derive(Debug, Copy, Clone, Serialize)]
 pub struct AffectedRows {
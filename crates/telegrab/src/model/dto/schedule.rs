@@ -0,0 +1,37 @@
+use crate::model::entity::task::TaskType;
+use crate::scheduler;
+use serde::Deserialize;
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CreateScheduleReq {
+    pub name: String,
+    pub cron: String,
+    pub task_type: TaskType,
+    pub enabled: Option<bool>,
+}
+
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct UpdateScheduleReq {
+    pub cron: Option<String>,
+    pub enabled: Option<bool>,
+}
+
+impl CreateScheduleReq {
+    pub fn validate(&self) -> Result<(), String> {
+        if self.name.trim().is_empty() {
+            return Err("name must not be empty".to_string());
+        }
+        scheduler::parse_cron(&self.cron).map(|_| ())
+    }
+}
+
+impl UpdateScheduleReq {
+    pub fn validate(&self) -> Result<(), String> {
+        match &self.cron {
+            Some(cron) => scheduler::parse_cron(cron).map(|_| ()),
+            None => Ok(()),
+        }
+    }
+}
@@ -0,0 +1,74 @@
+use crate::model::entity::webhook::WEBHOOK_EVENTS;
+use serde::Deserialize;
+
+/// secrets shorter than this are easy to guess
+pub const MIN_SECRET_LEN: usize = 16;
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CreateWebhookReq {
+    pub url: String,
+    pub secret: String,
+    /// every event when empty or missing
+    pub events: Option<Vec<String>>,
+    pub enabled: Option<bool>,
+    pub description: Option<String>,
+}
+
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct UpdateWebhookReq {
+    pub url: Option<String>,
+    pub secret: Option<String>,
+    pub events: Option<Vec<String>>,
+    pub enabled: Option<bool>,
+    /// an empty string removes the description
+    pub description: Option<String>,
+}
+
+impl CreateWebhookReq {
+    pub fn validate(&self) -> Result<(), String> {
+        validate_webhook_fields(Some(&self.url), Some(&self.secret), self.events.as_deref())
+    }
+}
+
+impl UpdateWebhookReq {
+    pub fn validate(&self) -> Result<(), String> {
+        validate_webhook_fields(
+            self.url.as_deref(),
+            self.secret.as_deref(),
+            self.events.as_deref(),
+        )
+    }
+}
+
+fn validate_webhook_fields(
+    url: Option<&str>,
+    secret: Option<&str>,
+    events: Option<&[String]>,
+) -> Result<(), String> {
+    if let Some(url) = url {
+        let parsed = url::Url::parse(url).map_err(|e| format!("Invalid url {}: {}", url, e))?;
+        if !matches!(parsed.scheme(), "http" | "https") {
+            return Err(format!("Unsupported scheme: {}", parsed.scheme()));
+        }
+    }
+    if let Some(secret) = secret
+        && secret.len() < MIN_SECRET_LEN
+    {
+        return Err(format!("secret must be at least {} characters", MIN_SECRET_LEN));
+    }
+    for event in events.into_iter().flatten() {
+        let known = event == "*"
+            || WEBHOOK_EVENTS.contains(&event.as_str())
+            || event.strip_suffix(".*").is_some_and(|prefix| {
+                WEBHOOK_EVENTS
+                    .iter()
+                    .any(|e| e.split('.').next() == Some(prefix))
+            });
+        if !known {
+            return Err(format!("Unknown event: {}", event));
+        }
+    }
+    Ok(())
+}
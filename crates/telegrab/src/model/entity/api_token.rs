@@ -0,0 +1,184 @@
+use serde::{Deserialize, Serialize};
+use sqlx::encode::IsNull;
+use sqlx::error::BoxDynError;
+use sqlx::{Decode, Encode, FromRow, Type};
+use sqlx_postgres::{PgArgumentBuffer, PgTypeInfo, PgValueRef, Postgres};
+#[cfg(feature = "sqlite")]
+use sqlx_sqlite::{Sqlite, SqliteArgumentValue, SqliteTypeInfo, SqliteValueRef};
+use std::fmt::Display;
+use std::str::FromStr;
+use time::serde::rfc3339;
+use time::OffsetDateTime;
+
+#[derive(Debug, Clone, Copy, Eq, PartialEq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ApiScope {
+    Read,
+    Write,
+    Admin,
+}
+
+impl ApiScope {
+    /// admin implies write, write implies read
+    pub fn covers(&self, required: ApiScope) -> bool {
+        match self {
+            ApiScope::Admin => true,
+            ApiScope::Write => matches!(required, ApiScope::Read | ApiScope::Write),
+            ApiScope::Read => matches!(required, ApiScope::Read),
+        }
+    }
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ApiScope::Read => "read",
+            ApiScope::Write => "write",
+            ApiScope::Admin => "admin",
+        }
+    }
+}
+
+impl Display for ApiScope {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+impl FromStr for ApiScope {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.trim().to_lowercase().as_str() {
+            "read" => Ok(ApiScope::Read),
+            "write" => Ok(ApiScope::Write),
+            "admin" => Ok(ApiScope::Admin),
+            other => Err(format!(
+                "{} is not a supported scope. Use `read`, `write` or `admin`",
+                other
+            )),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, FromRow)]
+#[serde(rename_all = "camelCase")]
+pub struct ApiToken {
+    pub id: i32,
+    pub name: String,
+    pub token_prefix: String,
+    #[serde(skip_serializing)]
+    pub token_hash: String,
+    #[sqlx(try_from = "ScopeList")]
+    pub scopes: Vec<String>,
+    #[serde(with = "rfc3339::option")]
+    pub last_used_at: Option<OffsetDateTime>,
+    #[serde(with = "rfc3339::option")]
+    pub revoked_at: Option<OffsetDateTime>,
+    #[serde(with = "rfc3339")]
+    pub created_at: OffsetDateTime,
+    #[serde(with = "rfc3339")]
+    pub updated_at: OffsetDateTime,
+}
+
+impl ApiToken {
+    pub fn api_scopes(&self) -> Vec<ApiScope> {
+        self.scopes
+            .iter()
+            .filter_map(|s| s.parse::<ApiScope>().ok())
+            .collect()
+    }
+}
+
+/// The `scopes` column, a text array in Postgres and a json list in SQLite
+#[derive(Debug, Clone, Default)]
+pub struct ScopeList(pub Vec<String>);
+
+impl From<ScopeList> for Vec<String> {
+    fn from(list: ScopeList) -> Self {
+        list.0
+    }
+}
+
+impl Type<Postgres> for ScopeList {
+    fn type_info() -> PgTypeInfo {
+        <Vec<String> as Type<Postgres>>::type_info()
+    }
+    fn compatible(ty: &PgTypeInfo) -> bool {
+        <Vec<String> as Type<Postgres>>::compatible(ty)
+    }
+}
+
+impl<'r> Decode<'r, Postgres> for ScopeList {
+    fn decode(value: PgValueRef<'r>) -> Result<Self, BoxDynError> {
+        <Vec<String> as Decode<Postgres>>::decode(value).map(ScopeList)
+    }
+}
+
+impl Encode<'_, Postgres> for ScopeList {
+    fn encode_by_ref(&self, buf: &mut PgArgumentBuffer) -> Result<IsNull, BoxDynError> {
+        <Vec<String> as Encode<Postgres>>::encode_by_ref(&self.0, buf)
+    }
+}
+
+#[cfg(feature = "sqlite")]
+impl Type<Sqlite> for ScopeList {
+    fn type_info() -> SqliteTypeInfo {
+        <str as Type<Sqlite>>::type_info()
+    }
+    fn compatible(ty: &SqliteTypeInfo) -> bool {
+        <str as Type<Sqlite>>::compatible(ty)
+    }
+}
+
+#[cfg(feature = "sqlite")]
+impl<'r> Decode<'r, Sqlite> for ScopeList {
+    fn decode(value: SqliteValueRef<'r>) -> Result<Self, BoxDynError> {
+        let json = <&str as Decode<Sqlite>>::decode(value)?;
+        Ok(ScopeList(serde_json::from_str(json)?))
+    }
+}
+
+#[cfg(feature = "sqlite")]
+impl<'q> Encode<'q, Sqlite> for ScopeList {
+    fn encode_by_ref(&self, buf: &mut Vec<SqliteArgumentValue<'q>>) -> Result<IsNull, BoxDynError> {
+        <String as Encode<Sqlite>>::encode(serde_json::to_string(&self.0)?, buf)
+    }
+}
+
+/// The caller resolved from a request, attached to axum request extensions and graphql context data
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ApiIdentity {
+    pub token_id: Option<i32>,
+    pub name: String,
+    pub scopes: Vec<ApiScope>,
+}
+
+impl ApiIdentity {
+    /// used when authentication is disabled in configuration
+    pub fn unrestricted() -> Self {
+        Self {
+            token_id: None,
+            name: "anonymous".to_string(),
+            scopes: vec![ApiScope::Admin],
+        }
+    }
+    pub fn has_scope(&self, required: ApiScope) -> bool {
+        self.scopes.iter().any(|s| s.covers(required))
+    }
+}
+
+impl From<ApiToken> for ApiIdentity {
+    fn from(token: ApiToken) -> Self {
+        Self {
+            token_id: Some(token.id),
+            scopes: token.api_scopes(),
+            name: token.name,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CreatedApiToken {
+    pub token: String,
+    pub api_token: ApiToken,
+}
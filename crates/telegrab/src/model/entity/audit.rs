@@ -0,0 +1,69 @@
+use async_graphql::Enum;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use sqlx::FromRow;
+use sqlx::types::Json;
+use time::OffsetDateTime;
+use time::serde::rfc3339;
+
+/// a row of `audit_event`, rows are only ever added
+#[derive(Debug, Clone, Serialize, Deserialize, FromRow)]
+#[serde(rename_all = "camelCase")]
+pub struct AuditEvent {
+    pub id: i32,
+    /// `rest`, `graphql` or `worker`
+    pub source: String,
+    /// the token name, `anonymous` without auth, `worker 3` for tasks
+    pub actor: String,
+    pub token_id: Option<i32>,
+    /// `x-request-id` of the request
+    pub request_id: Option<String>,
+    pub task_id: Option<String>,
+    /// `PATCH /api/doc/{id}`, `updateAlbum`, `CbzArchive`
+    pub action: String,
+    /// `doc`, `feed`, `cbz`, ...
+    pub entity: Option<String>,
+    pub entity_id: Option<String>,
+    /// `{"title": {"before": .., "after": ..}}` for metadata edits
+    pub changes: Option<Json<Value>>,
+    /// `ok` or `error`
+    pub outcome: String,
+    /// the status code, the error or the task result
+    pub detail: Option<String>,
+    #[serde(with = "rfc3339")]
+    pub created_at: OffsetDateTime,
+}
+
+#[derive(Debug, Clone, Copy, Eq, PartialEq, Enum, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum AuditSource {
+    Rest,
+    Graphql,
+    Worker,
+}
+
+impl AuditSource {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            AuditSource::Rest => "rest",
+            AuditSource::Graphql => "graphql",
+            AuditSource::Worker => "worker",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, Eq, PartialEq, Enum, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum AuditOutcome {
+    Ok,
+    Error,
+}
+
+impl AuditOutcome {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            AuditOutcome::Ok => "ok",
+            AuditOutcome::Error => "error",
+        }
+    }
+}
@@ -0,0 +1,28 @@
+use serde::Serialize;
+use time::OffsetDateTime;
+use time::serde::rfc3339;
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DirUsage {
+    /// the dir, or the bucket and prefix
+    pub path: String,
+    /// size of everything below the dir
+    pub used_bytes: u64,
+    /// of the file system the dir is on, `None` for storages without a fixed size
+    pub available_bytes: Option<u64>,
+    pub total_bytes: Option<u64>,
+    pub quota_bytes: Option<u64>,
+}
+
+/// last check of the pic and cbz storages, a reason is set while those tasks are paused
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DiskUsage {
+    pub pic: DirUsage,
+    pub cbz: DirUsage,
+    pub downloads_paused: Option<String>,
+    pub archives_paused: Option<String>,
+    #[serde(with = "rfc3339")]
+    pub checked_at: OffsetDateTime,
+}
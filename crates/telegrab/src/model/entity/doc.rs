@@ -1,3 +1,4 @@
+use async_graphql::Enum;
 use serde::{Deserialize, Serialize};
 use sqlx::FromRow;
 use time::serde::rfc3339;
@@ -8,7 +9,8 @@ use time::OffsetDateTime;
 pub struct Doc {
     pub id: i32,
     pub cbz_id: Option<i32>,
-    pub status: i16,
+    #[serde(with = "status_number")]
+    pub status: DocStatus,
     pub url: String,
     pub page_title: Option<String>,
     #[serde(with = "rfc3339::option")]
@@ -48,12 +50,113 @@ pub struct Doc {
     pub age_rating: Option<String>,
     pub community_rating: Option<String>,
     pub critical_rating: Option<String>,
+    pub etag: Option<String>,
+    pub last_modified: Option<String>,
+    #[serde(with = "rfc3339::option")]
+    pub checked_at: Option<OffsetDateTime>,
+    pub unavailable: bool,
+    pub cbz_stale: bool,
     #[serde(with = "rfc3339")]
     pub created_at: OffsetDateTime,
     #[serde(with = "rfc3339")]
     pub updated_at: OffsetDateTime,
 }
 
+/// Where a doc is in its lifecycle, stored as `doc.status`. Changes go through
+/// `service::doc::set_doc_status`, which refuses the ones [`DocStatus::can_become`] does not allow.
+#[derive(Debug, Clone, Copy, Eq, PartialEq, Enum, Serialize, Deserialize, sqlx::Type)]
+#[serde(rename_all = "camelCase")]
+#[repr(i16)]
+pub enum DocStatus {
+    New = 0,
+    Parsed = 1,
+    /// every pic is in the pic storage
+    Downloaded = 2,
+    Archived = 3,
+    /// parsing or downloading gave up, the next task for the doc retries
+    Failed = 4,
+    /// pics changed on a refresh since they were downloaded
+    Stale = 5,
+}
+
+impl DocStatus {
+    /// Staying in the same status is always allowed.
+    pub fn can_become(self, to: DocStatus) -> bool {
+        use DocStatus::*;
+        self == to
+            || matches!(
+                (self, to),
+                (New, Parsed | Failed)
+                    | (Parsed, Downloaded | Failed)
+                    | (Downloaded, Archived | Stale | Failed)
+                    | (Archived, Stale)
+                    | (Failed, Parsed | Downloaded)
+                    | (Stale, Parsed | Downloaded | Failed)
+            )
+    }
+
+    /// how far the doc got, a failed doc counts as new and a stale one as parsed
+    pub fn progress(self) -> u8 {
+        match self {
+            DocStatus::New | DocStatus::Failed => 0,
+            DocStatus::Parsed | DocStatus::Stale => 1,
+            DocStatus::Downloaded => 2,
+            DocStatus::Archived => 3,
+        }
+    }
+}
+
+impl TryFrom<i16> for DocStatus {
+    type Error = String;
+
+    fn try_from(value: i16) -> Result<Self, Self::Error> {
+        use DocStatus::*;
+        [New, Parsed, Downloaded, Archived, Failed, Stale]
+            .into_iter()
+            .find(|status| *status as i16 == value)
+            .ok_or_else(|| format!("Unknown doc status {}", value))
+    }
+}
+
+/// `Doc.status` stays the number it always was in the doc json
+mod status_number {
+    use super::DocStatus;
+    use serde::{de, Deserialize, Deserializer, Serializer};
+
+    pub fn serialize<S: Serializer>(status: &DocStatus, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_i16(*status as i16)
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<DocStatus, D::Error> {
+        DocStatus::try_from(i16::deserialize(deserializer)?).map_err(de::Error::custom)
+    }
+}
+
+/// a row of `doc_status_history`
+#[derive(Debug, Clone, Serialize, Deserialize, FromRow)]
+#[serde(rename_all = "camelCase")]
+pub struct DocStatusChange {
+    pub id: i32,
+    pub doc_id: i32,
+    pub from_status: DocStatus,
+    pub to_status: DocStatus,
+    /// `worker 3`, `cli`, ... whatever made the change
+    pub changed_by: String,
+    pub reason: Option<String>,
+    #[serde(with = "rfc3339")]
+    pub created_at: OffsetDateTime,
+}
+
+/// docs whose urls canonicalise to the same page, folded into `id`
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct MergedDoc {
+    pub id: i32,
+    pub old_url: String,
+    pub url: String,
+    pub merged_ids: Vec<i32>,
+}
+
 #[derive(Debug, Clone, Serialize, FromRow)]
 #[serde(rename_all = "camelCase")]
 pub struct ShimDoc {
@@ -91,14 +194,14 @@ impl PageInfo {
     }
 }
 
-#[derive(Debug, Clone, Serialize)]
+#[derive(Debug, Clone, Default, Serialize)]
 #[serde(rename_all = "PascalCase")]
 pub struct Pages {
     #[serde(rename = "Page")]
     pub page: Vec<PageInfo>,
 }
 
-#[derive(Debug, Clone, Serialize)]
+#[derive(Debug, Clone, Default, Serialize)]
 #[serde(rename_all = "PascalCase")]
 pub struct ComicInfo {
     #[serde(skip_serializing_if = "Option::is_none")]
@@ -0,0 +1,65 @@
+use crate::model::entity::doc::DocStatus;
+use serde::Serialize;
+
+/// Something that happened to the library, sent to the queue subscribers
+/// as `QueueEvent::Domain` next to the task events.
+/// The doc and cbz services publish them along with the change.
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase", rename_all_fields = "camelCase")]
+pub enum DomainEvent {
+    /// a doc was stored, by hand, an import or a feed
+    DocAdded {
+        id: i32,
+        url: String,
+    },
+    DocRemoved {
+        id: i32,
+    },
+    /// the metadata of a doc changed, by an edit, a parse, a refresh or a merge
+    DocUpdated {
+        id: i32,
+    },
+    DocStatusChanged {
+        id: i32,
+        from: DocStatus,
+        to: DocStatus,
+    },
+    /// a cbz was built for a doc, or found in `cbz_dir`
+    CbzCreated {
+        id: i32,
+        doc_id: Option<i32>,
+        path: String,
+    },
+    CbzRemoved {
+        id: i32,
+        doc_id: Option<i32>,
+        path: String,
+    },
+    /// a cbz was linked to another doc, or unlinked
+    CbzLinked {
+        id: i32,
+        doc_id: Option<i32>,
+        previous_doc_id: Option<i32>,
+        path: String,
+    },
+}
+
+impl DomainEvent {
+    /// whether the event is about the doc, a relinked cbz concerns both docs
+    pub fn concerns_doc(&self, doc_id: i32) -> bool {
+        match self {
+            DomainEvent::DocAdded { id, .. }
+            | DomainEvent::DocRemoved { id }
+            | DomainEvent::DocUpdated { id }
+            | DomainEvent::DocStatusChanged { id, .. } => *id == doc_id,
+            DomainEvent::CbzCreated { doc_id: id, .. } | DomainEvent::CbzRemoved { doc_id: id, .. } => {
+                *id == Some(doc_id)
+            }
+            DomainEvent::CbzLinked {
+                doc_id: id,
+                previous_doc_id,
+                ..
+            } => *id == Some(doc_id) || *previous_doc_id == Some(doc_id),
+        }
+    }
+}
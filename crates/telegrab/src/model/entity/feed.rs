@@ -0,0 +1,37 @@
+use serde::{Deserialize, Serialize};
+use sqlx::FromRow;
+use time::serde::rfc3339;
+use time::OffsetDateTime;
+
+#[derive(Debug, Clone, Serialize, Deserialize, FromRow)]
+#[serde(rename_all = "camelCase")]
+pub struct Feed {
+    pub id: i32,
+    pub url: String,
+    pub title: Option<String>,
+    pub enabled: bool,
+    pub auto_grab: bool,
+    pub include_pattern: Option<String>,
+    pub exclude_pattern: Option<String>,
+    pub poll_interval_secs: i32,
+    pub etag: Option<String>,
+    pub last_modified: Option<String>,
+    #[serde(with = "rfc3339::option")]
+    pub last_polled_at: Option<OffsetDateTime>,
+    pub last_error: Option<String>,
+    #[serde(with = "rfc3339")]
+    pub created_at: OffsetDateTime,
+    #[serde(with = "rfc3339")]
+    pub updated_at: OffsetDateTime,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, FromRow)]
+#[serde(rename_all = "camelCase")]
+pub struct FeedItem {
+    pub id: i32,
+    pub feed_id: i32,
+    pub url: String,
+    pub doc_id: Option<i32>,
+    #[serde(with = "rfc3339")]
+    pub created_at: OffsetDateTime,
+}
@@ -0,0 +1,62 @@
+use async_graphql::Enum;
+use serde::{Deserialize, Serialize};
+use sqlx::FromRow;
+use sqlx::types::Json;
+use time::OffsetDateTime;
+use time::serde::rfc3339;
+
+/// outcome of a `CollectGarbage` task
+#[derive(Debug, Clone, Serialize, Deserialize, FromRow)]
+#[serde(rename_all = "camelCase")]
+pub struct GcReport {
+    pub id: i32,
+    pub task_id: String,
+    pub items: Json<Vec<GcItem>>,
+    pub reclaimable_bytes: i64,
+    /// `delete` or `quarantine` once applied
+    pub action: Option<String>,
+    pub applied_bytes: Option<i64>,
+    #[serde(with = "rfc3339::option")]
+    pub applied_at: Option<OffsetDateTime>,
+    #[serde(with = "rfc3339")]
+    pub created_at: OffsetDateTime,
+}
+
+#[derive(Debug, Clone, Copy, Eq, PartialEq, Hash, Enum, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum GcItemKind {
+    /// a directory of the pic storage no doc points to
+    OrphanPicDir,
+    /// a file of the pic storage that is not the file of a pic row
+    OrphanPicFile,
+    /// a cbz row without a doc, with its file
+    OrphanCbz,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct GcItem {
+    pub kind: GcItemKind,
+    /// key in the pic or cbz storage
+    pub path: String,
+    pub bytes: u64,
+    pub cbz_id: Option<i32>,
+}
+
+#[derive(Debug, Clone, Copy, Default, Eq, PartialEq, Enum, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum GcAction {
+    Delete,
+    /// moved to the quarantine storage, nothing is lost
+    #[default]
+    Quarantine,
+}
+
+impl GcAction {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            GcAction::Delete => "delete",
+            GcAction::Quarantine => "quarantine",
+        }
+    }
+}
@@ -0,0 +1,31 @@
+use serde::Serialize;
+use time::OffsetDateTime;
+use time::serde::rfc3339;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub enum CheckStatus {
+    Ok,
+    Fail,
+    /// does not apply to this setup, e.g. the fs watcher with a remote storage
+    Skipped,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct HealthCheck {
+    pub name: String,
+    pub status: CheckStatus,
+    pub latency_ms: f64,
+    pub detail: Option<String>,
+}
+
+/// `ready` when no check failed
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Readiness {
+    pub ready: bool,
+    pub checks: Vec<HealthCheck>,
+    #[serde(with = "rfc3339")]
+    pub checked_at: OffsetDateTime,
+}
@@ -0,0 +1,69 @@
+use async_graphql::Enum;
+use serde::{Deserialize, Serialize};
+use sqlx::FromRow;
+use sqlx::types::Json;
+use time::OffsetDateTime;
+use time::serde::rfc3339;
+
+/// outcome of a `VerifyLibrary` task
+#[derive(Debug, Clone, Serialize, Deserialize, FromRow)]
+#[serde(rename_all = "camelCase")]
+pub struct LibraryReport {
+    pub id: i32,
+    pub task_id: String,
+    pub cbz_checked: i32,
+    pub pics_checked: i32,
+    pub issues: Json<Vec<LibraryIssue>>,
+    #[serde(with = "rfc3339")]
+    pub created_at: OffsetDateTime,
+}
+
+#[derive(Debug, Clone, Copy, Eq, PartialEq, Enum, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum IssueKind {
+    /// a cbz row whose file is gone
+    MissingCbzFile,
+    /// a cbz file without a row, a scan picks it up
+    UntrackedCbz,
+    InvalidZip,
+    MissingComicInfo,
+    InvalidComicInfo,
+    /// images in the cbz differ from `doc.page_count`
+    PageCountMismatch,
+    CorruptImage,
+    /// a downloaded pic (status 1) whose file is gone
+    MissingPicFile,
+}
+
+#[derive(Debug, Clone, Copy, Eq, PartialEq, Hash, Enum, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum RepairKind {
+    /// `id` is the pic
+    RedownloadPic,
+    /// `id` is the doc
+    Rearchive,
+    /// `id` is the cbz, its row is dropped
+    RemoveCbz,
+    /// scans `cbz_dir`, no `id`
+    Rescan,
+}
+
+#[derive(Debug, Clone, Copy, Eq, PartialEq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RepairAction {
+    pub kind: RepairKind,
+    pub id: Option<i32>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct LibraryIssue {
+    pub kind: IssueKind,
+    pub cbz_id: Option<i32>,
+    pub doc_id: Option<i32>,
+    pub pic_id: Option<i32>,
+    /// cbz file name, pic file or zip entry
+    pub path: Option<String>,
+    pub detail: String,
+    pub repair: Option<RepairAction>,
+}
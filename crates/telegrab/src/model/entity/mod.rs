@@ -1,4 +1,14 @@
+pub mod api_token;
+pub mod audit;
 pub mod cbz;
+pub mod disk;
 pub mod doc;
+pub mod event;
+pub mod feed;
+pub mod gc;
+pub mod health;
+pub mod library;
 pub mod pic;
+pub mod schedule;
 pub mod task;
+pub mod webhook;
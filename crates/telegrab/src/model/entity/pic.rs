@@ -17,3 +17,19 @@ pub struct Pic {
     pub updated_at: OffsetDateTime,
 }
 
+
+/// outcome of diffing a refreshed page against the stored pics
+#[derive(Debug, Clone, Default)]
+pub struct PicChanges {
+    pub added: Vec<Pic>,
+    /// moved to another seq or back on the page after being removed
+    pub updated: Vec<Pic>,
+    /// as they were before the tombstone
+    pub removed: Vec<Pic>,
+}
+
+impl PicChanges {
+    pub fn is_empty(&self) -> bool {
+        self.added.is_empty() && self.updated.is_empty() && self.removed.is_empty()
+    }
+}
@@ -0,0 +1,38 @@
+use crate::model::entity::task::TaskType;
+use serde::{Deserialize, Serialize};
+use sqlx::types::Json;
+use sqlx::FromRow;
+use time::serde::rfc3339;
+use time::OffsetDateTime;
+
+#[derive(Debug, Clone, Serialize, Deserialize, FromRow)]
+#[serde(rename_all = "camelCase")]
+pub struct Schedule {
+    pub id: i32,
+    pub name: String,
+    pub cron: String,
+    pub task_type: Json<TaskType>,
+    pub enabled: bool,
+    #[serde(with = "rfc3339::option")]
+    pub last_run_at: Option<OffsetDateTime>,
+    #[serde(with = "rfc3339::option")]
+    pub next_run_at: Option<OffsetDateTime>,
+    pub last_task_id: Option<String>,
+    #[serde(with = "rfc3339")]
+    pub created_at: OffsetDateTime,
+    #[serde(with = "rfc3339")]
+    pub updated_at: OffsetDateTime,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, FromRow)]
+#[serde(rename_all = "camelCase")]
+pub struct ScheduleRun {
+    pub id: i32,
+    pub schedule_id: i32,
+    pub task_id: Option<String>,
+    pub skipped: bool,
+    #[serde(with = "rfc3339")]
+    pub scheduled_at: OffsetDateTime,
+    #[serde(with = "rfc3339")]
+    pub created_at: OffsetDateTime,
+}
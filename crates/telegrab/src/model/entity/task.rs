@@ -1,9 +1,14 @@
 use async_graphql::Enum;
+use crate::model::entity::disk::DiskUsage;
+use crate::model::entity::event::DomainEvent;
+use crate::model::entity::gc::GcAction;
 use serde::{Deserialize, Serialize};
+use serde_variant::to_variant_name;
+use std::collections::HashMap;
 use time::OffsetDateTime;
 use uuid::Uuid;
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 #[serde(rename_all = "camelCase")]
 pub enum TaskType {
     HtmlParse { id: i32 },
@@ -15,6 +20,67 @@ pub enum TaskType {
     FSCbzAdded { path: String },
     FSCbzRemoved { path: String },
     HtmlParseAll,
+    MergeDuplicateDocs,
+    PollFeed { id: i32 },
+    PollDueFeeds,
+    CleanupCompleted,
+    RefreshDoc { id: i32, rebuild: bool },
+    VerifyLibrary,
+    /// a dry run only stores the report
+    #[serde(rename_all = "camelCase")]
+    CollectGarbage { dry_run: bool, action: GcAction },
+    /// applies a stored report to what is still orphaned
+    ApplyGcReport { id: i32, action: GcAction },
+}
+
+impl TaskType {
+    /// held back while downloads are paused for disk space
+    pub fn writes_pics(&self) -> bool {
+        matches!(
+            self,
+            TaskType::DocDownload { .. }
+                | TaskType::PicDownload { .. }
+                | TaskType::RefreshDoc { rebuild: true, .. }
+        )
+    }
+    /// held back while archives are paused for disk space
+    pub fn writes_cbz(&self) -> bool {
+        matches!(
+            self,
+            TaskType::CbzArchive { .. } | TaskType::RefreshDoc { rebuild: true, .. }
+        )
+    }
+    /// the doc the task works on
+    pub fn doc_id(&self) -> Option<i32> {
+        match self {
+            TaskType::HtmlParse { id }
+            | TaskType::DocDownload { id }
+            | TaskType::CbzArchive { id }
+            | TaskType::RefreshDoc { id, .. } => Some(*id),
+            _ => None,
+        }
+    }
+    /// what the task works on, as entity and id of the audit log
+    pub fn audit_entity(&self) -> (&'static str, Option<String>) {
+        match self {
+            TaskType::HtmlParse { id }
+            | TaskType::DocDownload { id }
+            | TaskType::CbzArchive { id }
+            | TaskType::RefreshDoc { id, .. } => ("doc", Some(id.to_string())),
+            TaskType::PicDownload { id } => ("pic", Some(id.to_string())),
+            TaskType::RemoveCbz { id } => ("cbz", Some(id.to_string())),
+            TaskType::FSCbzAdded { path } | TaskType::FSCbzRemoved { path } => {
+                ("cbz", Some(path.clone()))
+            }
+            TaskType::PollFeed { id } => ("feed", Some(id.to_string())),
+            TaskType::ApplyGcReport { id, .. } => ("gc", Some(id.to_string())),
+            TaskType::CollectGarbage { .. } => ("gc", None),
+            TaskType::VerifyLibrary | TaskType::ScanDir => ("library", None),
+            TaskType::HtmlParseAll | TaskType::MergeDuplicateDocs => ("doc", None),
+            TaskType::PollDueFeeds => ("feed", None),
+            TaskType::CleanupCompleted => ("task", None),
+        }
+    }
 }
 
 #[derive(Debug, Clone, Copy, Eq, PartialEq, Enum, Serialize, Deserialize)]
@@ -36,9 +102,25 @@ pub struct Task {
     pub completed_at: Option<OffsetDateTime>,
     pub result: Option<String>,
     pub error: Option<String>,
+    /// W3C trace context of whatever enqueued the task, its span becomes the parent
+    #[serde(skip)]
+    pub trace_context: Option<HashMap<String, String>>,
 }
 
 impl Task {
+    pub fn new(task_type: TaskType) -> Self {
+        Self {
+            id: Uuid::new_v4().to_string(),
+            task_type,
+            status: TaskStatus::Pending,
+            created_at: OffsetDateTime::now_utc(),
+            started_at: None,
+            completed_at: None,
+            result: None,
+            error: None,
+            trace_context: None,
+        }
+    }
     pub fn new_html_parse_task(doc_id: i32) -> Self {
         Self {
             id: Uuid::new_v4().to_string(),
@@ -49,6 +131,7 @@ impl Task {
             completed_at: None,
             result: None,
             error: None,
+            trace_context: None,
         }
     }
     pub fn new_doc_download_task(doc_id: i32) -> Self {
@@ -61,6 +144,7 @@ impl Task {
             completed_at: None,
             result: None,
             error: None,
+            trace_context: None,
         }
     }
     pub fn new_pic_download_task(pic_id: i32) -> Self {
@@ -73,6 +157,7 @@ impl Task {
             completed_at: None,
             result: None,
             error: None,
+            trace_context: None,
         }
     }
     pub fn new_cbz_archive_task(doc_id: i32) -> Self {
@@ -85,6 +170,7 @@ impl Task {
             completed_at: None,
             result: None,
             error: None,
+            trace_context: None,
         }
     }
     pub fn new_scan_dir_task() -> Self {
@@ -97,6 +183,7 @@ impl Task {
             completed_at: None,
             result: None,
             error: None,
+            trace_context: None,
         }
     }
     pub fn new_remove_cbz_task(cbz_id: i32) -> Self {
@@ -109,6 +196,7 @@ impl Task {
             completed_at: None,
             result: None,
             error: None,
+            trace_context: None,
         }
     }
     pub fn new_fs_cbz_added_task(path: String) -> Self {
@@ -121,6 +209,7 @@ impl Task {
             completed_at: None,
             result: None,
             error: None,
+            trace_context: None,
         }
     }
     pub fn new_fs_cbz_removed_task(path: String) -> Self {
@@ -133,6 +222,7 @@ impl Task {
             completed_at: None,
             result: None,
             error: None,
+            trace_context: None,
         }
     }
     pub fn new_html_parse_all_task() -> Self {
@@ -145,6 +235,91 @@ impl Task {
             completed_at: None,
             result: None,
             error: None,
+            trace_context: None,
+        }
+    }
+    pub fn new_merge_duplicate_docs_task() -> Self {
+        Self {
+            id: Uuid::new_v4().to_string(),
+            task_type: TaskType::MergeDuplicateDocs,
+            status: TaskStatus::Pending,
+            created_at: OffsetDateTime::now_utc(),
+            started_at: None,
+            completed_at: None,
+            result: None,
+            error: None,
+            trace_context: None,
+        }
+    }
+    pub fn new_refresh_doc_task(doc_id: i32, rebuild: bool) -> Self {
+        Self {
+            id: Uuid::new_v4().to_string(),
+            task_type: TaskType::RefreshDoc {
+                id: doc_id,
+                rebuild,
+            },
+            status: TaskStatus::Pending,
+            created_at: OffsetDateTime::now_utc(),
+            started_at: None,
+            completed_at: None,
+            result: None,
+            error: None,
+            trace_context: None,
+        }
+    }
+    pub fn new_verify_library_task() -> Self {
+        Self {
+            id: Uuid::new_v4().to_string(),
+            task_type: TaskType::VerifyLibrary,
+            status: TaskStatus::Pending,
+            created_at: OffsetDateTime::now_utc(),
+            started_at: None,
+            completed_at: None,
+            result: None,
+            error: None,
+            trace_context: None,
+        }
+    }
+    pub fn new_collect_garbage_task(dry_run: bool, action: GcAction) -> Self {
+        Self {
+            id: Uuid::new_v4().to_string(),
+            task_type: TaskType::CollectGarbage { dry_run, action },
+            status: TaskStatus::Pending,
+            created_at: OffsetDateTime::now_utc(),
+            started_at: None,
+            completed_at: None,
+            result: None,
+            error: None,
+            trace_context: None,
+        }
+    }
+    pub fn new_apply_gc_report_task(report_id: i32, action: GcAction) -> Self {
+        Self {
+            id: Uuid::new_v4().to_string(),
+            task_type: TaskType::ApplyGcReport {
+                id: report_id,
+                action,
+            },
+            status: TaskStatus::Pending,
+            created_at: OffsetDateTime::now_utc(),
+            started_at: None,
+            completed_at: None,
+            result: None,
+            error: None,
+            trace_context: None,
+        }
+    }
+    pub fn new_poll_feed_task(feed_id: i32) -> Self {
+        Self {
+            id: Uuid::new_v4().to_string(),
+            task_type: TaskType::PollFeed { id: feed_id },
+            status: TaskStatus::Pending,
+            created_at: OffsetDateTime::now_utc(),
+            started_at: None,
+            completed_at: None,
+            result: None,
+            error: None,
+            trace_context: None,
         }
     }
     pub fn mark_processing(&mut self) {
@@ -180,6 +355,19 @@ impl Task {
             TaskType::FSCbzAdded { path } => format!("FSCbzAdded: {}", path),
             TaskType::FSCbzRemoved { path } => format!("FSCbzRemoved: {}", path),
             TaskType::HtmlParseAll => "HtmlParseAll".to_string(),
+            TaskType::MergeDuplicateDocs => "Merge duplicate docs".to_string(),
+            TaskType::PollFeed { id: feed_id } => format!("Poll feed: {}", feed_id),
+            TaskType::PollDueFeeds => "Poll due feeds".to_string(),
+            TaskType::CleanupCompleted => "Cleanup completed tasks".to_string(),
+            TaskType::RefreshDoc { id: doc_id, .. } => format!("Refresh doc: {}", doc_id),
+            TaskType::VerifyLibrary => "Verify library".to_string(),
+            TaskType::CollectGarbage { dry_run: true, .. } => "Collect garbage (dry run)".to_string(),
+            TaskType::CollectGarbage { action, .. } => {
+                format!("Collect garbage ({})", action.as_str())
+            }
+            TaskType::ApplyGcReport { id, action } => {
+                format!("Apply gc report {} ({})", id, action.as_str())
+            }
         }
     }
 }
@@ -196,6 +384,14 @@ impl From<TaskType> for String {
             TaskType::FSCbzAdded { path } => format!("FSCbzAdded: {}", path),
             TaskType::FSCbzRemoved { path } => format!("FSCbzRemoved: {}", path),
             TaskType::HtmlParseAll => "HtmlParseAll".to_string(),
+            TaskType::MergeDuplicateDocs => "MergeDuplicateDocs".to_string(),
+            TaskType::PollFeed { id } => format!("PollFeed: {}", id),
+            TaskType::PollDueFeeds => "PollDueFeeds".to_string(),
+            TaskType::CleanupCompleted => "CleanupCompleted".to_string(),
+            TaskType::RefreshDoc { id, .. } => format!("RefreshDoc: {}", id),
+            TaskType::VerifyLibrary => "VerifyLibrary".to_string(),
+            TaskType::CollectGarbage { .. } => "CollectGarbage".to_string(),
+            TaskType::ApplyGcReport { id, .. } => format!("ApplyGcReport: {}", id),
         }
     }
 }
@@ -222,6 +418,16 @@ pub struct QueueInfo {
     pub stats: QueueStats,
 }
 
+/// The queue as it is at `last_event_id`, sent to sse clients that can not catch up event by event.
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct QueueSnapshot {
+    pub last_event_id: u64,
+    pub queued: Vec<Task>,
+    pub active: Vec<ActiveTaskInfo>,
+    pub disk_usage: Option<DiskUsage>,
+}
+
 #[derive(Debug, Clone, Serialize)]
 #[serde(rename_all = "camelCase")]
 pub struct QueueStats {
@@ -259,6 +465,94 @@ pub enum QueueEvent {
     TaskUpdated(Task),
     TaskProgress(String, f64),
     QueueCleared,
+    DiskUsage(DiskUsage),
+    Domain(DomainEvent),
+}
+
+impl QueueEvent {
+    /// the name the sse stream filters by, the variant of a domain event
+    pub fn kind(&self) -> &'static str {
+        match self {
+            QueueEvent::TaskAdded(_) => "taskAdded",
+            QueueEvent::TaskRemoved(_) => "taskRemoved",
+            QueueEvent::TaskUpdated(_) => "taskUpdated",
+            QueueEvent::TaskProgress(..) => "taskProgress",
+            QueueEvent::QueueCleared => "queueCleared",
+            QueueEvent::DiskUsage(_) => "diskUsage",
+            QueueEvent::Domain(DomainEvent::DocAdded { .. }) => "docAdded",
+            QueueEvent::Domain(DomainEvent::DocRemoved { .. }) => "docRemoved",
+            QueueEvent::Domain(DomainEvent::DocUpdated { .. }) => "docUpdated",
+            QueueEvent::Domain(DomainEvent::DocStatusChanged { .. }) => "docStatusChanged",
+            QueueEvent::Domain(DomainEvent::CbzCreated { .. }) => "cbzCreated",
+            QueueEvent::Domain(DomainEvent::CbzRemoved { .. }) => "cbzRemoved",
+            QueueEvent::Domain(DomainEvent::CbzLinked { .. }) => "cbzLinked",
+        }
+    }
+}
+
+/// A queue event with its place in the stream, ids grow by one from `1` on every start.
+#[derive(Debug, Clone, Serialize)]
+pub struct NumberedEvent {
+    pub id: u64,
+    pub event: QueueEvent,
+}
+
+/// Query of the sse stream, every param that is set must match. `kind` and `taskType`
+/// take comma separated names (`taskUpdated,taskProgress`, `docDownload,cbzArchive`).
+/// Task and doc filters keep the events of matching tasks and docs,
+/// `queueCleared` and `diskUsage` concern every task and only go by `kind`.
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct EventFilter {
+    pub kind: Option<String>,
+    pub task_id: Option<String>,
+    pub task_type: Option<String>,
+    pub doc_id: Option<i32>,
+}
+
+impl EventFilter {
+    pub fn has_task_filter(&self) -> bool {
+        self.task_id.is_some() || self.task_type.is_some() || self.doc_id.is_some()
+    }
+    pub fn wants_kind(&self, event: &QueueEvent) -> bool {
+        self.kind
+            .as_deref()
+            .is_none_or(|kinds| kinds.split(',').any(|k| k.trim() == event.kind()))
+    }
+    pub fn wants_task(&self, task: &Task) -> bool {
+        self.task_id.as_ref().is_none_or(|id| *id == task.id)
+            && self.task_type.as_deref().is_none_or(|types| {
+                to_variant_name(&task.task_type)
+                    .is_ok_and(|name| types.split(',').any(|t| t.trim() == name))
+            })
+            && self.doc_id.is_none_or(|id| task.task_type.doc_id() == Some(id))
+    }
+    /// `task` is the one a `taskRemoved` or `taskProgress` event names, when it is still known
+    pub fn wants(&self, event: &QueueEvent, task: Option<&Task>) -> bool {
+        if !self.wants_kind(event) {
+            return false;
+        }
+        if !self.has_task_filter() {
+            return true;
+        }
+        match event {
+            QueueEvent::TaskAdded(task) | QueueEvent::TaskUpdated(task) => self.wants_task(task),
+            QueueEvent::TaskRemoved(task_id) | QueueEvent::TaskProgress(task_id, _) => match task {
+                Some(task) => self.wants_task(task),
+                None => {
+                    self.task_type.is_none()
+                        && self.doc_id.is_none()
+                        && self.task_id.as_ref() == Some(task_id)
+                }
+            },
+            QueueEvent::QueueCleared | QueueEvent::DiskUsage(_) => true,
+            QueueEvent::Domain(event) => {
+                self.task_id.is_none()
+                    && self.task_type.is_none()
+                    && self.doc_id.is_some_and(|id| event.concerns_doc(id))
+            }
+        }
+    }
 }
 
 #[derive(Debug, Clone, Deserialize)]
@@ -0,0 +1,133 @@
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use sqlx::FromRow;
+use sqlx::types::Json;
+use time::OffsetDateTime;
+use time::serde::rfc3339;
+
+/// Events a webhook can subscribe to, `ping` is only sent by the test endpoint.
+pub const WEBHOOK_EVENTS: [&str; 11] = [
+    "task.added",
+    "task.completed",
+    "task.failed",
+    "queue.cleared",
+    "doc.parsed",
+    "doc.downloaded",
+    "doc.archived",
+    "doc.failed",
+    "doc.stale",
+    "cbz.created",
+    "cbz.removed",
+];
+
+#[derive(Debug, Clone, Serialize, Deserialize, FromRow)]
+#[serde(rename_all = "camelCase")]
+pub struct Webhook {
+    pub id: i32,
+    pub url: String,
+    /// signs the payloads, never sent back
+    #[serde(skip_serializing)]
+    pub secret: String,
+    /// names of [`WEBHOOK_EVENTS`] or `prefix.*`, empty for every event
+    pub events: Json<Vec<String>>,
+    pub enabled: bool,
+    pub description: Option<String>,
+    #[serde(with = "rfc3339")]
+    pub created_at: OffsetDateTime,
+    #[serde(with = "rfc3339")]
+    pub updated_at: OffsetDateTime,
+}
+
+impl Webhook {
+    pub fn wants(&self, event: &str) -> bool {
+        self.events.is_empty()
+            || self.events.iter().any(|e| {
+                e == event
+                    || e == "*"
+                    || e
+                        .strip_suffix(".*")
+                        .is_some_and(|prefix| event.split('.').next() == Some(prefix))
+            })
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, FromRow)]
+#[serde(rename_all = "camelCase")]
+pub struct WebhookDelivery {
+    pub id: i32,
+    pub webhook_id: i32,
+    pub event: String,
+    /// the body that was posted, `{"event", "createdAt", "data"}`
+    pub payload: Json<Value>,
+    pub status: WebhookDeliveryStatus,
+    pub attempts: i32,
+    /// of the last attempt
+    pub response_status: Option<i32>,
+    pub error: Option<String>,
+    #[serde(with = "rfc3339::option")]
+    pub next_attempt_at: Option<OffsetDateTime>,
+    #[serde(with = "rfc3339::option")]
+    pub delivered_at: Option<OffsetDateTime>,
+    #[serde(with = "rfc3339")]
+    pub created_at: OffsetDateTime,
+}
+
+#[derive(Debug, Clone, Copy, Eq, PartialEq, Serialize, Deserialize, sqlx::Type)]
+#[serde(rename_all = "camelCase")]
+#[repr(i16)]
+pub enum WebhookDeliveryStatus {
+    /// not attempted yet, or retried at `next_attempt_at`
+    Pending = 0,
+    Delivered = 1,
+    /// every attempt failed
+    Failed = 2,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn subscribed_to(events: &[&str]) -> Webhook {
+        Webhook {
+            id: 1,
+            url: "http://localhost:9999/hook".to_string(),
+            secret: "a secret of sixteen".to_string(),
+            events: Json(events.iter().map(|e| e.to_string()).collect()),
+            enabled: true,
+            description: None,
+            created_at: OffsetDateTime::UNIX_EPOCH,
+            updated_at: OffsetDateTime::UNIX_EPOCH,
+        }
+    }
+
+    #[test]
+    fn no_events_wants_everything() {
+        let webhook = subscribed_to(&[]);
+        assert!(webhook.wants("doc.archived"));
+        assert!(webhook.wants("ping"));
+    }
+
+    #[test]
+    fn exact_names_match_only_themselves() {
+        let webhook = subscribed_to(&["doc.archived", "task.failed"]);
+        assert!(webhook.wants("doc.archived"));
+        assert!(webhook.wants("task.failed"));
+        assert!(!webhook.wants("doc.parsed"));
+        assert!(!webhook.wants("task.completed"));
+    }
+
+    #[test]
+    fn prefix_wildcard_matches_the_whole_kind() {
+        let webhook = subscribed_to(&["cbz.*"]);
+        assert!(webhook.wants("cbz.created"));
+        assert!(webhook.wants("cbz.removed"));
+        assert!(!webhook.wants("doc.archived"));
+        // a prefix is a whole kind, not the start of one
+        assert!(!subscribed_to(&["cb.*"]).wants("cbz.created"));
+    }
+
+    #[test]
+    fn star_matches_everything() {
+        assert!(subscribed_to(&["*"]).wants("queue.cleared"));
+    }
+}
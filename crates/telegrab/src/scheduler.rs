@@ -0,0 +1,180 @@
+use crate::Result;
+use crate::configuration::{ScheduleConfig, Settings};
+use crate::model::dto::schedule::{CreateScheduleReq, UpdateScheduleReq};
+use crate::model::entity::schedule::{Schedule, ScheduleRun};
+use crate::model::entity::task::Task;
+use crate::service;
+use crate::state::{AppState, QueueState};
+use chrono::{DateTime, Utc};
+use crate::db::DbPool;
+use std::str::FromStr;
+use std::time::Duration;
+use time::OffsetDateTime;
+
+/// Standard five field expressions get a leading `0` seconds field,
+/// six and seven field ones (with seconds, and year) are taken as they are.
+pub fn parse_cron(expr: &str) -> std::result::Result<cron::Schedule, String> {
+    let expr = expr.trim();
+    let expr = if expr.split_whitespace().count() == 5 {
+        format!("0 {}", expr)
+    } else {
+        expr.to_string()
+    };
+    cron::Schedule::from_str(&expr).map_err(|e| format!("Invalid cron {}: {}", expr, e))
+}
+
+/// first time the expression fires after `after`, in utc; `None` when it never fires again
+pub fn next_run(
+    expr: &str,
+    after: OffsetDateTime,
+) -> std::result::Result<Option<OffsetDateTime>, String> {
+    let schedule = parse_cron(expr)?;
+    let after = DateTime::<Utc>::from_timestamp(after.unix_timestamp(), 0)
+        .ok_or_else(|| format!("Invalid time: {}", after))?;
+    Ok(schedule
+        .after(&after)
+        .next()
+        .and_then(|next| OffsetDateTime::from_unix_timestamp(next.timestamp()).ok()))
+}
+
+fn next_run_from_now(expr: &str) -> Result<Option<OffsetDateTime>> {
+    next_run(expr, OffsetDateTime::now_utc()).map_err(crate::Error::Message)
+}
+
+/// upserts the configured schedules by name
+pub async fn sync_schedules(pool: &DbPool, schedules: &[ScheduleConfig]) -> Result<()> {
+    for config in schedules {
+        let next_run_at = next_run_from_now(&config.cron)?;
+        service::schedule::upsert_schedule(
+            pool,
+            &config.name,
+            &config.cron,
+            &config.task,
+            config.enabled,
+            next_run_at,
+        )
+        .await?;
+    }
+    Ok(())
+}
+
+pub async fn create_schedule(pool: &DbPool, req: CreateScheduleReq) -> Result<Schedule> {
+    let next_run_at = next_run_from_now(&req.cron)?;
+    let schedule = service::schedule::create_schedule(
+        pool,
+        req.name.trim(),
+        &req.cron,
+        &req.task_type,
+        req.enabled.unwrap_or(true),
+        next_run_at,
+    )
+    .await?;
+    Ok(schedule)
+}
+
+/// a new cron expression or re-enabling starts counting from now,
+/// runs missed while disabled are not caught up
+pub async fn update_schedule(pool: &DbPool, id: i32, req: UpdateScheduleReq) -> Result<Schedule> {
+    let schedule = service::schedule::get_schedule_by_id(pool, id).await?;
+    let restart = req.cron.as_ref().is_some_and(|c| *c != schedule.cron)
+        || (req.enabled == Some(true) && !schedule.enabled);
+    let next_run_at = if restart {
+        next_run_from_now(req.cron.as_deref().unwrap_or(&schedule.cron))?
+    } else {
+        None
+    };
+    let schedule =
+        service::schedule::update_schedule(pool, id, req.cron, req.enabled, next_run_at).await?;
+    Ok(schedule)
+}
+
+/// Queues the task of the schedule, or records a skipped run when the same task
+/// is still queued or running. `next_run_at` is only moved when given.
+pub async fn run_schedule(
+    pool: &DbPool,
+    queue_state: &QueueState,
+    schedule: &Schedule,
+    scheduled_at: OffsetDateTime,
+    next_run_at: Option<OffsetDateTime>,
+) -> Result<ScheduleRun> {
+    let task_type = schedule.task_type.0.clone();
+    let task_id = if queue_state.is_task_pending(&task_type).await {
+        tracing::info!(
+            "Schedule {} skipped, {:?} is still pending",
+            schedule.name,
+            task_type
+        );
+        None
+    } else {
+        let task = Task::new(task_type);
+        let task_id = task.id.clone();
+        queue_state.enqueue(task).await;
+        Some(task_id)
+    };
+    let run =
+        service::schedule::record_run(pool, schedule.id, task_id, scheduled_at, next_run_at)
+            .await?;
+    Ok(run)
+}
+
+async fn run_due_schedules(state: &AppState) -> Result<()> {
+    let schedules = service::schedule::get_due_schedules(&state.db_pool).await?;
+    for schedule in schedules {
+        let scheduled_at = schedule
+            .next_run_at
+            .unwrap_or_else(OffsetDateTime::now_utc);
+        // fires once after downtime, however many runs were missed
+        let next_run_at = match next_run_from_now(&schedule.cron) {
+            Ok(next_run_at) => next_run_at,
+            Err(err) => {
+                tracing::warn!("Schedule {} has an invalid cron: {}", schedule.name, err);
+                None
+            }
+        };
+        // a schedule that never fires again is switched off
+        if next_run_at.is_none() {
+            service::schedule::update_schedule(
+                &state.db_pool,
+                schedule.id,
+                None,
+                Some(false),
+                None,
+            )
+            .await?;
+        }
+        run_schedule(
+            &state.db_pool,
+            &state.queue_state,
+            &schedule,
+            scheduled_at,
+            next_run_at,
+        )
+        .await?;
+    }
+    Ok(())
+}
+
+pub async fn start_scheduler(state: AppState, configuration: Settings) {
+    let settings = configuration.scheduler;
+    if let Err(err) = sync_schedules(&state.db_pool, &settings.schedules).await {
+        tracing::error!("Scheduler failed to sync configured schedules: {}", err);
+    }
+    let check_interval = settings.check_interval_secs.max(1);
+    tokio::spawn(async move {
+        let mut shutdown_rx = state.shutdown.get_shutdown_rx().await;
+        tracing::info!("Start Scheduler, check schedules in every {}s", check_interval);
+        loop {
+            tokio::select! {
+                _ = shutdown_rx.recv() => {
+                    tracing::info!("Scheduler received shutdown signal, stop.");
+                    break;
+                }
+                _ = tokio::time::sleep(Duration::from_secs(check_interval)) => {
+                    if let Err(err) = run_due_schedules(&state).await {
+                        tracing::warn!("Scheduler run due schedules failed: {}", err);
+                    }
+                }
+            }
+        }
+    });
+}
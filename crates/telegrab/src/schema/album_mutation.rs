@@ -1,20 +1,31 @@
+use crate::link::{self, LinkSource};
 use crate::model::dto::doc::{CreateDocReq, UpdateDocReq};
+use crate::model::dto::import::{ImportStatus, ImportedUrl};
+use crate::model::entity::api_token::ApiScope;
+use crate::model::entity::task::Task;
 use crate::schema::album_query::Album;
-use crate::schema::{from_global_id, ArcPgPool};
+use crate::schema::task_query::GTask;
+use crate::schema::{
+    from_global_id, from_global_id_of, note_audit, ArcDbPool, ArcStates, RelayTy, ScopeGuard,
+    UrlValidator,
+};
 use crate::service;
 use async_graphql::{Context, InputObject, Object, SimpleObject};
 use time::OffsetDateTime;
 
 #[derive(InputObject, Debug, Clone)]
 pub struct CreateAlbumInput {
-    #[graphql(validator(url))]
+    #[graphql(validator(custom = "UrlValidator"))]
     pub url: String,
     pub client_mutation_id: Option<String>,
 }
 
-impl From<CreateAlbumInput> for CreateDocReq {
-    fn from(input: CreateAlbumInput) -> Self {
-        Self { url: input.url }
+impl TryFrom<CreateAlbumInput> for CreateDocReq {
+    type Error = String;
+
+    fn try_from(input: CreateAlbumInput) -> Result<Self, Self::Error> {
+        let url = link::canonicalize(&input.url)?;
+        Ok(Self { url })
     }
 }
 
@@ -128,35 +139,134 @@ pub struct DeleteAlbumPayload {
     pub client_mutation_id: Option<String>,
 }
 
+/// text, bookmark html or a telegram `result.json` export
+#[derive(InputObject, Debug, Clone)]
+pub struct ImportAlbumsInput {
+    pub content: String,
+    #[graphql(default)]
+    pub source: LinkSource,
+    /// queue a parse task for every created album
+    #[graphql(default)]
+    pub enqueue: bool,
+    pub client_mutation_id: Option<String>,
+}
+
+#[derive(SimpleObject, Debug, Clone)]
+pub struct ImportedAlbum {
+    pub url: String,
+    pub canonical_url: Option<String>,
+    pub status: ImportStatus,
+    pub album: Option<Album>,
+    pub task_id: Option<String>,
+    pub error: Option<String>,
+}
+
+impl From<ImportedUrl> for ImportedAlbum {
+    fn from(imported: ImportedUrl) -> Self {
+        Self {
+            url: imported.url,
+            canonical_url: imported.canonical_url,
+            status: imported.status,
+            album: imported.doc.map(Into::into),
+            task_id: imported.task_id,
+            error: imported.error,
+        }
+    }
+}
+
+#[derive(SimpleObject, Debug, Clone)]
+pub struct ImportAlbumsPayload {
+    pub created: usize,
+    pub existing: usize,
+    pub invalid: usize,
+    pub results: Vec<ImportedAlbum>,
+    pub client_mutation_id: Option<String>,
+}
+
+#[derive(InputObject, Debug, Clone)]
+pub struct RefreshAlbumInput {
+    pub id: String,
+    /// download what changed and rebuild the cbz in the same task
+    #[graphql(default)]
+    pub rebuild: bool,
+    pub client_mutation_id: Option<String>,
+}
+
+#[derive(SimpleObject, Debug, Clone)]
+pub struct RefreshAlbumPayload {
+    pub task: GTask,
+    pub client_mutation_id: Option<String>,
+}
+
+/// for the mutations that queue one task for the whole library
+#[derive(InputObject, Debug, Clone)]
+pub struct AlbumsTaskInput {
+    pub client_mutation_id: Option<String>,
+}
+
+#[derive(SimpleObject, Debug, Clone)]
+pub struct AlbumsTaskPayload {
+    pub task: GTask,
+    pub client_mutation_id: Option<String>,
+}
+
 #[derive(Default)]
 pub struct AlbumMutation;
 
-#[Object]
+#[Object(guard = "ScopeGuard::new(ApiScope::Write)")]
 impl AlbumMutation {
     async fn add_album(
         &self,
         ctx: &Context<'_>,
         input: CreateAlbumInput,
     ) -> async_graphql::Result<CreateAlbumPayload> {
-        let pool = ctx.data::<ArcPgPool>()?;
+        let pool = ctx.data::<ArcDbPool>()?;
         let client_mutation_id = input.client_mutation_id.clone();
-        let new_doc: CreateDocReq = input.into();
-        let doc = service::doc::create_doc(pool, new_doc).await?;
+        let new_doc = CreateDocReq::try_from(input)?;
+        let states = ctx.data::<ArcStates>()?;
+        let doc = service::doc::create_doc(pool, states, new_doc).await?;
+        note_audit(ctx, "doc", Some(doc.id.to_string()), None);
         Ok(CreateAlbumPayload {
             album: doc.into(),
             client_mutation_id,
         })
     }
+    async fn import_albums(
+        &self,
+        ctx: &Context<'_>,
+        input: ImportAlbumsInput,
+    ) -> async_graphql::Result<ImportAlbumsPayload> {
+        let pool = ctx.data::<ArcDbPool>()?;
+        let states = ctx.data::<ArcStates>()?;
+        note_audit(ctx, "doc", None, None);
+        let mut report =
+            service::import::import_links(pool, states, &input.content, input.source).await?;
+        if input.enqueue {
+            service::import::enqueue_created(states, &mut report).await;
+        }
+        Ok(ImportAlbumsPayload {
+            created: report.created,
+            existing: report.existing,
+            invalid: report.invalid,
+            results: report.results.into_iter().map(Into::into).collect(),
+            client_mutation_id: input.client_mutation_id,
+        })
+    }
     async fn update_album(
         &self,
         ctx: &Context<'_>,
         input: UpdateAlbumInput,
     ) -> async_graphql::Result<UpdateAlbumPayload> {
-        let pool = ctx.data::<ArcPgPool>()?;
+        let pool = ctx.data::<ArcDbPool>()?;
+        let states = ctx.data::<ArcStates>()?;
         let (_, id) = from_global_id(input.id.as_str())?;
         let client_mutation_id = input.client_mutation_id.clone();
         let new_doc: UpdateDocReq = input.into();
-        let doc = service::doc::update_doc(pool, id as i32, new_doc).await?;
+        note_audit(ctx, "doc", Some(id.to_string()), None);
+        let before = service::doc::get_doc_by_id(pool, id as i32).await?;
+        let doc = service::doc::update_doc(pool, states, id as i32, new_doc).await?;
+        // again, with what changed
+        note_audit(ctx, "doc", Some(id.to_string()), service::audit::diff(&before, &doc));
         Ok(UpdateAlbumPayload {
             album: doc.into(),
             client_mutation_id,
@@ -167,11 +277,13 @@ impl AlbumMutation {
         ctx: &Context<'_>,
         input: DeleteAlbumInput,
     ) -> async_graphql::Result<DeleteAlbumPayload> {
-        let pool = ctx.data::<ArcPgPool>()?;
+        let pool = ctx.data::<ArcDbPool>()?;
+        let states = ctx.data::<ArcStates>()?;
         let input_id = input.id.clone();
         let (_, id) = from_global_id(input_id.as_str())?;
+        note_audit(ctx, "doc", Some(id.to_string()), None);
         let client_mutation_id = input.client_mutation_id.clone();
-        let count = service::doc::delete_doc_by_id(pool, id as i32).await?;
+        let count = service::doc::delete_doc_by_id(pool, states, id as i32).await?;
         if count == 0 {
             return Err(async_graphql::Error::new("No Album found"));
         }
@@ -180,4 +292,62 @@ impl AlbumMutation {
             client_mutation_id,
         })
     }
+    async fn refresh_album(
+        &self,
+        ctx: &Context<'_>,
+        input: RefreshAlbumInput,
+    ) -> async_graphql::Result<RefreshAlbumPayload> {
+        let pool = ctx.data::<ArcDbPool>()?;
+        let states = ctx.data::<ArcStates>()?;
+        let id = from_global_id_of(RelayTy::Album, input.id.as_str())?;
+        note_audit(ctx, "doc", Some(id.to_string()), None);
+        let doc = service::doc::get_doc_by_id(pool, id as i32).await?;
+        if states.is_doc_refresh_pending(doc.id).await {
+            return Err(async_graphql::Error::new("Album refresh is already queued"));
+        }
+        let task = Task::new_refresh_doc_task(doc.id, input.rebuild);
+        states.enqueue(task.clone()).await;
+        Ok(RefreshAlbumPayload {
+            task: task.into(),
+            client_mutation_id: input.client_mutation_id,
+        })
+    }
+    /// queues a task that queues parsing for every album not parsed yet
+    async fn parse_all_albums(
+        &self,
+        ctx: &Context<'_>,
+        input: AlbumsTaskInput,
+    ) -> async_graphql::Result<AlbumsTaskPayload> {
+        let states = ctx.data::<ArcStates>()?;
+        note_audit(ctx, "doc", None, None);
+        if states.is_parse_all_active().await {
+            return Err(async_graphql::Error::new(
+                "HtmlParseAll is active, no new HtmlParseAll tasks accepted",
+            ));
+        }
+        let task = Task::new_html_parse_all_task();
+        states.enqueue(task.clone()).await;
+        Ok(AlbumsTaskPayload {
+            task: task.into(),
+            client_mutation_id: input.client_mutation_id,
+        })
+    }
+    /// queues a task that merges albums stored under other spellings of the same url
+    async fn merge_duplicate_albums(
+        &self,
+        ctx: &Context<'_>,
+        input: AlbumsTaskInput,
+    ) -> async_graphql::Result<AlbumsTaskPayload> {
+        let states = ctx.data::<ArcStates>()?;
+        note_audit(ctx, "doc", None, None);
+        if states.is_merge_duplicates_pending().await {
+            return Err(async_graphql::Error::new("MergeDuplicateDocs is already queued"));
+        }
+        let task = Task::new_merge_duplicate_docs_task();
+        states.enqueue(task.clone()).await;
+        Ok(AlbumsTaskPayload {
+            task: task.into(),
+            client_mutation_id: input.client_mutation_id,
+        })
+    }
 }
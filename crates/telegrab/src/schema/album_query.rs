@@ -1,9 +1,11 @@
-use crate::model::entity::doc::Doc;
+use crate::model::entity::api_token::ApiScope;
+use crate::model::entity::doc::{Doc, DocStatus, DocStatusChange};
+use crate::schema::cbz_query::Cbz;
 use crate::schema::image_query::Image;
 use crate::schema::image_query::{ImagesConnectionName, ImagesEdgeName};
 use crate::schema::{
-    from_global_id, offset_to_cursor, process_pagination, to_global_id, ArcPgPool, ConnectionFields,
-    RelayTy,
+    from_global_id, offset_to_cursor, process_pagination, to_global_id, ArcDbPool, ConnectionFields,
+    RelayTy, ScopeGuard,
 };
 use crate::service;
 use async_graphql::connection::{Connection, ConnectionNameType, Edge, EdgeNameType, EmptyFields};
@@ -14,7 +16,7 @@ use std::sync::Arc;
 use time::OffsetDateTime;
 
 pub struct AlbumLoader {
-    pool: ArcPgPool,
+    pool: ArcDbPool,
 }
 impl Loader<i32> for AlbumLoader {
     type Value = Album;
@@ -44,9 +46,40 @@ pub struct Album {
     pub title: Option<String>,
     pub page_title: Option<String>,
     pub page_date: Option<OffsetDateTime>,
+    /// the `DocStatus` number, `0` new ... `5` stale
     pub status: i16,
+    /// `status` as an enum
+    pub state: DocStatus,
     pub count: usize,
     pub url: String,
+    /// the page answered 404/410 on the last refresh
+    pub unavailable: bool,
+    /// pics changed since the cbz was built
+    pub cbz_stale: bool,
+    pub checked_at: Option<OffsetDateTime>,
+}
+
+/// a change of `Album.state`
+#[derive(Debug, Clone, SimpleObject)]
+pub struct AlbumStatusChange {
+    pub from_status: DocStatus,
+    pub to_status: DocStatus,
+    /// `worker 3`, `cli`, ... whatever made the change
+    pub changed_by: String,
+    pub reason: Option<String>,
+    pub created_at: OffsetDateTime,
+}
+
+impl From<DocStatusChange> for AlbumStatusChange {
+    fn from(value: DocStatusChange) -> Self {
+        Self {
+            from_status: value.from_status,
+            to_status: value.to_status,
+            changed_by: value.changed_by,
+            reason: value.reason,
+            created_at: value.created_at,
+        }
+    }
 }
 
 impl From<Doc> for Album {
@@ -57,15 +90,34 @@ impl From<Doc> for Album {
             title: value.title,
             page_title: value.page_title,
             page_date: value.page_date,
-            status: value.status,
+            status: value.status as i16,
+            state: value.status,
             count: value.page_count.map(|s| s as usize).unwrap_or(0),
             url: value.url,
+            unavailable: value.unavailable,
+            cbz_stale: value.cbz_stale,
+            checked_at: value.checked_at,
         }
     }
 }
 
 #[ComplexObject]
 impl Album {
+    /// oldest first
+    async fn status_history(&self, ctx: &Context<'_>) -> async_graphql::Result<Vec<AlbumStatusChange>> {
+        let pool = ctx.data::<ArcDbPool>()?;
+        let history = service::doc::get_doc_status_history(pool, self.doc_id)
+            .await
+            .map_err(|e| async_graphql::Error::new(format!("{}", e)))?;
+        Ok(history.into_iter().map(|c| c.into()).collect())
+    }
+    async fn cbz(&self, ctx: &Context<'_>) -> async_graphql::Result<Option<Cbz>> {
+        let pool = ctx.data::<ArcDbPool>()?;
+        let cbz = service::cbz::get_cbz_by_doc_id(pool, self.doc_id)
+            .await
+            .map_err(|e| async_graphql::Error::new(format!("{}", e)))?;
+        Ok(cbz.map(Into::into))
+    }
     async fn images(
         &self,
         ctx: &Context<'_>,
@@ -83,7 +135,7 @@ impl Album {
             ImagesEdgeName,
         >,
     > {
-        let pool = ctx.data::<ArcPgPool>()?;
+        let pool = ctx.data::<ArcDbPool>()?;
         connection::query(
             after,
             before,
@@ -134,10 +186,10 @@ impl EdgeNameType for AlbumsEdgeName {
 #[derive(Default)]
 pub struct AlbumQuery;
 
-#[Object]
+#[Object(guard = "ScopeGuard::new(ApiScope::Read)")]
 impl AlbumQuery {
     async fn album(&self, ctx: &Context<'_>, id: ID) -> async_graphql::Result<Album> {
-        let pool = ctx.data::<ArcPgPool>()?;
+        let pool = ctx.data::<ArcDbPool>()?;
         let (_, id) = from_global_id(id.0.as_str())?;
         let _loader = ctx.data::<DataLoader<AlbumLoader, LruCache>>(); // todo: data loader
         let doc = service::doc::get_doc_by_id(pool, id as i32).await?;
@@ -163,7 +215,7 @@ impl AlbumQuery {
             AlbumsEdgeName,
         >,
     > {
-        let pool = ctx.data::<ArcPgPool>()?;
+        let pool = ctx.data::<ArcDbPool>()?;
         connection::query(
             after,
             before,
@@ -0,0 +1,205 @@
+use crate::model::entity::api_token::ApiScope;
+use crate::model::entity::doc::DocStatus;
+use crate::model::entity::event::DomainEvent;
+use crate::model::entity::task::QueueEvent;
+use crate::schema::album_query::Album;
+use crate::schema::helper::ArcStates;
+use crate::schema::{from_global_id_of, to_global_id, ArcDbPool, RelayTy, ScopeGuard};
+use crate::service;
+use async_graphql::{Context, Enum, Interface, Result, SimpleObject, Subscription, ID};
+use futures_util::future;
+use futures_util::stream::{Stream, StreamExt};
+use tokio_stream::wrappers::BroadcastStream;
+
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Enum)]
+pub enum LibraryEventType {
+    AlbumAdded,
+    AlbumRemoved,
+    AlbumUpdated,
+    AlbumStatusChanged,
+    CbzAdded,
+    CbzRemoved,
+    CbzLinked,
+}
+impl From<&LibraryEventType> for LibraryEventType {
+    fn from(val: &LibraryEventType) -> Self {
+        *val
+    }
+}
+
+#[derive(Debug, Clone, SimpleObject)]
+pub struct AlbumAdded {
+    pub r#type: LibraryEventType,
+    pub album_id: ID,
+    pub url: String,
+}
+#[derive(Debug, Clone, SimpleObject)]
+pub struct AlbumRemoved {
+    pub r#type: LibraryEventType,
+    pub album_id: ID,
+}
+/// the metadata changed, `album` as it is now
+#[derive(Debug, Clone, SimpleObject)]
+pub struct AlbumUpdated {
+    pub r#type: LibraryEventType,
+    pub album_id: ID,
+    pub album: Album,
+}
+#[derive(Debug, Clone, SimpleObject)]
+pub struct AlbumStatusChanged {
+    pub r#type: LibraryEventType,
+    pub album_id: ID,
+    pub from_status: DocStatus,
+    pub to_status: DocStatus,
+}
+/// a cbz built for an album, or found in the cbz dir
+#[derive(Debug, Clone, SimpleObject)]
+pub struct CbzAdded {
+    pub r#type: LibraryEventType,
+    pub cbz_id: ID,
+    pub album_id: Option<ID>,
+    pub path: String,
+}
+#[derive(Debug, Clone, SimpleObject)]
+pub struct CbzRemoved {
+    pub r#type: LibraryEventType,
+    pub cbz_id: ID,
+    pub album_id: Option<ID>,
+    pub path: String,
+}
+/// a cbz moved to another album, or lost its album
+#[derive(Debug, Clone, SimpleObject)]
+pub struct CbzLinked {
+    pub r#type: LibraryEventType,
+    pub cbz_id: ID,
+    pub album_id: Option<ID>,
+    pub previous_album_id: Option<ID>,
+    pub path: String,
+}
+
+#[derive(Interface)]
+#[graphql(field(name = "type", ty = "LibraryEventType", desc = "The type of a library event"))]
+pub enum LibraryEvent {
+    AlbumAdded(AlbumAdded),
+    AlbumRemoved(AlbumRemoved),
+    AlbumUpdated(AlbumUpdated),
+    AlbumStatusChanged(AlbumStatusChanged),
+    CbzAdded(CbzAdded),
+    CbzRemoved(CbzRemoved),
+    CbzLinked(CbzLinked),
+}
+
+fn album_id(id: i32) -> ID {
+    to_global_id(RelayTy::Album, id as usize).into()
+}
+fn cbz_id(id: i32) -> ID {
+    to_global_id(RelayTy::Cbz, id as usize).into()
+}
+
+/// `None` for an updated album that is gone by the time it is loaded
+async fn library_event(pool: &ArcDbPool, event: DomainEvent) -> Option<LibraryEvent> {
+    let event = match event {
+        DomainEvent::DocAdded { id, url } => LibraryEvent::AlbumAdded(AlbumAdded {
+            r#type: LibraryEventType::AlbumAdded,
+            album_id: album_id(id),
+            url,
+        }),
+        DomainEvent::DocRemoved { id } => LibraryEvent::AlbumRemoved(AlbumRemoved {
+            r#type: LibraryEventType::AlbumRemoved,
+            album_id: album_id(id),
+        }),
+        DomainEvent::DocUpdated { id } => {
+            let doc = service::doc::get_doc_by_id(pool, id).await.ok()?;
+            LibraryEvent::AlbumUpdated(AlbumUpdated {
+                r#type: LibraryEventType::AlbumUpdated,
+                album_id: album_id(id),
+                album: doc.into(),
+            })
+        }
+        DomainEvent::DocStatusChanged { id, from, to } => {
+            LibraryEvent::AlbumStatusChanged(AlbumStatusChanged {
+                r#type: LibraryEventType::AlbumStatusChanged,
+                album_id: album_id(id),
+                from_status: from,
+                to_status: to,
+            })
+        }
+        DomainEvent::CbzCreated { id, doc_id, path } => LibraryEvent::CbzAdded(CbzAdded {
+            r#type: LibraryEventType::CbzAdded,
+            cbz_id: cbz_id(id),
+            album_id: doc_id.map(album_id),
+            path,
+        }),
+        DomainEvent::CbzRemoved { id, doc_id, path } => LibraryEvent::CbzRemoved(CbzRemoved {
+            r#type: LibraryEventType::CbzRemoved,
+            cbz_id: cbz_id(id),
+            album_id: doc_id.map(album_id),
+            path,
+        }),
+        DomainEvent::CbzLinked {
+            id,
+            doc_id,
+            previous_doc_id,
+            path,
+        } => LibraryEvent::CbzLinked(CbzLinked {
+            r#type: LibraryEventType::CbzLinked,
+            cbz_id: cbz_id(id),
+            album_id: doc_id.map(album_id),
+            previous_album_id: previous_doc_id.map(album_id),
+            path,
+        }),
+    };
+    Some(event)
+}
+
+/// the domain events of the queue stream, a subscriber that fell behind skips what it missed
+fn domain_events(states: &ArcStates) -> impl Stream<Item = DomainEvent> + use<> {
+    BroadcastStream::new(states.sender.subscribe()).filter_map(|result| async move {
+        match result.ok()?.event {
+            QueueEvent::Domain(event) => Some(event),
+            _ => None,
+        }
+    })
+}
+
+#[derive(Default)]
+pub struct AlbumSubscription;
+
+#[Subscription(guard = "ScopeGuard::new(ApiScope::Read)")]
+impl AlbumSubscription {
+    /// Status, metadata and cbz changes of one album, and its removal.
+    async fn album_changed(
+        &self,
+        ctx: &Context<'_>,
+        id: ID,
+    ) -> Result<impl Stream<Item = LibraryEvent>> {
+        let pool = ctx.data_unchecked::<ArcDbPool>().clone();
+        let states = ctx.data_unchecked::<ArcStates>();
+        let doc_id = from_global_id_of(RelayTy::Album, id.as_str())? as i32;
+        Ok(domain_events(states)
+            .filter(move |event| future::ready(event.concerns_doc(doc_id)))
+            .filter_map(move |event| {
+                let pool = pool.clone();
+                async move { library_event(&pool, event).await }
+            }))
+    }
+    /// Albums added and removed, cbz files added and removed by builds, scans and the fs watcher.
+    async fn library_events(&self, ctx: &Context<'_>) -> impl Stream<Item = LibraryEvent> {
+        let pool = ctx.data_unchecked::<ArcDbPool>().clone();
+        let states = ctx.data_unchecked::<ArcStates>();
+        domain_events(states)
+            .filter(|event| {
+                future::ready(matches!(
+                    event,
+                    DomainEvent::DocAdded { .. }
+                        | DomainEvent::DocRemoved { .. }
+                        | DomainEvent::CbzCreated { .. }
+                        | DomainEvent::CbzRemoved { .. }
+                ))
+            })
+            .filter_map(move |event| {
+                let pool = pool.clone();
+                async move { library_event(&pool, event).await }
+            })
+    }
+}
@@ -0,0 +1,108 @@
+use crate::middleware::TeleGrabRequestId;
+use crate::model::dto::audit::NewAuditEvent;
+use crate::model::entity::api_token::ApiIdentity;
+use crate::model::entity::audit::{AuditOutcome, AuditSource};
+use crate::schema::helper::ArcDbPool;
+use crate::service;
+use async_graphql::extensions::{
+    Extension, ExtensionContext, ExtensionFactory, NextPrepareRequest, NextResolve, ResolveInfo,
+};
+use async_graphql::{Context, Request, ServerResult, Value};
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+
+/// Records every mutation with what its resolver noted through [`note_audit`].
+pub struct Audit;
+
+impl ExtensionFactory for Audit {
+    fn create(&self) -> Arc<dyn Extension> {
+        Arc::new(AuditExtension)
+    }
+}
+
+struct AuditNote {
+    entity: String,
+    entity_id: Option<String>,
+    changes: Option<serde_json::Value>,
+}
+
+/// notes of the mutations of a request, by their path
+#[derive(Default)]
+struct AuditNotes(Mutex<HashMap<String, AuditNote>>);
+
+/// What the mutation being resolved works on, and what it changed.
+pub fn note_audit(
+    ctx: &Context<'_>,
+    entity: &str,
+    entity_id: Option<String>,
+    changes: Option<serde_json::Value>,
+) {
+    if let (Some(notes), Some(path)) = (ctx.data_opt::<AuditNotes>(), &ctx.path_node) {
+        notes.0.lock().unwrap().insert(
+            path.to_string(),
+            AuditNote {
+                entity: entity.to_string(),
+                entity_id,
+                changes,
+            },
+        );
+    }
+}
+
+struct AuditExtension;
+
+#[async_trait::async_trait]
+impl Extension for AuditExtension {
+    async fn prepare_request(
+        &self,
+        ctx: &ExtensionContext<'_>,
+        request: Request,
+        next: NextPrepareRequest<'_>,
+    ) -> ServerResult<Request> {
+        next.run(ctx, request.data(AuditNotes::default())).await
+    }
+
+    async fn resolve(
+        &self,
+        ctx: &ExtensionContext<'_>,
+        info: ResolveInfo<'_>,
+        next: NextResolve<'_>,
+    ) -> ServerResult<Option<Value>> {
+        // only the top level fields of a mutation change anything
+        if info.parent_type != "MutationRoot" || info.path_node.parent.is_some() {
+            return next.run(ctx, info).await;
+        }
+        let action = info.name.to_string();
+        let path = info.path_node.to_string();
+        let result = next.run(ctx, info).await;
+        let note = ctx
+            .data_opt::<AuditNotes>()
+            .and_then(|notes| notes.0.lock().unwrap().remove(&path));
+        let identity = ctx.data_opt::<ApiIdentity>();
+        let event = NewAuditEvent {
+            source: AuditSource::Graphql,
+            actor: identity.map_or_else(|| "anonymous".to_string(), |i| i.name.clone()),
+            token_id: identity.and_then(|i| i.token_id),
+            request_id: ctx
+                .data_opt::<TeleGrabRequestId>()
+                .map(|r| r.get().to_string()),
+            task_id: None,
+            action,
+            entity: note.as_ref().map(|n| n.entity.clone()),
+            entity_id: note.as_ref().and_then(|n| n.entity_id.clone()),
+            changes: note.and_then(|n| n.changes),
+            outcome: if result.is_ok() {
+                AuditOutcome::Ok
+            } else {
+                AuditOutcome::Error
+            },
+            detail: result.as_ref().err().map(|e| e.message.clone()),
+        };
+        if let Ok(pool) = ctx.data::<ArcDbPool>()
+            && let Err(err) = service::audit::record(pool, event).await
+        {
+            tracing::warn!("Record audit event for {} failed: {}", path, err);
+        }
+        result
+    }
+}
@@ -0,0 +1,157 @@
+use crate::model::dto::audit::AuditFilter;
+use crate::model::entity::api_token::ApiScope;
+use crate::model::entity::audit::{AuditEvent, AuditOutcome, AuditSource};
+use crate::schema::helper::{offset_to_cursor, process_pagination, to_global_id, ArcDbPool, ConnectionFields, RelayTy};
+use crate::schema::ScopeGuard;
+use crate::service;
+use async_graphql::connection::{Connection, ConnectionNameType, Edge, EdgeNameType, EmptyFields};
+use async_graphql::{connection, Context, InputObject, Json, Object, OutputType, Result, SimpleObject};
+use time::OffsetDateTime;
+
+#[derive(Debug, Clone, SimpleObject)]
+#[graphql(name = "AuditEvent")]
+pub struct GAuditEvent {
+    pub id: String,
+    pub event_id: i32,
+    /// `rest`, `graphql` or `worker`
+    pub source: String,
+    /// the token name, `anonymous` without auth, `worker 3` for tasks
+    pub actor: String,
+    pub token_id: Option<i32>,
+    pub request_id: Option<String>,
+    pub task_id: Option<String>,
+    /// `PATCH /api/doc/{id}`, `updateAlbum`, `CbzArchive`
+    pub action: String,
+    pub entity: Option<String>,
+    pub entity_id: Option<String>,
+    /// `{"title": {"before": .., "after": ..}}` for metadata edits
+    pub changes: Option<Json<serde_json::Value>>,
+    /// `ok` or `error`
+    pub outcome: String,
+    pub detail: Option<String>,
+    pub created_at: OffsetDateTime,
+}
+
+impl From<AuditEvent> for GAuditEvent {
+    fn from(event: AuditEvent) -> Self {
+        Self {
+            id: to_global_id(RelayTy::AuditEvent, event.id as usize),
+            event_id: event.id,
+            source: event.source,
+            actor: event.actor,
+            token_id: event.token_id,
+            request_id: event.request_id,
+            task_id: event.task_id,
+            action: event.action,
+            entity: event.entity,
+            entity_id: event.entity_id,
+            changes: event.changes.map(|c| Json(c.0)),
+            outcome: event.outcome,
+            detail: event.detail,
+            created_at: event.created_at,
+        }
+    }
+}
+
+/// every field that is set must match, `since` and `until` bound `createdAt`
+#[derive(Debug, Default, InputObject)]
+pub struct AuditEventFilter {
+    pub source: Option<AuditSource>,
+    pub actor: Option<String>,
+    pub token_id: Option<i32>,
+    pub request_id: Option<String>,
+    pub task_id: Option<String>,
+    pub action: Option<String>,
+    pub entity: Option<String>,
+    pub entity_id: Option<String>,
+    pub outcome: Option<AuditOutcome>,
+    pub since: Option<OffsetDateTime>,
+    pub until: Option<OffsetDateTime>,
+}
+
+impl From<AuditEventFilter> for AuditFilter {
+    fn from(value: AuditEventFilter) -> Self {
+        Self {
+            source: value.source,
+            actor: value.actor,
+            token_id: value.token_id,
+            request_id: value.request_id,
+            task_id: value.task_id,
+            action: value.action,
+            entity: value.entity,
+            entity_id: value.entity_id,
+            outcome: value.outcome,
+            since: value.since,
+            until: value.until,
+        }
+    }
+}
+
+struct AuditEventsConnectionName;
+impl ConnectionNameType for AuditEventsConnectionName {
+    fn type_name<T: OutputType>() -> String {
+        "AuditEventsConnection".to_string()
+    }
+}
+struct AuditEventsEdgeName;
+impl EdgeNameType for AuditEventsEdgeName {
+    fn type_name<T: OutputType>() -> String {
+        "AuditEventsEdge".to_string()
+    }
+}
+
+#[derive(Default)]
+pub struct AuditQuery;
+
+#[Object(guard = "ScopeGuard::new(ApiScope::Admin)")]
+impl AuditQuery {
+    /// newest first
+    async fn audit_events(
+        &self,
+        ctx: &Context<'_>,
+        #[graphql(default)] filter: AuditEventFilter,
+        after: Option<String>,
+        before: Option<String>,
+        first: Option<i32>,
+        last: Option<i32>,
+    ) -> Result<
+        Connection<
+            String,
+            GAuditEvent,
+            ConnectionFields,
+            EmptyFields,
+            AuditEventsConnectionName,
+            AuditEventsEdgeName,
+        >,
+    > {
+        let pool = ctx.data::<ArcDbPool>()?;
+        let filter: AuditFilter = filter.into();
+        connection::query(
+            after,
+            before,
+            first,
+            last,
+            |after, before, first, last| async move {
+                let pagination = process_pagination(after, before, first, last)
+                    .map_err(|e| async_graphql::Error::new(e.message.to_string()))?;
+                let paged_events =
+                    service::audit::get_cursor_based_pagination_events(pool, pagination, &filter)
+                        .await
+                        .map_err(|e| async_graphql::Error::new(format!("{}", e)))?;
+                let mut connection = Connection::with_additional_fields(
+                    paged_events.has_prev,
+                    paged_events.has_next,
+                    ConnectionFields {
+                        total_count: paged_events.total as usize,
+                    },
+                );
+                connection.edges.extend(paged_events.data.into_iter().map(|e| {
+                    let cursor = offset_to_cursor(e.id as usize);
+                    Edge::with_additional_fields(cursor, GAuditEvent::from(e), EmptyFields)
+                }));
+                Ok::<_, async_graphql::Error>(connection)
+            },
+        )
+        .await
+    }
+}
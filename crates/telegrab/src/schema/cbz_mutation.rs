@@ -0,0 +1,94 @@
+use crate::model::entity::api_token::ApiScope;
+use crate::model::entity::task::Task;
+use crate::schema::cbz_query::Cbz;
+use crate::schema::task_query::GTask;
+use crate::schema::{from_global_id_of, note_audit, ArcDbPool, ArcStates, RelayTy, ScopeGuard};
+use crate::service;
+use async_graphql::{Context, InputObject, Object, Result, SimpleObject};
+
+#[derive(InputObject, Debug, Clone)]
+struct RelinkCbzInput {
+    pub id: String,
+    /// `null` unlinks the cbz
+    pub album_id: Option<String>,
+    pub client_mutation_id: Option<String>,
+}
+
+#[derive(InputObject, Debug, Clone)]
+struct RemoveCbzInput {
+    pub id: String,
+    pub client_mutation_id: Option<String>,
+}
+
+#[derive(InputObject, Debug, Clone)]
+struct ScanCbzDirInput {
+    pub client_mutation_id: Option<String>,
+}
+
+#[derive(SimpleObject, Debug, Clone)]
+struct RelinkCbzPayload {
+    pub cbz: Cbz,
+    pub client_mutation_id: Option<String>,
+}
+
+#[derive(SimpleObject, Debug, Clone)]
+struct CbzTaskPayload {
+    pub task: GTask,
+    pub client_mutation_id: Option<String>,
+}
+
+#[derive(Default)]
+pub struct CbzMutation;
+
+#[Object(guard = "ScopeGuard::new(ApiScope::Write)")]
+impl CbzMutation {
+    async fn relink_cbz(&self, ctx: &Context<'_>, input: RelinkCbzInput) -> Result<RelinkCbzPayload> {
+        let pool = ctx.data::<ArcDbPool>()?;
+        let states = ctx.data::<ArcStates>()?;
+        let id = from_global_id_of(RelayTy::Cbz, input.id.as_str())?;
+        note_audit(ctx, "cbz", Some(id.to_string()), None);
+        let doc_id = match input.album_id {
+            Some(album_id) => {
+                let doc_id = from_global_id_of(RelayTy::Album, album_id.as_str())?;
+                Some(service::doc::get_doc_by_id(pool, doc_id as i32).await?.id)
+            }
+            None => None,
+        };
+        let before = service::cbz::get_cbz_by_id(pool, id as i32).await?;
+        let cbz = service::cbz::update_cbz(pool, states, id as i32, doc_id).await?;
+        // again, with what changed
+        note_audit(ctx, "cbz", Some(id.to_string()), service::audit::diff(&before, &cbz));
+        Ok(RelinkCbzPayload {
+            cbz: cbz.into(),
+            client_mutation_id: input.client_mutation_id,
+        })
+    }
+    /// queues a task that deletes the file and the cbz
+    async fn remove_cbz(&self, ctx: &Context<'_>, input: RemoveCbzInput) -> Result<CbzTaskPayload> {
+        let pool = ctx.data::<ArcDbPool>()?;
+        let states = ctx.data::<ArcStates>()?;
+        let id = from_global_id_of(RelayTy::Cbz, input.id.as_str())?;
+        note_audit(ctx, "cbz", Some(id.to_string()), None);
+        let cbz = service::cbz::get_cbz_by_id(pool, id as i32).await?;
+        let task = Task::new_remove_cbz_task(cbz.id);
+        states.enqueue(task.clone()).await;
+        Ok(CbzTaskPayload {
+            task: task.into(),
+            client_mutation_id: input.client_mutation_id,
+        })
+    }
+    /// queues a task that registers the cbz files of the cbz dir that are not known yet
+    async fn scan_cbz_dir(&self, ctx: &Context<'_>, input: ScanCbzDirInput) -> Result<CbzTaskPayload> {
+        let states = ctx.data::<ArcStates>()?;
+        note_audit(ctx, "cbz", None, None);
+        if states.is_scan_active().await {
+            return Err("Scan is active, no new scan tasks accepted".into());
+        }
+        let task = Task::new_scan_dir_task();
+        states.enqueue(task.clone()).await;
+        Ok(CbzTaskPayload {
+            task: task.into(),
+            client_mutation_id: input.client_mutation_id,
+        })
+    }
+}
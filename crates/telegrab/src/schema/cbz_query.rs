@@ -0,0 +1,119 @@
+use crate::model::entity::api_token::ApiScope;
+use crate::model::entity::cbz::Cbz as CbzEntity;
+use crate::schema::album_query::Album;
+use crate::schema::{
+    from_global_id_of, offset_to_cursor, process_pagination, to_global_id, ArcDbPool, ConnectionFields,
+    RelayTy, ScopeGuard,
+};
+use crate::service;
+use async_graphql::connection::{Connection, ConnectionNameType, Edge, EdgeNameType, EmptyFields};
+use async_graphql::{connection, ComplexObject, Context, Object, OutputType, Result, SimpleObject, ID};
+use time::OffsetDateTime;
+
+#[derive(Debug, Clone, SimpleObject)]
+#[graphql(complex)]
+pub struct Cbz {
+    pub cbz_id: i32,
+    pub id: String,
+    /// the global id of the album it was built for, none for a file found in the cbz dir
+    pub album_id: Option<String>,
+    /// relative to the cbz storage
+    pub path: String,
+    pub created_at: OffsetDateTime,
+    pub updated_at: OffsetDateTime,
+    #[graphql(skip)]
+    pub doc_id: Option<i32>,
+}
+
+impl From<CbzEntity> for Cbz {
+    fn from(cbz: CbzEntity) -> Self {
+        Self {
+            cbz_id: cbz.id,
+            id: to_global_id(RelayTy::Cbz, cbz.id as usize),
+            album_id: cbz.doc_id.map(|id| to_global_id(RelayTy::Album, id as usize)),
+            path: cbz.path,
+            created_at: cbz.created_at,
+            updated_at: cbz.updated_at,
+            doc_id: cbz.doc_id,
+        }
+    }
+}
+
+#[ComplexObject]
+impl Cbz {
+    /// `null` when it has none, or the album was deleted since
+    async fn album(&self, ctx: &Context<'_>) -> Result<Option<Album>> {
+        let Some(doc_id) = self.doc_id else {
+            return Ok(None);
+        };
+        let pool = ctx.data::<ArcDbPool>()?;
+        match service::doc::get_doc_by_id(pool, doc_id).await {
+            Ok(doc) => Ok(Some(doc.into())),
+            Err(sqlx::Error::RowNotFound) => Ok(None),
+            Err(e) => Err(e.into()),
+        }
+    }
+}
+
+struct CbzsConnectionName;
+impl ConnectionNameType for CbzsConnectionName {
+    fn type_name<T: OutputType>() -> String {
+        "CbzsConnection".to_string()
+    }
+}
+struct CbzsEdgeName;
+impl EdgeNameType for CbzsEdgeName {
+    fn type_name<T: OutputType>() -> String {
+        "CbzsEdge".to_string()
+    }
+}
+
+#[derive(Default)]
+pub struct CbzQuery;
+
+#[Object(guard = "ScopeGuard::new(ApiScope::Read)")]
+impl CbzQuery {
+    async fn cbz(&self, ctx: &Context<'_>, id: ID) -> Result<Cbz> {
+        let pool = ctx.data::<ArcDbPool>()?;
+        let id = from_global_id_of(RelayTy::Cbz, id.as_str())?;
+        let cbz = service::cbz::get_cbz_by_id(pool, id as i32).await?;
+        Ok(cbz.into())
+    }
+    async fn cbzs(
+        &self,
+        ctx: &Context<'_>,
+        after: Option<String>,
+        before: Option<String>,
+        first: Option<i32>,
+        last: Option<i32>,
+    ) -> Result<Connection<String, Cbz, ConnectionFields, EmptyFields, CbzsConnectionName, CbzsEdgeName>>
+    {
+        let pool = ctx.data::<ArcDbPool>()?;
+        connection::query(
+            after,
+            before,
+            first,
+            last,
+            |after, before, first, last| async move {
+                let pagination = process_pagination(after, before, first, last)
+                    .map_err(|e| async_graphql::Error::new(e.message.to_string()))?;
+                let paged_cbzs = service::cbz::get_cursor_based_pagination_cbzs(pool, pagination)
+                    .await
+                    .map_err(|e| async_graphql::Error::new(format!("{}", e)))?;
+                let mut connection = Connection::with_additional_fields(
+                    paged_cbzs.has_prev,
+                    paged_cbzs.has_next,
+                    ConnectionFields {
+                        total_count: paged_cbzs.total as usize,
+                    },
+                );
+                connection.edges.extend(paged_cbzs.data.into_iter().map(|cbz| {
+                    let cbz: Cbz = cbz.into();
+                    Edge::with_additional_fields(offset_to_cursor(cbz.cbz_id as usize), cbz, EmptyFields)
+                }));
+                Ok::<_, async_graphql::Error>(connection)
+            },
+        )
+        .await
+    }
+}
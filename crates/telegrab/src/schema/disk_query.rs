@@ -0,0 +1,63 @@
+use crate::model::entity::api_token::ApiScope;
+use crate::model::entity::disk::{DirUsage, DiskUsage};
+use crate::schema::helper::ArcStates;
+use crate::schema::ScopeGuard;
+use async_graphql::{Context, Object, Result, SimpleObject};
+use time::OffsetDateTime;
+
+#[derive(Debug, Clone, SimpleObject)]
+#[graphql(name = "DirUsage")]
+pub struct GDirUsage {
+    pub path: String,
+    pub used_bytes: u64,
+    pub available_bytes: Option<u64>,
+    pub total_bytes: Option<u64>,
+    pub quota_bytes: Option<u64>,
+}
+
+#[derive(Debug, Clone, SimpleObject)]
+#[graphql(name = "DiskUsage")]
+pub struct GDiskUsage {
+    pub pic: GDirUsage,
+    pub cbz: GDirUsage,
+    /// why downloads wait, `null` while they run
+    pub downloads_paused: Option<String>,
+    pub archives_paused: Option<String>,
+    pub checked_at: OffsetDateTime,
+}
+
+impl From<DirUsage> for GDirUsage {
+    fn from(usage: DirUsage) -> Self {
+        Self {
+            path: usage.path,
+            used_bytes: usage.used_bytes,
+            available_bytes: usage.available_bytes,
+            total_bytes: usage.total_bytes,
+            quota_bytes: usage.quota_bytes,
+        }
+    }
+}
+
+impl From<DiskUsage> for GDiskUsage {
+    fn from(usage: DiskUsage) -> Self {
+        Self {
+            pic: usage.pic.into(),
+            cbz: usage.cbz.into(),
+            downloads_paused: usage.downloads_paused,
+            archives_paused: usage.archives_paused,
+            checked_at: usage.checked_at,
+        }
+    }
+}
+
+#[derive(Default)]
+pub struct DiskQuery;
+
+#[Object(guard = "ScopeGuard::new(ApiScope::Read)")]
+impl DiskQuery {
+    /// `null` until the disk monitor ran once
+    async fn disk_usage(&self, ctx: &Context<'_>) -> Result<Option<GDiskUsage>> {
+        let states = ctx.data::<ArcStates>()?;
+        Ok(states.get_disk_usage().await.map(Into::into))
+    }
+}
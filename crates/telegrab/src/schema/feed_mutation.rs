@@ -0,0 +1,148 @@
+use crate::model::dto::feed::{CreateFeedReq, UpdateFeedReq};
+use crate::model::entity::api_token::ApiScope;
+use crate::model::entity::task::Task;
+use crate::schema::feed_query::GFeed;
+use crate::schema::helper::{from_global_id_of, ArcDbPool, ArcStates, RelayTy};
+use crate::schema::task_query::GTask;
+use crate::schema::{note_audit, ScopeGuard};
+use crate::service;
+use async_graphql::{Context, InputObject, Object, Result, SimpleObject};
+
+#[derive(InputObject, Debug, Clone)]
+struct AddFeedInput {
+    pub url: String,
+    pub title: Option<String>,
+    pub enabled: Option<bool>,
+    pub auto_grab: Option<bool>,
+    pub include_pattern: Option<String>,
+    pub exclude_pattern: Option<String>,
+    pub poll_interval_secs: Option<i32>,
+    pub client_mutation_id: Option<String>,
+}
+
+impl From<AddFeedInput> for CreateFeedReq {
+    fn from(input: AddFeedInput) -> Self {
+        Self {
+            url: input.url,
+            title: input.title,
+            enabled: input.enabled,
+            auto_grab: input.auto_grab,
+            include_pattern: input.include_pattern,
+            exclude_pattern: input.exclude_pattern,
+            poll_interval_secs: input.poll_interval_secs,
+        }
+    }
+}
+
+/// an empty pattern removes it
+#[derive(InputObject, Debug, Clone)]
+struct UpdateFeedInput {
+    pub id: String,
+    pub url: Option<String>,
+    pub title: Option<String>,
+    pub enabled: Option<bool>,
+    pub auto_grab: Option<bool>,
+    pub include_pattern: Option<String>,
+    pub exclude_pattern: Option<String>,
+    pub poll_interval_secs: Option<i32>,
+    pub client_mutation_id: Option<String>,
+}
+
+impl From<UpdateFeedInput> for UpdateFeedReq {
+    fn from(input: UpdateFeedInput) -> Self {
+        Self {
+            url: input.url,
+            title: input.title,
+            enabled: input.enabled,
+            auto_grab: input.auto_grab,
+            include_pattern: input.include_pattern,
+            exclude_pattern: input.exclude_pattern,
+            poll_interval_secs: input.poll_interval_secs,
+        }
+    }
+}
+
+#[derive(SimpleObject, Debug, Clone)]
+struct FeedPayload {
+    pub feed: GFeed,
+    pub client_mutation_id: Option<String>,
+}
+
+#[derive(InputObject, Debug, Clone)]
+struct FeedIdInput {
+    pub id: String,
+    pub client_mutation_id: Option<String>,
+}
+
+#[derive(SimpleObject, Debug, Clone)]
+struct DeleteFeedPayload {
+    pub deleted_id: String,
+    pub client_mutation_id: Option<String>,
+}
+
+#[derive(SimpleObject, Debug, Clone)]
+struct PollFeedPayload {
+    pub task: GTask,
+    pub client_mutation_id: Option<String>,
+}
+
+#[derive(Default)]
+pub struct FeedMutation;
+
+#[Object(guard = "ScopeGuard::new(ApiScope::Write)")]
+impl FeedMutation {
+    async fn add_feed(&self, ctx: &Context<'_>, input: AddFeedInput) -> Result<FeedPayload> {
+        let pool = ctx.data::<ArcDbPool>()?;
+        let client_mutation_id = input.client_mutation_id.clone();
+        let req: CreateFeedReq = input.into();
+        req.validate()?;
+        let feed = service::feed::create_feed(pool, req).await?;
+        note_audit(ctx, "feed", Some(feed.id.to_string()), None);
+        Ok(FeedPayload {
+            feed: feed.into(),
+            client_mutation_id,
+        })
+    }
+    async fn update_feed(&self, ctx: &Context<'_>, input: UpdateFeedInput) -> Result<FeedPayload> {
+        let pool = ctx.data::<ArcDbPool>()?;
+        let id = from_global_id_of(RelayTy::Feed, &input.id)?;
+        note_audit(ctx, "feed", Some(id.to_string()), None);
+        let client_mutation_id = input.client_mutation_id.clone();
+        let req: UpdateFeedReq = input.into();
+        req.validate()?;
+        let feed = service::feed::update_feed(pool, id as i32, req).await?;
+        Ok(FeedPayload {
+            feed: feed.into(),
+            client_mutation_id,
+        })
+    }
+    async fn delete_feed(&self, ctx: &Context<'_>, input: FeedIdInput) -> Result<DeleteFeedPayload> {
+        let pool = ctx.data::<ArcDbPool>()?;
+        let id = from_global_id_of(RelayTy::Feed, &input.id)?;
+        note_audit(ctx, "feed", Some(id.to_string()), None);
+        let count = service::feed::delete_feed_by_id(pool, id as i32).await?;
+        if count == 0 {
+            return Err("No Feed found".into());
+        }
+        Ok(DeleteFeedPayload {
+            deleted_id: input.id,
+            client_mutation_id: input.client_mutation_id,
+        })
+    }
+    async fn poll_feed(&self, ctx: &Context<'_>, input: FeedIdInput) -> Result<PollFeedPayload> {
+        let pool = ctx.data::<ArcDbPool>()?;
+        let states = ctx.data::<ArcStates>()?;
+        let id = from_global_id_of(RelayTy::Feed, &input.id)?;
+        note_audit(ctx, "feed", Some(id.to_string()), None);
+        let feed = service::feed::get_feed_by_id(pool, id as i32).await?;
+        if states.is_feed_poll_pending(feed.id).await {
+            return Err("Feed poll is already queued".into());
+        }
+        let task = Task::new_poll_feed_task(feed.id);
+        states.enqueue(task.clone()).await;
+        Ok(PollFeedPayload {
+            task: task.into(),
+            client_mutation_id: input.client_mutation_id,
+        })
+    }
+}
@@ -0,0 +1,63 @@
+use crate::model::entity::api_token::ApiScope;
+use crate::model::entity::feed::Feed;
+use crate::schema::helper::{from_global_id_of, to_global_id, ArcDbPool, RelayTy};
+use crate::schema::ScopeGuard;
+use crate::service;
+use async_graphql::{Context, Object, Result, SimpleObject};
+use time::OffsetDateTime;
+
+#[derive(Debug, Clone, SimpleObject)]
+#[graphql(name = "Feed")]
+pub struct GFeed {
+    pub id: String,
+    pub feed_id: i32,
+    pub url: String,
+    pub title: Option<String>,
+    pub enabled: bool,
+    pub auto_grab: bool,
+    pub include_pattern: Option<String>,
+    pub exclude_pattern: Option<String>,
+    pub poll_interval_secs: i32,
+    pub last_polled_at: Option<OffsetDateTime>,
+    pub last_error: Option<String>,
+    pub created_at: OffsetDateTime,
+    pub updated_at: OffsetDateTime,
+}
+
+impl From<Feed> for GFeed {
+    fn from(feed: Feed) -> Self {
+        Self {
+            id: to_global_id(RelayTy::Feed, feed.id as usize),
+            feed_id: feed.id,
+            url: feed.url,
+            title: feed.title,
+            enabled: feed.enabled,
+            auto_grab: feed.auto_grab,
+            include_pattern: feed.include_pattern,
+            exclude_pattern: feed.exclude_pattern,
+            poll_interval_secs: feed.poll_interval_secs,
+            last_polled_at: feed.last_polled_at,
+            last_error: feed.last_error,
+            created_at: feed.created_at,
+            updated_at: feed.updated_at,
+        }
+    }
+}
+
+#[derive(Default)]
+pub struct FeedQuery;
+
+#[Object(guard = "ScopeGuard::new(ApiScope::Read)")]
+impl FeedQuery {
+    async fn feeds(&self, ctx: &Context<'_>) -> Result<Vec<GFeed>> {
+        let pool = ctx.data::<ArcDbPool>()?;
+        let feeds = service::feed::get_feeds(pool).await?;
+        Ok(feeds.into_iter().map(Into::into).collect())
+    }
+    async fn feed(&self, ctx: &Context<'_>, id: String) -> Result<GFeed> {
+        let pool = ctx.data::<ArcDbPool>()?;
+        let id = from_global_id_of(RelayTy::Feed, &id)?;
+        let feed = service::feed::get_feed_by_id(pool, id as i32).await?;
+        Ok(feed.into())
+    }
+}
@@ -0,0 +1,79 @@
+use crate::model::entity::api_token::ApiScope;
+use crate::model::entity::gc::GcAction;
+use crate::model::entity::task::Task;
+use crate::schema::helper::{from_global_id_of, ArcDbPool, ArcStates, RelayTy};
+use crate::schema::task_query::GTask;
+use crate::schema::{note_audit, ScopeGuard};
+use crate::service;
+use async_graphql::{Context, InputObject, Object, Result, SimpleObject};
+
+/// a dry run unless `dryRun` is false
+#[derive(InputObject, Debug, Clone)]
+struct CollectGarbageInput {
+    #[graphql(default = true)]
+    pub dry_run: bool,
+    #[graphql(default)]
+    pub action: GcAction,
+    pub client_mutation_id: Option<String>,
+}
+
+#[derive(InputObject, Debug, Clone)]
+struct ApplyGcReportInput {
+    pub report_id: String,
+    #[graphql(default)]
+    pub action: GcAction,
+    pub client_mutation_id: Option<String>,
+}
+
+#[derive(SimpleObject, Debug, Clone)]
+struct GcTaskPayload {
+    pub task: GTask,
+    pub client_mutation_id: Option<String>,
+}
+
+#[derive(Default)]
+pub struct GcMutation;
+
+#[Object(guard = "ScopeGuard::new(ApiScope::Admin)")]
+impl GcMutation {
+    async fn collect_garbage(
+        &self,
+        ctx: &Context<'_>,
+        input: CollectGarbageInput,
+    ) -> Result<GcTaskPayload> {
+        let states = ctx.data::<ArcStates>()?;
+        note_audit(ctx, "gc", None, None);
+        if states.is_gc_pending().await {
+            return Err("Garbage collection is already queued".into());
+        }
+        let task = Task::new_collect_garbage_task(input.dry_run, input.action);
+        states.enqueue(task.clone()).await;
+        Ok(GcTaskPayload {
+            task: task.into(),
+            client_mutation_id: input.client_mutation_id,
+        })
+    }
+    async fn apply_gc_report(
+        &self,
+        ctx: &Context<'_>,
+        input: ApplyGcReportInput,
+    ) -> Result<GcTaskPayload> {
+        let pool = ctx.data::<ArcDbPool>()?;
+        let states = ctx.data::<ArcStates>()?;
+        let id = from_global_id_of(RelayTy::GcReport, &input.report_id)?;
+        note_audit(ctx, "gc", Some(id.to_string()), None);
+        let report = service::gc::get_report_by_id(pool, id as i32).await?;
+        if report.applied_at.is_some() {
+            return Err("Report was already applied".into());
+        }
+        if states.is_gc_pending().await {
+            return Err("Garbage collection is already queued".into());
+        }
+        let task = Task::new_apply_gc_report_task(report.id, input.action);
+        states.enqueue(task.clone()).await;
+        Ok(GcTaskPayload {
+            task: task.into(),
+            client_mutation_id: input.client_mutation_id,
+        })
+    }
+}
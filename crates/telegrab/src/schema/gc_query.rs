@@ -0,0 +1,81 @@
+use crate::model::entity::api_token::ApiScope;
+use crate::model::entity::gc::{GcItem, GcItemKind, GcReport};
+use crate::schema::helper::{from_global_id_of, to_global_id, ArcDbPool, RelayTy};
+use crate::schema::ScopeGuard;
+use crate::service;
+use async_graphql::{Context, Object, Result, SimpleObject};
+use time::OffsetDateTime;
+
+#[derive(Debug, Clone, SimpleObject)]
+#[graphql(name = "GcItem")]
+pub struct GGcItem {
+    pub kind: GcItemKind,
+    pub path: String,
+    pub bytes: u64,
+    pub cbz_id: Option<i32>,
+}
+
+#[derive(Debug, Clone, SimpleObject)]
+#[graphql(name = "GcReport")]
+pub struct GGcReport {
+    pub id: String,
+    pub report_id: i32,
+    pub task_id: String,
+    pub items: Vec<GGcItem>,
+    pub reclaimable_bytes: i64,
+    pub action: Option<String>,
+    pub applied_bytes: Option<i64>,
+    pub applied_at: Option<OffsetDateTime>,
+    pub created_at: OffsetDateTime,
+}
+
+impl From<GcItem> for GGcItem {
+    fn from(item: GcItem) -> Self {
+        Self {
+            kind: item.kind,
+            path: item.path,
+            bytes: item.bytes,
+            cbz_id: item.cbz_id,
+        }
+    }
+}
+
+impl From<GcReport> for GGcReport {
+    fn from(report: GcReport) -> Self {
+        Self {
+            id: to_global_id(RelayTy::GcReport, report.id as usize),
+            report_id: report.id,
+            task_id: report.task_id,
+            items: report.items.0.into_iter().map(Into::into).collect(),
+            reclaimable_bytes: report.reclaimable_bytes,
+            action: report.action,
+            applied_bytes: report.applied_bytes,
+            applied_at: report.applied_at,
+            created_at: report.created_at,
+        }
+    }
+}
+
+#[derive(Default)]
+pub struct GcQuery;
+
+#[Object(guard = "ScopeGuard::new(ApiScope::Admin)")]
+impl GcQuery {
+    /// newest first
+    async fn gc_reports(&self, ctx: &Context<'_>) -> Result<Vec<GGcReport>> {
+        let pool = ctx.data::<ArcDbPool>()?;
+        let reports = service::gc::get_reports(pool).await?;
+        Ok(reports.into_iter().map(Into::into).collect())
+    }
+    async fn gc_report(&self, ctx: &Context<'_>, id: String) -> Result<GGcReport> {
+        let pool = ctx.data::<ArcDbPool>()?;
+        let id = from_global_id_of(RelayTy::GcReport, &id)?;
+        let report = service::gc::get_report_by_id(pool, id as i32).await?;
+        Ok(report.into())
+    }
+    async fn latest_gc_report(&self, ctx: &Context<'_>) -> Result<Option<GGcReport>> {
+        let pool = ctx.data::<ArcDbPool>()?;
+        let report = service::gc::get_latest_report(pool).await?;
+        Ok(report.map(Into::into))
+    }
+}
@@ -0,0 +1,26 @@
+use crate::model::entity::api_token::{ApiIdentity, ApiScope};
+use async_graphql::{Context, Guard, Result};
+
+pub struct ScopeGuard {
+    scope: ApiScope,
+}
+
+impl ScopeGuard {
+    pub fn new(scope: ApiScope) -> Self {
+        Self { scope }
+    }
+}
+
+impl Guard for ScopeGuard {
+    async fn check(&self, ctx: &Context<'_>) -> Result<()> {
+        match ctx.data_opt::<ApiIdentity>() {
+            Some(identity) if identity.has_scope(self.scope) => Ok(()),
+            Some(identity) => Err(format!(
+                "token `{}` lacks `{}` scope",
+                identity.name, self.scope
+            )
+            .into()),
+            None => Err("Unauthorized".into()),
+        }
+    }
+}
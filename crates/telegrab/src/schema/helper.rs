@@ -1,16 +1,20 @@
 use std::sync::Arc;
 use crate::schema::album_query::Album;
+use crate::schema::cbz_query::Cbz;
 use crate::schema::image_query::Image;
-use async_graphql::{Interface, SimpleObject};
+use async_graphql::{CustomValidator, InputValueError, Interface, SimpleObject};
 use base64::engine::general_purpose::STANDARD as base64;
 use base64::Engine;
 use serde::{Deserialize, Serialize};
-use sqlx_postgres::PgPool;
+use crate::db::DbPool;
+use crate::link;
 use crate::model::{Direction, PaginationArgs};
 use crate::state::QueueState;
+use crate::http_client::HttpClientManager;
 
-pub type ArcPgPool = Arc<PgPool>;
+pub type ArcDbPool = Arc<DbPool>;
 pub type ArcStates = Arc<QueueState>;
+pub type ArcHttpClient = Arc<HttpClientManager>;
 
 #[derive(Interface)]
 #[graphql(
@@ -20,13 +24,19 @@ pub type ArcStates = Arc<QueueState>;
 pub enum RelayNode {
     Album(Album),
     Image(Image),
+    Cbz(Cbz),
 }
 
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Clone, Copy, Eq, PartialEq, Serialize, Deserialize)]
 pub enum RelayTy {
     Album,
     Image,
     Cbz,
+    Feed,
+    Schedule,
+    LibraryReport,
+    GcReport,
+    AuditEvent,
     Offset,
 }
 pub fn to_global_id(ty: RelayTy, id: usize) -> String {
@@ -47,6 +57,13 @@ pub fn from_global_id(global_id: &str) -> async_graphql::Result<(RelayTy, usize)
         Err("Invalid format: missing colon".into())
     }
 }
+/// the id of a global id that has to be of type `expected`
+pub fn from_global_id_of(expected: RelayTy, global_id: &str) -> async_graphql::Result<usize> {
+    match from_global_id(global_id)? {
+        (ty, id) if ty == expected => Ok(id),
+        (ty, _) => Err(format!("Invalid type: expected {:?}, got {:?}", expected, ty).into()),
+    }
+}
 pub fn offset_to_cursor(offset: usize) -> String {
     to_global_id(RelayTy::Offset, offset)
 }
@@ -105,4 +122,14 @@ pub fn process_pagination(
             limit: 10,
         }),
     }
-}
\ No newline at end of file
+}
+/// accepts what `link::canonicalize` accepts, scheme-less and mirror links included
+pub struct UrlValidator;
+
+impl CustomValidator<String> for UrlValidator {
+    fn check(&self, value: &String) -> Result<(), InputValueError<String>> {
+        link::canonicalize(value)
+            .map(|_| ())
+            .map_err(InputValueError::custom)
+    }
+}
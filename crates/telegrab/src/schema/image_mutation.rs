@@ -0,0 +1,111 @@
+use crate::model::dto::pic::MutatePicReq;
+use crate::model::entity::api_token::ApiScope;
+use crate::schema::image_query::Image;
+use crate::schema::{from_global_id_of, note_audit, ArcDbPool, RelayTy, ScopeGuard};
+use crate::service;
+use async_graphql::{Context, InputObject, Object, Result, SimpleObject};
+
+#[derive(InputObject, Debug, Clone)]
+struct AddImageInput {
+    pub album_id: String,
+    pub url: String,
+    pub seq: i32,
+    pub client_mutation_id: Option<String>,
+}
+
+/// fields left out keep their value
+#[derive(InputObject, Debug, Clone)]
+struct UpdateImageInput {
+    pub id: String,
+    /// moves the image to another album
+    pub album_id: Option<String>,
+    pub url: Option<String>,
+    pub seq: Option<i32>,
+    pub client_mutation_id: Option<String>,
+}
+
+#[derive(InputObject, Debug, Clone)]
+struct DeleteImageInput {
+    pub id: String,
+    pub client_mutation_id: Option<String>,
+}
+
+#[derive(SimpleObject, Debug, Clone)]
+struct ImagePayload {
+    pub image: Image,
+    pub client_mutation_id: Option<String>,
+}
+
+#[derive(SimpleObject, Debug, Clone)]
+struct DeleteImagePayload {
+    pub deleted_id: String,
+    pub client_mutation_id: Option<String>,
+}
+
+#[derive(Default)]
+pub struct ImageMutation;
+
+#[Object(guard = "ScopeGuard::new(ApiScope::Write)")]
+impl ImageMutation {
+    async fn add_image(&self, ctx: &Context<'_>, input: AddImageInput) -> Result<ImagePayload> {
+        let pool = ctx.data::<ArcDbPool>()?;
+        let doc_id = from_global_id_of(RelayTy::Album, input.album_id.as_str())?;
+        let doc = service::doc::get_doc_by_id(pool, doc_id as i32).await?;
+        let pic = service::pic::create_pic(
+            pool,
+            MutatePicReq {
+                doc_id: doc.id,
+                url: input.url,
+                seq: input.seq,
+            },
+        )
+        .await?;
+        note_audit(ctx, "pic", Some(pic.id.to_string()), None);
+        Ok(ImagePayload {
+            image: pic.into(),
+            client_mutation_id: input.client_mutation_id,
+        })
+    }
+    async fn update_image(&self, ctx: &Context<'_>, input: UpdateImageInput) -> Result<ImagePayload> {
+        let pool = ctx.data::<ArcDbPool>()?;
+        let id = from_global_id_of(RelayTy::Image, input.id.as_str())?;
+        note_audit(ctx, "pic", Some(id.to_string()), None);
+        let before = service::pic::get_pic_by_id(pool, id as i32).await?;
+        let doc_id = match input.album_id {
+            Some(album_id) => {
+                let doc_id = from_global_id_of(RelayTy::Album, album_id.as_str())?;
+                service::doc::get_doc_by_id(pool, doc_id as i32).await?.id
+            }
+            None => before.doc_id,
+        };
+        let params = MutatePicReq {
+            doc_id,
+            url: input.url.unwrap_or_else(|| before.url.clone()),
+            seq: input.seq.unwrap_or(before.seq),
+        };
+        let pic = service::pic::update_pic_by_id(pool, id as i32, params).await?;
+        // again, with what changed
+        note_audit(ctx, "pic", Some(id.to_string()), service::audit::diff(&before, &pic));
+        Ok(ImagePayload {
+            image: pic.into(),
+            client_mutation_id: input.client_mutation_id,
+        })
+    }
+    async fn delete_image(
+        &self,
+        ctx: &Context<'_>,
+        input: DeleteImageInput,
+    ) -> Result<DeleteImagePayload> {
+        let pool = ctx.data::<ArcDbPool>()?;
+        let id = from_global_id_of(RelayTy::Image, input.id.as_str())?;
+        note_audit(ctx, "pic", Some(id.to_string()), None);
+        let count = service::pic::delete_pic_by_id(pool, id as i32).await?;
+        if count == 0 {
+            return Err("No Image found".into());
+        }
+        Ok(DeleteImagePayload {
+            deleted_id: input.id,
+            client_mutation_id: input.client_mutation_id,
+        })
+    }
+}
@@ -1,5 +1,6 @@
+use crate::model::entity::api_token::ApiScope;
 use crate::model::entity::pic::Pic;
-use crate::schema::{from_global_id, to_global_id, ArcPgPool, RelayTy};
+use crate::schema::{from_global_id, to_global_id, ArcDbPool, RelayTy, ScopeGuard};
 use crate::service;
 use async_graphql::connection::{ConnectionNameType, EdgeNameType};
 use async_graphql::dataloader::{DataLoader, Loader, LruCache};
@@ -9,7 +10,7 @@ use std::sync::Arc;
 use time::OffsetDateTime;
 
 pub struct ImageLoader {
-    pool: ArcPgPool,
+    pool: ArcDbPool,
 }
 impl Loader<i32> for ImageLoader {
     type Value = Image;
@@ -50,10 +51,10 @@ impl EdgeNameType for ImagesEdgeName {
 #[derive(Default)]
 pub struct ImageQuery;
 
-#[Object]
+#[Object(guard = "ScopeGuard::new(ApiScope::Read)")]
 impl ImageQuery {
     async fn image(&self, ctx: &Context<'_>, id: String) -> Result<Image> {
-        let pool = ctx.data::<ArcPgPool>()?;
+        let pool = ctx.data::<ArcDbPool>()?;
         let (_, id) = from_global_id(id.as_str())?;
         let _loader = ctx.data::<DataLoader<ImageLoader, LruCache>>(); // todo: data loader
         let pic = service::pic::get_pic_by_id(pool, id as i32).await?;
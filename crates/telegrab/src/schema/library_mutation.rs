@@ -0,0 +1,97 @@
+use crate::library;
+use crate::model::entity::api_token::ApiScope;
+use crate::model::entity::library::{RepairAction, RepairKind};
+use crate::model::entity::task::{Task, TaskType};
+use crate::schema::helper::{from_global_id_of, ArcDbPool, ArcStates, RelayTy};
+use crate::schema::task_query::GTask;
+use crate::schema::{note_audit, ScopeGuard};
+use crate::service;
+use async_graphql::{Context, InputObject, Object, Result, SimpleObject};
+
+#[derive(InputObject, Debug, Clone)]
+struct VerifyLibraryInput {
+    pub client_mutation_id: Option<String>,
+}
+
+#[derive(InputObject, Debug, Clone, Copy)]
+struct RepairActionInput {
+    pub kind: RepairKind,
+    pub target_id: Option<i32>,
+}
+
+impl From<RepairActionInput> for RepairAction {
+    fn from(input: RepairActionInput) -> Self {
+        Self {
+            kind: input.kind,
+            id: input.target_id,
+        }
+    }
+}
+
+/// either the `repair` of single issues or every issue of a report
+#[derive(InputObject, Debug, Clone)]
+struct RepairLibraryInput {
+    #[graphql(default)]
+    pub actions: Vec<RepairActionInput>,
+    pub report_id: Option<String>,
+    pub client_mutation_id: Option<String>,
+}
+
+#[derive(SimpleObject, Debug, Clone)]
+struct VerifyLibraryPayload {
+    pub task: GTask,
+    pub client_mutation_id: Option<String>,
+}
+
+#[derive(SimpleObject, Debug, Clone)]
+struct RepairLibraryPayload {
+    pub tasks: Vec<GTask>,
+    pub client_mutation_id: Option<String>,
+}
+
+#[derive(Default)]
+pub struct LibraryMutation;
+
+#[Object(guard = "ScopeGuard::new(ApiScope::Write)")]
+impl LibraryMutation {
+    async fn verify_library(
+        &self,
+        ctx: &Context<'_>,
+        input: VerifyLibraryInput,
+    ) -> Result<VerifyLibraryPayload> {
+        let states = ctx.data::<ArcStates>()?;
+        note_audit(ctx, "library", None, None);
+        if states.is_task_pending(&TaskType::VerifyLibrary).await {
+            return Err("VerifyLibrary is already queued".into());
+        }
+        let task = Task::new_verify_library_task();
+        states.enqueue(task.clone()).await;
+        Ok(VerifyLibraryPayload {
+            task: task.into(),
+            client_mutation_id: input.client_mutation_id,
+        })
+    }
+    async fn repair_library(
+        &self,
+        ctx: &Context<'_>,
+        input: RepairLibraryInput,
+    ) -> Result<RepairLibraryPayload> {
+        let pool = ctx.data::<ArcDbPool>()?;
+        let states = ctx.data::<ArcStates>()?;
+        let mut tasks = vec![];
+        note_audit(ctx, "library", None, None);
+        if let Some(report_id) = &input.report_id {
+            let id = from_global_id_of(RelayTy::LibraryReport, report_id)?;
+            note_audit(ctx, "library", Some(id.to_string()), None);
+            let report = service::library::get_report_by_id(pool, id as i32).await?;
+            tasks.extend(library::repair_all(pool, states, &report.issues).await?);
+        }
+        for action in input.actions {
+            tasks.push(library::repair(pool, states, action.into()).await?);
+        }
+        Ok(RepairLibraryPayload {
+            tasks: tasks.into_iter().map(Into::into).collect(),
+            client_mutation_id: input.client_mutation_id,
+        })
+    }
+}
@@ -0,0 +1,106 @@
+use crate::model::entity::api_token::ApiScope;
+use crate::model::entity::library::{
+    IssueKind, LibraryIssue, LibraryReport, RepairAction, RepairKind,
+};
+use crate::schema::helper::{from_global_id_of, to_global_id, ArcDbPool, RelayTy};
+use crate::schema::ScopeGuard;
+use crate::service;
+use async_graphql::{Context, Object, Result, SimpleObject};
+use time::OffsetDateTime;
+
+#[derive(Debug, Clone, SimpleObject)]
+#[graphql(name = "RepairAction")]
+pub struct GRepairAction {
+    pub kind: RepairKind,
+    /// pic, doc or cbz id, depending on `kind`
+    pub target_id: Option<i32>,
+}
+
+#[derive(Debug, Clone, SimpleObject)]
+#[graphql(name = "LibraryIssue")]
+pub struct GLibraryIssue {
+    pub kind: IssueKind,
+    pub cbz_id: Option<i32>,
+    pub album_id: Option<String>,
+    pub image_id: Option<String>,
+    pub path: Option<String>,
+    pub detail: String,
+    pub repair: Option<GRepairAction>,
+}
+
+#[derive(Debug, Clone, SimpleObject)]
+#[graphql(name = "LibraryReport")]
+pub struct GLibraryReport {
+    pub id: String,
+    pub report_id: i32,
+    pub task_id: String,
+    pub cbz_checked: i32,
+    pub pics_checked: i32,
+    pub issues: Vec<GLibraryIssue>,
+    pub created_at: OffsetDateTime,
+}
+
+impl From<RepairAction> for GRepairAction {
+    fn from(action: RepairAction) -> Self {
+        Self {
+            kind: action.kind,
+            target_id: action.id,
+        }
+    }
+}
+
+impl From<LibraryIssue> for GLibraryIssue {
+    fn from(issue: LibraryIssue) -> Self {
+        Self {
+            kind: issue.kind,
+            cbz_id: issue.cbz_id,
+            album_id: issue
+                .doc_id
+                .map(|id| to_global_id(RelayTy::Album, id as usize)),
+            image_id: issue
+                .pic_id
+                .map(|id| to_global_id(RelayTy::Image, id as usize)),
+            path: issue.path,
+            detail: issue.detail,
+            repair: issue.repair.map(Into::into),
+        }
+    }
+}
+
+impl From<LibraryReport> for GLibraryReport {
+    fn from(report: LibraryReport) -> Self {
+        Self {
+            id: to_global_id(RelayTy::LibraryReport, report.id as usize),
+            report_id: report.id,
+            task_id: report.task_id,
+            cbz_checked: report.cbz_checked,
+            pics_checked: report.pics_checked,
+            issues: report.issues.0.into_iter().map(Into::into).collect(),
+            created_at: report.created_at,
+        }
+    }
+}
+
+#[derive(Default)]
+pub struct LibraryQuery;
+
+#[Object(guard = "ScopeGuard::new(ApiScope::Read)")]
+impl LibraryQuery {
+    /// newest first
+    async fn library_reports(&self, ctx: &Context<'_>) -> Result<Vec<GLibraryReport>> {
+        let pool = ctx.data::<ArcDbPool>()?;
+        let reports = service::library::get_reports(pool).await?;
+        Ok(reports.into_iter().map(Into::into).collect())
+    }
+    async fn library_report(&self, ctx: &Context<'_>, id: String) -> Result<GLibraryReport> {
+        let pool = ctx.data::<ArcDbPool>()?;
+        let id = from_global_id_of(RelayTy::LibraryReport, &id)?;
+        let report = service::library::get_report_by_id(pool, id as i32).await?;
+        Ok(report.into())
+    }
+    async fn latest_library_report(&self, ctx: &Context<'_>) -> Result<Option<GLibraryReport>> {
+        let pool = ctx.data::<ArcDbPool>()?;
+        let report = service::library::get_latest_report(pool).await?;
+        Ok(report.map(Into::into))
+    }
+}
@@ -1,13 +1,33 @@
 mod root_schema;
 mod album_mutation;
+mod guard;
 mod helper;
 mod album_query;
 mod node_query;
 mod image_query;
+mod cbz_query;
+mod cbz_mutation;
+mod image_mutation;
 mod task_query;
 mod task_mutation;
 mod task_subscription;
+mod album_subscription;
+mod settings_query;
+mod feed_query;
+mod feed_mutation;
+mod schedule_query;
+mod schedule_mutation;
+mod library_query;
+mod library_mutation;
+mod gc_query;
+mod gc_mutation;
+mod disk_query;
+mod settings_mutation;
+mod audit;
+mod audit_query;
 
+use audit::note_audit;
+use guard::ScopeGuard;
 use helper::*;
 
 pub use root_schema::create_schema;
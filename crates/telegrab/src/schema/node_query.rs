@@ -1,13 +1,14 @@
-use crate::schema::{from_global_id, ArcPgPool, RelayNode, RelayTy};
+use crate::model::entity::api_token::ApiScope;
+use crate::schema::{from_global_id, ArcDbPool, RelayNode, RelayTy, ScopeGuard};
 use crate::service;
 use async_graphql::{Context, Object, Result};
 
 #[derive(Default)]
 pub struct NodeQuery;
-#[Object]
+#[Object(guard = "ScopeGuard::new(ApiScope::Read)")]
 impl NodeQuery {
     async fn node(&self, ctx: &Context<'_>, id: String) -> Result<Option<RelayNode>> {
-        let pool = ctx.data::<ArcPgPool>()?;
+        let pool = ctx.data::<ArcDbPool>()?;
         let (ty, id) = from_global_id(id.as_str())?;
         match ty {
             RelayTy::Album => {
@@ -18,6 +19,10 @@ impl NodeQuery {
                 let pic = service::pic::get_pic_by_id(pool, id as i32).await?;
                 Ok(Some(RelayNode::Image(pic.into())))
             }
+            RelayTy::Cbz => {
+                let cbz = service::cbz::get_cbz_by_id(pool, id as i32).await?;
+                Ok(Some(RelayNode::Cbz(cbz.into())))
+            }
 
             _ => Err(async_graphql::Error::new("Invalid node type")),
         }
@@ -1,23 +1,67 @@
 use crate::schema::album_mutation::AlbumMutation;
 use crate::schema::album_query::AlbumQuery;
-use crate::schema::helper::ArcStates;
+use crate::schema::album_subscription::AlbumSubscription;
+use crate::schema::audit::Audit;
+use crate::schema::audit_query::AuditQuery;
+use crate::schema::cbz_mutation::CbzMutation;
+use crate::schema::cbz_query::CbzQuery;
+use crate::schema::feed_mutation::FeedMutation;
+use crate::schema::feed_query::FeedQuery;
+use crate::schema::disk_query::DiskQuery;
+use crate::schema::gc_mutation::GcMutation;
+use crate::schema::gc_query::GcQuery;
+use crate::schema::helper::{ArcHttpClient, ArcStates};
+use crate::schema::image_mutation::ImageMutation;
 use crate::schema::image_query::ImageQuery;
+use crate::schema::library_mutation::LibraryMutation;
+use crate::schema::library_query::LibraryQuery;
 use crate::schema::node_query::NodeQuery;
+use crate::schema::schedule_mutation::ScheduleMutation;
+use crate::schema::schedule_query::ScheduleQuery;
+use crate::schema::settings_mutation::SettingsMutation;
+use crate::schema::settings_query::SettingsQuery;
 use crate::schema::task_mutation::TaskMutation;
 use crate::schema::task_query::TaskQuery;
 use crate::schema::task_subscription::TaskSubscription;
-use crate::schema::ArcPgPool;
+use crate::schema::ArcDbPool;
 use async_graphql::{MergedObject, MergedSubscription, Schema};
 
 pub type GallerySchema = Schema<QueryRoot, MutationRoot, SubscriptionRoot>;
 #[derive(MergedObject, Default)]
-pub struct QueryRoot(AlbumQuery, ImageQuery, TaskQuery, NodeQuery);
+pub struct QueryRoot(
+    AlbumQuery,
+    ImageQuery,
+    CbzQuery,
+    TaskQuery,
+    NodeQuery,
+    FeedQuery,
+    ScheduleQuery,
+    LibraryQuery,
+    GcQuery,
+    DiskQuery,
+    SettingsQuery,
+    AuditQuery,
+);
 #[derive(MergedObject, Default)]
-pub struct MutationRoot(AlbumMutation, TaskMutation);
+pub struct MutationRoot(
+    AlbumMutation,
+    ImageMutation,
+    CbzMutation,
+    TaskMutation,
+    FeedMutation,
+    ScheduleMutation,
+    LibraryMutation,
+    GcMutation,
+    SettingsMutation,
+);
 #[derive(MergedSubscription, Default)]
-pub struct SubscriptionRoot(TaskSubscription);
+pub struct SubscriptionRoot(TaskSubscription, AlbumSubscription);
 
-pub fn create_schema(pool: ArcPgPool, states: ArcStates) -> GallerySchema {
+pub fn create_schema(
+    pool: ArcDbPool,
+    states: ArcStates,
+    http_client: ArcHttpClient,
+) -> GallerySchema {
     Schema::build(
         QueryRoot::default(),
         MutationRoot::default(),
@@ -25,5 +69,7 @@ pub fn create_schema(pool: ArcPgPool, states: ArcStates) -> GallerySchema {
     )
     .data(pool)
     .data(states)
+    .data(http_client)
+    .extension(Audit)
     .finish()
 }
@@ -0,0 +1,87 @@
+use crate::model::dto::schedule::UpdateScheduleReq;
+use crate::model::entity::api_token::ApiScope;
+use crate::scheduler;
+use crate::schema::helper::{from_global_id_of, ArcDbPool, ArcStates, RelayTy};
+use crate::schema::schedule_query::{GSchedule, GScheduleRun};
+use crate::schema::{note_audit, ScopeGuard};
+use crate::service;
+use async_graphql::{Context, InputObject, Object, Result, SimpleObject};
+use time::OffsetDateTime;
+
+#[derive(InputObject, Debug, Clone)]
+struct UpdateScheduleInput {
+    pub id: String,
+    pub cron: Option<String>,
+    pub enabled: Option<bool>,
+    pub client_mutation_id: Option<String>,
+}
+
+impl From<UpdateScheduleInput> for UpdateScheduleReq {
+    fn from(input: UpdateScheduleInput) -> Self {
+        Self {
+            cron: input.cron,
+            enabled: input.enabled,
+        }
+    }
+}
+
+#[derive(SimpleObject, Debug, Clone)]
+struct SchedulePayload {
+    pub schedule: GSchedule,
+    pub client_mutation_id: Option<String>,
+}
+
+#[derive(InputObject, Debug, Clone)]
+struct RunScheduleInput {
+    pub id: String,
+    pub client_mutation_id: Option<String>,
+}
+
+#[derive(SimpleObject, Debug, Clone)]
+struct RunSchedulePayload {
+    pub run: GScheduleRun,
+    pub client_mutation_id: Option<String>,
+}
+
+#[derive(Default)]
+pub struct ScheduleMutation;
+
+#[Object(guard = "ScopeGuard::new(ApiScope::Write)")]
+impl ScheduleMutation {
+    async fn update_schedule(
+        &self,
+        ctx: &Context<'_>,
+        input: UpdateScheduleInput,
+    ) -> Result<SchedulePayload> {
+        let pool = ctx.data::<ArcDbPool>()?;
+        let id = from_global_id_of(RelayTy::Schedule, &input.id)?;
+        note_audit(ctx, "schedule", Some(id.to_string()), None);
+        let client_mutation_id = input.client_mutation_id.clone();
+        let req: UpdateScheduleReq = input.into();
+        req.validate()?;
+        let schedule = scheduler::update_schedule(pool, id as i32, req).await?;
+        Ok(SchedulePayload {
+            schedule: schedule.into(),
+            client_mutation_id,
+        })
+    }
+    /// the run is marked skipped when the same task is still queued or running
+    async fn run_schedule(
+        &self,
+        ctx: &Context<'_>,
+        input: RunScheduleInput,
+    ) -> Result<RunSchedulePayload> {
+        let pool = ctx.data::<ArcDbPool>()?;
+        let states = ctx.data::<ArcStates>()?;
+        let id = from_global_id_of(RelayTy::Schedule, &input.id)?;
+        note_audit(ctx, "schedule", Some(id.to_string()), None);
+        let schedule = service::schedule::get_schedule_by_id(pool, id as i32).await?;
+        let run =
+            scheduler::run_schedule(pool, states, &schedule, OffsetDateTime::now_utc(), None)
+                .await?;
+        Ok(RunSchedulePayload {
+            run: run.into(),
+            client_mutation_id: input.client_mutation_id,
+        })
+    }
+}
@@ -0,0 +1,91 @@
+use crate::model::entity::api_token::ApiScope;
+use crate::model::entity::schedule::{Schedule, ScheduleRun};
+use crate::schema::helper::{from_global_id_of, to_global_id, ArcDbPool, RelayTy};
+use crate::schema::task_query::{task_type_to_g, GTaskType};
+use crate::schema::ScopeGuard;
+use crate::service;
+use async_graphql::{Context, Object, Result, SimpleObject};
+use time::OffsetDateTime;
+
+#[derive(Debug, Clone, SimpleObject)]
+#[graphql(name = "Schedule")]
+pub struct GSchedule {
+    pub id: String,
+    pub schedule_id: i32,
+    pub name: String,
+    pub cron: String,
+    pub task_type: GTaskType,
+    /// the album, image, cbz or feed the task works on
+    pub inner_id: Option<String>,
+    pub enabled: bool,
+    pub last_run_at: Option<OffsetDateTime>,
+    pub next_run_at: Option<OffsetDateTime>,
+    pub last_task_id: Option<String>,
+    pub created_at: OffsetDateTime,
+    pub updated_at: OffsetDateTime,
+}
+
+#[derive(Debug, Clone, SimpleObject)]
+#[graphql(name = "ScheduleRun")]
+pub struct GScheduleRun {
+    pub task_id: Option<String>,
+    pub skipped: bool,
+    pub scheduled_at: OffsetDateTime,
+    pub created_at: OffsetDateTime,
+}
+
+impl From<Schedule> for GSchedule {
+    fn from(schedule: Schedule) -> Self {
+        let (inner_id, task_type) = task_type_to_g(schedule.task_type.0);
+        Self {
+            id: to_global_id(RelayTy::Schedule, schedule.id as usize),
+            schedule_id: schedule.id,
+            name: schedule.name,
+            cron: schedule.cron,
+            task_type,
+            inner_id,
+            enabled: schedule.enabled,
+            last_run_at: schedule.last_run_at,
+            next_run_at: schedule.next_run_at,
+            last_task_id: schedule.last_task_id,
+            created_at: schedule.created_at,
+            updated_at: schedule.updated_at,
+        }
+    }
+}
+
+impl From<ScheduleRun> for GScheduleRun {
+    fn from(run: ScheduleRun) -> Self {
+        Self {
+            task_id: run.task_id,
+            skipped: run.skipped,
+            scheduled_at: run.scheduled_at,
+            created_at: run.created_at,
+        }
+    }
+}
+
+#[derive(Default)]
+pub struct ScheduleQuery;
+
+#[Object(guard = "ScopeGuard::new(ApiScope::Read)")]
+impl ScheduleQuery {
+    async fn schedules(&self, ctx: &Context<'_>) -> Result<Vec<GSchedule>> {
+        let pool = ctx.data::<ArcDbPool>()?;
+        let schedules = service::schedule::get_schedules(pool).await?;
+        Ok(schedules.into_iter().map(Into::into).collect())
+    }
+    async fn schedule(&self, ctx: &Context<'_>, id: String) -> Result<GSchedule> {
+        let pool = ctx.data::<ArcDbPool>()?;
+        let id = from_global_id_of(RelayTy::Schedule, &id)?;
+        let schedule = service::schedule::get_schedule_by_id(pool, id as i32).await?;
+        Ok(schedule.into())
+    }
+    /// newest first
+    async fn schedule_runs(&self, ctx: &Context<'_>, id: String) -> Result<Vec<GScheduleRun>> {
+        let pool = ctx.data::<ArcDbPool>()?;
+        let id = from_global_id_of(RelayTy::Schedule, &id)?;
+        let runs = service::schedule::get_schedule_runs(pool, id as i32).await?;
+        Ok(runs.into_iter().map(Into::into).collect())
+    }
+}
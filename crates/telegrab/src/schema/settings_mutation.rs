@@ -0,0 +1,138 @@
+use crate::configuration::{
+    HostPolicy, HostRateLimit, HttpClientSettings, ProxySettings, RateLimitSettings,
+};
+use crate::model::entity::api_token::ApiScope;
+use crate::schema::helper::ArcHttpClient;
+use crate::schema::settings_query::GHttpClientSettings;
+use crate::schema::{note_audit, ScopeGuard};
+use async_graphql::{Context, InputObject, Object, Result, SimpleObject};
+use secrecy::SecretString;
+
+#[derive(InputObject, Debug, Clone)]
+struct HostPolicyInput {
+    pub requests_per_second: f64,
+    pub max_concurrency: usize,
+}
+
+#[derive(InputObject, Debug, Clone)]
+struct HostRateLimitInput {
+    pub host: String,
+    pub requests_per_second: f64,
+    pub max_concurrency: usize,
+}
+
+#[derive(InputObject, Debug, Clone)]
+struct RateLimitSettingsInput {
+    pub default: HostPolicyInput,
+    #[graphql(default)]
+    pub hosts: Vec<HostRateLimitInput>,
+    pub max_retries: u32,
+    pub max_retry_after_secs: u64,
+}
+
+/// leave `password` out to keep the current one
+#[derive(InputObject, Debug, Clone)]
+struct ProxySettingsInput {
+    pub url: String,
+    pub username: Option<String>,
+    pub password: Option<String>,
+    #[graphql(default)]
+    pub hosts: Vec<String>,
+    #[graphql(default)]
+    pub bypass: Vec<String>,
+}
+
+#[derive(InputObject, Debug, Clone)]
+struct UpdateHttpClientSettingsInput {
+    pub connect_timeout_secs: u64,
+    pub timeout_secs: u64,
+    pub max_connections: usize,
+    pub pool_enabled: bool,
+    pub user_agent: String,
+    pub rate_limit: RateLimitSettingsInput,
+    pub proxy: Option<ProxySettingsInput>,
+    pub client_mutation_id: Option<String>,
+}
+
+#[derive(SimpleObject, Debug, Clone)]
+struct UpdateHttpClientSettingsPayload {
+    pub settings: GHttpClientSettings,
+    pub client_mutation_id: Option<String>,
+}
+
+impl From<HostPolicyInput> for HostPolicy {
+    fn from(p: HostPolicyInput) -> Self {
+        Self {
+            requests_per_second: p.requests_per_second,
+            max_concurrency: p.max_concurrency,
+        }
+    }
+}
+
+impl From<HostRateLimitInput> for HostRateLimit {
+    fn from(h: HostRateLimitInput) -> Self {
+        Self {
+            host: h.host,
+            requests_per_second: h.requests_per_second,
+            max_concurrency: h.max_concurrency,
+        }
+    }
+}
+
+impl From<RateLimitSettingsInput> for RateLimitSettings {
+    fn from(r: RateLimitSettingsInput) -> Self {
+        Self {
+            default: r.default.into(),
+            hosts: r.hosts.into_iter().map(Into::into).collect(),
+            max_retries: r.max_retries,
+            max_retry_after_secs: r.max_retry_after_secs,
+        }
+    }
+}
+
+impl From<ProxySettingsInput> for ProxySettings {
+    fn from(p: ProxySettingsInput) -> Self {
+        Self {
+            url: p.url,
+            username: p.username,
+            password: p.password.map(SecretString::from),
+            hosts: p.hosts,
+            bypass: p.bypass,
+        }
+    }
+}
+
+impl From<UpdateHttpClientSettingsInput> for HttpClientSettings {
+    fn from(i: UpdateHttpClientSettingsInput) -> Self {
+        Self {
+            connect_timeout_secs: i.connect_timeout_secs,
+            timeout_secs: i.timeout_secs,
+            max_connections: i.max_connections,
+            pool_enabled: i.pool_enabled,
+            user_agent: i.user_agent,
+            rate_limit: i.rate_limit.into(),
+            proxy: i.proxy.map(Into::into),
+        }
+    }
+}
+
+#[derive(Default)]
+pub struct SettingsMutation;
+
+#[Object(guard = "ScopeGuard::new(ApiScope::Admin)")]
+impl SettingsMutation {
+    async fn update_http_client_settings(
+        &self,
+        ctx: &Context<'_>,
+        input: UpdateHttpClientSettingsInput,
+    ) -> Result<UpdateHttpClientSettingsPayload> {
+        let http_client = ctx.data::<ArcHttpClient>()?;
+        note_audit(ctx, "http-client", None, None);
+        let client_mutation_id = input.client_mutation_id.clone();
+        http_client.update_config(input.into()).await?;
+        Ok(UpdateHttpClientSettingsPayload {
+            settings: http_client.config().await.into(),
+            client_mutation_id,
+        })
+    }
+}
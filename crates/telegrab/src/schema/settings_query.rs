@@ -0,0 +1,121 @@
+use crate::configuration::{
+    HostPolicy, HostRateLimit, HttpClientSettings, ProxySettings, RateLimitSettings,
+};
+use crate::model::entity::api_token::ApiScope;
+use crate::schema::helper::ArcHttpClient;
+use crate::schema::ScopeGuard;
+use async_graphql::{Context, Object, Result, SimpleObject};
+
+#[derive(Debug, Clone, SimpleObject)]
+#[graphql(name = "HttpClientSettings")]
+pub struct GHttpClientSettings {
+    pub connect_timeout_secs: u64,
+    pub timeout_secs: u64,
+    pub max_connections: usize,
+    pub pool_enabled: bool,
+    pub user_agent: String,
+    pub rate_limit: GRateLimitSettings,
+    pub proxy: Option<GProxySettings>,
+}
+
+#[derive(Debug, Clone, SimpleObject)]
+#[graphql(name = "RateLimitSettings")]
+pub struct GRateLimitSettings {
+    pub default: GHostPolicy,
+    pub hosts: Vec<GHostRateLimit>,
+    pub max_retries: u32,
+    pub max_retry_after_secs: u64,
+}
+
+#[derive(Debug, Clone, SimpleObject)]
+#[graphql(name = "HostPolicy")]
+pub struct GHostPolicy {
+    pub requests_per_second: f64,
+    pub max_concurrency: usize,
+}
+
+#[derive(Debug, Clone, SimpleObject)]
+#[graphql(name = "HostRateLimit")]
+pub struct GHostRateLimit {
+    pub host: String,
+    pub requests_per_second: f64,
+    pub max_concurrency: usize,
+}
+
+/// the password itself is never exposed
+#[derive(Debug, Clone, SimpleObject)]
+#[graphql(name = "ProxySettings")]
+pub struct GProxySettings {
+    pub url: String,
+    pub username: Option<String>,
+    pub has_password: bool,
+    pub hosts: Vec<String>,
+    pub bypass: Vec<String>,
+}
+
+impl From<HttpClientSettings> for GHttpClientSettings {
+    fn from(s: HttpClientSettings) -> Self {
+        Self {
+            connect_timeout_secs: s.connect_timeout_secs,
+            timeout_secs: s.timeout_secs,
+            max_connections: s.max_connections,
+            pool_enabled: s.pool_enabled,
+            user_agent: s.user_agent,
+            rate_limit: s.rate_limit.into(),
+            proxy: s.proxy.map(Into::into),
+        }
+    }
+}
+
+impl From<RateLimitSettings> for GRateLimitSettings {
+    fn from(s: RateLimitSettings) -> Self {
+        Self {
+            default: s.default.into(),
+            hosts: s.hosts.into_iter().map(Into::into).collect(),
+            max_retries: s.max_retries,
+            max_retry_after_secs: s.max_retry_after_secs,
+        }
+    }
+}
+
+impl From<HostPolicy> for GHostPolicy {
+    fn from(p: HostPolicy) -> Self {
+        Self {
+            requests_per_second: p.requests_per_second,
+            max_concurrency: p.max_concurrency,
+        }
+    }
+}
+
+impl From<HostRateLimit> for GHostRateLimit {
+    fn from(h: HostRateLimit) -> Self {
+        Self {
+            host: h.host,
+            requests_per_second: h.requests_per_second,
+            max_concurrency: h.max_concurrency,
+        }
+    }
+}
+
+impl From<ProxySettings> for GProxySettings {
+    fn from(p: ProxySettings) -> Self {
+        Self {
+            url: p.url,
+            username: p.username,
+            has_password: p.password.is_some(),
+            hosts: p.hosts,
+            bypass: p.bypass,
+        }
+    }
+}
+
+#[derive(Default)]
+pub struct SettingsQuery;
+
+#[Object(guard = "ScopeGuard::new(ApiScope::Admin)")]
+impl SettingsQuery {
+    async fn http_client_settings(&self, ctx: &Context<'_>) -> Result<GHttpClientSettings> {
+        let http_client = ctx.data::<ArcHttpClient>()?;
+        Ok(http_client.config().await.into())
+    }
+}
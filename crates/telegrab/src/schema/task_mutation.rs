@@ -1,6 +1,8 @@
+use crate::model::entity::api_token::ApiScope;
 use crate::model::entity::task::{Task, TaskStatus};
 use crate::schema::helper::{from_global_id, ArcStates, RelayTy};
 use crate::schema::task_query::GTask;
+use crate::schema::{note_audit, ScopeGuard};
 use async_graphql::{Context, InputObject, Object, Result, SimpleObject};
 
 #[derive(InputObject, Debug, Clone)]
@@ -29,7 +31,7 @@ struct CleanUpPayload {
 #[derive(Default)]
 pub struct TaskMutation;
 
-#[Object]
+#[Object(guard = "ScopeGuard::new(ApiScope::Write)")]
 impl TaskMutation {
     async fn enqueue_task(
         &self,
@@ -41,6 +43,7 @@ impl TaskMutation {
         let (ty, id) = from_global_id(input.id.as_str())?;
         match ty {
             RelayTy::Album => {
+                note_audit(ctx, "doc", Some(id.to_string()), None);
                 if let Some(task) = states.find_doc_in_queue(id as i32).await {
                     return Ok(EnqueueTaskPayload {
                         task: task.into(),
@@ -56,6 +59,7 @@ impl TaskMutation {
                 })
             }
             RelayTy::Image => {
+                note_audit(ctx, "pic", Some(id.to_string()), None);
                 if let Some(task) = states.find_pic_in_queue(id as i32).await {
                     return Ok(EnqueueTaskPayload {
                         task: task.into(),
@@ -79,6 +83,7 @@ impl TaskMutation {
         input: CleanUpInput,
     ) -> Result<CleanUpPayload> {
         let states = ctx.data::<ArcStates>()?;
+        note_audit(ctx, "task", None, None);
         let client_mutation_id = input.client_mutation_id.clone();
         let removed_count = states.cleanup_completed_tasks(input.keep_recent).await;
         let tasks = states.task_store.read().await;
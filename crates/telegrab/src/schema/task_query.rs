@@ -1,5 +1,7 @@
+use crate::model::entity::api_token::ApiScope;
 use crate::model::entity::task::{ActiveTaskInfo, Task, TaskStatus, TaskType};
 use crate::schema::helper::{to_global_id, ArcStates, RelayTy};
+use crate::schema::ScopeGuard;
 use async_graphql::{Context, Enum, Object, Result, SimpleObject};
 use time::OffsetDateTime;
 
@@ -15,6 +17,14 @@ pub enum GTaskType {
     FSCbzAdded,
     FSCbzRemoved,
     HtmlParseAll,
+    MergeDuplicateAlbums,
+    FeedPoll,
+    DueFeedsPoll,
+    CleanupCompleted,
+    AlbumRefresh,
+    VerifyLibrary,
+    CollectGarbage,
+    ApplyGcReport,
 }
 #[derive(Debug, Clone, SimpleObject)]
 #[graphql(name = "Task")]
@@ -42,7 +52,7 @@ pub struct GActiveTask {
     pub progress: Option<f64>,
 }
 
-fn task_type_to_g(task_type: TaskType)->(Option<String>, GTaskType){
+pub fn task_type_to_g(task_type: TaskType) -> (Option<String>, GTaskType) {
     match task_type {
         TaskType::HtmlParse { id } => (
             Some(to_global_id(RelayTy::Album, id as usize)),
@@ -68,6 +78,23 @@ fn task_type_to_g(task_type: TaskType)->(Option<String>, GTaskType){
         TaskType::FSCbzAdded { .. } => (None, GTaskType::FSCbzAdded),
         TaskType::FSCbzRemoved { .. } => (None, GTaskType::FSCbzRemoved),
         TaskType::HtmlParseAll => (None, GTaskType::HtmlParseAll),
+        TaskType::MergeDuplicateDocs => (None, GTaskType::MergeDuplicateAlbums),
+        TaskType::PollFeed { id } => (
+            Some(to_global_id(RelayTy::Feed, id as usize)),
+            GTaskType::FeedPoll,
+        ),
+        TaskType::PollDueFeeds => (None, GTaskType::DueFeedsPoll),
+        TaskType::CleanupCompleted => (None, GTaskType::CleanupCompleted),
+        TaskType::RefreshDoc { id, .. } => (
+            Some(to_global_id(RelayTy::Album, id as usize)),
+            GTaskType::AlbumRefresh,
+        ),
+        TaskType::VerifyLibrary => (None, GTaskType::VerifyLibrary),
+        TaskType::CollectGarbage { .. } => (None, GTaskType::CollectGarbage),
+        TaskType::ApplyGcReport { id, .. } => (
+            Some(to_global_id(RelayTy::GcReport, id as usize)),
+            GTaskType::ApplyGcReport,
+        ),
     }
 }
 
@@ -105,7 +132,7 @@ impl From<ActiveTaskInfo> for GActiveTask {
 #[derive(Default)]
 pub struct TaskQuery;
 
-#[Object]
+#[Object(guard = "ScopeGuard::new(ApiScope::Read)")]
 impl TaskQuery {
     async fn tasks(&self, ctx: &Context<'_>) -> Result<Vec<GTask>> {
         let states = ctx.data::<ArcStates>()?;
@@ -1,8 +1,12 @@
-use crate::model::entity::task::QueueEvent;
+use crate::model::entity::task::{QueueEvent, TaskStatus};
+use crate::model::entity::api_token::ApiScope;
 use crate::schema::helper::ArcStates;
+use crate::schema::ScopeGuard;
+use crate::schema::disk_query::GDiskUsage;
 use crate::schema::task_query::GTask;
 use async_graphql::{Context, Enum, Interface, Result, SimpleObject, Subscription};
-use futures_util::stream::{Stream, StreamExt};
+use futures_util::future;
+use futures_util::stream::{self, Stream, StreamExt};
 use std::convert::Infallible;
 use std::ops::{Deref, DerefMut};
 use tokio_stream::wrappers::BroadcastStream;
@@ -14,6 +18,7 @@ pub enum TaskEventType {
     TaskRemoved,
     TaskProgress,
     QueueCleared,
+    DiskUsage,
 }
 impl AsRef<TaskEventType> for TaskEventType {
     fn as_ref(&self) -> &Self {
@@ -62,6 +67,12 @@ pub struct QueueCleared {
     pub r#type: TaskEventType,
 }
 
+#[derive(Debug, Clone, SimpleObject)]
+pub struct DiskUsageChanged {
+    pub r#type: TaskEventType,
+    pub usage: GDiskUsage,
+}
+
 #[derive(Interface)]
 #[graphql(field(name = "type", ty = "TaskEventType", desc = "The type of a task event"))]
 pub enum TaskEvent {
@@ -70,12 +81,13 @@ pub enum TaskEvent {
     TaskRemoved(TaskRemoved),
     TaskProgress(TaskProgress),
     QueueCleared(QueueCleared),
+    DiskUsageChanged(DiskUsageChanged),
 }
 
 #[derive(Default)]
 pub struct TaskSubscription;
 
-#[Subscription]
+#[Subscription(guard = "ScopeGuard::new(ApiScope::Read)")]
 impl TaskSubscription {
     async fn events(&self, ctx: &Context<'_>) -> impl Stream<Item = Result<TaskEvent, Infallible>> {
         let states = ctx.data_unchecked::<ArcStates>();
@@ -84,8 +96,8 @@ impl TaskSubscription {
 
         stream.filter_map(|result| async move {
             match result {
-                Ok(q_event) => {
-                    let t_event = match q_event {
+                Ok(numbered) => {
+                    let t_event = match numbered.event {
                         QueueEvent::TaskAdded(task) => TaskEvent::TaskAdded(TaskAdded {
                             r#type: TaskEventType::TaskAdded,
                             task: task.into(),
@@ -108,6 +120,14 @@ impl TaskSubscription {
                         QueueEvent::QueueCleared => TaskEvent::QueueCleared(QueueCleared {
                             r#type: TaskEventType::QueueCleared,
                         }),
+                        QueueEvent::DiskUsage(usage) => {
+                            TaskEvent::DiskUsageChanged(DiskUsageChanged {
+                                r#type: TaskEventType::DiskUsage,
+                                usage: usage.into(),
+                            })
+                        }
+                        // not a task event
+                        QueueEvent::Domain(_) => return None,
                     };
                     Some(Ok(t_event))
                 }
@@ -115,4 +135,55 @@ impl TaskSubscription {
             }
         })
     }
+    /// Progress of one task, the stream ends once the task completed, failed or left the queue.
+    async fn task_progress(
+        &self,
+        ctx: &Context<'_>,
+        task_id: String,
+    ) -> Result<impl Stream<Item = TaskProgress>> {
+        let states = ctx.data_unchecked::<ArcStates>().clone();
+        // subscribed before the look up, nothing is lost in between
+        let rx = states.sender.subscribe();
+        let task = states
+            .get_task(&task_id)
+            .await
+            .ok_or_else(|| async_graphql::Error::new("No Task found"))?;
+        if matches!(task.status, TaskStatus::Completed | TaskStatus::Failed) {
+            return Ok(stream::empty().left_stream());
+        }
+
+        Ok(BroadcastStream::new(rx)
+            .filter_map(move |result| {
+                let task_id = task_id.clone();
+                let states = states.clone();
+                async move {
+                    match result.ok()?.event {
+                        QueueEvent::TaskProgress(id, progress) if id == task_id => {
+                            Some(Some(TaskProgress {
+                                r#type: TaskEventType::TaskProgress,
+                                task_id,
+                                progress,
+                            }))
+                        }
+                        QueueEvent::TaskUpdated(task)
+                            if task.id == task_id
+                                && matches!(task.status, TaskStatus::Completed | TaskStatus::Failed) =>
+                        {
+                            Some(None)
+                        }
+                        QueueEvent::TaskRemoved(id) if id == task_id => Some(None),
+                        // a pending task went with the queue, a running one goes on
+                        QueueEvent::QueueCleared
+                            if !states.active_tasks.read().await.contains_key(&task_id) =>
+                        {
+                            Some(None)
+                        }
+                        _ => None,
+                    }
+                }
+            })
+            .take_while(|progress| future::ready(progress.is_some()))
+            .filter_map(future::ready)
+            .right_stream())
+    }
 }
@@ -0,0 +1,96 @@
+use crate::model::entity::api_token::{ApiScope, ApiToken, CreatedApiToken, ScopeList};
+use sha2::{Digest, Sha256};
+use sqlx::{query, query_as};
+use time::{Duration, OffsetDateTime};
+use crate::db::{with_pool, DbPool};
+use uuid::Uuid;
+
+const TOKEN_PREFIX: &str = "tg_";
+/// how stale last_used_at may get before a request writes it again
+const LAST_USED_THROTTLE: Duration = Duration::minutes(1);
+
+pub fn hash_token(token: &str) -> String {
+    let digest = Sha256::digest(token.as_bytes());
+    digest.iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+fn generate_token() -> String {
+    format!(
+        "{}{}{}",
+        TOKEN_PREFIX,
+        Uuid::new_v4().simple(),
+        Uuid::new_v4().simple()
+    )
+}
+
+#[tracing::instrument(skip_all, fields(otel.kind = "client", db.system = pool.system()))]
+pub async fn create_api_token(
+    pool: &DbPool,
+    name: String,
+    scopes: &[ApiScope],
+) -> Result<CreatedApiToken, sqlx::Error> {
+    let token = generate_token();
+    let token_prefix: String = token.chars().take(TOKEN_PREFIX.len() + 8).collect();
+    let scopes = ScopeList(scopes.iter().map(|s| s.to_string()).collect());
+    let sql = "INSERT INTO api_token (name, token_prefix, token_hash, scopes) VALUES ($1, $2, $3, $4) RETURNING *";
+    let api_token = with_pool!(pool, db => {
+        query_as(sql)
+            .bind(name)
+            .bind(token_prefix)
+            .bind(hash_token(&token))
+            .bind(scopes)
+            .fetch_one(db)
+            .await
+    })?;
+    Ok(CreatedApiToken { token, api_token })
+}
+
+#[tracing::instrument(skip_all, fields(otel.kind = "client", db.system = pool.system()))]
+pub async fn get_api_tokens(pool: &DbPool) -> Result<Vec<ApiToken>, sqlx::Error> {
+    let sql = "SELECT * FROM api_token ORDER BY id";
+    with_pool!(pool, db => query_as(sql).fetch_all(db).await)
+}
+
+/// find a non-revoked token by its plain value, touching its last_used_at at most
+/// once per LAST_USED_THROTTLE so reads stay reads
+#[tracing::instrument(skip_all, fields(otel.kind = "client", db.system = pool.system()))]
+pub async fn authenticate(pool: &DbPool, token: &str) -> Result<Option<ApiToken>, sqlx::Error> {
+    let sql = "SELECT * FROM api_token WHERE token_hash = $1 AND revoked_at IS NULL";
+    let api_token: Option<ApiToken> = with_pool!(pool, db => {
+        query_as(sql)
+            .bind(hash_token(token))
+            .fetch_optional(db)
+            .await
+    })?;
+    let Some(mut api_token) = api_token else {
+        return Ok(None);
+    };
+    let now = OffsetDateTime::now_utc();
+    let stale = api_token
+        .last_used_at
+        .is_none_or(|t| now - t >= LAST_USED_THROTTLE);
+    if stale {
+        let sql = format!("UPDATE api_token SET last_used_at = {now} WHERE id = $1", now = pool.now());
+        with_pool!(pool, db => {
+            query(&sql)
+                .bind(api_token.id)
+                .execute(db)
+                .await
+                .map(|_| ())
+        })?;
+        api_token.last_used_at = Some(now);
+    }
+    Ok(Some(api_token))
+}
+
+#[tracing::instrument(skip_all, fields(otel.kind = "client", db.system = pool.system()))]
+pub async fn revoke_api_token(pool: &DbPool, id: i32) -> Result<u64, sqlx::Error> {
+    let sql = format!("UPDATE api_token SET revoked_at = {now}, updated_at = {now} WHERE id = $1 AND revoked_at IS NULL", now = pool.now());
+    with_pool!(pool, db => {
+        query(&sql)
+            .bind(id)
+            .execute(db)
+            .await
+            .map(|r| r.rows_affected())
+    })
+}
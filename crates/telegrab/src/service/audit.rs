@@ -0,0 +1,174 @@
+use crate::db::{with_pool, DbPool};
+use crate::model::dto::audit::{AuditFilter, NewAuditEvent};
+use crate::model::dto::pagination::{CursorBasedPaginationResponse, PaginationQuery, PaginationResponse};
+use crate::model::entity::audit::AuditEvent;
+use crate::model::{Direction, PaginationArgs};
+use crate::service::helper::build_cursor_pagination;
+use serde::Serialize;
+use serde_json::{json, Map, Value};
+use sqlx::types::Json;
+use sqlx::{query, query_as, query_scalar};
+use time::format_description::well_known::Rfc3339;
+
+#[tracing::instrument(skip_all, fields(otel.kind = "client", db.system = pool.system()))]
+pub async fn record(pool: &DbPool, event: NewAuditEvent) -> Result<(), sqlx::Error> {
+    let sql = r#"INSERT INTO audit_event
+    (source, actor, token_id, request_id, task_id, action, entity, entity_id, changes, outcome, detail)
+    VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11)"#;
+    with_pool!(pool, db => {
+        query(sql)
+            .bind(event.source.as_str())
+            .bind(&event.actor)
+            .bind(event.token_id)
+            .bind(&event.request_id)
+            .bind(&event.task_id)
+            .bind(&event.action)
+            .bind(&event.entity)
+            .bind(&event.entity_id)
+            .bind(event.changes.as_ref().map(Json))
+            .bind(event.outcome.as_str())
+            .bind(&event.detail)
+            .execute(db)
+            .await
+            .map(|_| ())
+    })
+}
+
+/// Fields whose json differs, as `{"field": {"before": .., "after": ..}}`.
+/// `None` when nothing but `updatedAt` changed.
+pub fn diff<T: Serialize>(before: &T, after: &T) -> Option<Value> {
+    let (Ok(Value::Object(before)), Ok(Value::Object(after))) =
+        (serde_json::to_value(before), serde_json::to_value(after))
+    else {
+        return None;
+    };
+    let changes: Map<String, Value> = after
+        .into_iter()
+        .filter(|(key, _)| key != "updatedAt")
+        .filter_map(|(key, new)| {
+            let old = before.get(&key).cloned().unwrap_or(Value::Null);
+            (old != new).then(|| (key, json!({ "before": old, "after": new })))
+        })
+        .collect();
+    (!changes.is_empty()).then_some(Value::Object(changes))
+}
+
+/// `$1`..`$11` of [`filter_binds`], an unset filter matches every row
+fn filter_clause(pool: &DbPool) -> String {
+    format!(
+        r#"($1 IS NULL OR source = $1)
+    AND ($2 IS NULL OR actor = $2)
+    AND ($3 IS NULL OR token_id = CAST($3 AS integer))
+    AND ($4 IS NULL OR request_id = $4)
+    AND ($5 IS NULL OR task_id = $5)
+    AND ($6 IS NULL OR action = $6)
+    AND ($7 IS NULL OR entity = $7)
+    AND ($8 IS NULL OR entity_id = $8)
+    AND ($9 IS NULL OR outcome = $9)
+    AND ($10 IS NULL OR created_at >= {since})
+    AND ($11 IS NULL OR created_at < {until})"#,
+        since = pool.dialect("CAST($10 AS timestamptz)", "strftime('%Y-%m-%dT%H:%M:%fZ', $10)"),
+        until = pool.dialect("CAST($11 AS timestamptz)", "strftime('%Y-%m-%dT%H:%M:%fZ', $11)"),
+    )
+}
+
+/// bound as text on both backends, the clause casts what is not
+fn filter_binds(filter: &AuditFilter) -> Vec<Option<String>> {
+    let time = |t: Option<time::OffsetDateTime>| t.and_then(|t| t.format(&Rfc3339).ok());
+    vec![
+        filter.source.map(|s| s.as_str().to_string()),
+        filter.actor.clone(),
+        filter.token_id.map(|id| id.to_string()),
+        filter.request_id.clone(),
+        filter.task_id.clone(),
+        filter.action.clone(),
+        filter.entity.clone(),
+        filter.entity_id.clone(),
+        filter.outcome.map(|o| o.as_str().to_string()),
+        time(filter.since),
+        time(filter.until),
+    ]
+}
+
+#[tracing::instrument(skip_all, fields(otel.kind = "client", db.system = pool.system()))]
+async fn count_events(pool: &DbPool, where_clause: &str, binds: &[Option<String>]) -> Result<i64, sqlx::Error> {
+    let sql = format!("SELECT COUNT(*) FROM audit_event WHERE {}", where_clause);
+    with_pool!(pool, db => {
+        let mut q = query_scalar(&sql);
+        for value in binds {
+            q = q.bind(value.clone());
+        }
+        q.fetch_one(db).await
+    })
+}
+
+/// newest first
+#[tracing::instrument(skip_all, fields(otel.kind = "client", db.system = pool.system()))]
+pub async fn get_events(
+    pool: &DbPool,
+    filter: &AuditFilter,
+    pagination: &PaginationQuery,
+) -> Result<PaginationResponse<AuditEvent>, sqlx::Error> {
+    let where_clause = filter_clause(pool);
+    let binds = filter_binds(filter);
+    let total = count_events(pool, &where_clause, &binds).await?;
+    let sql = format!(
+        "SELECT * FROM audit_event WHERE {} ORDER BY id DESC LIMIT {} OFFSET {}",
+        where_clause,
+        pagination.limit(),
+        pagination.offset()
+    );
+    let events = with_pool!(pool, db => {
+        let mut q = query_as(&sql);
+        for value in &binds {
+            q = q.bind(value.clone());
+        }
+        q.fetch_all(db).await
+    })?;
+    Ok(PaginationResponse {
+        data: events,
+        total: total as u64,
+    })
+}
+
+/// newest first, `Forward` pages go back in time
+#[tracing::instrument(skip_all, fields(otel.kind = "client", db.system = pool.system()))]
+pub async fn get_cursor_based_pagination_events(
+    pool: &DbPool,
+    pagination_args: PaginationArgs,
+    filter: &AuditFilter,
+) -> Result<CursorBasedPaginationResponse<AuditEvent>, sqlx::Error> {
+    let where_clause = filter_clause(pool);
+    let binds = filter_binds(filter);
+    let total = count_events(pool, &where_clause, &binds).await?;
+    let PaginationArgs {
+        limit,
+        cursor,
+        direction,
+    } = pagination_args;
+    let (cursor_clause, order_by_clause) = match direction {
+        Direction::Forward => ("($12 IS NULL OR id < $12)", "ORDER BY id DESC"),
+        Direction::Backward => ("($12 IS NULL OR id > $12)", "ORDER BY id"),
+    };
+    let sql = format!(
+        "SELECT * FROM audit_event WHERE {} AND {} {} LIMIT $13",
+        where_clause, cursor_clause, order_by_clause
+    );
+    let events = with_pool!(pool, db => {
+        let mut q = query_as(&sql);
+        for value in &binds {
+            q = q.bind(value.clone());
+        }
+        q.bind(cursor)
+            .bind(limit as i64 + 1) // 多查一条用来判断是否有下一页
+            .fetch_all(db)
+            .await
+    })?;
+    Ok(build_cursor_pagination(
+        events,
+        total as u64,
+        limit,
+        direction,
+        cursor.is_some(),
+    ))
+}
@@ -1,44 +1,118 @@
-use crate::model::dto::pagination::{PaginationQuery, PaginationResponse, RefineSortOrder};
+use crate::model::dto::pagination::{
+    CursorBasedPaginationResponse, PaginationQuery, PaginationResponse, RefineSortOrder,
+};
 use crate::model::entity::cbz::Cbz;
+use crate::model::entity::event::DomainEvent;
+use crate::model::{Direction, PaginationArgs};
+use crate::service::helper::build_cursor_pagination;
+use crate::state::QueueState;
 use convert_case::{Case, Casing};
-use sqlx::{query, query_as};
-use sqlx_postgres::PgPool;
+use sqlx::{query, query_as, query_scalar};
+use crate::db::{with_pool, DbPool};
 
-pub async fn create_cbz(db_pool: &PgPool, path: String) -> Result<Cbz, sqlx::Error> {
+#[tracing::instrument(skip_all, fields(otel.kind = "client", db.system = db_pool.system()))]
+pub async fn create_cbz(
+    db_pool: &DbPool,
+    queue_state: &QueueState,
+    path: String,
+) -> Result<Cbz, sqlx::Error> {
     let sql = "INSERT INTO cbz (path) VALUES ($1) RETURNING *";
-    query_as(sql).bind(path).fetch_one(db_pool).await
+    let cbz = with_pool!(db_pool, db => query_as(sql).bind(path).fetch_one(db).await)?;
+    publish_cbz_created(queue_state, &cbz);
+    Ok(cbz)
 }
 
+#[tracing::instrument(skip_all, fields(otel.kind = "client", db.system = db_pool.system()))]
 pub async fn create_cbz_with_doc_id(
-    db_pool: &PgPool,
+    db_pool: &DbPool,
+    queue_state: &QueueState,
     doc_id: i32,
     path: String,
 ) -> Result<Cbz, sqlx::Error> {
     let sql = "INSERT INTO cbz (doc_id, path) VALUES ($1, $2) RETURNING *";
-    query_as(sql)
-        .bind(doc_id)
-        .bind(path)
-        .fetch_one(db_pool)
-        .await
+    let cbz = with_pool!(db_pool, db => {
+        query_as(sql)
+            .bind(doc_id)
+            .bind(path)
+            .fetch_one(db)
+            .await
+    })?;
+    publish_cbz_created(queue_state, &cbz);
+    Ok(cbz)
+}
+
+/// also for a cbz whose file was rebuilt, it is new to whoever reads it
+pub fn publish_cbz_created(queue_state: &QueueState, cbz: &Cbz) {
+    queue_state.publish(DomainEvent::CbzCreated {
+        id: cbz.id,
+        doc_id: cbz.doc_id,
+        path: cbz.path.clone(),
+    });
 }
 
-pub async fn get_cbz_by_id(db_pool: &PgPool, id: i32) -> Result<Cbz, sqlx::Error> {
+#[tracing::instrument(skip_all, fields(otel.kind = "client", db.system = db_pool.system()))]
+pub async fn get_cbzs(db_pool: &DbPool) -> Result<Vec<Cbz>, sqlx::Error> {
+    let sql = "SELECT * FROM cbz ORDER BY id";
+    with_pool!(db_pool, db => query_as(sql).fetch_all(db).await)
+}
+
+#[tracing::instrument(skip_all, fields(otel.kind = "client", db.system = db_pool.system()))]
+pub async fn get_cursor_based_pagination_cbzs(
+    db_pool: &DbPool,
+    pagination_args: PaginationArgs,
+) -> Result<CursorBasedPaginationResponse<Cbz>, sqlx::Error> {
+    let total: i64 =
+        with_pool!(db_pool, db => query_scalar("SELECT COUNT(*) FROM cbz").fetch_one(db).await)?;
+    let PaginationArgs {
+        limit,
+        cursor,
+        direction,
+    } = pagination_args;
+    let order_by_clause = match direction {
+        Direction::Forward => "ORDER BY id",
+        Direction::Backward => "ORDER BY id DESC",
+    };
+    let cbzs = if let Some(cursor) = cursor {
+        let sql = format!(
+            "SELECT * FROM cbz WHERE id {} $1 {} LIMIT $2",
+            if direction == Direction::Forward { ">" } else { "<" },
+            order_by_clause
+        );
+        with_pool!(db_pool, db => {
+            query_as(&sql)
+                .bind(cursor)
+                .bind(limit as i64 + 1)
+                .fetch_all(db)
+                .await
+        })?
+    } else {
+        let sql = format!("SELECT * FROM cbz {} LIMIT $1", order_by_clause);
+        with_pool!(db_pool, db => query_as(&sql).bind(limit as i64 + 1).fetch_all(db).await)?
+    };
+    Ok(build_cursor_pagination(cbzs, total as u64, limit, direction, cursor.is_some()))
+}
+
+#[tracing::instrument(skip_all, fields(otel.kind = "client", db.system = db_pool.system()))]
+pub async fn get_cbz_by_id(db_pool: &DbPool, id: i32) -> Result<Cbz, sqlx::Error> {
     let sql = "SELECT * FROM cbz WHERE id = $1";
-    query_as(sql).bind(id).fetch_one(db_pool).await
+    with_pool!(db_pool, db => query_as(sql).bind(id).fetch_one(db).await)
 }
 
-pub async fn get_cbz_by_doc_id(db_pool: &PgPool, doc_id: i32) -> Result<Option<Cbz>, sqlx::Error> {
+#[tracing::instrument(skip_all, fields(otel.kind = "client", db.system = db_pool.system()))]
+pub async fn get_cbz_by_doc_id(db_pool: &DbPool, doc_id: i32) -> Result<Option<Cbz>, sqlx::Error> {
     let sql = "SELECT * FROM cbz WHERE doc_id = $1";
-    query_as(sql).bind(doc_id).fetch_optional(db_pool).await
+    with_pool!(db_pool, db => query_as(sql).bind(doc_id).fetch_optional(db).await)
 }
 
-pub async fn get_cbz_by_path(db_pool: &PgPool, path: String) -> Result<Option<Cbz>, sqlx::Error> {
+#[tracing::instrument(skip_all, fields(otel.kind = "client", db.system = db_pool.system()))]
+pub async fn get_cbz_by_path(db_pool: &DbPool, path: String) -> Result<Option<Cbz>, sqlx::Error> {
     let sql = "SELECT * FROM cbz WHERE path = $1";
-    query_as(sql).bind(path).fetch_optional(db_pool).await
+    with_pool!(db_pool, db => query_as(sql).bind(path).fetch_optional(db).await)
 }
 
+#[tracing::instrument(skip_all, fields(otel.kind = "client", db.system = pool.system()))]
 pub async fn get_cbz_page(
-    pool: &PgPool,
+    pool: &DbPool,
     query: &PaginationQuery,
 ) -> Result<PaginationResponse<Cbz>, sqlx::Error> {
     let sort_clause = if let Some(sort) = &query.sort {
@@ -66,15 +140,17 @@ pub async fn get_cbz_page(
     let pagination_clause = format!(" LIMIT {} OFFSET {}", query.limit(), query.offset());
 
     // 执行查询获取总数
-    let (total,): (i64,) = query_as("SELECT COUNT(*) FROM cbz").fetch_one(pool).await?;
+    let (total,): (i64,) = with_pool!(pool, db => query_as("SELECT COUNT(*) FROM cbz").fetch_one(db).await)?;
 
     // 执行查询获取数据
-    let cbz_v = query_as(&format!(
-        "SELECT * FROM cbz{}{}",
-        sort_clause, pagination_clause
-    ))
-    .fetch_all(pool)
-    .await?;
+    let cbz_v = with_pool!(pool, db => {
+        query_as(&format!(
+            "SELECT * FROM cbz{}{}",
+            sort_clause, pagination_clause
+        ))
+        .fetch_all(db)
+        .await
+    })?;
 
     // 构建并返回分页响应
     Ok(PaginationResponse {
@@ -83,34 +159,61 @@ pub async fn get_cbz_page(
     })
 }
 
+#[tracing::instrument(skip_all, fields(otel.kind = "client", db.system = db_pool.system()))]
 pub async fn update_cbz(
-    db_pool: &PgPool,
+    db_pool: &DbPool,
+    queue_state: &QueueState,
     id: i32,
     doc_id: Option<i32>,
 ) -> Result<Cbz, sqlx::Error> {
+    let previous = get_cbz_by_id(db_pool, id).await?;
     let sql = "UPDATE cbz SET doc_id = $1 WHERE id = $2 RETURNING *";
-    query_as(sql).bind(doc_id).bind(id).fetch_one(db_pool).await
+    let cbz: Cbz =
+        with_pool!(db_pool, db => query_as(sql).bind(doc_id).bind(id).fetch_one(db).await)?;
+    if cbz.doc_id != previous.doc_id {
+        queue_state.publish(DomainEvent::CbzLinked {
+            id,
+            doc_id: cbz.doc_id,
+            previous_doc_id: previous.doc_id,
+            path: cbz.path.clone(),
+        });
+    }
+    Ok(cbz)
 }
 
+#[tracing::instrument(skip_all, fields(otel.kind = "client", db.system = db_pool.system()))]
 pub async fn update_cbz_doc_id_with_path(
-    db_pool: &PgPool,
+    db_pool: &DbPool,
     doc_id: i32,
     path: String,
 ) -> Result<u64, sqlx::Error> {
     let sql = "UPDATE cbz SET doc_id = $1 WHERE path = $2";
-    query(sql)
-        .bind(doc_id)
-        .bind(path)
-        .execute(db_pool)
-        .await
-        .map(|r| r.rows_affected())
+    with_pool!(db_pool, db => {
+        query(sql)
+            .bind(doc_id)
+            .bind(path)
+            .execute(db)
+            .await
+            .map(|r| r.rows_affected())
+    })
 }
 
-pub async fn remove_cbz_by_id(db_pool: &PgPool, id: i32) -> Result<u64, sqlx::Error> {
-    let sql = "DELETE FROM cbz WHERE id = $1";
-    query(sql)
-        .bind(id)
-        .execute(db_pool)
-        .await
-        .map(|r| r.rows_affected())
+#[tracing::instrument(skip_all, fields(otel.kind = "client", db.system = db_pool.system()))]
+pub async fn remove_cbz_by_id(
+    db_pool: &DbPool,
+    queue_state: &QueueState,
+    id: i32,
+) -> Result<u64, sqlx::Error> {
+    let sql = "DELETE FROM cbz WHERE id = $1 RETURNING *";
+    let removed: Option<Cbz> =
+        with_pool!(db_pool, db => query_as(sql).bind(id).fetch_optional(db).await)?;
+    let Some(cbz) = removed else {
+        return Ok(0);
+    };
+    queue_state.publish(DomainEvent::CbzRemoved {
+        id: cbz.id,
+        doc_id: cbz.doc_id,
+        path: cbz.path,
+    });
+    Ok(1)
 }
@@ -1,33 +1,96 @@
 use crate::model::dto::doc::{CreateDocReq, UpdateDocReq};
 use crate::model::dto::pagination::{CursorBasedPaginationResponse, PaginationResponse};
 use crate::model::dto::pagination::{PaginationQuery, RefineSortOrder};
-use crate::model::entity::doc::{Doc, ShimDoc, TelegraphPost};
+use crate::link;
+use crate::model::entity::cbz::Cbz;
+use crate::model::entity::doc::{Doc, DocStatus, DocStatusChange, MergedDoc, ShimDoc, TelegraphPost};
+use crate::model::entity::event::DomainEvent;
 use crate::model::{Direction, PaginationArgs};
 use crate::service::helper::build_cursor_pagination;
 use convert_case::{Case, Casing};
-use sqlx::{query, query_as, query_scalar};
-use sqlx_postgres::PgPool;
+use sqlx::{query, query_as, query_scalar, FromRow};
+use std::cmp::Reverse;
+use std::collections::BTreeMap;
+use crate::db::{with_pool, DbPool};
+use crate::state::QueueState;
 use time::OffsetDateTime;
 
-pub async fn create_doc(pool: &PgPool, req: CreateDocReq) -> Result<Doc, sqlx::Error> {
+#[tracing::instrument(skip_all, fields(otel.kind = "client", db.system = pool.system()))]
+pub async fn create_doc(
+    pool: &DbPool,
+    queue_state: &QueueState,
+    req: CreateDocReq,
+) -> Result<Doc, sqlx::Error> {
     let sql = "INSERT INTO doc (url) VALUES ($1) RETURNING *, (SELECT id FROM cbz WHERE doc_id = doc.id) AS cbz_id";
-    query_as(sql).bind(req.url).fetch_one(pool).await
+    let doc: Doc = with_pool!(pool, db => query_as(sql).bind(req.url).fetch_one(db).await)?;
+    publish_doc_added(queue_state, &doc);
+    Ok(doc)
+}
+/// `None` when the url is already stored
+#[tracing::instrument(skip_all, fields(otel.kind = "client", db.system = pool.system()))]
+pub async fn create_doc_if_absent(
+    pool: &DbPool,
+    queue_state: &QueueState,
+    url: &str,
+) -> Result<Option<Doc>, sqlx::Error> {
+    let sql = "INSERT INTO doc (url) VALUES ($1) ON CONFLICT (url) DO NOTHING RETURNING *, (SELECT id FROM cbz WHERE doc_id = doc.id) AS cbz_id";
+    let doc: Option<Doc> =
+        with_pool!(pool, db => query_as(sql).bind(url).fetch_optional(db).await)?;
+    if let Some(doc) = &doc {
+        publish_doc_added(queue_state, doc);
+    }
+    Ok(doc)
 }
-pub async fn get_doc_by_id(pool: &PgPool, id: i32) -> Result<Doc, sqlx::Error> {
+fn publish_doc_added(queue_state: &QueueState, doc: &Doc) {
+    queue_state.publish(DomainEvent::DocAdded {
+        id: doc.id,
+        url: doc.url.clone(),
+    });
+}
+#[tracing::instrument(skip_all, fields(otel.kind = "client", db.system = pool.system()))]
+pub async fn get_doc_by_url(pool: &DbPool, url: &str) -> Result<Option<Doc>, sqlx::Error> {
+    let sql = "SELECT doc.*, cbz.id as cbz_id FROM doc left join cbz on doc.id = cbz.doc_id WHERE doc.url = $1";
+    with_pool!(pool, db => query_as(sql).bind(url).fetch_optional(db).await)
+}
+#[tracing::instrument(skip_all, fields(otel.kind = "client", db.system = pool.system()))]
+pub async fn get_doc_by_id(pool: &DbPool, id: i32) -> Result<Doc, sqlx::Error> {
     let sql = "SELECT doc.*, cbz.id as cbz_id FROM doc left join cbz on doc.id = cbz.doc_id WHERE doc.id = $1";
-    query_as(sql).bind(id).fetch_one(pool).await
+    with_pool!(pool, db => query_as(sql).bind(id).fetch_one(db).await)
 }
 
+#[tracing::instrument(skip_all, fields(otel.kind = "client", db.system = pool.system()))]
 pub async fn get_docs_by_ids(
-    pool: &PgPool,
+    pool: &DbPool,
     ids: &[i32],
 ) -> Result<Vec<Doc>, sqlx::Error> {
-    let sql = "SELECT doc.*, cbz.id as cbz_id FROM doc left join cbz on doc.id = cbz.doc_id WHERE doc.id = ANY($1)";
-    query_as(sql).bind(ids).fetch_all(pool).await
+    let sql = "SELECT doc.*, cbz.id as cbz_id FROM doc left join cbz on doc.id = cbz.doc_id WHERE doc.id";
+    match pool {
+        DbPool::Postgres(db) => {
+            query_as(&format!("{} = ANY($1)", sql))
+                .bind(ids)
+                .fetch_all(db)
+                .await
+        }
+        // sqlite has no arrays, the ids go in as a json list
+        #[cfg(feature = "sqlite")]
+        DbPool::Sqlite(db) => {
+            query_as(&format!("{} IN (SELECT value FROM json_each($1))", sql))
+                .bind(sqlx::types::Json(ids))
+                .fetch_all(db)
+                .await
+        }
+    }
 }
 
+#[tracing::instrument(skip_all, fields(otel.kind = "client", db.system = pool.system()))]
+pub async fn get_all_docs(pool: &DbPool) -> Result<Vec<Doc>, sqlx::Error> {
+    let sql = "SELECT doc.*, cbz.id as cbz_id FROM doc left join cbz on doc.id = cbz.doc_id ORDER BY doc.id";
+    with_pool!(pool, db => query_as(sql).fetch_all(db).await)
+}
+
+#[tracing::instrument(skip_all, fields(otel.kind = "client", db.system = pool.system()))]
 pub async fn get_docs(
-    pool: &PgPool,
+    pool: &DbPool,
     query: &PaginationQuery,
 ) -> Result<PaginationResponse<Doc>, sqlx::Error> {
     // 构建排序子句
@@ -56,15 +119,17 @@ pub async fn get_docs(
     let pagination_clause = format!(" LIMIT {} OFFSET {}", query.limit(), query.offset());
 
     // 执行查询获取总数
-    let (total,): (i64,) = query_as("SELECT COUNT(*) FROM doc").fetch_one(pool).await?;
+    let (total,): (i64,) = with_pool!(pool, db => query_as("SELECT COUNT(*) FROM doc").fetch_one(db).await)?;
 
     // 执行查询获取数据
-    let docs = query_as(&format!(
-        "SELECT doc.*, cbz.id as cbz_id FROM doc left join cbz on doc.id = cbz.doc_id{}{}",
-        sort_clause, pagination_clause
-    ))
-    .fetch_all(pool)
-    .await?;
+    let docs = with_pool!(pool, db => {
+        query_as(&format!(
+            "SELECT doc.*, cbz.id as cbz_id FROM doc left join cbz on doc.id = cbz.doc_id{}{}",
+            sort_clause, pagination_clause
+        ))
+        .fetch_all(db)
+        .await
+    })?;
 
     // 构建并返回分页响应
     Ok(PaginationResponse {
@@ -73,26 +138,46 @@ pub async fn get_docs(
     })
 }
 
-pub async fn get_parsed_docs(pool: &PgPool) -> Result<Vec<ShimDoc>, sqlx::Error> {
-    let sql = "SELECT doc.id, cbz.id as cbz_id, url, page_title, title FROM doc left join cbz on doc.id = cbz.doc_id WHERE status > 0 ORDER BY doc.id";
-    query_as::<_, ShimDoc>(sql).fetch_all(pool).await
+#[tracing::instrument(skip_all, fields(otel.kind = "client", db.system = pool.system()))]
+pub async fn get_parsed_docs(pool: &DbPool) -> Result<Vec<ShimDoc>, sqlx::Error> {
+    let sql = "SELECT doc.id, cbz.id as cbz_id, url, page_title, title FROM doc left join cbz on doc.id = cbz.doc_id WHERE status IN (1, 2, 3, 5) ORDER BY doc.id";
+    with_pool!(pool, db => query_as::<_, ShimDoc>(sql).fetch_all(db).await)
 }
-pub async fn get_unparsed_docs(pool: &PgPool) -> Result<Vec<Doc>, sqlx::Error> {
-    let sql = "SELECT doc.*, cbz.id as cbz_id FROM doc left join cbz on doc.id = cbz.doc_id WHERE status = 0";
-    query_as(sql).fetch_all(pool).await
+#[tracing::instrument(skip_all, fields(otel.kind = "client", db.system = pool.system()))]
+pub async fn get_unparsed_docs(pool: &DbPool) -> Result<Vec<Doc>, sqlx::Error> {
+    let sql = "SELECT doc.*, cbz.id as cbz_id FROM doc left join cbz on doc.id = cbz.doc_id WHERE status = 0 OR (status = 4 AND page_title IS NULL)";
+    with_pool!(pool, db => query_as(sql).fetch_all(db).await)
 }
 
-pub async fn delete_doc_by_id(pool: &PgPool, id: i32) -> Result<u64, sqlx::Error> {
+#[tracing::instrument(skip_all, fields(otel.kind = "client", db.system = pool.system()))]
+pub async fn delete_doc_by_id(
+    pool: &DbPool,
+    queue_state: &QueueState,
+    id: i32,
+) -> Result<u64, sqlx::Error> {
     let sql = "DELETE FROM doc WHERE id = $1";
-    query(sql)
-        .bind(id)
-        .execute(pool)
-        .await
-        .map(|r| r.rows_affected())
+    let count = with_pool!(pool, db => {
+        query(sql)
+            .bind(id)
+            .execute(db)
+            .await
+            .map(|r| r.rows_affected())
+    })?;
+    if count > 0 {
+        queue_state.publish(DomainEvent::DocRemoved { id });
+    }
+    Ok(count)
 }
 
-pub async fn update_doc(pool: &PgPool, id: i32, req: UpdateDocReq) -> Result<Doc, sqlx::Error> {
-    let sql = r#"UPDATE doc
+#[tracing::instrument(skip_all, fields(otel.kind = "client", db.system = pool.system()))]
+pub async fn update_doc(
+    pool: &DbPool,
+    queue_state: &QueueState,
+    id: i32,
+    req: UpdateDocReq,
+) -> Result<Doc, sqlx::Error> {
+    let sql = format!(
+        r#"UPDATE doc
     SET page_title = $1,
         page_date = $2,
         title = $3,
@@ -130,60 +215,67 @@ pub async fn update_doc(pool: &PgPool, id: i32, req: UpdateDocReq) -> Result<Doc
         age_rating = $35,
         community_rating = $36,
         critical_rating = $37,
-        updated_at = now()
+        updated_at = {now}
     WHERE id = $38
     RETURNING *, (SELECT id FROM cbz WHERE doc_id = doc.id) AS cbz_id
-    "#;
+    "#,
+        now = pool.now()
+    );
 
-    query_as(sql)
-        .bind(req.page_title)
-        .bind(req.page_date)
-        .bind(req.title)
-        .bind(req.series)
-        .bind(req.number)
-        .bind(req.count)
-        .bind(req.volume)
-        .bind(req.summary)
-        .bind(req.notes)
-        .bind(req.year)
-        .bind(req.month)
-        .bind(req.day)
-        .bind(req.writer)
-        .bind(req.penciller)
-        .bind(req.inker)
-        .bind(req.colorist)
-        .bind(req.letterer)
-        .bind(req.cover_artist)
-        .bind(req.editor)
-        .bind(req.publisher)
-        .bind(req.imprint)
-        .bind(req.genre)
-        .bind(req.tags)
-        .bind(req.web)
-        .bind(req.page_count)
-        .bind(req.language)
-        .bind(req.format)
-        .bind(req.black_and_white)
-        .bind(req.characters)
-        .bind(req.teams)
-        .bind(req.locations)
-        .bind(req.scan_information)
-        .bind(req.story_arc)
-        .bind(req.series_group)
-        .bind(req.age_rating)
-        .bind(req.community_rating)
-        .bind(req.critical_rating)
-        .bind(id)
-        .fetch_one(pool)
-        .await
+    let doc = with_pool!(pool, db => {
+        query_as(&sql)
+            .bind(req.page_title)
+            .bind(req.page_date)
+            .bind(req.title)
+            .bind(req.series)
+            .bind(req.number)
+            .bind(req.count)
+            .bind(req.volume)
+            .bind(req.summary)
+            .bind(req.notes)
+            .bind(req.year)
+            .bind(req.month)
+            .bind(req.day)
+            .bind(req.writer)
+            .bind(req.penciller)
+            .bind(req.inker)
+            .bind(req.colorist)
+            .bind(req.letterer)
+            .bind(req.cover_artist)
+            .bind(req.editor)
+            .bind(req.publisher)
+            .bind(req.imprint)
+            .bind(req.genre)
+            .bind(req.tags)
+            .bind(req.web)
+            .bind(req.page_count)
+            .bind(req.language)
+            .bind(req.format)
+            .bind(req.black_and_white)
+            .bind(req.characters)
+            .bind(req.teams)
+            .bind(req.locations)
+            .bind(req.scan_information)
+            .bind(req.story_arc)
+            .bind(req.series_group)
+            .bind(req.age_rating)
+            .bind(req.community_rating)
+            .bind(req.critical_rating)
+            .bind(id)
+            .fetch_one(db)
+            .await
+    })?;
+    queue_state.publish(DomainEvent::DocUpdated { id });
+    Ok(doc)
 }
 
+#[tracing::instrument(skip_all, fields(otel.kind = "client", db.system = pool.system()))]
 pub async fn update_parsed_doc(
-    pool: &PgPool,
+    pool: &DbPool,
+    queue_state: &QueueState,
     id: i32,
     p: TelegraphPost,
 ) -> Result<Doc, sqlx::Error> {
-    let mut tx = pool.begin().await?;
     let parsed_date = p.date.as_deref().and_then(|date_str| {
         OffsetDateTime::parse(
             date_str,
@@ -191,55 +283,198 @@ pub async fn update_parsed_doc(
         )
         .ok()
     });
-    let doc_sql = r#"UPDATE doc SET page_title = $1, page_date = $2, page_count = $3, web = $4, status = 1 WHERE id = $5 RETURNING *, (SELECT id FROM cbz WHERE doc_id = $5) AS cbz_id"#;
-    let doc = query_as(doc_sql)
-        .bind(p.title)
-        .bind(parsed_date)
-        .bind(p.image_urls.len() as i16)
-        .bind(p.url.clone())
-        .bind(id)
-        .fetch_one(&mut *tx)
-        .await?;
-
+    let doc_sql = r#"UPDATE doc SET page_title = $1, page_date = $2, page_count = $3, web = $4 WHERE id = $5 RETURNING *, (SELECT id FROM cbz WHERE doc_id = $5) AS cbz_id"#;
     let pic_sql = r#"INSERT INTO pic (doc_id, url, seq) VALUES ($1, $2, $3)"#;
     let check_sql = r#"SELECT COUNT(*) FROM pic WHERE doc_id = $1 and url = $2"#;
-    for (i, url) in p.image_urls.iter().enumerate() {
-        let (count,): (i64,) = query_as(check_sql)
+    let doc = with_pool!(pool, db => {
+        let mut tx = db.begin().await?;
+        let doc = query_as(doc_sql)
+            .bind(p.title)
+            .bind(parsed_date)
+            .bind(p.image_urls.len() as i16)
+            .bind(p.url.clone())
             .bind(id)
-            .bind(url)
-            .fetch_one(pool)
+            .fetch_one(&mut *tx)
             .await?;
-        if count == 0 {
-            query(pic_sql)
+
+        for (i, url) in p.image_urls.iter().enumerate() {
+            let (count,): (i64,) = query_as(check_sql)
                 .bind(id)
                 .bind(url)
-                .bind(i as i32)
-                .execute(&mut *tx)
+                .fetch_one(db)
                 .await?;
+            if count == 0 {
+                query(pic_sql)
+                    .bind(id)
+                    .bind(url)
+                    .bind(i as i32)
+                    .execute(&mut *tx)
+                    .await?;
+            }
         }
-    }
-    tx.commit().await?;
+        tx.commit().await?;
+        Ok::<Doc, sqlx::Error>(doc)
+    })?;
+    queue_state.publish(DomainEvent::DocUpdated { id });
     Ok(doc)
 }
 
-pub async fn update_doc_status(pool: &PgPool, id: i32, status: i32) -> Result<u64, sqlx::Error> {
-    let sql = "UPDATE doc SET status = $1 WHERE id = $2";
-    query(sql)
-        .bind(status)
-        .bind(id)
-        .execute(pool)
-        .await
-        .map(|r| r.rows_affected())
+/// Stores a refetched page. When pics changed its cbz, if any, is marked stale,
+/// the status is left to the caller.
+#[tracing::instrument(skip_all, fields(otel.kind = "client", db.system = pool.system()))]
+pub async fn update_refreshed_doc(
+    pool: &DbPool,
+    queue_state: &QueueState,
+    id: i32,
+    p: &TelegraphPost,
+    etag: Option<String>,
+    last_modified: Option<String>,
+    pics_changed: bool,
+) -> Result<Doc, sqlx::Error> {
+    let parsed_date = p.date.as_deref().and_then(|date_str| {
+        OffsetDateTime::parse(
+            date_str,
+            &time::format_description::well_known::Iso8601::DEFAULT,
+        )
+        .ok()
+    });
+    let sql = format!(
+        r#"UPDATE doc
+    SET page_title = $1,
+        page_date = COALESCE($2, page_date),
+        page_count = $3,
+        etag = $4,
+        last_modified = $5,
+        checked_at = {now},
+        unavailable = false,
+        cbz_stale = cbz_stale OR ($6 AND EXISTS (SELECT 1 FROM cbz WHERE doc_id = $7)),
+        updated_at = {now}
+    WHERE id = $7
+    RETURNING *, (SELECT id FROM cbz WHERE doc_id = $7) AS cbz_id"#,
+        now = pool.now()
+    );
+    let doc = with_pool!(pool, db => {
+        query_as(&sql)
+            .bind(&p.title)
+            .bind(parsed_date)
+            .bind(p.image_urls.len() as i16)
+            .bind(etag)
+            .bind(last_modified)
+            .bind(pics_changed)
+            .bind(id)
+            .fetch_one(db)
+            .await
+    })?;
+    queue_state.publish(DomainEvent::DocUpdated { id });
+    Ok(doc)
+}
+
+/// a refresh that got no new content, `unavailable` when the page is gone
+#[tracing::instrument(skip_all, fields(otel.kind = "client", db.system = pool.system()))]
+pub async fn update_doc_checked(
+    pool: &DbPool,
+    id: i32,
+    unavailable: bool,
+) -> Result<u64, sqlx::Error> {
+    let sql = format!("UPDATE doc SET checked_at = {now}, unavailable = $1 WHERE id = $2", now = pool.now());
+    with_pool!(pool, db => {
+        query(&sql)
+            .bind(unavailable)
+            .bind(id)
+            .execute(db)
+            .await
+            .map(|r| r.rows_affected())
+    })
+}
+
+#[tracing::instrument(skip_all, fields(otel.kind = "client", db.system = pool.system()))]
+pub async fn update_doc_cbz_stale(pool: &DbPool, id: i32, stale: bool) -> Result<u64, sqlx::Error> {
+    let sql = "UPDATE doc SET cbz_stale = $1 WHERE id = $2";
+    with_pool!(pool, db => {
+        query(sql)
+            .bind(stale)
+            .bind(id)
+            .execute(db)
+            .await
+            .map(|r| r.rows_affected())
+    })
+}
+
+/// Moves a doc to `to` and records who or what did it in `doc_status_history`,
+/// returns the status it was in. `None` when the current status does not allow it,
+/// staying in the same status is not recorded nor published.
+#[tracing::instrument(skip_all, fields(otel.kind = "client", db.system = pool.system()))]
+pub async fn set_doc_status(
+    pool: &DbPool,
+    queue_state: &QueueState,
+    id: i32,
+    to: DocStatus,
+    changed_by: &str,
+    reason: Option<&str>,
+) -> Result<Option<DocStatus>, sqlx::Error> {
+    let status_sql = "SELECT COALESCE(status, CAST(0 AS smallint)) FROM doc WHERE id = $1";
+    let from: DocStatus = with_pool!(pool, db => query_scalar(status_sql).bind(id).fetch_one(db).await)?;
+    if from == to {
+        return Ok(Some(from));
+    }
+    if !from.can_become(to) {
+        tracing::warn!("doc {} can not go from {:?} to {:?}", id, from, to);
+        return Ok(None);
+    }
+    // the status may have moved on since it was read
+    let update_sql = format!(
+        "UPDATE doc SET status = $1, updated_at = {now} WHERE id = $2 AND COALESCE(status, CAST(0 AS smallint)) = $3",
+        now = pool.now()
+    );
+    let history_sql = "INSERT INTO doc_status_history (doc_id, from_status, to_status, changed_by, reason) VALUES ($1, $2, $3, $4, $5)";
+    with_pool!(pool, db => {
+        let mut tx = db.begin().await?;
+        let updated = query(&update_sql)
+            .bind(to)
+            .bind(id)
+            .bind(from)
+            .execute(&mut *tx)
+            .await?
+            .rows_affected();
+        if updated == 0 {
+            return Ok(None);
+        }
+        query(history_sql)
+            .bind(id)
+            .bind(from)
+            .bind(to)
+            .bind(changed_by)
+            .bind(reason)
+            .execute(&mut *tx)
+            .await?;
+        tx.commit().await?;
+    });
+    queue_state.publish(DomainEvent::DocStatusChanged { id, from, to });
+    Ok(Some(from))
 }
 
+/// status changes of a doc, oldest first
+#[tracing::instrument(skip_all, fields(otel.kind = "client", db.system = pool.system()))]
+pub async fn get_doc_status_history(
+    pool: &DbPool,
+    doc_id: i32,
+) -> Result<Vec<DocStatusChange>, sqlx::Error> {
+    let sql = "SELECT * FROM doc_status_history WHERE doc_id = $1 ORDER BY id";
+    with_pool!(pool, db => query_as(sql).bind(doc_id).fetch_all(db).await)
+}
+
+#[tracing::instrument(skip_all, fields(otel.kind = "client", db.system = pool.system()))]
+#[allow(unused_variables)]
 pub async fn get_cursor_based_pagination_docs(
-    pool: &PgPool,
+    pool: &DbPool,
     pagination_args: PaginationArgs,
     title:Option<String>,
 ) -> Result<CursorBasedPaginationResponse<Doc>, sqlx::Error> {
-    let total: i64 = query_scalar("SELECT COUNT(*) FROM doc")
-        .fetch_one(pool)
-        .await?;
+    let total: i64 = with_pool!(pool, db => {
+        query_scalar("SELECT COUNT(*) FROM doc")
+            .fetch_one(db)
+            .await
+    })?;
     let PaginationArgs {
         limit,
         cursor,
@@ -263,19 +498,137 @@ pub async fn get_cursor_based_pagination_docs(
             }
         );
         let sql = format!("{} {} {} LIMIT $2", main_sql, where_clause, order_by_clause);
-        query_as(&sql)
-            .bind(cursor)
-            .bind(limit as i64 + 1) // 多查一条用来判断是否有下一页
-            .fetch_all(pool)
-            .await?
+        with_pool!(pool, db => {
+            query_as(&sql)
+                .bind(cursor)
+                .bind(limit as i64 + 1) // 多查一条用来判断是否有下一页
+                .fetch_all(db)
+                .await
+        })?
     } else {
         let sql = format!("{} {} LIMIT $1", main_sql, order_by_clause);
-        query_as(&sql)
-            .bind(limit as i64 + 1) // 多查一条用来判断是否有下一页
-            .fetch_all(pool)
-            .await?
+        with_pool!(pool, db => {
+            query_as(&sql)
+                .bind(limit as i64 + 1) // 多查一条用来判断是否有下一页
+                .fetch_all(db)
+                .await
+        })?
     };
 
     let paged = build_cursor_pagination(docs, total as u64, limit, direction, cursor.is_some());
     Ok(paged)
 }
+
+/// metadata a duplicate may fill in on the doc it is merged into
+const MERGED_COLUMNS: &[&str] = &[
+    "page_title", "page_date", "title", "series", "number", "count", "volume", "summary",
+    "notes", "year", "month", "day", "writer", "penciller", "inker", "colorist", "letterer",
+    "cover_artist", "editor", "publisher", "imprint", "genre", "tags", "web", "page_count",
+    "language", "format", "black_and_white", "characters", "teams", "locations",
+    "scan_information", "story_arc", "series_group", "age_rating", "community_rating",
+    "critical_rating",
+];
+
+#[derive(FromRow)]
+struct DocUrl {
+    id: i32,
+    url: String,
+    status: DocStatus,
+    has_cbz: bool,
+}
+
+/// Groups docs by canonical url and folds every group into its most advanced doc:
+/// furthest status, then the one linked to a cbz, then the oldest.
+/// Pics and the cbz move over unless the kept doc has its own, empty metadata is filled in.
+#[tracing::instrument(skip_all, fields(otel.kind = "client", db.system = pool.system()))]
+pub async fn merge_duplicate_docs(
+    pool: &DbPool,
+    queue_state: &QueueState,
+) -> Result<Vec<MergedDoc>, sqlx::Error> {
+    let sql = "SELECT id, url, COALESCE(status, CAST(0 AS smallint)) AS status, EXISTS (SELECT 1 FROM cbz WHERE cbz.doc_id = doc.id) AS has_cbz FROM doc ORDER BY id";
+    let docs: Vec<DocUrl> = with_pool!(pool, db => query_as(sql).fetch_all(db).await)?;
+    let mut groups: BTreeMap<String, Vec<DocUrl>> = BTreeMap::new();
+    for doc in docs {
+        match link::canonicalize(&doc.url) {
+            Ok(canonical) => groups.entry(canonical).or_default().push(doc),
+            Err(e) => tracing::warn!("doc {} keeps its url: {}", doc.id, e),
+        }
+    }
+    let assignments = MERGED_COLUMNS
+        .iter()
+        .map(|c| format!("{c} = COALESCE(doc.{c}, dup.{c})"))
+        .collect::<Vec<_>>()
+        .join(", ");
+    let metadata_sql = format!(
+        "UPDATE doc SET {} FROM doc dup WHERE doc.id = $1 AND dup.id = $2",
+        assignments
+    );
+    let url_sql = format!("UPDATE doc SET url = $1, updated_at = {} WHERE id = $2", pool.now());
+    let mut merged = vec![];
+    for (url, mut group) in groups {
+        if group.len() == 1 && group[0].url == url {
+            continue;
+        }
+        group.sort_by_key(|d| (Reverse(d.status.progress()), Reverse(d.has_cbz), d.id));
+        let keeper = &group[0];
+        let mut relinked = vec![];
+        with_pool!(pool, db => {
+            let mut tx = db.begin().await?;
+            for dup in &group[1..] {
+                query(&metadata_sql)
+                    .bind(keeper.id)
+                    .bind(dup.id)
+                    .execute(&mut *tx)
+                    .await?;
+                // pic files are named by seq, mixing two pic lists would give two pages the same name;
+                // dropped pics come back with a refresh of the kept doc
+                let keeper_pics: i64 = query_scalar("SELECT COUNT(*) FROM pic WHERE doc_id = $1")
+                    .bind(keeper.id)
+                    .fetch_one(&mut *tx)
+                    .await?;
+                if keeper_pics == 0 {
+                    query("UPDATE pic SET doc_id = $1 WHERE doc_id = $2")
+                        .bind(keeper.id)
+                        .bind(dup.id)
+                        .execute(&mut *tx)
+                        .await?;
+                }
+                let cbz: Option<Cbz> = query_as("UPDATE cbz SET doc_id = $1 WHERE doc_id = $2 AND NOT EXISTS (SELECT 1 FROM cbz WHERE doc_id = $1) RETURNING *")
+                    .bind(keeper.id)
+                    .bind(dup.id)
+                    .fetch_optional(&mut *tx)
+                    .await?;
+                relinked.extend(cbz.map(|cbz| (cbz, dup.id)));
+                query("DELETE FROM doc WHERE id = $1")
+                    .bind(dup.id)
+                    .execute(&mut *tx)
+                    .await?;
+            }
+            query(&url_sql)
+                .bind(&url)
+                .bind(keeper.id)
+                .execute(&mut *tx)
+                .await?;
+            tx.commit().await
+        })?;
+        for dup in &group[1..] {
+            queue_state.publish(DomainEvent::DocRemoved { id: dup.id });
+        }
+        for (cbz, previous_doc_id) in relinked {
+            queue_state.publish(DomainEvent::CbzLinked {
+                id: cbz.id,
+                doc_id: cbz.doc_id,
+                previous_doc_id: Some(previous_doc_id),
+                path: cbz.path,
+            });
+        }
+        queue_state.publish(DomainEvent::DocUpdated { id: keeper.id });
+        merged.push(MergedDoc {
+            id: keeper.id,
+            old_url: keeper.url.clone(),
+            merged_ids: group[1..].iter().map(|d| d.id).collect(),
+            url,
+        });
+    }
+    Ok(merged)
+}
@@ -0,0 +1,178 @@
+use crate::model::dto::feed::{CreateFeedReq, UpdateFeedReq};
+use crate::model::entity::feed::{Feed, FeedItem};
+use sqlx::{query, query_as};
+use crate::db::{with_pool, DbPool};
+
+#[tracing::instrument(skip_all, fields(otel.kind = "client", db.system = pool.system()))]
+pub async fn create_feed(pool: &DbPool, req: CreateFeedReq) -> Result<Feed, sqlx::Error> {
+    let sql = r#"INSERT INTO feed (url, title, enabled, auto_grab, include_pattern, exclude_pattern, poll_interval_secs)
+    VALUES ($1, $2, COALESCE($3, true), COALESCE($4, false), NULLIF($5, ''), NULLIF($6, ''), COALESCE($7, 3600))
+    RETURNING *"#;
+    with_pool!(pool, db => {
+        query_as(sql)
+            .bind(req.url)
+            .bind(req.title)
+            .bind(req.enabled)
+            .bind(req.auto_grab)
+            .bind(req.include_pattern)
+            .bind(req.exclude_pattern)
+            .bind(req.poll_interval_secs)
+            .fetch_one(db)
+            .await
+    })
+}
+
+#[tracing::instrument(skip_all, fields(otel.kind = "client", db.system = pool.system()))]
+pub async fn get_feeds(pool: &DbPool) -> Result<Vec<Feed>, sqlx::Error> {
+    let sql = "SELECT * FROM feed ORDER BY id";
+    with_pool!(pool, db => query_as(sql).fetch_all(db).await)
+}
+
+#[tracing::instrument(skip_all, fields(otel.kind = "client", db.system = pool.system()))]
+pub async fn get_feed_by_id(pool: &DbPool, id: i32) -> Result<Feed, sqlx::Error> {
+    let sql = "SELECT * FROM feed WHERE id = $1";
+    with_pool!(pool, db => query_as(sql).bind(id).fetch_one(db).await)
+}
+
+/// enabled feeds never polled or polled longer than their interval ago
+#[tracing::instrument(skip_all, fields(otel.kind = "client", db.system = pool.system()))]
+pub async fn get_due_feeds(pool: &DbPool) -> Result<Vec<Feed>, sqlx::Error> {
+    let sql = pool.dialect(
+        r#"SELECT * FROM feed
+    WHERE enabled
+      AND (last_polled_at IS NULL OR last_polled_at + poll_interval_secs * interval '1 second' <= now())
+    ORDER BY id"#,
+        r#"SELECT * FROM feed
+    WHERE enabled
+      AND (last_polled_at IS NULL OR unixepoch(last_polled_at) + poll_interval_secs <= unixepoch())
+    ORDER BY id"#,
+    );
+    with_pool!(pool, db => query_as(sql).fetch_all(db).await)
+}
+
+#[tracing::instrument(skip_all, fields(otel.kind = "client", db.system = pool.system()))]
+pub async fn update_feed(pool: &DbPool, id: i32, req: UpdateFeedReq) -> Result<Feed, sqlx::Error> {
+    // a changed url starts over without cached validators
+    let sql = format!(
+        r#"UPDATE feed
+    SET url = COALESCE($1, url),
+        title = COALESCE($2, title),
+        enabled = COALESCE($3, enabled),
+        auto_grab = COALESCE($4, auto_grab),
+        include_pattern = CASE WHEN CAST($5 AS text) IS NULL THEN include_pattern ELSE NULLIF($5, '') END,
+        exclude_pattern = CASE WHEN CAST($6 AS text) IS NULL THEN exclude_pattern ELSE NULLIF($6, '') END,
+        poll_interval_secs = COALESCE($7, poll_interval_secs),
+        etag = CASE WHEN $1 IS NULL OR $1 = url THEN etag END,
+        last_modified = CASE WHEN $1 IS NULL OR $1 = url THEN last_modified END,
+        updated_at = {now}
+    WHERE id = $8
+    RETURNING *"#,
+        now = pool.now()
+    );
+    with_pool!(pool, db => {
+        query_as(&sql)
+            .bind(req.url)
+            .bind(req.title)
+            .bind(req.enabled)
+            .bind(req.auto_grab)
+            .bind(req.include_pattern)
+            .bind(req.exclude_pattern)
+            .bind(req.poll_interval_secs)
+            .bind(id)
+            .fetch_one(db)
+            .await
+    })
+}
+
+#[tracing::instrument(skip_all, fields(otel.kind = "client", db.system = pool.system()))]
+pub async fn delete_feed_by_id(pool: &DbPool, id: i32) -> Result<u64, sqlx::Error> {
+    let sql = "DELETE FROM feed WHERE id = $1";
+    with_pool!(pool, db => {
+        query(sql)
+            .bind(id)
+            .execute(db)
+            .await
+            .map(|r| r.rows_affected())
+    })
+}
+
+/// keeps the validators of the response, a 304 passes the stored ones back
+#[tracing::instrument(skip_all, fields(otel.kind = "client", db.system = pool.system()))]
+pub async fn record_poll_success(
+    pool: &DbPool,
+    id: i32,
+    title: Option<String>,
+    etag: Option<String>,
+    last_modified: Option<String>,
+) -> Result<u64, sqlx::Error> {
+    let sql = format!(
+        r#"UPDATE feed
+    SET title = COALESCE(title, $1),
+        etag = $2,
+        last_modified = $3,
+        last_polled_at = {now},
+        last_error = NULL
+    WHERE id = $4"#,
+        now = pool.now()
+    );
+    with_pool!(pool, db => {
+        query(&sql)
+            .bind(title)
+            .bind(etag)
+            .bind(last_modified)
+            .bind(id)
+            .execute(db)
+            .await
+            .map(|r| r.rows_affected())
+    })
+}
+
+/// the feed is still marked as polled, it is retried after its interval
+#[tracing::instrument(skip_all, fields(otel.kind = "client", db.system = pool.system()))]
+pub async fn record_poll_error(pool: &DbPool, id: i32, error: String) -> Result<u64, sqlx::Error> {
+    let sql = format!("UPDATE feed SET last_polled_at = {now}, last_error = $1 WHERE id = $2", now = pool.now());
+    with_pool!(pool, db => {
+        query(&sql)
+            .bind(error)
+            .bind(id)
+            .execute(db)
+            .await
+            .map(|r| r.rows_affected())
+    })
+}
+
+/// `None` when the feed delivered this url before
+#[tracing::instrument(skip_all, fields(otel.kind = "client", db.system = pool.system()))]
+pub async fn create_feed_item(
+    pool: &DbPool,
+    feed_id: i32,
+    url: &str,
+) -> Result<Option<FeedItem>, sqlx::Error> {
+    let sql = "INSERT INTO feed_item (feed_id, url) VALUES ($1, $2) ON CONFLICT (feed_id, url) DO NOTHING RETURNING *";
+    with_pool!(pool, db => {
+        query_as(sql)
+            .bind(feed_id)
+            .bind(url)
+            .fetch_optional(db)
+            .await
+    })
+}
+
+#[tracing::instrument(skip_all, fields(otel.kind = "client", db.system = pool.system()))]
+pub async fn update_feed_item_doc(pool: &DbPool, id: i32, doc_id: i32) -> Result<u64, sqlx::Error> {
+    let sql = "UPDATE feed_item SET doc_id = $1 WHERE id = $2";
+    with_pool!(pool, db => {
+        query(sql)
+            .bind(doc_id)
+            .bind(id)
+            .execute(db)
+            .await
+            .map(|r| r.rows_affected())
+    })
+}
+
+#[tracing::instrument(skip_all, fields(otel.kind = "client", db.system = pool.system()))]
+pub async fn get_feed_items(pool: &DbPool, feed_id: i32) -> Result<Vec<FeedItem>, sqlx::Error> {
+    let sql = "SELECT * FROM feed_item WHERE feed_id = $1 ORDER BY id DESC";
+    with_pool!(pool, db => query_as(sql).bind(feed_id).fetch_all(db).await)
+}
@@ -0,0 +1,67 @@
+use crate::model::entity::gc::{GcAction, GcItem, GcReport};
+use sqlx::query_as;
+use sqlx::types::Json;
+use crate::db::{with_pool, DbPool};
+
+#[tracing::instrument(skip_all, fields(otel.kind = "client", db.system = pool.system()))]
+pub async fn create_report(
+    pool: &DbPool,
+    task_id: &str,
+    items: Vec<GcItem>,
+) -> Result<GcReport, sqlx::Error> {
+    let reclaimable_bytes: u64 = items.iter().map(|i| i.bytes).sum();
+    let sql = r#"INSERT INTO gc_report (task_id, items, reclaimable_bytes)
+    VALUES ($1, $2, $3)
+    RETURNING *"#;
+    with_pool!(pool, db => {
+        query_as(sql)
+            .bind(task_id)
+            .bind(Json(items))
+            .bind(reclaimable_bytes as i64)
+            .fetch_one(db)
+            .await
+    })
+}
+
+#[tracing::instrument(skip_all, fields(otel.kind = "client", db.system = pool.system()))]
+pub async fn get_reports(pool: &DbPool) -> Result<Vec<GcReport>, sqlx::Error> {
+    let sql = "SELECT * FROM gc_report ORDER BY id DESC";
+    with_pool!(pool, db => query_as(sql).fetch_all(db).await)
+}
+
+#[tracing::instrument(skip_all, fields(otel.kind = "client", db.system = pool.system()))]
+pub async fn get_report_by_id(pool: &DbPool, id: i32) -> Result<GcReport, sqlx::Error> {
+    let sql = "SELECT * FROM gc_report WHERE id = $1";
+    with_pool!(pool, db => query_as(sql).bind(id).fetch_one(db).await)
+}
+
+#[tracing::instrument(skip_all, fields(otel.kind = "client", db.system = pool.system()))]
+pub async fn get_latest_report(pool: &DbPool) -> Result<Option<GcReport>, sqlx::Error> {
+    let sql = "SELECT * FROM gc_report ORDER BY id DESC LIMIT 1";
+    with_pool!(pool, db => query_as(sql).fetch_optional(db).await)
+}
+
+/// `None` when the report was applied before
+#[tracing::instrument(skip_all, fields(otel.kind = "client", db.system = pool.system()))]
+pub async fn mark_applied(
+    pool: &DbPool,
+    id: i32,
+    action: GcAction,
+    applied_bytes: u64,
+) -> Result<Option<GcReport>, sqlx::Error> {
+    let sql = format!(
+        r#"UPDATE gc_report
+    SET action = $1, applied_bytes = $2, applied_at = {now}
+    WHERE id = $3 AND applied_at IS NULL
+    RETURNING *"#,
+        now = pool.now()
+    );
+    with_pool!(pool, db => {
+        query_as(&sql)
+            .bind(action.as_str())
+            .bind(applied_bytes as i64)
+            .bind(id)
+            .fetch_optional(db)
+            .await
+    })
+}
@@ -0,0 +1,78 @@
+use crate::link::{self, LinkSource};
+use crate::model::dto::import::{ImportReport, ImportStatus, ImportedUrl};
+use crate::model::entity::task::Task;
+use crate::service;
+use crate::state::QueueState;
+use std::collections::HashSet;
+use crate::db::DbPool;
+
+/// Stores every supported link found in `content` that is not in `doc` yet.
+#[tracing::instrument(skip_all)]
+pub async fn import_links(
+    pool: &DbPool,
+    queue_state: &QueueState,
+    content: &str,
+    source: LinkSource,
+) -> Result<ImportReport, sqlx::Error> {
+    let mut report = ImportReport::default();
+    let mut seen = HashSet::new();
+    for url in link::extract_links(content, source) {
+        let canonical_url = match link::canonicalize(&url) {
+            Ok(canonical_url) => canonical_url,
+            Err(e) => {
+                report.invalid += 1;
+                report.results.push(ImportedUrl {
+                    url,
+                    canonical_url: None,
+                    status: ImportStatus::Invalid,
+                    doc_id: None,
+                    task_id: None,
+                    error: Some(e),
+                    doc: None,
+                });
+                continue;
+            }
+        };
+        // the same page linked in several spellings
+        if !seen.insert(canonical_url.clone()) {
+            continue;
+        }
+        let (status, doc) = match service::doc::create_doc_if_absent(pool, queue_state, &canonical_url).await? {
+            Some(doc) => (ImportStatus::Created, Some(doc)),
+            None => (
+                ImportStatus::Existing,
+                service::doc::get_doc_by_url(pool, &canonical_url).await?,
+            ),
+        };
+        match status {
+            ImportStatus::Created => report.created += 1,
+            _ => report.existing += 1,
+        }
+        report.results.push(ImportedUrl {
+            url,
+            canonical_url: Some(canonical_url),
+            status,
+            doc_id: doc.as_ref().map(|d| d.id),
+            task_id: None,
+            error: None,
+            doc,
+        });
+    }
+    Ok(report)
+}
+
+/// queue a parse task for every doc created by the import
+#[tracing::instrument(skip_all)]
+pub async fn enqueue_created(states: &QueueState, report: &mut ImportReport) {
+    for result in report
+        .results
+        .iter_mut()
+        .filter(|r| r.status == ImportStatus::Created)
+    {
+        if let Some(doc_id) = result.doc_id {
+            let task = Task::new_html_parse_task(doc_id);
+            result.task_id = Some(task.id.clone());
+            states.enqueue(task).await;
+        }
+    }
+}
@@ -0,0 +1,44 @@
+use crate::model::entity::library::{LibraryIssue, LibraryReport};
+use sqlx::query_as;
+use sqlx::types::Json;
+use crate::db::{with_pool, DbPool};
+
+#[tracing::instrument(skip_all, fields(otel.kind = "client", db.system = pool.system()))]
+pub async fn create_report(
+    pool: &DbPool,
+    task_id: &str,
+    cbz_checked: i32,
+    pics_checked: i32,
+    issues: Vec<LibraryIssue>,
+) -> Result<LibraryReport, sqlx::Error> {
+    let sql = r#"INSERT INTO library_report (task_id, cbz_checked, pics_checked, issues)
+    VALUES ($1, $2, $3, $4)
+    RETURNING *"#;
+    with_pool!(pool, db => {
+        query_as(sql)
+            .bind(task_id)
+            .bind(cbz_checked)
+            .bind(pics_checked)
+            .bind(Json(issues))
+            .fetch_one(db)
+            .await
+    })
+}
+
+#[tracing::instrument(skip_all, fields(otel.kind = "client", db.system = pool.system()))]
+pub async fn get_reports(pool: &DbPool) -> Result<Vec<LibraryReport>, sqlx::Error> {
+    let sql = "SELECT * FROM library_report ORDER BY id DESC";
+    with_pool!(pool, db => query_as(sql).fetch_all(db).await)
+}
+
+#[tracing::instrument(skip_all, fields(otel.kind = "client", db.system = pool.system()))]
+pub async fn get_report_by_id(pool: &DbPool, id: i32) -> Result<LibraryReport, sqlx::Error> {
+    let sql = "SELECT * FROM library_report WHERE id = $1";
+    with_pool!(pool, db => query_as(sql).bind(id).fetch_one(db).await)
+}
+
+#[tracing::instrument(skip_all, fields(otel.kind = "client", db.system = pool.system()))]
+pub async fn get_latest_report(pool: &DbPool) -> Result<Option<LibraryReport>, sqlx::Error> {
+    let sql = "SELECT * FROM library_report ORDER BY id DESC LIMIT 1";
+    with_pool!(pool, db => query_as(sql).fetch_optional(db).await)
+}
@@ -1,4 +1,12 @@
+pub mod api_token;
+pub mod audit;
 pub mod cbz;
 pub mod doc;
+pub mod feed;
+pub mod gc;
+pub mod import;
+pub mod library;
 pub mod pic;
+pub mod schedule;
+pub mod webhook;
 mod helper;
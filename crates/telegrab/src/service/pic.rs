@@ -2,41 +2,61 @@ use crate::controller::pic::PicQuery;
 use crate::model::dto::pagination::{CursorBasedPaginationResponse, RefineSortOrder};
 use crate::model::dto::pagination::{PaginationQuery, PaginationResponse};
 use crate::model::dto::pic::MutatePicReq;
-use crate::model::entity::pic::Pic;
+use crate::model::entity::pic::{Pic, PicChanges};
 use crate::model::{Direction, PaginationArgs};
 use crate::service::helper::build_cursor_pagination;
 use convert_case::{Case, Casing};
 use sqlx::{query, query_as, query_scalar};
-use sqlx_postgres::PgPool;
+use crate::db::{with_pool, DbPool};
 
-pub async fn create_pic(pool: &PgPool, params: MutatePicReq) -> Result<Pic, sqlx::Error> {
+#[tracing::instrument(skip_all, fields(otel.kind = "client", db.system = pool.system()))]
+pub async fn create_pic(pool: &DbPool, params: MutatePicReq) -> Result<Pic, sqlx::Error> {
     let sql = "INSERT INTO pic (url, doc_id, seq) VALUES ($1, $2, $3) RETURNING *";
-    query_as(sql)
-        .bind(params.url)
-        .bind(params.doc_id)
-        .bind(params.seq)
-        .fetch_one(pool)
-        .await
+    with_pool!(pool, db => {
+        query_as(sql)
+            .bind(params.url)
+            .bind(params.doc_id)
+            .bind(params.seq)
+            .fetch_one(db)
+            .await
+    })
 }
 
-pub async fn get_pic_by_id(pool: &PgPool, id: i32) -> Result<Pic, sqlx::Error> {
+#[tracing::instrument(skip_all, fields(otel.kind = "client", db.system = pool.system()))]
+pub async fn get_pic_by_id(pool: &DbPool, id: i32) -> Result<Pic, sqlx::Error> {
     let sql = "SELECT * FROM pic WHERE id = $1";
-    query_as(sql).bind(id).fetch_one(pool).await
+    with_pool!(pool, db => query_as(sql).bind(id).fetch_one(db).await)
 }
-pub async fn get_cover_pic_by_doc_id(pool: &PgPool, doc_id: i32)->Result<Pic, sqlx::Error>{
-    let sql = "SELECT * FROM pic WHERE doc_id = $1 and seq = 0 ORDER BY seq LIMIT 1";
-    query_as(sql).bind(doc_id).fetch_one(pool).await
+#[tracing::instrument(skip_all, fields(otel.kind = "client", db.system = pool.system()))]
+pub async fn get_cover_pic_by_doc_id(pool: &DbPool, doc_id: i32)->Result<Pic, sqlx::Error>{
+    let sql = "SELECT * FROM pic WHERE doc_id = $1 and seq = 0 AND status IS DISTINCT FROM 3 ORDER BY seq LIMIT 1";
+    with_pool!(pool, db => query_as(sql).bind(doc_id).fetch_one(db).await)
 }
+#[tracing::instrument(skip_all, fields(otel.kind = "client", db.system = pool.system()))]
 pub async fn get_pics_by_ids(
-    pool: &PgPool,
+    pool: &DbPool,
     ids: &[i32],
 ) -> Result<Vec<Pic>, sqlx::Error> {
-    let sql = "SELECT * FROM pic WHERE id = ANY($1)";
-    query_as(sql).bind(ids).fetch_all(pool).await
+    match pool {
+        DbPool::Postgres(db) => {
+            query_as("SELECT * FROM pic WHERE id = ANY($1)")
+                .bind(ids)
+                .fetch_all(db)
+                .await
+        }
+        #[cfg(feature = "sqlite")]
+        DbPool::Sqlite(db) => {
+            query_as("SELECT * FROM pic WHERE id IN (SELECT value FROM json_each($1))")
+                .bind(sqlx::types::Json(ids))
+                .fetch_all(db)
+                .await
+        }
+    }
 }
 
+#[tracing::instrument(skip_all, fields(otel.kind = "client", db.system = pool.system()))]
 pub async fn get_pics(
-    pool: &PgPool,
+    pool: &DbPool,
     query: &PaginationQuery,
     pic_query: &PicQuery,
 ) -> Result<PaginationResponse<Pic>, sqlx::Error> {
@@ -72,17 +92,21 @@ pub async fn get_pics(
     let pagination_clause = format!(" LIMIT {} OFFSET {}", query.limit(), query.offset());
 
     // 执行查询获取总数
-    let (total,): (i64,) = query_as(&format!("SELECT COUNT(*) FROM pic{}", filter_clause))
-        .fetch_one(pool)
-        .await?;
+    let (total,): (i64,) = with_pool!(pool, db => {
+        query_as(&format!("SELECT COUNT(*) FROM pic{}", filter_clause))
+            .fetch_one(db)
+            .await
+    })?;
 
     // 执行查询获取数据
-    let pics = query_as(&format!(
-        "SELECT * FROM pic{}{}{}",
-        filter_clause, sort_clause, pagination_clause
-    ))
-    .fetch_all(pool)
-    .await?;
+    let pics = with_pool!(pool, db => {
+        query_as(&format!(
+            "SELECT * FROM pic{}{}{}",
+            filter_clause, sort_clause, pagination_clause
+        ))
+        .fetch_all(db)
+        .await
+    })?;
 
     // 构建并返回分页响应
     Ok(PaginationResponse {
@@ -91,65 +115,91 @@ pub async fn get_pics(
     })
 }
 
+#[tracing::instrument(skip_all, fields(otel.kind = "client", db.system = pool.system()))]
 pub async fn update_pic_by_id(
-    pool: &PgPool,
+    pool: &DbPool,
     id: i32,
     params: MutatePicReq,
 ) -> Result<Pic, sqlx::Error> {
     let sql = "UPDATE pic SET url = $1, doc_id = $2, seq = $3 WHERE id = $4 RETURNING *";
-    query_as(sql)
-        .bind(params.url)
-        .bind(params.doc_id)
-        .bind(params.seq)
-        .bind(id)
-        .fetch_one(pool)
-        .await
+    with_pool!(pool, db => {
+        query_as(sql)
+            .bind(params.url)
+            .bind(params.doc_id)
+            .bind(params.seq)
+            .bind(id)
+            .fetch_one(db)
+            .await
+    })
 }
+#[tracing::instrument(skip_all, fields(otel.kind = "client", db.system = pool.system()))]
 pub async fn update_pic_status_by_id(
-    pool: &PgPool,
+    pool: &DbPool,
     id: i32,
     status: i16,
 ) -> Result<Pic, sqlx::Error> {
     let sql = "UPDATE pic SET status = $1 WHERE id = $2 RETURNING *";
-    query_as(sql)
-        .bind(status)
-        .bind(id)
-        .fetch_one(pool)
-        .await
+    with_pool!(pool, db => {
+        query_as(sql)
+            .bind(status)
+            .bind(id)
+            .fetch_one(db)
+            .await
+    })
 }
-pub async fn delete_pic_by_id(pool: &PgPool, id: i32) -> Result<u64, sqlx::Error> {
+#[tracing::instrument(skip_all, fields(otel.kind = "client", db.system = pool.system()))]
+pub async fn delete_pic_by_id(pool: &DbPool, id: i32) -> Result<u64, sqlx::Error> {
     let sql = "DELETE FROM pic WHERE id = $1";
-    query(sql)
-        .bind(id)
-        .execute(pool)
-        .await
-        .map(|r| r.rows_affected())
+    with_pool!(pool, db => {
+        query(sql)
+            .bind(id)
+            .execute(db)
+            .await
+            .map(|r| r.rows_affected())
+    })
 }
 
-pub async fn get_pics_by_doc_id(pool: &PgPool, doc_id: i32) -> Result<Vec<Pic>, sqlx::Error> {
-    let sql = "SELECT * FROM pic WHERE doc_id = $1 ORDER BY seq";
-    query_as(sql).bind(doc_id).fetch_all(pool).await
+/// pics still on the page, removed ones (status 3) are left out
+#[tracing::instrument(skip_all, fields(otel.kind = "client", db.system = pool.system()))]
+pub async fn get_pics_by_doc_id(pool: &DbPool, doc_id: i32) -> Result<Vec<Pic>, sqlx::Error> {
+    let sql = "SELECT * FROM pic WHERE doc_id = $1 AND status IS DISTINCT FROM 3 ORDER BY seq";
+    with_pool!(pool, db => query_as(sql).bind(doc_id).fetch_all(db).await)
+}
+#[tracing::instrument(skip_all, fields(otel.kind = "client", db.system = pool.system()))]
+pub async fn get_downloaded_pics(pool: &DbPool) -> Result<Vec<Pic>, sqlx::Error> {
+    let sql = "SELECT * FROM pic WHERE status = 1 ORDER BY doc_id, seq";
+    with_pool!(pool, db => query_as(sql).fetch_all(db).await)
 }
-pub async fn has_status_0_pics_by_doc_id(pool: &PgPool, doc_id: i32) -> Result<bool, sqlx::Error> {
-    let sql = r#"SELECT EXISTS(SELECT 1 FROM pic WHERE doc_id = $1 AND status == 0 ORDER BY seq) AS "exists: bool""#;
-    query_scalar(sql).bind(doc_id).fetch_one(pool).await
+/// pics of every doc except removed ones
+#[tracing::instrument(skip_all, fields(otel.kind = "client", db.system = pool.system()))]
+pub async fn get_live_pics(pool: &DbPool) -> Result<Vec<Pic>, sqlx::Error> {
+    let sql = "SELECT * FROM pic WHERE status IS DISTINCT FROM 3 ORDER BY doc_id, seq";
+    with_pool!(pool, db => query_as(sql).fetch_all(db).await)
+}
+#[tracing::instrument(skip_all, fields(otel.kind = "client", db.system = pool.system()))]
+pub async fn has_status_0_pics_by_doc_id(pool: &DbPool, doc_id: i32) -> Result<bool, sqlx::Error> {
+    let sql = r#"SELECT EXISTS(SELECT 1 FROM pic WHERE doc_id = $1 AND status = 0 ORDER BY seq) AS "exists: bool""#;
+    with_pool!(pool, db => query_scalar(sql).bind(doc_id).fetch_one(db).await)
 }
 
+#[tracing::instrument(skip_all, fields(otel.kind = "client", db.system = pool.system()))]
 pub async fn get_cursor_based_pagination_pics(
-    pool: &PgPool,
+    pool: &DbPool,
     pagination_args: PaginationArgs,
     doc_id: i32,
 ) -> Result<CursorBasedPaginationResponse<Pic>, sqlx::Error> {
-    let total: i64 = query_scalar("SELECT COUNT(*) from pic WHERE doc_id = $1")
-        .bind(doc_id)
-        .fetch_one(pool)
-        .await?;
+    let total: i64 = with_pool!(pool, db => {
+        query_scalar("SELECT COUNT(*) from pic WHERE doc_id = $1 AND status IS DISTINCT FROM 3")
+            .bind(doc_id)
+            .fetch_one(db)
+            .await
+    })?;
     let PaginationArgs {
         limit,
         cursor,
         direction,
     } = pagination_args;
-    let main_sql = "SELECT * FROM pic WHERE doc_id = $1";
+    let main_sql = "SELECT * FROM pic WHERE doc_id = $1 AND status IS DISTINCT FROM 3";
     let order_by_clause = match direction {
         Direction::Forward => "ORDER BY seq",
         Direction::Backward => "ORDER BY seq DESC",
@@ -164,20 +214,95 @@ pub async fn get_cursor_based_pagination_pics(
             }
         );
         let sql = format!("{} {} {} LIMIT $3", main_sql, where_clause, order_by_clause);
-        query_as(&sql)
-            .bind(doc_id)
-            .bind(cursor)
-            .bind(limit as i64 + 1)
-            .fetch_all(pool)
-            .await?
+        with_pool!(pool, db => {
+            query_as(&sql)
+                .bind(doc_id)
+                .bind(cursor)
+                .bind(limit as i64 + 1)
+                .fetch_all(db)
+                .await
+        })?
     } else {
         let sql = format!("{} {} LIMIT $2", main_sql, order_by_clause);
-        query_as(&sql)
-            .bind(doc_id)
-            .bind(limit as i64 + 1)
-            .fetch_all(pool)
-            .await?
+        with_pool!(pool, db => {
+            query_as(&sql)
+                .bind(doc_id)
+                .bind(limit as i64 + 1)
+                .fetch_all(db)
+                .await
+        })?
     };
     let paged = build_cursor_pagination(pics, total as u64, limit, direction, cursor.is_some());
     Ok(paged)
 }
+
+/// Brings the pics of a doc in line with the image list of a refreshed page.
+/// Pics are matched by url: new urls are added, pics that moved or came back
+/// get their new seq, the rest are tombstoned with status 3. Changed pics are
+/// reset to status 0 so they get downloaded again.
+#[tracing::instrument(skip_all, fields(otel.kind = "client", db.system = pool.system()))]
+pub async fn sync_doc_pics(
+    pool: &DbPool,
+    doc_id: i32,
+    image_urls: &[String],
+) -> Result<PicChanges, sqlx::Error> {
+    let now = pool.now();
+    let update_sql = format!("UPDATE pic SET seq = $1, status = 0, updated_at = {} WHERE id = $2 RETURNING *", now);
+    let insert_sql = "INSERT INTO pic (doc_id, url, seq) VALUES ($1, $2, $3) RETURNING *";
+    let remove_sql = format!("UPDATE pic SET status = 3, updated_at = {} WHERE id = $1", now);
+    with_pool!(pool, db => {
+        let mut tx = db.begin().await?;
+        let existing: Vec<Pic> = query_as("SELECT * FROM pic WHERE doc_id = $1 ORDER BY seq, id")
+            .bind(doc_id)
+            .fetch_all(&mut *tx)
+            .await?;
+        let mut changes = PicChanges::default();
+        for (seq, url) in image_urls.iter().enumerate() {
+            let seq = seq as i32;
+            match existing.iter().find(|p| &p.url == url) {
+                Some(pic) if pic.seq == seq && pic.status != 3 => {}
+                Some(pic) => {
+                    let updated: Pic = query_as(&update_sql)
+                        .bind(seq)
+                        .bind(pic.id)
+                        .fetch_one(&mut *tx)
+                        .await?;
+                    changes.updated.push(updated);
+                }
+                None => {
+                    let added: Pic = query_as(insert_sql)
+                        .bind(doc_id)
+                        .bind(url)
+                        .bind(seq)
+                        .fetch_one(&mut *tx)
+                        .await?;
+                    changes.added.push(added);
+                }
+            }
+        }
+        for pic in existing {
+            if pic.status != 3 && !image_urls.contains(&pic.url) {
+                query(&remove_sql)
+                    .bind(pic.id)
+                    .execute(&mut *tx)
+                    .await?;
+                changes.removed.push(pic);
+            }
+        }
+        tx.commit().await?;
+        Ok(changes)
+    })
+}
+
+/// everything is downloaded again, e.g. after the file names changed
+#[tracing::instrument(skip_all, fields(otel.kind = "client", db.system = pool.system()))]
+pub async fn reset_pics_status_by_doc_id(pool: &DbPool, doc_id: i32) -> Result<u64, sqlx::Error> {
+    let sql = format!("UPDATE pic SET status = 0, updated_at = {now} WHERE doc_id = $1 AND status IS DISTINCT FROM 3", now = pool.now());
+    with_pool!(pool, db => {
+        query(&sql)
+            .bind(doc_id)
+            .execute(db)
+            .await
+            .map(|r| r.rows_affected())
+    })
+}
@@ -0,0 +1,187 @@
+use crate::model::entity::schedule::{Schedule, ScheduleRun};
+use crate::model::entity::task::TaskType;
+use sqlx::types::Json;
+use sqlx::{query, query_as};
+use crate::db::{with_pool, DbPool};
+use time::OffsetDateTime;
+
+/// runs kept per schedule, older ones are dropped when a run is recorded
+const MAX_RUNS_PER_SCHEDULE: i64 = 100;
+
+#[tracing::instrument(skip_all, fields(otel.kind = "client", db.system = pool.system()))]
+pub async fn get_schedules(pool: &DbPool) -> Result<Vec<Schedule>, sqlx::Error> {
+    let sql = "SELECT * FROM schedule ORDER BY id";
+    with_pool!(pool, db => query_as(sql).fetch_all(db).await)
+}
+
+#[tracing::instrument(skip_all, fields(otel.kind = "client", db.system = pool.system()))]
+pub async fn get_schedule_by_id(pool: &DbPool, id: i32) -> Result<Schedule, sqlx::Error> {
+    let sql = "SELECT * FROM schedule WHERE id = $1";
+    with_pool!(pool, db => query_as(sql).bind(id).fetch_one(db).await)
+}
+
+#[tracing::instrument(skip_all, fields(otel.kind = "client", db.system = pool.system()))]
+pub async fn get_due_schedules(pool: &DbPool) -> Result<Vec<Schedule>, sqlx::Error> {
+    let sql = pool.dialect(
+        "SELECT * FROM schedule WHERE enabled AND next_run_at <= now() ORDER BY next_run_at, id",
+        "SELECT * FROM schedule WHERE enabled AND unixepoch(next_run_at) <= unixepoch() ORDER BY next_run_at, id",
+    );
+    with_pool!(pool, db => query_as(sql).fetch_all(db).await)
+}
+
+#[tracing::instrument(skip_all, fields(otel.kind = "client", db.system = pool.system()))]
+pub async fn create_schedule(
+    pool: &DbPool,
+    name: &str,
+    cron: &str,
+    task_type: &TaskType,
+    enabled: bool,
+    next_run_at: Option<OffsetDateTime>,
+) -> Result<Schedule, sqlx::Error> {
+    let sql = r#"INSERT INTO schedule (name, cron, task_type, enabled, next_run_at)
+    VALUES ($1, $2, $3, $4, $5)
+    RETURNING *"#;
+    with_pool!(pool, db => {
+        query_as(sql)
+            .bind(name)
+            .bind(cron)
+            .bind(Json(task_type))
+            .bind(enabled)
+            .bind(next_run_at)
+            .fetch_one(db)
+            .await
+    })
+}
+
+/// schedules from the configuration, an existing row keeps its `enabled`
+/// and its next run unless the cron expression changed
+#[tracing::instrument(skip_all, fields(otel.kind = "client", db.system = pool.system()))]
+pub async fn upsert_schedule(
+    pool: &DbPool,
+    name: &str,
+    cron: &str,
+    task_type: &TaskType,
+    enabled: bool,
+    next_run_at: Option<OffsetDateTime>,
+) -> Result<Schedule, sqlx::Error> {
+    let sql = format!(
+        r#"INSERT INTO schedule (name, cron, task_type, enabled, next_run_at)
+    VALUES ($1, $2, $3, $4, $5)
+    ON CONFLICT (name) DO UPDATE
+    SET task_type = EXCLUDED.task_type,
+        next_run_at = CASE
+            WHEN schedule.cron <> EXCLUDED.cron OR schedule.next_run_at IS NULL THEN EXCLUDED.next_run_at
+            ELSE schedule.next_run_at END,
+        cron = EXCLUDED.cron,
+        updated_at = {now}
+    RETURNING *"#,
+        now = pool.now()
+    );
+    with_pool!(pool, db => {
+        query_as(&sql)
+            .bind(name)
+            .bind(cron)
+            .bind(Json(task_type))
+            .bind(enabled)
+            .bind(next_run_at)
+            .fetch_one(db)
+            .await
+    })
+}
+
+#[tracing::instrument(skip_all, fields(otel.kind = "client", db.system = pool.system()))]
+pub async fn update_schedule(
+    pool: &DbPool,
+    id: i32,
+    cron: Option<String>,
+    enabled: Option<bool>,
+    next_run_at: Option<OffsetDateTime>,
+) -> Result<Schedule, sqlx::Error> {
+    let sql = format!(
+        r#"UPDATE schedule
+    SET cron = COALESCE($1, cron),
+        enabled = COALESCE($2, enabled),
+        next_run_at = COALESCE($3, next_run_at),
+        updated_at = {now}
+    WHERE id = $4
+    RETURNING *"#,
+        now = pool.now()
+    );
+    with_pool!(pool, db => {
+        query_as(&sql)
+            .bind(cron)
+            .bind(enabled)
+            .bind(next_run_at)
+            .bind(id)
+            .fetch_one(db)
+            .await
+    })
+}
+
+#[tracing::instrument(skip_all, fields(otel.kind = "client", db.system = pool.system()))]
+pub async fn delete_schedule_by_id(pool: &DbPool, id: i32) -> Result<u64, sqlx::Error> {
+    let sql = "DELETE FROM schedule WHERE id = $1";
+    with_pool!(pool, db => {
+        query(sql)
+            .bind(id)
+            .execute(db)
+            .await
+            .map(|r| r.rows_affected())
+    })
+}
+
+/// `task_id` is `None` for a skipped run, `next_run_at` is left alone when `None`
+#[tracing::instrument(skip_all, fields(otel.kind = "client", db.system = pool.system()))]
+pub async fn record_run(
+    pool: &DbPool,
+    schedule_id: i32,
+    task_id: Option<String>,
+    scheduled_at: OffsetDateTime,
+    next_run_at: Option<OffsetDateTime>,
+) -> Result<ScheduleRun, sqlx::Error> {
+    let insert_sql = r#"INSERT INTO schedule_run (schedule_id, task_id, skipped, scheduled_at)
+    VALUES ($1, $2, $2 IS NULL, $3)
+    RETURNING *"#;
+    let update_sql = format!(
+        r#"UPDATE schedule
+    SET last_run_at = {},
+        last_task_id = COALESCE($1, last_task_id),
+        next_run_at = COALESCE($2, next_run_at)
+    WHERE id = $3"#,
+        pool.now()
+    );
+    let prune_sql = r#"DELETE FROM schedule_run
+    WHERE schedule_id = $1
+      AND id NOT IN (SELECT id FROM schedule_run WHERE schedule_id = $1 ORDER BY id DESC LIMIT $2)"#;
+    with_pool!(pool, db => {
+        let mut tx = db.begin().await?;
+        let run: ScheduleRun = query_as(insert_sql)
+            .bind(schedule_id)
+            .bind(&task_id)
+            .bind(scheduled_at)
+            .fetch_one(&mut *tx)
+            .await?;
+        query(&update_sql)
+            .bind(task_id)
+            .bind(next_run_at)
+            .bind(schedule_id)
+            .execute(&mut *tx)
+            .await?;
+        query(prune_sql)
+            .bind(schedule_id)
+            .bind(MAX_RUNS_PER_SCHEDULE)
+            .execute(&mut *tx)
+            .await?;
+        tx.commit().await?;
+        Ok(run)
+    })
+}
+
+#[tracing::instrument(skip_all, fields(otel.kind = "client", db.system = pool.system()))]
+pub async fn get_schedule_runs(
+    pool: &DbPool,
+    schedule_id: i32,
+) -> Result<Vec<ScheduleRun>, sqlx::Error> {
+    let sql = "SELECT * FROM schedule_run WHERE schedule_id = $1 ORDER BY id DESC";
+    with_pool!(pool, db => query_as(sql).bind(schedule_id).fetch_all(db).await)
+}
@@ -0,0 +1,206 @@
+use crate::db::{with_pool, DbPool};
+use crate::model::dto::pagination::{PaginationQuery, PaginationResponse};
+use crate::model::dto::webhook::{CreateWebhookReq, UpdateWebhookReq};
+use crate::model::entity::webhook::{Webhook, WebhookDelivery, WebhookDeliveryStatus};
+use serde_json::Value;
+use sqlx::types::Json;
+use sqlx::{query, query_as, query_scalar};
+use time::OffsetDateTime;
+
+#[tracing::instrument(skip_all, fields(otel.kind = "client", db.system = pool.system()))]
+pub async fn create_webhook(pool: &DbPool, req: CreateWebhookReq) -> Result<Webhook, sqlx::Error> {
+    let sql = r#"INSERT INTO webhook (url, secret, events, enabled, description)
+    VALUES ($1, $2, $3, COALESCE($4, true), NULLIF($5, ''))
+    RETURNING *"#;
+    with_pool!(pool, db => {
+        query_as(sql)
+            .bind(req.url)
+            .bind(req.secret)
+            .bind(Json(req.events.unwrap_or_default()))
+            .bind(req.enabled)
+            .bind(req.description)
+            .fetch_one(db)
+            .await
+    })
+}
+
+#[tracing::instrument(skip_all, fields(otel.kind = "client", db.system = pool.system()))]
+pub async fn get_webhooks(pool: &DbPool) -> Result<Vec<Webhook>, sqlx::Error> {
+    let sql = "SELECT * FROM webhook ORDER BY id";
+    with_pool!(pool, db => query_as(sql).fetch_all(db).await)
+}
+
+#[tracing::instrument(skip_all, fields(otel.kind = "client", db.system = pool.system()))]
+pub async fn get_enabled_webhooks(pool: &DbPool) -> Result<Vec<Webhook>, sqlx::Error> {
+    let sql = "SELECT * FROM webhook WHERE enabled ORDER BY id";
+    with_pool!(pool, db => query_as(sql).fetch_all(db).await)
+}
+
+#[tracing::instrument(skip_all, fields(otel.kind = "client", db.system = pool.system()))]
+pub async fn get_webhook_by_id(pool: &DbPool, id: i32) -> Result<Webhook, sqlx::Error> {
+    let sql = "SELECT * FROM webhook WHERE id = $1";
+    with_pool!(pool, db => query_as(sql).bind(id).fetch_one(db).await)
+}
+
+#[tracing::instrument(skip_all, fields(otel.kind = "client", db.system = pool.system()))]
+pub async fn update_webhook(
+    pool: &DbPool,
+    id: i32,
+    req: UpdateWebhookReq,
+) -> Result<Webhook, sqlx::Error> {
+    let sql = format!(
+        r#"UPDATE webhook
+    SET url = COALESCE($1, url),
+        secret = COALESCE($2, secret),
+        events = COALESCE($3, events),
+        enabled = COALESCE($4, enabled),
+        description = CASE WHEN CAST($5 AS text) IS NULL THEN description ELSE NULLIF($5, '') END,
+        updated_at = {now}
+    WHERE id = $6
+    RETURNING *"#,
+        now = pool.now()
+    );
+    with_pool!(pool, db => {
+        query_as(&sql)
+            .bind(req.url)
+            .bind(req.secret)
+            .bind(req.events.map(Json))
+            .bind(req.enabled)
+            .bind(req.description)
+            .bind(id)
+            .fetch_one(db)
+            .await
+    })
+}
+
+#[tracing::instrument(skip_all, fields(otel.kind = "client", db.system = pool.system()))]
+pub async fn delete_webhook_by_id(pool: &DbPool, id: i32) -> Result<u64, sqlx::Error> {
+    let sql = "DELETE FROM webhook WHERE id = $1";
+    with_pool!(pool, db => {
+        query(sql)
+            .bind(id)
+            .execute(db)
+            .await
+            .map(|r| r.rows_affected())
+    })
+}
+
+/// pending and due right away
+#[tracing::instrument(skip_all, fields(otel.kind = "client", db.system = pool.system()))]
+pub async fn create_delivery(
+    pool: &DbPool,
+    webhook_id: i32,
+    event: &str,
+    payload: &Value,
+) -> Result<WebhookDelivery, sqlx::Error> {
+    let sql = format!(
+        r#"INSERT INTO webhook_delivery (webhook_id, event, payload, next_attempt_at)
+    VALUES ($1, $2, $3, {now})
+    RETURNING *"#,
+        now = pool.now()
+    );
+    with_pool!(pool, db => {
+        query_as(&sql)
+            .bind(webhook_id)
+            .bind(event)
+            .bind(Json(payload))
+            .fetch_one(db)
+            .await
+    })
+}
+
+/// never sent, kept as failed so the gap shows up in the deliveries of the webhook
+#[tracing::instrument(skip_all, fields(otel.kind = "client", db.system = pool.system()))]
+pub async fn create_missed_delivery(
+    pool: &DbPool,
+    webhook_id: i32,
+    event: &str,
+    payload: &Value,
+    error: &str,
+) -> Result<WebhookDelivery, sqlx::Error> {
+    let sql = r#"INSERT INTO webhook_delivery (webhook_id, event, payload, status, error)
+    VALUES ($1, $2, $3, $4, $5)
+    RETURNING *"#;
+    with_pool!(pool, db => {
+        query_as(sql)
+            .bind(webhook_id)
+            .bind(event)
+            .bind(Json(payload))
+            .bind(WebhookDeliveryStatus::Failed)
+            .bind(error)
+            .fetch_one(db)
+            .await
+    })
+}
+
+/// pending deliveries of enabled webhooks whose next attempt is due, oldest first;
+/// a disabled webhook keeps its deliveries until it is enabled again
+#[tracing::instrument(skip_all, fields(otel.kind = "client", db.system = pool.system()))]
+pub async fn get_due_deliveries(pool: &DbPool, limit: i64) -> Result<Vec<WebhookDelivery>, sqlx::Error> {
+    let sql = pool.dialect(
+        r#"SELECT d.* FROM webhook_delivery d JOIN webhook w ON w.id = d.webhook_id
+    WHERE w.enabled AND d.status = 0 AND d.next_attempt_at <= now()
+    ORDER BY d.id LIMIT $1"#,
+        r#"SELECT d.* FROM webhook_delivery d JOIN webhook w ON w.id = d.webhook_id
+    WHERE w.enabled AND d.status = 0 AND unixepoch(d.next_attempt_at) <= unixepoch()
+    ORDER BY d.id LIMIT $1"#,
+    );
+    with_pool!(pool, db => query_as(sql).bind(limit).fetch_all(db).await)
+}
+
+/// `next_attempt_at` is only kept while the delivery is still pending
+#[tracing::instrument(skip_all, fields(otel.kind = "client", db.system = pool.system()))]
+pub async fn record_attempt(
+    pool: &DbPool,
+    id: i32,
+    status: WebhookDeliveryStatus,
+    response_status: Option<i32>,
+    error: Option<String>,
+    next_attempt_at: Option<OffsetDateTime>,
+) -> Result<WebhookDelivery, sqlx::Error> {
+    let sql = format!(
+        r#"UPDATE webhook_delivery
+    SET status = $1,
+        attempts = attempts + 1,
+        response_status = $2,
+        error = $3,
+        next_attempt_at = $4,
+        delivered_at = CASE WHEN $1 = 1 THEN {now} END
+    WHERE id = $5
+    RETURNING *"#,
+        now = pool.now()
+    );
+    with_pool!(pool, db => {
+        query_as(&sql)
+            .bind(status)
+            .bind(response_status)
+            .bind(error)
+            .bind(next_attempt_at)
+            .bind(id)
+            .fetch_one(db)
+            .await
+    })
+}
+
+/// newest first
+#[tracing::instrument(skip_all, fields(otel.kind = "client", db.system = pool.system()))]
+pub async fn get_deliveries(
+    pool: &DbPool,
+    webhook_id: i32,
+    pagination: &PaginationQuery,
+) -> Result<PaginationResponse<WebhookDelivery>, sqlx::Error> {
+    let count_sql = "SELECT COUNT(*) FROM webhook_delivery WHERE webhook_id = $1";
+    let total: i64 =
+        with_pool!(pool, db => query_scalar(count_sql).bind(webhook_id).fetch_one(db).await)?;
+    let sql = format!(
+        "SELECT * FROM webhook_delivery WHERE webhook_id = $1 ORDER BY id DESC LIMIT {} OFFSET {}",
+        pagination.limit(),
+        pagination.offset()
+    );
+    let deliveries =
+        with_pool!(pool, db => query_as(&sql).bind(webhook_id).fetch_all(db).await)?;
+    Ok(PaginationResponse {
+        data: deliveries,
+        total: total as u64,
+    })
+}
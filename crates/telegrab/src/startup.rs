@@ -1,12 +1,16 @@
 use crate::{
     Result,
     configuration::Settings,
-    controller::{assets, cbz, doc, health_check, pic, task, gallery},
+    controller::{admin, assets, audit, cbz, disk, doc, feed, gc, health_check, library, metrics, pic, schedule, task, webhook, gallery},
     errors::Error::ListenerError,
     listener,
-    middleware::{TeleGrabRequestId, request_id_middleware},
+    middleware::{
+        TeleGrabRequestId, audit_middleware, auth_middleware, metrics_middleware,
+        request_id_middleware,
+    },
     shutdown_signal::shutdown_signal,
     state::AppState,
+    telemetry,
 };
 use axum::{Router, http, routing::get};
 use tower_http::trace::TraceLayer;
@@ -16,15 +20,26 @@ pub fn app(state: AppState) -> Router {
         .nest("/resource", assets::routers(&state))
         .nest("/graphql", gallery::routers(&state))
         .route("/api/health", get(health_check::health))
+        .route("/api/health/live", get(health_check::live))
+        .route("/api/health/ready", get(health_check::ready))
+        .route("/metrics", get(metrics::metrics_handler))
         .nest("/api/doc", doc::routers())
         .nest("/api/pic", pic::routers())
         .nest("/api/cbz", cbz::routers())
         .nest("/api/task", task::routers())
+        .nest("/api/feed", feed::routers())
+        .nest("/api/schedule", schedule::routers())
+        .nest("/api/library", library::routers())
+        .nest("/api/disk", disk::routers())
+        .nest("/api/admin/gc", gc::routers())
+        .nest("/api/admin/audit", audit::routers())
+        .nest("/api/admin/webhook", webhook::routers())
+        .nest("/api/admin", admin::routers())
         .with_state(state)
 }
 
 pub async fn run_app_until_stopped(state: AppState, configuration: Settings) -> Result<()> {
-    let app = register_layer(app(state.clone()), &configuration).await;
+    let app = register_layer(app(state.clone()), &state, &configuration).await;
 
     let listener_handles =
         listener::start_listeners(app, &configuration, state.shutdown.clone()).await?;
@@ -54,8 +69,18 @@ pub async fn run_app_until_stopped(state: AppState, configuration: Settings) ->
         }
     }
 }
-pub async fn register_layer(app: Router, _configuration: &Settings) -> Router {
-    app.layer(
+pub async fn register_layer(app: Router, state: &AppState, _configuration: &Settings) -> Router {
+    // the audit runs inside the auth, which puts the identity in the request
+    app.layer(axum::middleware::from_fn_with_state(
+        state.clone(),
+        audit_middleware,
+    ))
+    .layer(axum::middleware::from_fn_with_state(
+        state.clone(),
+        auth_middleware,
+    ))
+    .layer(axum::middleware::from_fn(metrics_middleware))
+    .layer(
         TraceLayer::new_for_http().make_span_with(|request: &http::Request<_>| {
             let ext = request.extensions();
             let request_id = ext
@@ -66,14 +91,16 @@ pub async fn register_layer(app: Router, _configuration: &Settings) -> Router {
                 .get(axum::http::header::USER_AGENT)
                 .map_or("", |h| h.to_str().unwrap_or(""));
 
-            tracing::error_span!(
+            let span = tracing::error_span!(
                 "http-request",
                 "http.method" = tracing::field::display(request.method()),
                 "http.uri" = tracing::field::display(request.uri()),
                 "http.version" = tracing::field::debug(request.version()),
                 "http.user_agent" = tracing::field::display(user_agent),
                 request_id = tracing::field::display(request_id),
-            )
+            );
+            telemetry::set_parent_from_headers(&span, request.headers());
+            span
         }),
     )
     .layer(axum::middleware::from_fn(request_id_middleware))
@@ -1,21 +1,46 @@
-use crate::configuration::Settings;
+use crate::configuration::{DiskSettings, HealthSettings, Settings, WebhookSettings};
+use crate::db::DbPool;
 use crate::graceful::GracefulShutdown;
 use crate::http_client::HttpClientManager;
-use crate::model::entity::task::{ActiveTaskInfo, QueueEvent, Task, TaskStatus, TaskType};
-use sqlx_postgres::{PgPool, PgPoolOptions};
+use crate::migration;
+use crate::model::entity::disk::DiskUsage;
+use crate::model::entity::event::DomainEvent;
+use crate::model::entity::task::{
+    ActiveTaskInfo, NumberedEvent, QueueEvent, QueueSnapshot, Task, TaskStatus, TaskType,
+};
+use crate::storage::Storages;
+use crate::telemetry;
+use crate::webhook::WebhookCache;
 use std::collections::{HashMap, VecDeque};
-use std::sync::Arc;
+use std::sync::{Arc, Mutex as StdMutex};
 use std::time::Duration;
 use time::OffsetDateTime;
+use tokio::sync::broadcast::error::SendError;
 use tokio::sync::{broadcast, Mutex, Notify, RwLock};
 
+/// events kept by the journal, a client further behind gets a snapshot instead
+pub const EVENT_JOURNAL_CAPACITY: usize = 1024;
+
+#[derive(Debug, Default)]
+pub struct EventJournal {
+    /// the id of the latest event, `0` before the first one
+    last_id: u64,
+    events: VecDeque<NumberedEvent>,
+}
+
 #[derive(Debug, Clone)]
 pub struct QueueState {
     pub tasks: Arc<RwLock<VecDeque<Task>>>,
     pub active_tasks: Arc<RwLock<HashMap<String, ActiveTaskInfo>>>,
     pub task_store: Arc<RwLock<HashMap<String, Task>>>,
-    pub sender: broadcast::Sender<QueueEvent>,
+    /// every event goes through [`QueueState::send`], which numbers it
+    pub sender: broadcast::Sender<NumberedEvent>,
+    /// the latest events, for clients catching up after a reconnect
+    pub journal: Arc<StdMutex<EventJournal>>,
     pub notify: Arc<Notify>,
+    pub disk_usage: Arc<RwLock<Option<DiskUsage>>>,
+    /// when each worker last polled the queue, by worker id
+    pub heartbeats: Arc<RwLock<HashMap<usize, OffsetDateTime>>>,
 }
 
 impl Default for QueueState {
@@ -32,7 +57,64 @@ impl QueueState {
             active_tasks: Arc::new(RwLock::new(HashMap::new())),
             task_store: Arc::new(RwLock::new(HashMap::new())),
             sender,
+            journal: Arc::new(StdMutex::new(EventJournal::default())),
             notify: Arc::new(Notify::new()),
+            disk_usage: Arc::new(RwLock::new(None)),
+            heartbeats: Arc::new(RwLock::new(HashMap::new())),
+        }
+    }
+    /// Numbers the event, keeps it in the journal and sends it to the subscribers.
+    pub fn send(&self, event: QueueEvent) -> Result<usize, Box<SendError<NumberedEvent>>> {
+        // held while sending, subscribers see the ids in order
+        let mut journal = self.journal.lock().unwrap();
+        journal.last_id += 1;
+        let numbered = NumberedEvent {
+            id: journal.last_id,
+            event,
+        };
+        if journal.events.len() == EVENT_JOURNAL_CAPACITY {
+            journal.events.pop_front();
+        }
+        journal.events.push_back(numbered.clone());
+        self.sender.send(numbered).map_err(Box::new)
+    }
+    /// A receiver of the events after the returned id, no event falls between the two.
+    pub fn subscribe(&self) -> (u64, broadcast::Receiver<NumberedEvent>) {
+        let journal = self.journal.lock().unwrap();
+        (journal.last_id, self.sender.subscribe())
+    }
+    pub fn last_event_id(&self) -> u64 {
+        self.journal.lock().unwrap().last_id
+    }
+    /// The events after `last_id`, `None` when some of them are no longer kept
+    /// or the id was never handed out (by an earlier run).
+    pub fn events_since(&self, last_id: u64) -> Option<Vec<NumberedEvent>> {
+        let journal = self.journal.lock().unwrap();
+        if last_id > journal.last_id {
+            return None;
+        }
+        let oldest = journal.events.front().map_or(journal.last_id + 1, |e| e.id);
+        if last_id + 1 < oldest {
+            return None;
+        }
+        Some(
+            journal
+                .events
+                .iter()
+                .filter(|e| e.id > last_id)
+                .cloned()
+                .collect(),
+        )
+    }
+    /// events up to `last_event_id` are part of it, ones after it may be too
+    pub async fn snapshot(&self) -> QueueSnapshot {
+        let last_event_id = self.last_event_id();
+        let queued = self.tasks.read().await.iter().cloned().collect();
+        QueueSnapshot {
+            last_event_id,
+            queued,
+            active: self.get_active_tasks().await,
+            disk_usage: self.get_disk_usage().await,
         }
     }
     pub async fn register_active_task(&self, task: &Task, worker_id: usize) {
@@ -65,7 +147,6 @@ impl QueueState {
             let diff = (OffsetDateTime::now_utc() - active_task.started_at).whole_milliseconds();
             active_task.duration_secs = diff as f64 / 1000.0;
             if let Err(e) = self
-                .sender
                 .send(QueueEvent::TaskProgress(task_id.to_string(), progress))
             {
                 tracing::warn!("send task progress event failed: {:?}", e);
@@ -142,6 +223,46 @@ impl QueueState {
             .values()
             .any(|t| matches!(t.task_type, TaskType::HtmlParseAll))
     }
+    /// queued or running
+    pub async fn is_task_pending(&self, task_type: &TaskType) -> bool {
+        let tasks = self.tasks.read().await;
+        let active_tasks = self.active_tasks.read().await;
+        tasks
+            .iter()
+            .map(|t| &t.task_type)
+            .chain(active_tasks.values().map(|t| &t.task_type))
+            .any(|t| t == task_type)
+    }
+    pub async fn is_merge_duplicates_pending(&self) -> bool {
+        self.is_task_pending(&TaskType::MergeDuplicateDocs).await
+    }
+    pub async fn is_doc_refresh_pending(&self, doc_id: i32) -> bool {
+        let tasks = self.tasks.read().await;
+        let active_tasks = self.active_tasks.read().await;
+        tasks
+            .iter()
+            .map(|t| &t.task_type)
+            .chain(active_tasks.values().map(|t| &t.task_type))
+            .any(|t| matches!(t, TaskType::RefreshDoc { id, .. } if *id == doc_id))
+    }
+    /// collecting and applying both list the pic storage, only one of them runs at a time
+    pub async fn is_gc_pending(&self) -> bool {
+        let tasks = self.tasks.read().await;
+        let active_tasks = self.active_tasks.read().await;
+        tasks
+            .iter()
+            .map(|t| &t.task_type)
+            .chain(active_tasks.values().map(|t| &t.task_type))
+            .any(|t| {
+                matches!(
+                    t,
+                    TaskType::CollectGarbage { .. } | TaskType::ApplyGcReport { .. }
+                )
+            })
+    }
+    pub async fn is_feed_poll_pending(&self, feed_id: i32) -> bool {
+        self.is_task_pending(&TaskType::PollFeed { id: feed_id }).await
+    }
     pub async fn size(&self) -> usize {
         let tasks = self.tasks.read().await;
         tasks.len()
@@ -157,30 +278,42 @@ impl QueueState {
     pub async fn update_task(&self, updated_task: Task) -> bool {
         let mut task_store = self.task_store.write().await;
         task_store.insert(updated_task.id.clone(), updated_task.clone());
-        if let Err(e) = self.sender.send(QueueEvent::TaskUpdated(updated_task)) {
+        if let Err(e) = self.send(QueueEvent::TaskUpdated(updated_task)) {
             tracing::warn!("send task updated event failed: {:?}", e);
         }
         true
     }
-    pub async fn enqueue(&self, task: Task) {
+    pub async fn enqueue(&self, mut task: Task) {
+        if task.trace_context.is_none() {
+            task.trace_context = telemetry::current_trace_context();
+        }
         let task_clone = task.clone();
         let mut tasks = self.tasks.write().await;
         tasks.push_back(task.clone());
         let mut task_store = self.task_store.write().await;
         task_store.insert(task.id.clone(), task);
         self.notify.notify_one();
-        if let Err(e) = self.sender.send(QueueEvent::TaskAdded(task_clone)) {
+        if let Err(e) = self.send(QueueEvent::TaskAdded(task_clone)) {
             tracing::warn!("send task enqueued event failed: {:?}", e);
         }
     }
+    /// the first task not held back by a disk space pause
     pub async fn dequeue(&self) -> Option<Task> {
         let mut tasks = self.tasks.write().await;
-        tasks.pop_front()
+        let disk_usage = self.disk_usage.read().await;
+        let idx = tasks
+            .iter()
+            .position(|t| !is_paused(&t.task_type, disk_usage.as_ref()))?;
+        tasks.remove(idx)
     }
     pub async fn wait_for_task(&self, timeout: Option<Duration>) -> bool {
         {
             let tasks = self.tasks.read().await;
-            if !tasks.is_empty() {
+            let disk_usage = self.disk_usage.read().await;
+            if tasks
+                .iter()
+                .any(|t| !is_paused(&t.task_type, disk_usage.as_ref()))
+            {
                 return true;
             }
         }
@@ -197,11 +330,66 @@ impl QueueState {
             }
         }
     }
+    pub async fn heartbeat(&self, worker_id: usize) {
+        let mut heartbeats = self.heartbeats.write().await;
+        heartbeats.insert(worker_id, OffsetDateTime::now_utc());
+    }
+    /// a stopped worker is gone from the heartbeats
+    pub async fn remove_heartbeat(&self, worker_id: usize) {
+        let mut heartbeats = self.heartbeats.write().await;
+        heartbeats.remove(&worker_id);
+    }
+    pub async fn get_heartbeats(&self) -> HashMap<usize, OffsetDateTime> {
+        self.heartbeats.read().await.clone()
+    }
+    pub async fn get_disk_usage(&self) -> Option<DiskUsage> {
+        self.disk_usage.read().await.clone()
+    }
+    pub async fn downloads_paused(&self) -> bool {
+        let disk_usage = self.disk_usage.read().await;
+        disk_usage
+            .as_ref()
+            .is_some_and(|u| u.downloads_paused.is_some())
+    }
+    /// stores the latest check and wakes the workers when held back tasks may run again
+    pub async fn set_disk_usage(&self, usage: DiskUsage) {
+        let mut disk_usage = self.disk_usage.write().await;
+        let (was_downloads, was_archives) = disk_usage
+            .as_ref()
+            .map(|u| (u.downloads_paused.is_some(), u.archives_paused.is_some()))
+            .unwrap_or_default();
+        for (kind, was, reason) in [
+            ("Downloads", was_downloads, &usage.downloads_paused),
+            ("Archives", was_archives, &usage.archives_paused),
+        ] {
+            match (was, reason) {
+                (false, Some(reason)) => tracing::warn!("{} paused: {}", kind, reason),
+                (true, None) => tracing::info!("{} resumed", kind),
+                _ => {}
+            }
+        }
+        let resumed = (was_downloads && usage.downloads_paused.is_none())
+            || (was_archives && usage.archives_paused.is_none());
+        *disk_usage = Some(usage.clone());
+        drop(disk_usage);
+        if resumed {
+            self.notify.notify_waiters();
+        }
+        if let Err(e) = self.send(QueueEvent::DiskUsage(usage)) {
+            tracing::debug!("send disk usage event failed: {:?}", e);
+        }
+    }
+    /// nobody listens outside `serve`, a failed send is not worth a warning
+    pub fn publish(&self, event: DomainEvent) {
+        if let Err(e) = self.send(QueueEvent::Domain(event)) {
+            tracing::debug!("send domain event failed: {:?}", e);
+        }
+    }
     pub async fn clear(&self) -> Vec<Task> {
         let mut tasks = self.tasks.write().await;
         let cleared: Vec<Task> = tasks.drain(..).collect();
         if !cleared.is_empty()
-            && let Err(e) = self.sender.send(QueueEvent::QueueCleared)
+            && let Err(e) = self.send(QueueEvent::QueueCleared)
         {
             tracing::warn!("send tasks cleared event failed: {:?}", e);
         }
@@ -232,26 +420,37 @@ impl QueueState {
     }
 }
 
+fn is_paused(task_type: &TaskType, disk_usage: Option<&DiskUsage>) -> bool {
+    disk_usage.is_some_and(|u| {
+        (u.downloads_paused.is_some() && task_type.writes_pics())
+            || (u.archives_paused.is_some() && task_type.writes_cbz())
+    })
+}
+
 #[derive(Debug, Clone)]
 pub struct AppState {
     pub queue_state: Arc<QueueState>,
     pub fs_watcher: Arc<Mutex<Option<notify::RecommendedWatcher>>>,
     pub shutdown: Arc<GracefulShutdown>,
-    pub db_pool: Arc<PgPool>,
+    pub db_pool: Arc<DbPool>,
     pub http_client: Arc<HttpClientManager>,
     pub base_url: String,
+    pub auth_enabled: bool,
     pub worker_count: usize,
-    pub pic_dir: String,
-    pub cbz_dir: String,
+    pub storage: Arc<Storages>,
+    pub disk: DiskSettings,
+    pub health: HealthSettings,
+    pub webhook: WebhookSettings,
+    /// dropped by every change of a webhook
+    pub webhooks: Arc<WebhookCache>,
 }
 
 impl AppState {
     pub async fn build(configuration: &Settings) -> Self {
         let queue_state = Arc::new(QueueState::new());
         let db_pool = Arc::new(
-            PgPoolOptions::new()
-                .acquire_timeout(Duration::from_secs(2))
-                .connect_lazy_with(configuration.database.with_db()),
+            DbPool::connect_lazy(&configuration.database, Duration::from_secs(2))
+                .expect("Invalid database settings"),
         );
         let shutdown = Arc::new(GracefulShutdown::new());
 
@@ -259,12 +458,10 @@ impl AppState {
             configuration.http_client.clone(),
         )));
 
-        {
-            //db migration
-            /*sqlx::migrate!()
-            .run(&*db_pool)
-            .await
-            .expect("Could not run database migrations.");*/
+        if configuration.database.auto_migrate {
+            migration::up(&db_pool)
+                .await
+                .expect("Could not run database migrations.");
         }
 
         Self {
@@ -274,9 +471,13 @@ impl AppState {
             db_pool,
             http_client,
             base_url: configuration.application.base_url.clone(),
+            auth_enabled: configuration.auth.enabled,
             worker_count: configuration.worker.count,
-            pic_dir: configuration.pic_dir.clone(),
-            cbz_dir: configuration.cbz_dir.clone(),
+            storage: Arc::new(Storages::build(configuration).expect("Invalid storage settings")),
+            disk: configuration.disk.clone(),
+            health: configuration.health.clone(),
+            webhook: configuration.webhook.clone(),
+            webhooks: Arc::new(WebhookCache::default()),
         }
     }
 }
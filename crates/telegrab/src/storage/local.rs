@@ -0,0 +1,185 @@
+use super::{ByteStream, Capacity, Storage, StorageObject};
+use crate::{Error, Result};
+use async_trait::async_trait;
+use bytes::Bytes;
+use futures::StreamExt;
+use std::fmt::{self, Display};
+use std::io::ErrorKind;
+use std::path::{Component, Path, PathBuf};
+use std::time::Duration;
+use tokio_util::io::ReaderStream;
+use url::Url;
+use uuid::Uuid;
+
+/// files below a dir, keys are their paths relative to it
+#[derive(Debug, Clone)]
+pub struct LocalStorage {
+    root: PathBuf,
+}
+
+impl LocalStorage {
+    pub fn new<P: AsRef<Path>>(root: P) -> Self {
+        Self {
+            root: root.as_ref().to_path_buf(),
+        }
+    }
+    /// keys come from urls too, nothing may point outside of the root
+    fn path(&self, key: &str) -> Result<PathBuf> {
+        let relative = Path::new(key.trim_start_matches('/'));
+        if relative
+            .components()
+            .any(|c| !matches!(c, Component::Normal(_)))
+        {
+            return Err(Error::BadRequest(format!("Invalid storage key: {}", key)));
+        }
+        Ok(self.root.join(relative))
+    }
+    /// empty dirs left behind by a delete, up to the root
+    async fn remove_empty_parents(&self, path: &Path) {
+        let mut dir = path.parent();
+        while let Some(d) = dir {
+            if d == self.root || !d.starts_with(&self.root) {
+                break;
+            }
+            if tokio::fs::remove_dir(d).await.is_err() {
+                break;
+            }
+            dir = d.parent();
+        }
+    }
+}
+
+impl Display for LocalStorage {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.root.display())
+    }
+}
+
+fn list_recursive(root: &Path, dir: &Path, objects: &mut Vec<StorageObject>) -> std::io::Result<()> {
+    for entry in std::fs::read_dir(dir)? {
+        let entry = entry?;
+        // temporary files of a put in progress
+        if entry.file_name().to_string_lossy().starts_with('.') {
+            continue;
+        }
+        let path = entry.path();
+        let meta = entry.metadata()?;
+        if meta.is_dir() {
+            list_recursive(root, &path, objects)?;
+        } else if meta.is_file()
+            && let Ok(relative) = path.strip_prefix(root)
+        {
+            let key = relative
+                .components()
+                .map(|c| c.as_os_str().to_string_lossy())
+                .collect::<Vec<_>>()
+                .join("/");
+            objects.push(StorageObject {
+                key,
+                size: meta.len(),
+            });
+        }
+    }
+    Ok(())
+}
+
+#[async_trait]
+impl Storage for LocalStorage {
+    async fn put(&self, key: &str, bytes: Bytes) -> Result<()> {
+        let path = self.path(key)?;
+        if let Some(parent) = path.parent() {
+            tokio::fs::create_dir_all(parent).await?;
+        }
+        // a dot file is skipped by scans while it is written
+        let tmp = path.with_file_name(format!(".{}.tmp", Uuid::new_v4()));
+        if let Err(err) = tokio::fs::write(&tmp, &bytes).await {
+            let _ = tokio::fs::remove_file(&tmp).await;
+            return Err(err.into());
+        }
+        tokio::fs::rename(&tmp, &path).await?;
+        Ok(())
+    }
+    async fn put_file(&self, key: &str, source: &Path) -> Result<()> {
+        let path = self.path(key)?;
+        if let Some(parent) = path.parent() {
+            tokio::fs::create_dir_all(parent).await?;
+        }
+        if tokio::fs::rename(source, &path).await.is_err() {
+            // another file system
+            let tmp = path.with_file_name(format!(".{}.tmp", Uuid::new_v4()));
+            if let Err(err) = tokio::fs::copy(source, &tmp).await {
+                let _ = tokio::fs::remove_file(&tmp).await;
+                return Err(err.into());
+            }
+            tokio::fs::rename(&tmp, &path).await?;
+            tokio::fs::remove_file(source).await?;
+        }
+        Ok(())
+    }
+    async fn get(&self, key: &str) -> Result<Bytes> {
+        let bytes = tokio::fs::read(self.path(key)?).await?;
+        Ok(Bytes::from(bytes))
+    }
+    async fn stream(&self, key: &str) -> Result<ByteStream> {
+        let file = tokio::fs::File::open(self.path(key)?).await?;
+        Ok(ReaderStream::new(file).map(|r| r.map_err(Error::from)).boxed())
+    }
+    async fn head(&self, key: &str) -> Result<Option<StorageObject>> {
+        match tokio::fs::metadata(self.path(key)?).await {
+            Ok(meta) if meta.is_file() => Ok(Some(StorageObject {
+                key: key.to_string(),
+                size: meta.len(),
+            })),
+            Ok(_) => Ok(None),
+            Err(err) if err.kind() == ErrorKind::NotFound => Ok(None),
+            Err(err) => Err(err.into()),
+        }
+    }
+    async fn list(&self, prefix: &str) -> Result<Vec<StorageObject>> {
+        let dir = self.path(prefix)?;
+        let root = self.root.clone();
+        let objects = tokio::task::spawn_blocking(move || {
+            let mut objects = vec![];
+            if dir.is_dir() {
+                list_recursive(&root, &dir, &mut objects)?;
+            }
+            objects.sort_by(|a, b| a.key.cmp(&b.key));
+            Ok::<_, std::io::Error>(objects)
+        })
+        .await??;
+        Ok(objects)
+    }
+    async fn delete(&self, key: &str) -> Result<()> {
+        let path = self.path(key)?;
+        match tokio::fs::remove_file(&path).await {
+            Ok(()) => {}
+            Err(err) if err.kind() == ErrorKind::NotFound => {}
+            Err(err) => return Err(err.into()),
+        }
+        self.remove_empty_parents(&path).await;
+        Ok(())
+    }
+    async fn rename_prefix(&self, from: &str, to: &str) -> Result<()> {
+        let (from, to) = (self.path(from)?, self.path(to)?);
+        if let Some(parent) = to.parent() {
+            tokio::fs::create_dir_all(parent).await?;
+        }
+        tokio::fs::rename(from, to).await?;
+        Ok(())
+    }
+    async fn presign(&self, _key: &str, _expires_in: Duration) -> Result<Option<Url>> {
+        Ok(None)
+    }
+    async fn capacity(&self) -> Result<Option<Capacity>> {
+        tokio::fs::create_dir_all(&self.root).await?;
+        let root = self.root.clone();
+        let stats = tokio::task::spawn_blocking(move || fs4::statvfs(root)).await??;
+        Ok(Some(Capacity {
+            available: stats.available_space(),
+            total: stats.total_space(),
+        }))
+    }
+    fn local_path(&self, key: &str) -> Option<PathBuf> {
+        self.path(key).ok()
+    }
+}
@@ -0,0 +1,129 @@
+mod local;
+mod s3;
+
+pub use local::LocalStorage;
+pub use s3::S3Storage;
+
+use crate::configuration::{Settings, StorageBackend};
+use crate::{Error, Result};
+use async_trait::async_trait;
+use bytes::Bytes;
+use futures::stream::BoxStream;
+use std::fmt::{Debug, Display};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::Duration;
+use url::Url;
+
+pub type ByteStream = BoxStream<'static, Result<Bytes>>;
+
+#[derive(Debug, Clone)]
+pub struct StorageObject {
+    pub key: String,
+    pub size: u64,
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct Capacity {
+    pub available: u64,
+    pub total: u64,
+}
+
+/// Where pics, archives and quarantined files live. Keys are `/` separated
+/// and relative to the root of the storage, e.g. `Some-Page-01-01/001.jpg`.
+#[async_trait]
+pub trait Storage: Debug + Display + Send + Sync {
+    /// replaces an existing key, readers never see a partly written object
+    async fn put(&self, key: &str, bytes: Bytes) -> Result<()>;
+    /// uploads a local file and removes it, archives are built on local disk first
+    async fn put_file(&self, key: &str, path: &Path) -> Result<()>;
+    async fn get(&self, key: &str) -> Result<Bytes>;
+    async fn stream(&self, key: &str) -> Result<ByteStream>;
+    /// `None` when there is no such key
+    async fn head(&self, key: &str) -> Result<Option<StorageObject>>;
+    /// every key below `prefix`, recursively; `""` lists everything
+    async fn list(&self, prefix: &str) -> Result<Vec<StorageObject>>;
+    /// a key that is already gone is not an error
+    async fn delete(&self, key: &str) -> Result<()>;
+    /// moves every key below `from` to below `to`
+    async fn rename_prefix(&self, from: &str, to: &str) -> Result<()>;
+    /// a url that serves the key without telegrab, `None` when the backend can not sign
+    async fn presign(&self, key: &str, expires_in: Duration) -> Result<Option<Url>>;
+    /// `None` when the backend has no fixed size
+    async fn capacity(&self) -> Result<Option<Capacity>>;
+    /// the file behind a key, only on local backends
+    fn local_path(&self, key: &str) -> Option<PathBuf>;
+}
+
+/// one storage for each kind of file, on local backends those are the configured dirs
+#[derive(Debug, Clone)]
+pub struct Storages {
+    pub pic: Arc<dyn Storage>,
+    pub cbz: Arc<dyn Storage>,
+    pub quarantine: Arc<dyn Storage>,
+    /// how long presigned `/resource` urls are valid, `None` serves everything through telegrab
+    pub presign_expiry: Option<Duration>,
+}
+
+impl Storages {
+    pub fn build(configuration: &Settings) -> Result<Self> {
+        match configuration.storage.backend {
+            StorageBackend::Local => Ok(Self {
+                pic: Arc::new(LocalStorage::new(&configuration.pic_dir)),
+                cbz: Arc::new(LocalStorage::new(&configuration.cbz_dir)),
+                quarantine: Arc::new(LocalStorage::new(&configuration.quarantine_dir)),
+                presign_expiry: None,
+            }),
+            StorageBackend::S3 => {
+                let settings = configuration.storage.s3.as_ref().ok_or_else(|| {
+                    Error::Message("storage.s3 is required for the s3 backend".to_string())
+                })?;
+                let store = s3::build_store(settings)?;
+                Ok(Self {
+                    pic: Arc::new(S3Storage::new(
+                        store.clone(),
+                        &settings.bucket,
+                        &settings.pic_prefix,
+                    )),
+                    cbz: Arc::new(S3Storage::new(
+                        store.clone(),
+                        &settings.bucket,
+                        &settings.cbz_prefix,
+                    )),
+                    quarantine: Arc::new(S3Storage::new(
+                        store,
+                        &settings.bucket,
+                        &settings.quarantine_prefix,
+                    )),
+                    presign_expiry: (settings.presign_expiry_secs > 0)
+                        .then(|| Duration::from_secs(settings.presign_expiry_secs)),
+                })
+            }
+        }
+    }
+}
+
+/// `a/b` for the key `b` below `a`
+pub fn join_key(prefix: &str, key: &str) -> String {
+    match (prefix.trim_matches('/'), key.trim_matches('/')) {
+        ("", key) => key.to_string(),
+        (prefix, "") => prefix.to_string(),
+        (prefix, key) => format!("{}/{}", prefix, key),
+    }
+}
+
+/// Moves a key to another storage, a rename when both are local.
+pub async fn move_key(from: &dyn Storage, key: &str, to: &dyn Storage, to_key: &str) -> Result<()> {
+    if let (Some(source), Some(target)) = (from.local_path(key), to.local_path(to_key)) {
+        if let Some(parent) = target.parent() {
+            tokio::fs::create_dir_all(parent).await?;
+        }
+        if tokio::fs::rename(&source, &target).await.is_ok() {
+            // drops the dirs the file left empty
+            return from.delete(key).await;
+        }
+    }
+    let bytes = from.get(key).await?;
+    to.put(to_key, bytes).await?;
+    from.delete(key).await
+}
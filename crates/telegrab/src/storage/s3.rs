@@ -0,0 +1,195 @@
+use super::{ByteStream, Capacity, Storage, StorageObject, join_key};
+use crate::configuration::S3Settings;
+use crate::{Error, Result};
+use async_trait::async_trait;
+use axum::http::Method;
+use bytes::Bytes;
+use futures::{StreamExt, TryStreamExt};
+use object_store::aws::{AmazonS3, AmazonS3Builder};
+use object_store::path::Path as ObjectPath;
+use object_store::signer::Signer;
+use object_store::{ObjectStore, ObjectStoreExt, PutPayload, WriteMultipart};
+use percent_encoding::percent_decode_str;
+use secrecy::ExposeSecret;
+use std::fmt::{self, Display};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::io::AsyncReadExt;
+use url::Url;
+
+/// parts of archives are uploaded in chunks of this size
+const UPLOAD_CHUNK_SIZE: usize = 8 * 1024 * 1024;
+
+pub(super) fn build_store(settings: &S3Settings) -> Result<Arc<AmazonS3>> {
+    let mut builder = AmazonS3Builder::from_env()
+        .with_bucket_name(&settings.bucket)
+        .with_region(&settings.region)
+        .with_allow_http(settings.allow_http);
+    if let Some(endpoint) = &settings.endpoint {
+        // MinIO and most other S3 compatible services expect path style requests
+        builder = builder
+            .with_endpoint(endpoint)
+            .with_virtual_hosted_style_request(false);
+    }
+    if let Some(access_key_id) = &settings.access_key_id {
+        builder = builder.with_access_key_id(access_key_id);
+    }
+    if let Some(secret_access_key) = &settings.secret_access_key {
+        builder = builder.with_secret_access_key(secret_access_key.expose_secret());
+    }
+    let store = builder
+        .build()
+        .map_err(|e| Error::Message(format!("Invalid s3 storage: {}", e)))?;
+    Ok(Arc::new(store))
+}
+
+/// objects below a prefix of a bucket, the prefix is not part of the keys
+#[derive(Debug, Clone)]
+pub struct S3Storage {
+    store: Arc<AmazonS3>,
+    bucket: String,
+    prefix: String,
+}
+
+impl S3Storage {
+    pub fn new(store: Arc<AmazonS3>, bucket: &str, prefix: &str) -> Self {
+        Self {
+            store,
+            bucket: bucket.to_string(),
+            prefix: prefix.trim_matches('/').to_string(),
+        }
+    }
+    /// parts are escaped where S3 keys need it, `key_of` reverses that
+    fn path(&self, key: &str) -> ObjectPath {
+        ObjectPath::from_iter(
+            join_key(&self.prefix, key)
+                .split('/')
+                .filter(|part| !part.is_empty()),
+        )
+    }
+    fn key_of(&self, path: &ObjectPath) -> String {
+        let parts: Vec<String> = path
+            .parts()
+            .map(|part| percent_decode_str(part.as_ref()).decode_utf8_lossy().into_owned())
+            .collect();
+        let skip = if self.prefix.is_empty() {
+            0
+        } else {
+            self.prefix.split('/').count()
+        };
+        parts[skip.min(parts.len())..].join("/")
+    }
+}
+
+impl Display for S3Storage {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "s3://{}", join_key(&self.bucket, &self.prefix))
+    }
+}
+
+fn map_err(err: object_store::Error) -> Error {
+    match err {
+        object_store::Error::NotFound { .. } => Error::NotFound,
+        err => Error::Message(format!("S3 storage: {}", err)),
+    }
+}
+
+#[async_trait]
+impl Storage for S3Storage {
+    async fn put(&self, key: &str, bytes: Bytes) -> Result<()> {
+        self.store
+            .put(&self.path(key), PutPayload::from_bytes(bytes))
+            .await
+            .map_err(map_err)?;
+        Ok(())
+    }
+    async fn put_file(&self, key: &str, source: &Path) -> Result<()> {
+        let upload = self
+            .store
+            .put_multipart(&self.path(key))
+            .await
+            .map_err(map_err)?;
+        let mut writer = WriteMultipart::new_with_chunk_size(upload, UPLOAD_CHUNK_SIZE);
+        let mut file = tokio::fs::File::open(source).await?;
+        let mut buf = vec![0; UPLOAD_CHUNK_SIZE];
+        loop {
+            let n = file.read(&mut buf).await?;
+            if n == 0 {
+                break;
+            }
+            writer.wait_for_capacity(4).await.map_err(map_err)?;
+            writer.write(&buf[..n]);
+        }
+        writer.finish().await.map_err(map_err)?;
+        tokio::fs::remove_file(source).await?;
+        Ok(())
+    }
+    async fn get(&self, key: &str) -> Result<Bytes> {
+        let result = self.store.get(&self.path(key)).await.map_err(map_err)?;
+        result.bytes().await.map_err(map_err)
+    }
+    async fn stream(&self, key: &str) -> Result<ByteStream> {
+        let result = self.store.get(&self.path(key)).await.map_err(map_err)?;
+        Ok(result.into_stream().map_err(map_err).boxed())
+    }
+    async fn head(&self, key: &str) -> Result<Option<StorageObject>> {
+        match self.store.head(&self.path(key)).await {
+            Ok(meta) => Ok(Some(StorageObject {
+                key: key.to_string(),
+                size: meta.size,
+            })),
+            Err(object_store::Error::NotFound { .. }) => Ok(None),
+            Err(err) => Err(map_err(err)),
+        }
+    }
+    async fn list(&self, prefix: &str) -> Result<Vec<StorageObject>> {
+        let prefix = self.path(prefix);
+        let mut objects: Vec<StorageObject> = self
+            .store
+            .list(Some(&prefix))
+            .map_ok(|meta| StorageObject {
+                key: self.key_of(&meta.location),
+                size: meta.size,
+            })
+            .try_collect()
+            .await
+            .map_err(map_err)?;
+        objects.sort_by(|a, b| a.key.cmp(&b.key));
+        Ok(objects)
+    }
+    async fn delete(&self, key: &str) -> Result<()> {
+        match self.store.delete(&self.path(key)).await {
+            Ok(()) | Err(object_store::Error::NotFound { .. }) => Ok(()),
+            Err(err) => Err(map_err(err)),
+        }
+    }
+    /// S3 has no rename, every object is copied and removed
+    async fn rename_prefix(&self, from: &str, to: &str) -> Result<()> {
+        let from = from.trim_matches('/');
+        for object in self.list(from).await? {
+            let rest = object.key[from.len()..].trim_start_matches('/');
+            let target = self.path(&join_key(to, rest));
+            self.store
+                .copy(&self.path(&object.key), &target)
+                .await
+                .map_err(map_err)?;
+            self.delete(&object.key).await?;
+        }
+        Ok(())
+    }
+    async fn presign(&self, key: &str, expires_in: Duration) -> Result<Option<Url>> {
+        let url = self
+            .store
+            .signed_url(Method::GET, &self.path(key), expires_in)
+            .await
+            .map_err(map_err)?;
+        Ok(Some(url))
+    }
+    async fn capacity(&self) -> Result<Option<Capacity>> {
+        Ok(None)
+    }
+    fn local_path(&self, _key: &str) -> Option<PathBuf> {
+        None
+    }
+}
@@ -1,6 +1,16 @@
+use axum::http::HeaderMap;
+use opentelemetry::propagation::{Extractor, TextMapPropagator};
+use opentelemetry::trace::TracerProvider;
+use opentelemetry_otlp::WithExportConfig;
+use opentelemetry_sdk::Resource;
+use opentelemetry_sdk::propagation::TraceContextPropagator;
+use opentelemetry_sdk::trace::{Sampler, SdkTracerProvider};
 use serde::{Deserialize, Serialize};
 use serde_variant::to_variant_name;
+use std::collections::HashMap;
+use std::sync::OnceLock;
 use tokio::task::JoinHandle;
+use tracing_opentelemetry::OpenTelemetrySpanExt;
 use tracing_subscriber::{
     EnvFilter, Layer, Registry,
     fmt::{self, MakeWriter},
@@ -8,7 +18,9 @@ use tracing_subscriber::{
     util::SubscriberInitExt,
 };
 
-use crate::configuration::LoggerSettings;
+use crate::configuration::{LoggerSettings, OtlpSettings};
+
+static TRACER_PROVIDER: OnceLock<SdkTracerProvider> = OnceLock::new();
 
 #[derive(Debug, Default, Clone, Deserialize, Serialize)]
 pub enum LogLevel {
@@ -58,6 +70,13 @@ pub fn init(logger_settings: &LoggerSettings) {
     let mut layers: Vec<Box<dyn Layer<Registry> + Sync + Send>> = Vec::new();
     let stdout_layer = init_layer(std::io::stdout, &logger_settings.format, true);
     layers.push(stdout_layer);
+    if let Some(otlp) = &logger_settings.otlp {
+        match init_otlp_layer(otlp) {
+            Ok(layer) => layers.push(layer),
+            // logging works without it, the error is printed once tracing is up
+            Err(err) => eprintln!("OTLP exporter disabled: {}", err),
+        }
+    }
     let env_filter = init_env_filter(&logger_settings.level);
     tracing_subscriber::registry()
         .with(layers)
@@ -65,6 +84,72 @@ pub fn init(logger_settings: &LoggerSettings) {
         .init();
 }
 
+fn init_otlp_layer(
+    settings: &OtlpSettings,
+) -> Result<Box<dyn Layer<Registry> + Sync + Send>, String> {
+    let exporter = opentelemetry_otlp::SpanExporter::builder()
+        .with_http()
+        .with_endpoint(&settings.endpoint)
+        .build()
+        .map_err(|e| e.to_string())?;
+    let provider = SdkTracerProvider::builder()
+        .with_batch_exporter(exporter)
+        // a sampled parent, e.g. from a traceparent header, keeps its children
+        .with_sampler(Sampler::ParentBased(Box::new(Sampler::TraceIdRatioBased(
+            settings.sample_ratio,
+        ))))
+        .with_resource(
+            Resource::builder()
+                .with_service_name(settings.service_name.clone())
+                .build(),
+        )
+        .build();
+    let tracer = provider.tracer("telegrab");
+    opentelemetry::global::set_text_map_propagator(TraceContextPropagator::new());
+    opentelemetry::global::set_tracer_provider(provider.clone());
+    let _ = TRACER_PROVIDER.set(provider);
+    Ok(tracing_opentelemetry::layer().with_tracer(tracer).boxed())
+}
+
+/// exports the spans that are still buffered, call before the process exits
+pub fn shutdown() {
+    if let Some(provider) = TRACER_PROVIDER.get()
+        && let Err(err) = provider.shutdown()
+    {
+        eprintln!("OTLP exporter shutdown failed: {}", err);
+    }
+}
+
+/// `traceparent` and `tracestate` of the current span, `None` when nothing is exported
+pub fn current_trace_context() -> Option<HashMap<String, String>> {
+    let mut carrier = HashMap::new();
+    TraceContextPropagator::new().inject_context(&tracing::Span::current().context(), &mut carrier);
+    (!carrier.is_empty()).then_some(carrier)
+}
+
+/// continues the trace of a carrier from `current_trace_context`
+pub fn set_parent(span: &tracing::Span, carrier: &HashMap<String, String>) {
+    let parent = TraceContextPropagator::new().extract(carrier);
+    let _ = span.set_parent(parent);
+}
+
+struct HeaderExtractor<'a>(&'a HeaderMap);
+
+impl Extractor for HeaderExtractor<'_> {
+    fn get(&self, key: &str) -> Option<&str> {
+        self.0.get(key).and_then(|v| v.to_str().ok())
+    }
+    fn keys(&self) -> Vec<&str> {
+        self.0.keys().map(|k| k.as_str()).collect()
+    }
+}
+
+/// continues the trace of an incoming W3C `traceparent` header
+pub fn set_parent_from_headers(span: &tracing::Span, headers: &HeaderMap) {
+    let parent = TraceContextPropagator::new().extract(&HeaderExtractor(headers));
+    let _ = span.set_parent(parent);
+}
+
 fn init_env_filter(level: &LogLevel) -> EnvFilter {
     EnvFilter::try_from_default_env()
         .or_else(|_| {
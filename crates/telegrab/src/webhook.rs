@@ -0,0 +1,411 @@
+use crate::Result;
+use crate::configuration::{Settings, WebhookSettings};
+use crate::db::DbPool;
+use crate::model::entity::event::DomainEvent;
+use crate::model::entity::task::{NumberedEvent, QueueEvent, TaskStatus};
+use crate::model::entity::webhook::{Webhook, WebhookDelivery, WebhookDeliveryStatus};
+use crate::service;
+use crate::state::AppState;
+use hmac::{Hmac, Mac};
+use reqwest::Client;
+use reqwest::header::CONTENT_TYPE;
+use serde_json::{Value, json};
+use serde_variant::to_variant_name;
+use sha2::Sha256;
+use std::collections::BTreeMap;
+use std::sync::Arc;
+use std::time::Duration;
+use time::OffsetDateTime;
+use time::format_description::well_known::Rfc3339;
+use tokio::sync::{Notify, RwLock};
+use tokio::sync::broadcast::error::RecvError;
+
+/// `sha256=<hex hmac of the body>`, keyed with the secret of the webhook
+pub const SIGNATURE_HEADER: &str = "X-Telegrab-Signature-256";
+pub const EVENT_HEADER: &str = "X-Telegrab-Event";
+/// the id of the delivery, the same on every attempt
+pub const DELIVERY_HEADER: &str = "X-Telegrab-Delivery";
+
+/// due deliveries taken per check
+const DELIVERY_BATCH: i64 = 100;
+/// the delivery recording events the dispatcher fell too far behind on
+pub const MISSED_EVENT: &str = "events.missed";
+/// no retry is put off for longer than a week, whatever `retry_max_secs` says
+const MAX_RETRY_DELAY_SECS: u64 = 7 * 24 * 60 * 60;
+
+/// The webhook event name and data of a queue event, `None` for the ones
+/// webhooks do not get (progress, removals from the queue, disk usage, edits).
+pub fn webhook_event(event: &QueueEvent) -> Option<(String, Value)> {
+    match event {
+        QueueEvent::TaskAdded(task) => Some(("task.added".to_string(), json!(task))),
+        QueueEvent::TaskUpdated(task) => match task.status {
+            TaskStatus::Completed => Some(("task.completed".to_string(), json!(task))),
+            TaskStatus::Failed => Some(("task.failed".to_string(), json!(task))),
+            TaskStatus::Pending | TaskStatus::Processing => None,
+        },
+        QueueEvent::QueueCleared => Some(("queue.cleared".to_string(), json!({}))),
+        QueueEvent::Domain(DomainEvent::DocStatusChanged { id, from, to }) => Some((
+            format!("doc.{}", to_variant_name(to).ok()?),
+            json!({ "id": id, "from": from, "to": to }),
+        )),
+        QueueEvent::Domain(DomainEvent::CbzCreated { id, doc_id, path }) => Some((
+            "cbz.created".to_string(),
+            json!({ "id": id, "docId": doc_id, "path": path }),
+        )),
+        QueueEvent::Domain(DomainEvent::CbzRemoved { id, doc_id, path }) => Some((
+            "cbz.removed".to_string(),
+            json!({ "id": id, "docId": doc_id, "path": path }),
+        )),
+        QueueEvent::TaskRemoved(_) | QueueEvent::TaskProgress(..) | QueueEvent::DiskUsage(_) => {
+            None
+        }
+        // library changes for the ui, the status changes cover them for other tools
+        QueueEvent::Domain(
+            DomainEvent::DocAdded { .. }
+            | DomainEvent::DocRemoved { .. }
+            | DomainEvent::DocUpdated { .. }
+            | DomainEvent::CbzLinked { .. },
+        ) => None,
+    }
+}
+
+pub fn payload(event: &str, data: Value) -> Value {
+    let created_at = OffsetDateTime::now_utc().format(&Rfc3339).unwrap_or_default();
+    json!({ "event": event, "createdAt": created_at, "data": data })
+}
+
+pub fn sign(secret: &str, body: &[u8]) -> String {
+    let mut mac =
+        Hmac::<Sha256>::new_from_slice(secret.as_bytes()).expect("hmac takes keys of any length");
+    mac.update(body);
+    let hex: String = mac
+        .finalize()
+        .into_bytes()
+        .iter()
+        .map(|b| format!("{:02x}", b))
+        .collect();
+    format!("sha256={}", hex)
+}
+
+/// webhooks are called directly, without the proxy and rate limits of the page fetches
+pub fn client(settings: &WebhookSettings) -> Result<Client> {
+    Ok(Client::builder()
+        .timeout(Duration::from_secs(settings.timeout_secs))
+        .build()?)
+}
+
+/// `retry_base_secs` after the first attempt, doubled after every further one
+fn backoff(settings: &WebhookSettings, attempts: i32) -> Duration {
+    let factor = 1u64 << (attempts - 1).clamp(0, 30);
+    Duration::from_secs(
+        settings
+            .retry_base_secs
+            .saturating_mul(factor)
+            .min(settings.retry_max_secs)
+            .min(MAX_RETRY_DELAY_SECS),
+    )
+}
+
+/// Posts the payload once and records the attempt; a failed one stays pending
+/// for a retry until `max_attempts` is reached.
+pub async fn deliver(
+    pool: &DbPool,
+    client: &Client,
+    settings: &WebhookSettings,
+    webhook: &Webhook,
+    delivery: WebhookDelivery,
+) -> Result<WebhookDelivery> {
+    let body = serde_json::to_vec(&delivery.payload.0)?;
+    let signature = sign(&webhook.secret, &body);
+    let result = client
+        .post(&webhook.url)
+        .header(CONTENT_TYPE, "application/json")
+        .header(EVENT_HEADER, &delivery.event)
+        .header(DELIVERY_HEADER, delivery.id.to_string())
+        .header(SIGNATURE_HEADER, signature)
+        .body(body)
+        .send()
+        .await;
+    let (response_status, error) = match result {
+        Ok(response) if response.status().is_success() => {
+            (Some(response.status().as_u16() as i32), None)
+        }
+        Ok(response) => (
+            Some(response.status().as_u16() as i32),
+            Some(format!("HTTP {}", response.status())),
+        ),
+        Err(err) => (None, Some(err.to_string())),
+    };
+    let attempts = delivery.attempts + 1;
+    let (status, next_attempt_at) = match &error {
+        None => (WebhookDeliveryStatus::Delivered, None),
+        Some(_) if attempts >= settings.max_attempts => (WebhookDeliveryStatus::Failed, None),
+        Some(_) => match time::Duration::try_from(backoff(settings, attempts))
+            .ok()
+            .and_then(|delay| OffsetDateTime::now_utc().checked_add(delay))
+        {
+            Some(next_attempt_at) => (WebhookDeliveryStatus::Pending, Some(next_attempt_at)),
+            None => (WebhookDeliveryStatus::Failed, None),
+        },
+    };
+    if let Some(error) = &error {
+        tracing::warn!(
+            "Webhook {} delivery {} attempt {} failed: {}",
+            webhook.id,
+            delivery.id,
+            attempts,
+            error
+        );
+    }
+    let delivery = service::webhook::record_attempt(
+        pool,
+        delivery.id,
+        status,
+        response_status,
+        error,
+        next_attempt_at,
+    )
+    .await?;
+    Ok(delivery)
+}
+
+/// Sends a `ping` to the webhook right away, whatever its events and even when disabled.
+pub async fn test_fire(
+    pool: &DbPool,
+    settings: &WebhookSettings,
+    webhook: &Webhook,
+) -> Result<WebhookDelivery> {
+    let payload = payload("ping", json!({ "webhookId": webhook.id }));
+    let delivery = service::webhook::create_delivery(pool, webhook.id, "ping", &payload).await?;
+    deliver(pool, &client(settings)?, settings, webhook, delivery).await
+}
+
+/// The enabled webhooks, loaded on first use and dropped on every webhook change,
+/// so events no webhook wants do not touch the database.
+#[derive(Debug, Default)]
+pub struct WebhookCache {
+    enabled: RwLock<Option<Arc<Vec<Webhook>>>>,
+}
+
+impl WebhookCache {
+    pub async fn enabled(&self, pool: &DbPool) -> Result<Arc<Vec<Webhook>>> {
+        if let Some(webhooks) = self.enabled.read().await.as_ref() {
+            return Ok(webhooks.clone());
+        }
+        let mut enabled = self.enabled.write().await;
+        if let Some(webhooks) = enabled.as_ref() {
+            return Ok(webhooks.clone());
+        }
+        let webhooks = Arc::new(service::webhook::get_enabled_webhooks(pool).await?);
+        *enabled = Some(webhooks.clone());
+        Ok(webhooks)
+    }
+    /// the next event loads the webhooks again
+    pub async fn invalidate(&self) {
+        *self.enabled.write().await = None;
+    }
+}
+
+/// a delivery for every enabled webhook that wants the event, returns how many
+async fn enqueue_deliveries(
+    pool: &DbPool,
+    cache: &WebhookCache,
+    event: &QueueEvent,
+) -> Result<usize> {
+    let Some((name, data)) = webhook_event(event) else {
+        return Ok(0);
+    };
+    let webhooks = cache.enabled(pool).await?;
+    if !webhooks.iter().any(|w| w.wants(&name)) {
+        return Ok(0);
+    }
+    let payload = payload(&name, data);
+    let mut queued = 0;
+    for webhook in webhooks.iter().filter(|w| w.wants(&name)) {
+        service::webhook::create_delivery(pool, webhook.id, &name, &payload).await?;
+        queued += 1;
+    }
+    Ok(queued)
+}
+
+/// Events `from..=to` never became deliveries, every enabled webhook gets a failed
+/// `events.missed` delivery for them, it is not sent.
+async fn record_missed_events(
+    pool: &DbPool,
+    cache: &WebhookCache,
+    from: u64,
+    to: u64,
+) -> Result<()> {
+    tracing::warn!("Webhook dispatcher fell behind, events {}..={} missed", from, to);
+    let data = json!({ "fromEventId": from, "toEventId": to });
+    let payload = payload(MISSED_EVENT, data);
+    let error = format!("the dispatcher fell behind, events {}..={} were not delivered", from, to);
+    for webhook in cache.enabled(pool).await?.iter() {
+        service::webhook::create_missed_delivery(pool, webhook.id, MISSED_EVENT, &payload, &error)
+            .await?;
+    }
+    Ok(())
+}
+
+/// Handles the events after `last` that are still kept, and records the ones that are not.
+/// `last` ends up at the last event handled.
+async fn dispatch(
+    pool: &DbPool,
+    cache: &WebhookCache,
+    last: &mut u64,
+    numbered: &NumberedEvent,
+) -> Result<usize> {
+    if numbered.id <= *last {
+        return Ok(0);
+    }
+    if numbered.id > *last + 1 {
+        record_missed_events(pool, cache, *last + 1, numbered.id - 1).await?;
+    }
+    *last = numbered.id;
+    enqueue_deliveries(pool, cache, &numbered.event).await
+}
+
+/// webhooks are called side by side, the deliveries of one webhook in order
+async fn deliver_due(pool: &DbPool, client: &Client, settings: &WebhookSettings) -> Result<()> {
+    let mut by_webhook: BTreeMap<i32, Vec<WebhookDelivery>> = BTreeMap::new();
+    for delivery in service::webhook::get_due_deliveries(pool, DELIVERY_BATCH).await? {
+        by_webhook.entry(delivery.webhook_id).or_default().push(delivery);
+    }
+    futures::future::join_all(by_webhook.into_iter().map(|(webhook_id, deliveries)| async move {
+        let webhook = match service::webhook::get_webhook_by_id(pool, webhook_id).await {
+            Ok(webhook) => webhook,
+            Err(err) => {
+                tracing::warn!("Load webhook {} failed: {}", webhook_id, err);
+                return;
+            }
+        };
+        for delivery in deliveries {
+            let delivery_id = delivery.id;
+            if let Err(err) = deliver(pool, client, settings, &webhook, delivery).await {
+                tracing::warn!("Webhook delivery {} failed: {}", delivery_id, err);
+            }
+        }
+    }))
+    .await;
+    Ok(())
+}
+
+/// Turns queue and domain events into deliveries, and delivers them.
+/// Deliveries are stored first, pending ones are picked up again after a restart.
+pub async fn start_webhook_dispatcher(state: AppState, configuration: Settings) {
+    let settings = configuration.webhook;
+    let client = match client(&settings) {
+        Ok(client) => client,
+        Err(err) => {
+            tracing::error!("Webhook dispatcher failed to build its http client: {}", err);
+            return;
+        }
+    };
+    let notify = Arc::new(Notify::new());
+    let (mut last, mut rx) = state.queue_state.subscribe();
+    let queue_state = state.queue_state.clone();
+    let mut shutdown_rx = state.shutdown.get_shutdown_rx().await;
+    let pool = state.db_pool.clone();
+    let cache = state.webhooks.clone();
+    let wake = notify.clone();
+    tokio::spawn(async move {
+        loop {
+            tokio::select! {
+                _ = shutdown_rx.recv() => break,
+                event = rx.recv() => {
+                    // after a lag the journal may still have what the receiver dropped,
+                    // a gap it does not cover is recorded by `dispatch`
+                    let events = match event {
+                        Ok(numbered) => vec![numbered],
+                        Err(RecvError::Lagged(_)) => {
+                            queue_state.events_since(last).unwrap_or_default()
+                        }
+                        Err(RecvError::Closed) => break,
+                    };
+                    for numbered in &events {
+                        match dispatch(&pool, &cache, &mut last, numbered).await {
+                            Ok(0) => {}
+                            Ok(_) => wake.notify_one(),
+                            Err(err) => {
+                                tracing::warn!("Webhook dispatcher queue deliveries failed: {}", err)
+                            }
+                        }
+                    }
+                }
+            }
+        }
+    });
+    let check_interval = settings.check_interval_secs.max(1);
+    tokio::spawn(async move {
+        let mut shutdown_rx = state.shutdown.get_shutdown_rx().await;
+        tracing::info!("Start webhook dispatcher, check due retries in every {}s", check_interval);
+        loop {
+            tokio::select! {
+                _ = shutdown_rx.recv() => {
+                    tracing::info!("Webhook dispatcher received shutdown signal, stop.");
+                    break;
+                }
+                _ = notify.notified() => {}
+                _ = tokio::time::sleep(Duration::from_secs(check_interval)) => {}
+            }
+            if let Err(err) = deliver_due(&state.db_pool, &client, &settings).await {
+                tracing::warn!("Webhook dispatcher deliver due deliveries failed: {}", err);
+            }
+        }
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn retry_settings(retry_base_secs: u64, retry_max_secs: u64) -> WebhookSettings {
+        WebhookSettings {
+            retry_base_secs,
+            retry_max_secs,
+            ..WebhookSettings::default()
+        }
+    }
+
+    #[test]
+    fn sign_is_prefixed_lowercase_hex_hmac_sha256() {
+        // RFC 4231, test case 2
+        assert_eq!(
+            sign("Jefe", b"what do ya want for nothing?"),
+            "sha256=5bdcc146bf60754e6a042426089575c75a003f089d2739839dec58b964ec3843"
+        );
+    }
+
+    #[test]
+    fn sign_depends_on_secret_and_body() {
+        let signature = sign("a secret of sixteen", b"{}");
+        assert_ne!(signature, sign("another secret of sixteen", b"{}"));
+        assert_ne!(signature, sign("a secret of sixteen", b"{ }"));
+        assert_eq!(signature.len(), "sha256=".len() + 64);
+    }
+
+    #[test]
+    fn backoff_doubles_from_the_base() {
+        let settings = retry_settings(10, 3600);
+        let delays: Vec<u64> = (1..=5).map(|a| backoff(&settings, a).as_secs()).collect();
+        assert_eq!(delays, vec![10, 20, 40, 80, 160]);
+    }
+
+    #[test]
+    fn backoff_is_capped() {
+        let settings = retry_settings(10, 300);
+        assert_eq!(backoff(&settings, 6), Duration::from_secs(300));
+        assert_eq!(backoff(&settings, 1000), Duration::from_secs(300));
+    }
+
+    #[test]
+    fn backoff_stays_below_a_week() {
+        let huge = retry_settings(u64::MAX, u64::MAX);
+        assert_eq!(backoff(&huge, 40), Duration::from_secs(MAX_RETRY_DELAY_SECS));
+    }
+
+    #[test]
+    fn backoff_before_the_first_attempt_is_the_base() {
+        assert_eq!(backoff(&retry_settings(10, 300), 0), Duration::from_secs(10));
+    }
+}
@@ -1,31 +1,45 @@
+use crate::archive;
 use crate::configuration::Settings;
 use crate::graceful::{GracefulShutdown, TaskGuard};
-use crate::http_client::HttpClientManager;
-use crate::model::entity::doc::{ComicInfo, Doc};
-use crate::model::entity::pic::Pic;
-use crate::model::entity::task::{QueueEvent, Task, TaskStatus, TaskType};
+use crate::http_client::{HttpClientManager, PostFetch};
+use crate::feed::{parse_feed, FeedFilter};
+use crate::model::entity::doc::{ComicInfo, Doc, DocStatus};
+use crate::model::entity::feed::Feed;
+use crate::model::entity::gc::GcAction;
+use crate::model::entity::library::{IssueKind, LibraryIssue, RepairAction, RepairKind};
+use crate::model::entity::pic::{Pic, PicChanges};
+use crate::model::dto::audit::NewAuditEvent;
+use crate::model::entity::audit::{AuditOutcome, AuditSource};
+use crate::model::entity::task::{QueueEvent, Task, TaskType};
+use crate::gc;
+use crate::library;
+use crate::metrics::metrics;
 use crate::service;
 use crate::state::{AppState, QueueState};
-use crate::Result;
+use crate::telemetry;
+use crate::storage::{StorageObject, Storages, join_key};
+use crate::{Error, Result};
 use notify::event::{CreateKind, RemoveKind};
 use notify::{Event, EventKind, RecursiveMode, Watcher};
-use sqlx_postgres::PgPool;
-use std::collections::HashSet;
-use std::io::Write;
-use std::path::{Path, PathBuf};
+use crate::db::DbPool;
+use std::collections::{HashMap, HashSet};
+use std::path::Path;
 use std::sync::Arc;
-use std::time::Duration;
-use zip::write::SimpleFileOptions;
+use std::time::{Duration, Instant};
+use serde_variant::to_variant_name;
+use tracing::Instrument;
 
 #[derive(Debug, Clone)]
 pub struct TaskWorker {
     queue_state: Arc<QueueState>,
     shutdown: Arc<GracefulShutdown>,
     http_client: Arc<HttpClientManager>,
-    db_pool: Arc<PgPool>,
+    db_pool: Arc<DbPool>,
     worker_id: usize,
-    pic_dir: String,
-    cbz_dir: String,
+    /// `changed_by` of the doc status changes it makes
+    actor: String,
+    storage: Arc<Storages>,
+    max_completed_tasks: usize,
 }
 
 impl TaskWorker {
@@ -35,11 +49,16 @@ impl TaskWorker {
             shutdown: app_state.shutdown.clone(),
             http_client: app_state.http_client.clone(),
             db_pool: app_state.db_pool.clone(),
-            pic_dir: configuration.pic_dir.clone(),
-            cbz_dir: configuration.cbz_dir.clone(),
+            storage: app_state.storage.clone(),
+            max_completed_tasks: configuration.worker.max_completed_tasks,
             worker_id,
+            actor: format!("worker {}", worker_id),
         }
     }
+    pub fn with_actor(mut self, actor: &str) -> Self {
+        self.actor = actor.to_string();
+        self
+    }
     pub async fn start(&self) {
         tracing::info!("Worker {} started", self.worker_id);
         let mut shutdown_rx = self.shutdown.get_shutdown_rx().await;
@@ -55,6 +74,7 @@ impl TaskWorker {
                             tracing::info!("Worker {} is shutting down, no more waiting  for tasks", self.worker_id);
                             return;
                         }
+                        self.queue_state.heartbeat(self.worker_id).await;
                         let has_task = self.queue_state.wait_for_task(Some(Duration::from_secs(5))).await;
                         if has_task {
                             break;
@@ -86,6 +106,7 @@ impl TaskWorker {
             self.worker_id
         );
         self.wait_for_current_tasks().await;
+        self.queue_state.remove_heartbeat(self.worker_id).await;
         tracing::info!("Worker {} stopped", self.worker_id);
     }
     async fn process_queue_with_guard(&self) -> Result<Option<bool>, String> {
@@ -111,28 +132,22 @@ impl TaskWorker {
                 self.queue_state
                     .register_active_task(&task, self.worker_id)
                     .await;
-                let result = match &task.task_type {
-                    TaskType::HtmlParse { id: doc_id } => {
-                        self.process_html_parse_task(doc_id).await
-                    }
-                    TaskType::DocDownload { id: doc_id } => {
-                        self.process_doc_download_task(doc_id, &task.id).await
-                    }
-                    TaskType::PicDownload { id: pic_id } => {
-                        self.process_pic_download_task(pic_id).await
-                    }
-                    TaskType::CbzArchive { id: doc_id } => {
-                        self.process_cbz_archive_task(doc_id).await
-                    }
-                    TaskType::ScanDir => self.process_scan_dir_task().await,
-                    TaskType::RemoveCbz { id: cbz_id } => {
-                        self.process_remove_cbz_task(cbz_id).await
-                    }
-                    TaskType::FSCbzAdded { path } => self.process_fs_cbz_added_task(path).await,
-                    TaskType::FSCbzRemoved { path } => self.process_fs_cbz_removed_task(path).await,
-                    TaskType::HtmlParseAll => self.process_html_parse_all_task(&task.id).await,
-                };
+                metrics().worker_started();
+                let started = Instant::now();
+                let span = tracing::info_span!(
+                    "task",
+                    "task.id" = %task.id,
+                    "task.type" = %task.description(),
+                    "worker.id" = self.worker_id,
+                    "otel.status_code" = tracing::field::Empty,
+                );
+                if let Some(carrier) = &task.trace_context {
+                    telemetry::set_parent(&span, carrier);
+                }
+                let result = self.run_task(&task).instrument(span.clone()).await;
+                span.record("otel.status_code", if result.is_ok() { "OK" } else { "ERROR" });
                 self.queue_state.unregister_active_task(&task.id).await;
+                metrics().worker_finished(&task.task_type, result.is_ok(), started.elapsed());
                 match result {
                     Ok(task_result) => {
                         task.mark_completed(task_result);
@@ -152,6 +167,7 @@ impl TaskWorker {
                         );
                     }
                 }
+                self.audit_task(&task).await;
                 if !self.queue_state.update_task(task.clone()).await {
                     tracing::warn!(
                         "Worker {} can not update task {} to final state",
@@ -161,7 +177,6 @@ impl TaskWorker {
                 }
                 if let Err(err) = self
                     .queue_state
-                    .sender
                     .send(QueueEvent::TaskRemoved(task.id.clone()))
                 {
                     tracing::warn!(
@@ -176,9 +191,106 @@ impl TaskWorker {
             None => Ok(Some(false)),
         }
     }
+    /// Runs a task in place, without the queue, the command line shares it with the workers.
+    pub async fn run_task(&self, task: &Task) -> Result<Option<String>> {
+        match &task.task_type {
+            TaskType::HtmlParse { id: doc_id } => {
+                self.process_html_parse_task(doc_id).await
+            }
+            TaskType::DocDownload { id: doc_id } => {
+                self.process_doc_download_task(doc_id, &task.id).await
+            }
+            TaskType::PicDownload { id: pic_id } => {
+                self.process_pic_download_task(pic_id).await
+            }
+            TaskType::CbzArchive { id: doc_id } => {
+                self.process_cbz_archive_task(doc_id).await
+            }
+            TaskType::ScanDir => self.process_scan_dir_task().await,
+            TaskType::RemoveCbz { id: cbz_id } => {
+                self.process_remove_cbz_task(cbz_id).await
+            }
+            TaskType::FSCbzAdded { path } => self.process_fs_cbz_added_task(path).await,
+            TaskType::FSCbzRemoved { path } => self.process_fs_cbz_removed_task(path).await,
+            TaskType::HtmlParseAll => self.process_html_parse_all_task(&task.id).await,
+            TaskType::MergeDuplicateDocs => self.process_merge_duplicate_docs_task().await,
+            TaskType::PollFeed { id: feed_id } => self.process_poll_feed_task(feed_id).await,
+            TaskType::PollDueFeeds => self.process_poll_due_feeds_task().await,
+            TaskType::CleanupCompleted => self.process_cleanup_completed_task().await,
+            TaskType::RefreshDoc { id: doc_id, rebuild } => {
+                self.process_refresh_doc_task(doc_id, *rebuild).await
+            }
+            TaskType::VerifyLibrary => self.process_verify_library_task(&task.id).await,
+            TaskType::CollectGarbage { dry_run, action } => {
+                self.process_collect_garbage_task(&task.id, *dry_run, *action)
+                    .await
+            }
+            TaskType::ApplyGcReport { id, action } => {
+                self.process_apply_gc_report_task(id, *action).await
+            }
+        }
+    }
+    /// Records how a finished task went. Pic downloads are left out, one is queued
+    /// for every pic and their doc download or archive is recorded.
+    async fn audit_task(&self, task: &Task) {
+        if matches!(task.task_type, TaskType::PicDownload { .. }) {
+            return;
+        }
+        let (entity, entity_id) = task.task_type.audit_entity();
+        let event = NewAuditEvent {
+            source: AuditSource::Worker,
+            actor: self.actor.clone(),
+            token_id: None,
+            request_id: None,
+            task_id: Some(task.id.clone()),
+            action: to_variant_name(&task.task_type).unwrap_or("unknown").to_string(),
+            entity: Some(entity.to_string()),
+            entity_id,
+            changes: None,
+            outcome: if task.error.is_some() {
+                AuditOutcome::Error
+            } else {
+                AuditOutcome::Ok
+            },
+            detail: task.error.clone().or_else(|| task.result.clone()),
+        };
+        if let Err(err) = service::audit::record(&self.db_pool, event).await {
+            tracing::warn!(
+                "Worker {} record audit event for task {} failed: {}",
+                self.worker_id,
+                task.id,
+                err
+            );
+        }
+    }
+    /// A change the doc status does not allow is logged and skipped, it is not an error of the task.
+    async fn set_doc_status(&self, id: i32, to: DocStatus, reason: &str) -> Result<Option<DocStatus>> {
+        Ok(service::doc::set_doc_status(
+            &self.db_pool,
+            &self.queue_state,
+            id,
+            to,
+            &self.actor,
+            Some(reason),
+        )
+        .await?)
+    }
     async fn inner_process_html_parse(&self, doc: &Doc) -> Result<Option<String>> {
-        let telegraph_post = self.http_client.parse_telegraph_post(&doc.url).await?;
-        let doc = service::doc::update_parsed_doc(&self.db_pool, doc.id, telegraph_post).await?;
+        let telegraph_post = match self.http_client.parse_telegraph_post(&doc.url).await {
+            Ok(post) => post,
+            Err(err) => {
+                self.set_doc_status(doc.id, DocStatus::Failed, &format!("parse failed: {}", err))
+                    .await?;
+                return Err(err);
+            }
+        };
+        let doc = service::doc::update_parsed_doc(
+            &self.db_pool,
+            &self.queue_state,
+            doc.id,
+            telegraph_post,
+        ).await?;
+        self.set_doc_status(doc.id, DocStatus::Parsed, "page parsed").await?;
         let cover_pic = service::pic::get_cover_pic_by_doc_id(&self.db_pool, doc.id).await?;
         let cover_task = Task::new_pic_download_task(cover_pic.id);
         self.queue_state.enqueue(cover_task).await;
@@ -186,18 +298,138 @@ impl TaskWorker {
     }
     async fn process_html_parse_task(&self, id: &i32) -> Result<Option<String>> {
         let doc = service::doc::get_doc_by_id(&self.db_pool, *id).await?;
-        if doc.status == 1 && doc.page_title.is_some() {
+        if doc.status == DocStatus::Parsed && doc.page_title.is_some() {
             return Ok(doc.page_title);
         }
         self.inner_process_html_parse(&doc).await
     }
+    async fn process_refresh_doc_task(&self, id: &i32, rebuild: bool) -> Result<Option<String>> {
+        let doc = service::doc::get_doc_by_id(&self.db_pool, *id).await?;
+        if doc.page_title.is_none() {
+            // never parsed, nothing to compare with
+            return self.inner_process_html_parse(&doc).await;
+        }
+        let fetched = self
+            .http_client
+            .fetch_telegraph_post(&doc.url, doc.etag.as_deref(), doc.last_modified.as_deref())
+            .await?;
+        let (post, etag, last_modified) = match fetched {
+            PostFetch::NotModified => {
+                service::doc::update_doc_checked(&self.db_pool, doc.id, false).await?;
+                return Ok(Some("Not modified".to_string()));
+            }
+            PostFetch::Gone(status) => {
+                service::doc::update_doc_checked(&self.db_pool, doc.id, true).await?;
+                return Ok(Some(format!("Page unavailable ({})", status)));
+            }
+            PostFetch::Fetched {
+                post,
+                etag,
+                last_modified,
+            } => (post, etag, last_modified),
+        };
+        let old_pics = service::pic::get_pics_by_doc_id(&self.db_pool, doc.id).await?;
+        let old_total = doc.page_count.map(|n| n as usize).unwrap_or(old_pics.len());
+        let new_total = post.image_urls.len();
+        let changes = service::pic::sync_doc_pics(&self.db_pool, doc.id, &post.image_urls).await?;
+        let changed = !changes.is_empty();
+        let summary = format!(
+            "{} added, {} updated, {} removed",
+            changes.added.len(),
+            changes.updated.len(),
+            changes.removed.len()
+        );
+        let doc = service::doc::update_refreshed_doc(
+            &self.db_pool,
+            &self.queue_state,
+            doc.id,
+            &post,
+            etag,
+            last_modified,
+            changed,
+        )
+        .await?;
+        if changed && matches!(doc.status, DocStatus::Downloaded | DocStatus::Archived) {
+            self.set_doc_status(doc.id, DocStatus::Stale, &format!("refresh: {}", summary))
+                .await?;
+        }
+        if changed {
+            self.remove_replaced_pic_files(&doc, &old_pics, old_total, new_total, &changes)
+                .await?;
+        }
+        let pending: Vec<Pic> = service::pic::get_pics_by_doc_id(&self.db_pool, doc.id)
+            .await?
+            .into_iter()
+            .filter(|p| p.status == 0)
+            .collect();
+        if !(rebuild && (changed || doc.cbz_stale)) {
+            for pic in &pending {
+                self.queue_state
+                    .enqueue(Task::new_pic_download_task(pic.id))
+                    .await;
+            }
+            return Ok(Some(summary));
+        }
+        let segment = url_last_segment(&doc.url);
+        for pic in &pending {
+            self.inner_process_pic_download(pic, new_total, &segment)
+                .await?;
+        }
+        if service::pic::has_status_0_pics_by_doc_id(&self.db_pool, doc.id).await? {
+            return Err(Error::Message(format!(
+                "{}, some pics failed to download, cbz not rebuilt",
+                summary
+            )));
+        }
+        self.set_doc_status(doc.id, DocStatus::Downloaded, "pics downloaded for the rebuild")
+            .await?;
+        self.process_cbz_archive_task(&doc.id).await?;
+        Ok(Some(format!("{}, cbz rebuilt", summary)))
+    }
+    /// Pic files are named by seq, a slot whose pic changed must be fetched again.
+    /// When the page count changes the width of the names everything is.
+    async fn remove_replaced_pic_files(
+        &self,
+        doc: &Doc,
+        old_pics: &[Pic],
+        old_total: usize,
+        new_total: usize,
+        changes: &PicChanges,
+    ) -> Result<()> {
+        let renamed_all =
+            format_page_filename(0, old_total, "") != format_page_filename(0, new_total, "");
+        let changed_seqs: HashSet<i32> = changes
+            .added
+            .iter()
+            .chain(changes.updated.iter())
+            .chain(changes.removed.iter())
+            .map(|p| p.seq)
+            .chain(changes.updated.iter().filter_map(|u| {
+                old_pics.iter().find(|p| p.id == u.id).map(|p| p.seq)
+            }))
+            .collect();
+        let segment = url_last_segment(&doc.url);
+        for pic in old_pics {
+            if !renamed_all && !changed_seqs.contains(&pic.seq) {
+                continue;
+            }
+            self.storage
+                .pic
+                .delete(&archive::pic_key(&segment, pic.seq as usize, old_total, &pic.url))
+                .await?;
+        }
+        if renamed_all {
+            service::pic::reset_pics_status_by_doc_id(&self.db_pool, doc.id).await?;
+        }
+        Ok(())
+    }
     async fn process_html_parse_all_task(&self, task_id: &str) -> Result<Option<String>> {
         let docs = service::doc::get_unparsed_docs(&self.db_pool).await?;
         let total = docs.len();
         let mut succeeded = 0;
         let mut progress = 0f64;
         for doc in docs {
-            if doc.status == 0
+            if matches!(doc.status, DocStatus::New | DocStatus::Failed)
                 && let Err(_) = self.inner_process_html_parse(&doc).await
             {
                 tracing::warn!(
@@ -225,15 +457,15 @@ impl TaskWorker {
         let pic = service::pic::get_pic_by_id(&self.db_pool, *id).await?;
         let doc = service::doc::get_doc_by_id(&self.db_pool, pic.doc_id).await?;
         let total: usize = doc.page_count.map(|n| n as usize).unwrap_or(1);
-        let last_path_segment = url_last_segment(&doc.url);
-        let save_dir = PathBuf::from(&self.pic_dir).join(last_path_segment);
-        ensure_dir_exists(&save_dir).await?;
+        let segment = url_last_segment(&doc.url);
         if self
-            .inner_process_pic_download(&pic, total, &save_dir)
+            .inner_process_pic_download(&pic, total, &segment)
             .await
             .is_ok()
             && !service::pic::has_status_0_pics_by_doc_id(&self.db_pool, doc.id).await? {
-                let _ = service::doc::update_doc_status(&self.db_pool, doc.id, 1).await;
+                let _ = self
+                    .set_doc_status(doc.id, DocStatus::Downloaded, "last pic downloaded")
+                    .await;
             }
         Ok(None)
     }
@@ -241,17 +473,14 @@ impl TaskWorker {
         &self,
         pic: &Pic,
         total: usize,
-        save_dir: &Path,
+        segment: &str,
     ) -> Result<Option<String>> {
         if pic.status != 0 {
             return Ok(None);
         }
         let pic_url = pic.url.clone();
-        let seq = pic.seq;
-        let ext = pic_url.split('.').next_back().unwrap_or("jpg");
-        let filename = format_page_filename(seq as usize, total, ext);
-        let filepath = save_dir.join(filename);
-        if Path::new(&filepath).exists() {
+        let key = archive::pic_key(segment, pic.seq as usize, total, &pic_url);
+        if self.storage.pic.head(&key).await?.is_some() {
             tracing::info!(
                 "Worker {} pic {} already exists, skip download",
                 self.worker_id,
@@ -260,7 +489,11 @@ impl TaskWorker {
             service::pic::update_pic_status_by_id(&self.db_pool, pic.id, 1).await?;
             return Ok(Some(format!("Pic {} already exists", pic.id)));
         }
-        if let Err(err) = self.http_client.download_file(&pic_url, &filepath).await {
+        if let Err(err) = self
+            .http_client
+            .download_file(&pic_url, self.storage.pic.as_ref(), &key)
+            .await
+        {
             tracing::warn!(
                 "Worker {} download pic {} failed: {}",
                 self.worker_id,
@@ -275,15 +508,23 @@ impl TaskWorker {
     }
     async fn process_doc_download_task(&self, id: &i32, task_id: &str) -> Result<Option<String>> {
         let doc = service::doc::get_doc_by_id(&self.db_pool, *id).await?;
-        let last_path_segment = url_last_segment(&doc.url);
-        let save_dir = PathBuf::from(&self.pic_dir).join(last_path_segment);
-        ensure_dir_exists(&save_dir).await?;
+        let segment = url_last_segment(&doc.url);
         let pics = service::pic::get_pics_by_doc_id(&self.db_pool, *id).await?;
         let total = pics.len();
         let mut succeeded = 0;
         let mut progress = 0f64;
         for pic in pics.iter() {
-            if let Ok(Some(_)) = self.inner_process_pic_download(pic, total, &save_dir).await {
+            // pics already saved are skipped when the requeued task runs
+            if self.queue_state.downloads_paused().await {
+                self.queue_state
+                    .enqueue(Task::new_doc_download_task(*id))
+                    .await;
+                return Ok(Some(format!(
+                    "Downloads paused for disk space at {}/{}, requeued",
+                    succeeded, total
+                )));
+            }
+            if let Ok(Some(_)) = self.inner_process_pic_download(pic, total, &segment).await {
                 succeeded += 1;
                 let new_progress = succeeded as f64 / total as f64;
                 if new_progress - progress > 1.0 {
@@ -294,8 +535,17 @@ impl TaskWorker {
                 }
             }
         }
-        if succeeded == total {
-            service::doc::update_doc_status(&self.db_pool, *id, 2).await?;
+        // pics downloaded by an earlier run are not counted in `succeeded`
+        if service::pic::has_status_0_pics_by_doc_id(&self.db_pool, *id).await? {
+            self.set_doc_status(
+                *id,
+                DocStatus::Failed,
+                &format!("{}/{} pics downloaded", succeeded, total),
+            )
+            .await?;
+        } else {
+            self.set_doc_status(*id, DocStatus::Downloaded, "pics downloaded")
+                .await?;
             progress = 1.0;
             self.queue_state
                 .update_task_progress(task_id, progress)
@@ -303,7 +553,7 @@ impl TaskWorker {
         }
         Ok(Some(format!(
             "{},{}/{}",
-            save_dir.to_str().unwrap(),
+            join_key(&self.storage.pic.to_string(), &segment),
             succeeded,
             total
         )))
@@ -312,128 +562,430 @@ impl TaskWorker {
         let mut doc = service::doc::get_doc_by_id(&self.db_pool, *id).await?;
         let pics = service::pic::get_pics_by_doc_id(&self.db_pool, *id).await?;
         doc.page_count = Some(pics.len() as i16);
-        let doc_xml: ComicInfo = ComicInfo::from(doc.clone());
-        let mut xml = String::new();
-        quick_xml::se::to_writer(&mut xml, &doc_xml).expect("Failed to serialize ComicInfo Xml");
-        let xml_with_decl = format!(r#"<?xml version="1.0" encoding="utf-8"?>{}"#, xml);
         let last_path_segment = url_last_segment(&doc.url);
-        ensure_dir_exists(&self.cbz_dir).await?;
-        let pic_dir = PathBuf::from(&self.pic_dir).join(&last_path_segment);
-        let files_result = get_files_in_dir(&pic_dir);
-        if let Err(err) = files_result {
-            tracing::warn!(
-                "Worker {} get files in dir {} failed: {}",
-                self.worker_id,
-                pic_dir.display(),
-                err
-            );
-            return Ok(None);
-        }
-        let cbz_filename = match (doc.writer, doc.title, doc.page_title) {
-            (Some(writer), Some(title), _) => format!("[{}]{}", writer, title),
-            (_, None, Some(page_title)) => page_title.to_string(),
-            _ => last_path_segment.to_string(),
-        };
-        let cbz_full_filename = format!("{}.cbz", cbz_filename);
-        let zip_file_path = PathBuf::from(&self.cbz_dir).join(cbz_full_filename.clone());
-        let zip_file = std::fs::File::create(&zip_file_path)?;
-        let mut zip_writer = zip::ZipWriter::new(zip_file);
-        let r = zip_writer.start_file("ComicInfo.xml", SimpleFileOptions::default());
-        if let Err(err) = r {
-            tracing::warn!(
-                "Worker {} start file {} in zip failed: {}",
-                self.worker_id,
-                "ComicInfo.xml",
-                err
-            );
-            return Ok(None);
-        }
-        let r = zip_writer.write_all(xml_with_decl.as_bytes());
-        if let Err(err) = r {
-            tracing::warn!(
-                "Worker {} write file {} in zip failed: {}",
-                self.worker_id,
-                "ComicInfo.xml",
-                err
-            );
-            return Ok(None);
-        }
-
-        let files = files_result?;
-        let simple_options = SimpleFileOptions::default();
-        for file in files {
-            let filename = file.file_name().unwrap().to_string_lossy().to_string();
-            let r = zip_writer.start_file(&filename, simple_options);
-            if let Err(err) = r {
+        let files = match self.storage.pic.list(&last_path_segment).await {
+            Ok(files) if !files.is_empty() => files,
+            Ok(_) => {
                 tracing::warn!(
-                    "Worker {} add file {} to zip failed: {}",
+                    "Worker {} no pics found in {}",
                     self.worker_id,
-                    filename,
-                    err
+                    join_key(&self.storage.pic.to_string(), &last_path_segment)
                 );
                 return Ok(None);
             }
-            let img = std::fs::read(file)?;
-            let r = zip_writer.write_all(&img);
-            if let Err(err) = r {
+            Err(err) => {
                 tracing::warn!(
-                    "Worker {} write file {} in zip failed: {}",
+                    "Worker {} list pics in {} failed: {}",
                     self.worker_id,
-                    filename,
+                    join_key(&self.storage.pic.to_string(), &last_path_segment),
                     err
                 );
                 return Ok(None);
             }
-        }
-        let r = zip_writer.finish();
-        if let Err(err) = r {
-            tracing::warn!("Worker {} finish zip file failed: {}", self.worker_id, err);
+        };
+        let cbz_full_filename = archive::cbz_filename(
+            doc.writer.as_deref(),
+            doc.title.as_deref(),
+            doc.page_title.as_deref(),
+            &last_path_segment,
+        );
+        let comic_info = ComicInfo::from(doc);
+        let Some(zip_file_path) =
+            archive::build_cbz(&comic_info, self.storage.pic.as_ref(), &files).await?
+        else {
             return Ok(None);
+        };
+        if let Err(err) = self
+            .storage
+            .cbz
+            .put_file(&cbz_full_filename, &zip_file_path)
+            .await
+        {
+            let _ = tokio::fs::remove_file(&zip_file_path).await;
+            return Err(err);
+        }
+        self.set_doc_status(*id, DocStatus::Archived, "cbz built").await?;
+        service::doc::update_doc_cbz_stale(&self.db_pool, *id, false).await?;
+        let cbz_path = cbz_full_filename.clone();
+        let cbz_option = service::cbz::get_cbz_by_path(&self.db_pool, cbz_path.clone()).await?;
+        if let Some(cbz) = cbz_option {
+            let cbz =
+                service::cbz::update_cbz(&self.db_pool, &self.queue_state, cbz.id, Some(*id))
+                    .await?;
+            // a rebuilt cbz is announced again, its file is new
+            service::cbz::publish_cbz_created(&self.queue_state, &cbz);
         } else {
-            service::doc::update_doc_status(&self.db_pool, *id, 3).await?;
-            let cbz_path = cbz_full_filename.clone();
-            let cbz_option = service::cbz::get_cbz_by_path(&self.db_pool, cbz_path.clone()).await?;
-            if let Some(cbz) = cbz_option {
-                service::cbz::update_cbz(&self.db_pool, cbz.id, Some(*id)).await?;
-            } else {
-                service::cbz::create_cbz_with_doc_id(&self.db_pool, *id, cbz_path).await?;
-            }
+            service::cbz::create_cbz_with_doc_id(&self.db_pool, &self.queue_state, *id, cbz_path)
+                .await?;
         }
         Ok(None)
     }
-    async fn process_scan_dir_task(&self) -> Result<Option<String>> {
-        let dir = Path::new(&self.cbz_dir);
-        let mut files = HashSet::new();
-        scan_dir_recursive(dir, &mut files).await;
+    async fn process_merge_duplicate_docs_task(&self) -> Result<Option<String>> {
+        let merged = service::doc::merge_duplicate_docs(&self.db_pool, &self.queue_state).await?;
+        for doc in &merged {
+            // pics are stored under the last url segment, follow the new spelling
+            let old_segment = url_last_segment(&doc.old_url);
+            let new_segment = url_last_segment(&doc.url);
+            if old_segment.is_empty() || old_segment == new_segment {
+                continue;
+            }
+            let has_old = !self.storage.pic.list(&old_segment).await?.is_empty();
+            let has_new = !self.storage.pic.list(&new_segment).await?.is_empty();
+            if has_old
+                && !has_new
+                && let Err(err) = self
+                    .storage
+                    .pic
+                    .rename_prefix(&old_segment, &new_segment)
+                    .await
+            {
+                tracing::warn!(
+                    "Worker {} move {} to {} failed: {}",
+                    self.worker_id,
+                    old_segment,
+                    new_segment,
+                    err
+                );
+            }
+        }
+        let removed: usize = merged.iter().map(|d| d.merged_ids.len()).sum();
+        Ok(Some(format!(
+            "{} docs canonicalised, {} duplicates merged",
+            merged.len(),
+            removed
+        )))
+    }
+    async fn process_poll_feed_task(&self, id: &i32) -> Result<Option<String>> {
+        let feed = service::feed::get_feed_by_id(&self.db_pool, *id).await?;
+        match self.inner_process_poll_feed(&feed).await {
+            Ok(result) => Ok(result),
+            Err(err) => {
+                service::feed::record_poll_error(&self.db_pool, feed.id, err.to_string()).await?;
+                Err(err)
+            }
+        }
+    }
+    async fn inner_process_poll_feed(&self, feed: &Feed) -> Result<Option<String>> {
+        let filter = FeedFilter::new(feed.include_pattern.as_deref(), feed.exclude_pattern.as_deref())
+            .map_err(|e| Error::Message(format!("Invalid pattern: {}", e)))?;
+        let response = self
+            .http_client
+            .fetch_feed(&feed.url, feed.etag.as_deref(), feed.last_modified.as_deref())
+            .await?;
+        let Some(response) = response else {
+            service::feed::record_poll_success(
+                &self.db_pool,
+                feed.id,
+                None,
+                feed.etag.clone(),
+                feed.last_modified.clone(),
+            )
+            .await?;
+            return Ok(Some("Not modified".to_string()));
+        };
+        let parsed = parse_feed(&response.body, &filter).map_err(Error::Message)?;
+        let mut created = 0;
+        for url in &parsed.links {
+            // seen in an earlier poll, even if the doc was deleted since
+            let Some(item) = service::feed::create_feed_item(&self.db_pool, feed.id, url).await?
+            else {
+                continue;
+            };
+            let doc = match service::doc::create_doc_if_absent(&self.db_pool, &self.queue_state, url).await? {
+                Some(doc) => {
+                    created += 1;
+                    if feed.auto_grab {
+                        self.queue_state
+                            .enqueue(Task::new_html_parse_task(doc.id))
+                            .await;
+                    }
+                    Some(doc)
+                }
+                None => service::doc::get_doc_by_url(&self.db_pool, url).await?,
+            };
+            if let Some(doc) = doc {
+                service::feed::update_feed_item_doc(&self.db_pool, item.id, doc.id).await?;
+            }
+        }
+        service::feed::record_poll_success(
+            &self.db_pool,
+            feed.id,
+            parsed.title,
+            response.etag,
+            response.last_modified,
+        )
+        .await?;
+        Ok(Some(format!(
+            "{} links, {} new docs",
+            parsed.links.len(),
+            created
+        )))
+    }
+    /// queues a poll for every feed whose interval has passed
+    async fn process_poll_due_feeds_task(&self) -> Result<Option<String>> {
+        let feeds = service::feed::get_due_feeds(&self.db_pool).await?;
+        let mut queued = 0;
+        for feed in feeds {
+            if !self.queue_state.is_feed_poll_pending(feed.id).await {
+                self.queue_state
+                    .enqueue(Task::new_poll_feed_task(feed.id))
+                    .await;
+                queued += 1;
+            }
+        }
+        Ok(Some(format!("{} feed polls queued", queued)))
+    }
+    async fn process_cleanup_completed_task(&self) -> Result<Option<String>> {
+        let removed_count = self
+            .queue_state
+            .cleanup_completed_tasks(self.max_completed_tasks)
+            .await;
+        if removed_count > 0 {
+            tracing::info!("{} tasks cleaned", removed_count);
+        }
+        Ok(Some(format!("{} tasks cleaned", removed_count)))
+    }
+    async fn process_verify_library_task(&self, task_id: &str) -> Result<Option<String>> {
+        let files: Vec<StorageObject> = self
+            .storage
+            .cbz
+            .list("")
+            .await?
+            .into_iter()
+            .filter(|o| o.key.ends_with(".cbz"))
+            .collect();
+        let cbzs = service::cbz::get_cbzs(&self.db_pool).await?;
+        let pics = service::pic::get_downloaded_pics(&self.db_pool).await?;
+        let mut doc_ids: Vec<i32> = cbzs
+            .iter()
+            .filter_map(|c| c.doc_id)
+            .chain(pics.iter().map(|p| p.doc_id))
+            .collect();
+        doc_ids.sort_unstable();
+        doc_ids.dedup();
+        let docs: HashMap<i32, Doc> = service::doc::get_docs_by_ids(&self.db_pool, &doc_ids)
+            .await?
+            .into_iter()
+            .map(|d| (d.id, d))
+            .collect();
+        let total = files.len() + pics.len();
+        let mut done = 0;
+        let mut progress = 0f64;
+        let mut issues = vec![];
+        let mut on_disk = HashSet::new();
         for file in files {
-            let filename = file.file_name().unwrap().to_string_lossy().to_string();
+            let filename = file.key.rsplit('/').next().unwrap_or(&file.key).to_string();
+            let cbz = cbzs.iter().find(|c| c.path == filename);
+            let doc = cbz.and_then(|c| c.doc_id).and_then(|id| docs.get(&id));
+            let rearchive = doc.map(|d| RepairAction {
+                kind: RepairKind::Rearchive,
+                id: Some(d.id),
+            });
+            let check = library::check_stored_cbz(self.storage.cbz.as_ref(), &file.key).await?;
+            if cbz.is_none() {
+                issues.push(LibraryIssue {
+                    kind: IssueKind::UntrackedCbz,
+                    cbz_id: None,
+                    doc_id: None,
+                    pic_id: None,
+                    path: Some(filename.clone()),
+                    detail: "No cbz row for this file".to_string(),
+                    repair: Some(RepairAction {
+                        kind: RepairKind::Rescan,
+                        id: None,
+                    }),
+                });
+            }
+            for (kind, entry, detail) in check.problems {
+                issues.push(LibraryIssue {
+                    kind,
+                    cbz_id: cbz.map(|c| c.id),
+                    doc_id: doc.map(|d| d.id),
+                    pic_id: None,
+                    path: Some(match entry {
+                        Some(entry) => format!("{}/{}", filename, entry),
+                        None => filename.clone(),
+                    }),
+                    detail,
+                    repair: rearchive,
+                });
+            }
+            if let (Some(doc), Some(image_count)) = (doc, check.image_count)
+                && let Some(page_count) = doc.page_count
+                && page_count as usize != image_count
+            {
+                issues.push(LibraryIssue {
+                    kind: IssueKind::PageCountMismatch,
+                    cbz_id: cbz.map(|c| c.id),
+                    doc_id: Some(doc.id),
+                    pic_id: None,
+                    path: Some(filename.clone()),
+                    detail: format!("{} images, doc has {} pages", image_count, page_count),
+                    repair: rearchive,
+                });
+            }
+            on_disk.insert(filename);
+            done += 1;
+            progress = self.report_progress(task_id, done, total, progress).await;
+        }
+        for cbz in cbzs.iter().filter(|c| !on_disk.contains(&c.path)) {
+            let repair = match cbz.doc_id.filter(|id| docs.contains_key(id)) {
+                Some(doc_id) => RepairAction {
+                    kind: RepairKind::Rearchive,
+                    id: Some(doc_id),
+                },
+                None => RepairAction {
+                    kind: RepairKind::RemoveCbz,
+                    id: Some(cbz.id),
+                },
+            };
+            issues.push(LibraryIssue {
+                kind: IssueKind::MissingCbzFile,
+                cbz_id: Some(cbz.id),
+                doc_id: cbz.doc_id,
+                pic_id: None,
+                path: Some(cbz.path.clone()),
+                detail: format!("File not found in {}", self.storage.cbz),
+                repair: Some(repair),
+            });
+        }
+        for pic in &pics {
+            if let Some(doc) = docs.get(&pic.doc_id) {
+                let total_pages = doc.page_count.map(|n| n as usize).unwrap_or(1);
+                let key = archive::pic_key(
+                    &url_last_segment(&doc.url),
+                    pic.seq as usize,
+                    total_pages,
+                    &pic.url,
+                );
+                if self.storage.pic.head(&key).await?.is_none() {
+                    issues.push(LibraryIssue {
+                        kind: IssueKind::MissingPicFile,
+                        cbz_id: None,
+                        doc_id: Some(doc.id),
+                        pic_id: Some(pic.id),
+                        path: Some(join_key(&self.storage.pic.to_string(), &key)),
+                        detail: format!("Downloaded pic not found in {}", self.storage.pic),
+                        repair: Some(RepairAction {
+                            kind: RepairKind::RedownloadPic,
+                            id: Some(pic.id),
+                        }),
+                    });
+                }
+            }
+            done += 1;
+            progress = self.report_progress(task_id, done, total, progress).await;
+        }
+        let issue_count = issues.len();
+        let report = service::library::create_report(
+            &self.db_pool,
+            task_id,
+            on_disk.len() as i32,
+            pics.len() as i32,
+            issues,
+        )
+        .await?;
+        self.queue_state.update_task_progress(task_id, 1.0).await;
+        Ok(Some(format!(
+            "Report {}: {} issues",
+            report.id, issue_count
+        )))
+    }
+    async fn process_collect_garbage_task(
+        &self,
+        task_id: &str,
+        dry_run: bool,
+        action: GcAction,
+    ) -> Result<Option<String>> {
+        let items = gc::find_orphans(&self.db_pool, &self.storage).await?;
+        let report = service::gc::create_report(&self.db_pool, task_id, items).await?;
+        let summary = format!(
+            "Report {}: {} items, {} bytes reclaimable",
+            report.id,
+            report.items.len(),
+            report.reclaimable_bytes
+        );
+        if dry_run || report.items.is_empty() {
+            return Ok(Some(summary));
+        }
+        let applied = self.process_apply_gc_report_task(&report.id, action).await?;
+        Ok(Some(format!("{}; {}", summary, applied.unwrap_or_default())))
+    }
+    async fn process_apply_gc_report_task(
+        &self,
+        id: &i32,
+        action: GcAction,
+    ) -> Result<Option<String>> {
+        let report = service::gc::get_report_by_id(&self.db_pool, *id).await?;
+        if report.applied_at.is_some() {
+            return Err(Error::Message(format!("Gc report {} was already applied", id)));
+        }
+        let bytes = gc::apply(
+            &self.db_pool,
+            &self.queue_state,
+            report.id,
+            &report.items,
+            action,
+            &self.storage,
+        )
+        .await?;
+        service::gc::mark_applied(&self.db_pool, report.id, action, bytes).await?;
+        Ok(Some(match action {
+            GcAction::Delete => format!("{} bytes deleted", bytes),
+            GcAction::Quarantine => format!("{} bytes quarantined", bytes),
+        }))
+    }
+    /// sends progress in steps of at least 1%, returns the last value sent
+    async fn report_progress(&self, task_id: &str, done: usize, total: usize, sent: f64) -> f64 {
+        let progress = done as f64 / total.max(1) as f64;
+        if progress - sent >= 0.01 {
+            self.queue_state
+                .update_task_progress(task_id, progress)
+                .await;
+            progress
+        } else {
+            sent
+        }
+    }
+    async fn process_scan_dir_task(&self) -> Result<Option<String>> {
+        let files: HashSet<String> = self
+            .storage
+            .cbz
+            .list("")
+            .await?
+            .into_iter()
+            .filter(|o| o.key.ends_with(".cbz"))
+            .map(|o| o.key.rsplit('/').next().unwrap_or(&o.key).to_string())
+            .collect();
+        let mut added = 0;
+        for filename in files {
             let cbz_in_db = service::cbz::get_cbz_by_path(&self.db_pool, filename.clone()).await?;
             if cbz_in_db.is_none() {
-                service::cbz::create_cbz(&self.db_pool, filename).await?;
+                service::cbz::create_cbz(&self.db_pool, &self.queue_state, filename).await?;
+                added += 1;
             }
         }
-        Ok(None)
+        Ok(Some(format!("{} new cbz", added)))
     }
     async fn process_remove_cbz_task(&self, cbz_id: &i32) -> Result<Option<String>> {
         let cbz = service::cbz::get_cbz_by_id(&self.db_pool, *cbz_id).await?;
-        let cbz_path = PathBuf::from(&self.cbz_dir).join(cbz.path);
-        if let Err(err) = std::fs::remove_file(cbz_path) {
+        if let Err(err) = self.storage.cbz.delete(&cbz.path).await {
             tracing::warn!("Remove cbz {} failed: {}", cbz_id, err);
         }
-        service::cbz::remove_cbz_by_id(&self.db_pool, *cbz_id).await?;
+        service::cbz::remove_cbz_by_id(&self.db_pool, &self.queue_state, *cbz_id).await?;
         Ok(None)
     }
     async fn process_fs_cbz_added_task(&self, path: &str) -> Result<Option<String>> {
         let cbz_in_db = service::cbz::get_cbz_by_path(&self.db_pool, path.to_string()).await?;
         if cbz_in_db.is_none() {
-            service::cbz::create_cbz(&self.db_pool, path.to_string()).await?;
+            service::cbz::create_cbz(&self.db_pool, &self.queue_state, path.to_string()).await?;
         }
         Ok(None)
     }
     async fn process_fs_cbz_removed_task(&self, path: &str) -> Result<Option<String>> {
         let cbz_in_db = service::cbz::get_cbz_by_path(&self.db_pool, path.to_string()).await?;
         if let Some(cbz) = cbz_in_db {
-            service::cbz::remove_cbz_by_id(&self.db_pool, cbz.id).await?;
+            service::cbz::remove_cbz_by_id(&self.db_pool, &self.queue_state, cbz.id).await?;
         }
         Ok(None)
     }
@@ -472,6 +1024,7 @@ impl TaskWorker {
 pub async fn start_background_workers(state: AppState, configuration: Settings) {
     let worker_count = configuration.worker.count;
     tracing::info!("Start {} worker(s)", worker_count);
+    metrics().set_workers(worker_count);
     for worker_id in 0..worker_count {
         let worker = TaskWorker::new(&state, configuration.clone(), worker_id);
         tokio::spawn(async move {
@@ -479,44 +1032,19 @@ pub async fn start_background_workers(state: AppState, configuration: Settings)
         });
     }
 }
-pub async fn start_auto_cleanup_task(state: AppState, configuration: Settings) {
-    let cleanup_interval = configuration.worker.auto_cleanup_interval_secs;
-    let max_completed_tasks = configuration.worker.max_completed_tasks;
-    tokio::spawn(async move {
-        let mut shutdown_rx = state.shutdown.get_shutdown_rx().await;
-        tracing::info!(
-            "Start Auto Cleanup Task, cleanup in every {}s, remain {} tasks at most",
-            cleanup_interval,
-            max_completed_tasks
-        );
-        loop {
-            tokio::select! {
-                _ = shutdown_rx.recv() => {
-                    tracing::info!("Auto Cleanup Task received shutdown signal, stop.");
-                    break;
-                }
-                _ = tokio::time::sleep(Duration::from_secs(cleanup_interval)) => {
-                    let removed_count = state.queue_state.cleanup_completed_tasks(max_completed_tasks).await;
-                    if removed_count > 0{
-                        tracing::info!("{} tasks cleaned", removed_count);
-                        let tasks = state.queue_state.get_tasks().await;
-                        let remaining_completed = tasks.iter().filter(|t| matches!(t.status, TaskStatus::Completed)).count();
-                        tracing::info!("{} tasks remaining, {} tasks total.",  remaining_completed,tasks.len());
-                    }
-                }
-            }
-        }
-    });
-}
-pub async fn setup_fs_monitor(state: AppState, configuration: Settings) {
-    let cbz_dir = configuration.cbz_dir;
+pub async fn setup_fs_monitor(state: AppState) {
+    state.queue_state.enqueue(Task::new_scan_dir_task()).await;
+    // remote storages have nothing to watch, they are picked up by scans
+    let Some(cbz_dir) = state.storage.cbz.local_path("") else {
+        tracing::info!("FS monitor disabled for {}", state.storage.cbz);
+        return;
+    };
     let result = ensure_dir_exists(&cbz_dir).await;
     if let Err(err) = result {
         tracing::error!("Failed to ensure cbz dir exists: {:?}", err);
         return;
     }
-    state.queue_state.enqueue(Task::new_scan_dir_task()).await;
-    let watch_path = Path::new(&cbz_dir);
+    let watch_path = cbz_dir.as_path();
     let mut watcher = notify::recommended_watcher(move |evt: Result<Event, notify::Error>| {
         if let Ok(event) = evt {
             match event.kind {
@@ -565,59 +1093,12 @@ async fn ensure_dir_exists<P: AsRef<Path>>(p: P) -> Result<()> {
     Ok(())
 }
 
-fn format_page_filename(page_idx: usize, total_pages: usize, ext: &str) -> String {
+pub(crate) fn format_page_filename(page_idx: usize, total_pages: usize, ext: &str) -> String {
     let num_digits = ((total_pages as f64).log10().floor() as usize + 1).max(3);
     format!("{:0width$}.{}", page_idx, ext, width = num_digits)
 }
 
-fn get_files_in_dir<P: AsRef<Path>>(dir_path: P) -> Result<Vec<PathBuf>, std::io::Error> {
-    let dir = dir_path.as_ref();
-
-    if !dir.is_dir() {
-        return Err(std::io::Error::new(
-            std::io::ErrorKind::NotFound,
-            format!("dir not exists: {}", dir.display()),
-        ));
-    }
-
-    let mut files = Vec::new();
-    for entry in std::fs::read_dir(dir)? {
-        let entry = entry?;
-        let path = entry.path();
-
-        // 只保留文件（排除目录）
-        if path.is_file() {
-            files.push(path);
-        }
-    }
-
-    Ok(files)
-}
-
-async fn scan_dir_recursive(dir_path: &Path, files: &mut HashSet<PathBuf>) {
-    if !dir_path.is_dir() {
-        tracing::error!("dir not exists: {:?}", dir_path);
-    }
-    let entries = std::fs::read_dir(dir_path).expect("Failed to read dir");
-
-    for entry in entries {
-        if let Err(err) = entry {
-            tracing::error!("Failed to read entry: {:?}", err);
-            continue;
-        }
-        let entry = entry.expect("Failed to read entry");
-        let path = entry.path();
-
-        // 递归处理子目录
-        if path.is_dir() {
-            let fut = Box::pin(scan_dir_recursive(&path, files));
-            fut.await;
-        } else if path.is_file() && path.extension() == Some("cbz".as_ref()) {
-            files.insert(path);
-        }
-    }
-}
-fn url_last_segment(url: &str) -> String {
+pub(crate) fn url_last_segment(url: &str) -> String {
     let parsed_url = url::Url::parse(url).expect("Invalid url");
     let last_path_segment = parsed_url.path_segments().unwrap().next_back().unwrap();
     url::form_urlencoded::parse(last_path_segment.as_bytes())
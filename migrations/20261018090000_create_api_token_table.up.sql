@@ -0,0 +1,13 @@
+-- Add migration script here
+create table api_token
+(
+    id           serial primary key,
+    name         text        not null,
+    token_prefix text        not null,
+    token_hash   text        not null unique, -- sha256 hex of the plain token
+    scopes       text[]      not null default '{read}', -- read, write, admin
+    last_used_at timestamptz,
+    revoked_at   timestamptz,
+    created_at   timestamptz not null default now(),
+    updated_at   timestamptz not null default now()
+);
@@ -0,0 +1,3 @@
+-- Add migration script here
+drop table feed_item;
+drop table feed;
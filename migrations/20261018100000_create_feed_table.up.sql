@@ -0,0 +1,28 @@
+-- Add migration script here
+create table feed
+(
+    id                 serial primary key,
+    url                text        not null unique,
+    title              text,
+    enabled            bool        not null default true,
+    auto_grab          bool        not null default false, -- queue parsing of new docs
+    include_pattern    text,                               -- regex on entry title and link
+    exclude_pattern    text,
+    poll_interval_secs int         not null default 3600,
+    etag               text,
+    last_modified      text,
+    last_polled_at     timestamptz,
+    last_error         text,
+    created_at         timestamptz not null default now(),
+    updated_at         timestamptz not null default now()
+);
+
+create table feed_item
+(
+    id         serial primary key,
+    feed_id    int         not null references feed (id) on delete cascade,
+    url        text        not null, -- canonical doc url
+    doc_id     int references doc (id) on delete set null,
+    created_at timestamptz not null default now(),
+    unique (feed_id, url)
+);
@@ -0,0 +1,24 @@
+-- Add migration script here
+create table schedule
+(
+    id           serial primary key,
+    name         text        not null unique,
+    cron         text        not null,           -- utc, with or without a leading seconds field
+    task_type    jsonb       not null,           -- serialized TaskType, e.g. "scanDir"
+    enabled      bool        not null default true,
+    last_run_at  timestamptz,
+    next_run_at  timestamptz,
+    last_task_id text,
+    created_at   timestamptz not null default now(),
+    updated_at   timestamptz not null default now()
+);
+
+create table schedule_run
+(
+    id           serial primary key,
+    schedule_id  int         not null references schedule (id) on delete cascade,
+    task_id      text,                           -- null when skipped
+    skipped      bool        not null default false, -- the same task was still queued or running
+    scheduled_at timestamptz not null,
+    created_at   timestamptz not null default now()
+);
@@ -0,0 +1,9 @@
+-- Add migration script here
+alter table doc
+    drop column etag,
+    drop column last_modified,
+    drop column checked_at,
+    drop column unavailable,
+    drop column cbz_stale;
+
+comment on column pic.status is null;
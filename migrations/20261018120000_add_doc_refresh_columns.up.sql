@@ -0,0 +1,9 @@
+-- Add migration script here
+alter table doc
+    add column etag            text,
+    add column last_modified   text,
+    add column checked_at      timestamptz,                 -- last refresh against the page
+    add column unavailable     bool not null default false, -- the page answered 404/410
+    add column cbz_stale       bool not null default false; -- pics changed since the cbz was built
+
+comment on column pic.status is '0: new, 1: downloaded, 2: error, 3: removed from the page';
@@ -0,0 +1,10 @@
+-- Add migration script here
+create table library_report
+(
+    id           serial primary key,
+    task_id      text        not null,
+    cbz_checked  int         not null,
+    pics_checked int         not null,
+    issues       jsonb       not null default '[]', -- LibraryIssue list
+    created_at   timestamptz not null default now()
+);
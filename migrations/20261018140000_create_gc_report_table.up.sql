@@ -0,0 +1,12 @@
+-- Add migration script here
+create table gc_report
+(
+    id                serial primary key,
+    task_id           text        not null,
+    items             jsonb       not null default '[]', -- GcItem list
+    reclaimable_bytes bigint      not null,
+    action            text,                             -- delete or quarantine, null while it is a dry run
+    applied_bytes     bigint,
+    applied_at        timestamptz,
+    created_at        timestamptz not null default now()
+);
@@ -0,0 +1,4 @@
+-- Add migration script here
+drop table doc_status_history;
+
+comment on column doc.status is null;
@@ -0,0 +1,13 @@
+-- Add migration script here
+create table doc_status_history
+(
+    id          serial primary key,
+    doc_id      int         not null references doc (id) on delete cascade,
+    from_status smallint    not null,
+    to_status   smallint    not null,
+    changed_by  text        not null, -- worker 3, cli, ...
+    reason      text,
+    created_at  timestamptz not null default now()
+);
+
+comment on column doc.status is '0: new, 1: parsed, 2: pics downloaded, 3: cbz linked, 4: failed, 5: stale';
@@ -0,0 +1,3 @@
+-- Add migration script here
+drop table audit_event;
+drop function audit_event_append_only();
@@ -0,0 +1,31 @@
+-- Add migration script here
+create table audit_event
+(
+    id         serial primary key,
+    source     text        not null, -- rest, graphql or worker
+    actor      text        not null, -- token name, anonymous without auth, worker 3
+    token_id   int,                  -- no reference, the log outlives the token
+    request_id text,
+    task_id    text,
+    action     text        not null, -- PATCH /api/doc/{id}, updateAlbum, CbzArchive
+    entity     text,
+    entity_id  text,
+    changes    jsonb,                -- {"field": {"before": .., "after": ..}} of metadata edits
+    outcome    text        not null, -- ok or error
+    detail     text,                 -- status code, error or task result
+    created_at timestamptz not null default now()
+);
+
+create function audit_event_append_only() returns trigger
+    language plpgsql as
+$$
+begin
+    raise exception 'audit_event is append-only';
+end;
+$$;
+
+create trigger audit_event_append_only
+    before update or delete
+    on audit_event
+    for each row
+execute function audit_event_append_only();
@@ -0,0 +1,27 @@
+-- Add migration script here
+create table webhook
+(
+    id          serial primary key,
+    url         text        not null,
+    secret      text        not null,           -- hmac-sha256 key of the X-Telegrab-Signature-256 header
+    events      jsonb       not null default '[]', -- e.g. ["cbz.created", "task.*"], empty for every event
+    enabled     bool        not null default true,
+    description text,
+    created_at  timestamptz not null default now(),
+    updated_at  timestamptz not null default now()
+);
+
+create table webhook_delivery
+(
+    id              serial primary key,
+    webhook_id      int         not null references webhook (id) on delete cascade,
+    event           text        not null,
+    payload         jsonb       not null,
+    status          smallint    not null default 0, -- 0 pending, 1 delivered, 2 failed (gave up)
+    attempts        int         not null default 0,
+    response_status int,                          -- of the last attempt
+    error           text,                         -- of the last attempt
+    next_attempt_at timestamptz,
+    delivered_at    timestamptz,
+    created_at      timestamptz not null default now()
+);
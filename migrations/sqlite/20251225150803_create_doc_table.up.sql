@@ -0,0 +1,46 @@
+-- Add migration script here
+create table doc
+(
+    id               integer primary key autoincrement,
+    status           smallint default 0, -- 0: new, 1: parsed, 2: pics downloaded, 3: cbz linked
+    url              text     not null unique,
+    page_title       text,
+    page_date        datetime,
+    title            text,
+    series           text,
+    number           text,
+    count            text,
+    volume           text,
+    summary          text,
+    notes            text,
+    year             int,
+    month            int,
+    day              int,
+    writer           text,
+    penciller        text,
+    inker            text,
+    colorist         text,
+    letterer         text,
+    cover_artist     text,
+    editor           text,
+    publisher        text,
+    imprint          text,
+    genre            text,
+    tags             text,
+    web              text,
+    page_count       smallint,
+    language         text,
+    format           text,
+    black_and_white  bool,
+    characters       text,
+    teams            text,
+    locations        text,
+    scan_information text,
+    story_arc        text,
+    series_group     text,
+    age_rating       text,
+    community_rating text,
+    critical_rating  text,
+    created_at       datetime not null default (strftime('%Y-%m-%dT%H:%M:%fZ', 'now')),
+    updated_at       datetime not null default (strftime('%Y-%m-%dT%H:%M:%fZ', 'now'))
+);
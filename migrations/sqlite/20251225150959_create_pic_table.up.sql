@@ -0,0 +1,12 @@
+-- Add migration script here
+create table pic
+(
+    id         integer primary key autoincrement,
+    doc_id     int      not null references doc (id) on delete cascade,
+    url        text     not null,
+    seq        int      not null,
+    status     smallint default 0, -- 0: new, 1: downloaded, 2: error
+    created_at datetime not null default (strftime('%Y-%m-%dT%H:%M:%fZ', 'now')),
+    updated_at datetime not null default (strftime('%Y-%m-%dT%H:%M:%fZ', 'now')),
+    unique (doc_id, url, seq)
+);
@@ -0,0 +1,9 @@
+-- Add migration script here
+create table cbz
+(
+    id         integer primary key autoincrement,
+    doc_id     int references doc (id) on delete set null,
+    path       text     not null unique,
+    created_at datetime not null default (strftime('%Y-%m-%dT%H:%M:%fZ', 'now')),
+    updated_at datetime not null default (strftime('%Y-%m-%dT%H:%M:%fZ', 'now'))
+);
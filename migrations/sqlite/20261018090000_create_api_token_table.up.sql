@@ -0,0 +1,13 @@
+-- Add migration script here
+create table api_token
+(
+    id           integer primary key autoincrement,
+    name         text     not null,
+    token_prefix text     not null,
+    token_hash   text     not null unique,            -- sha256 hex of the plain token
+    scopes       text     not null default '["read"]', -- json array of read, write, admin
+    last_used_at datetime,
+    revoked_at   datetime,
+    created_at   datetime not null default (strftime('%Y-%m-%dT%H:%M:%fZ', 'now')),
+    updated_at   datetime not null default (strftime('%Y-%m-%dT%H:%M:%fZ', 'now'))
+);
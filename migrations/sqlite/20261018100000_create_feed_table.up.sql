@@ -0,0 +1,28 @@
+-- Add migration script here
+create table feed
+(
+    id                 integer primary key autoincrement,
+    url                text     not null unique,
+    title              text,
+    enabled            bool     not null default true,
+    auto_grab          bool     not null default false, -- queue parsing of new docs
+    include_pattern    text,                            -- regex on entry title and link
+    exclude_pattern    text,
+    poll_interval_secs int      not null default 3600,
+    etag               text,
+    last_modified      text,
+    last_polled_at     datetime,
+    last_error         text,
+    created_at         datetime not null default (strftime('%Y-%m-%dT%H:%M:%fZ', 'now')),
+    updated_at         datetime not null default (strftime('%Y-%m-%dT%H:%M:%fZ', 'now'))
+);
+
+create table feed_item
+(
+    id         integer primary key autoincrement,
+    feed_id    int      not null references feed (id) on delete cascade,
+    url        text     not null, -- canonical doc url
+    doc_id     int references doc (id) on delete set null,
+    created_at datetime not null default (strftime('%Y-%m-%dT%H:%M:%fZ', 'now')),
+    unique (feed_id, url)
+);
@@ -0,0 +1,3 @@
+-- Add migration script here
+drop table schedule_run;
+drop table schedule;
@@ -0,0 +1,24 @@
+-- Add migration script here
+create table schedule
+(
+    id           integer primary key autoincrement,
+    name         text     not null unique,
+    cron         text     not null,           -- utc, with or without a leading seconds field
+    task_type    text     not null,           -- serialized TaskType as json, e.g. "scanDir"
+    enabled      bool     not null default true,
+    last_run_at  datetime,
+    next_run_at  datetime,
+    last_task_id text,
+    created_at   datetime not null default (strftime('%Y-%m-%dT%H:%M:%fZ', 'now')),
+    updated_at   datetime not null default (strftime('%Y-%m-%dT%H:%M:%fZ', 'now'))
+);
+
+create table schedule_run
+(
+    id           integer primary key autoincrement,
+    schedule_id  int      not null references schedule (id) on delete cascade,
+    task_id      text,                           -- null when skipped
+    skipped      bool     not null default false, -- the same task was still queued or running
+    scheduled_at datetime not null,
+    created_at   datetime not null default (strftime('%Y-%m-%dT%H:%M:%fZ', 'now'))
+);
@@ -0,0 +1,6 @@
+-- Add migration script here
+alter table doc drop column etag;
+alter table doc drop column last_modified;
+alter table doc drop column checked_at;
+alter table doc drop column unavailable;
+alter table doc drop column cbz_stale;
@@ -0,0 +1,7 @@
+-- Add migration script here
+-- pic.status gains 3: removed from the page
+alter table doc add column etag text;
+alter table doc add column last_modified text;
+alter table doc add column checked_at datetime;                   -- last refresh against the page
+alter table doc add column unavailable bool not null default false; -- the page answered 404/410
+alter table doc add column cbz_stale bool not null default false;   -- pics changed since the cbz was built
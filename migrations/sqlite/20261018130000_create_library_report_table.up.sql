@@ -0,0 +1,10 @@
+-- Add migration script here
+create table library_report
+(
+    id           integer primary key autoincrement,
+    task_id      text     not null,
+    cbz_checked  int      not null,
+    pics_checked int      not null,
+    issues       text     not null default '[]', -- LibraryIssue list as json
+    created_at   datetime not null default (strftime('%Y-%m-%dT%H:%M:%fZ', 'now'))
+);
@@ -0,0 +1,12 @@
+-- Add migration script here
+create table gc_report
+(
+    id                integer primary key autoincrement,
+    task_id           text     not null,
+    items             text     not null default '[]', -- GcItem list as json
+    reclaimable_bytes bigint   not null,
+    action            text,                          -- delete or quarantine, null while it is a dry run
+    applied_bytes     bigint,
+    applied_at        datetime,
+    created_at        datetime not null default (strftime('%Y-%m-%dT%H:%M:%fZ', 'now'))
+);
@@ -0,0 +1,11 @@
+-- Add migration script here
+create table doc_status_history
+(
+    id          integer primary key autoincrement,
+    doc_id      int      not null references doc (id) on delete cascade,
+    from_status smallint not null, -- 0: new, 1: parsed, 2: pics downloaded, 3: cbz linked, 4: failed, 5: stale
+    to_status   smallint not null,
+    changed_by  text     not null, -- worker 3, cli, ...
+    reason      text,
+    created_at  datetime not null default (strftime('%Y-%m-%dT%H:%M:%fZ', 'now'))
+);
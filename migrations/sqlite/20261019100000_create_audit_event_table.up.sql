@@ -0,0 +1,31 @@
+-- Add migration script here
+create table audit_event
+(
+    id         integer primary key autoincrement,
+    source     text     not null, -- rest, graphql or worker
+    actor      text     not null, -- token name, anonymous without auth, worker 3
+    token_id   int,               -- no reference, the log outlives the token
+    request_id text,
+    task_id    text,
+    action     text     not null, -- PATCH /api/doc/{id}, updateAlbum, CbzArchive
+    entity     text,
+    entity_id  text,
+    changes    text,              -- {"field": {"before": .., "after": ..}} of metadata edits, as json
+    outcome    text     not null, -- ok or error
+    detail     text,              -- status code, error or task result
+    created_at datetime not null default (strftime('%Y-%m-%dT%H:%M:%fZ', 'now'))
+);
+
+create trigger audit_event_no_update
+    before update
+    on audit_event
+begin
+    select raise(abort, 'audit_event is append-only');
+end;
+
+create trigger audit_event_no_delete
+    before delete
+    on audit_event
+begin
+    select raise(abort, 'audit_event is append-only');
+end;
@@ -0,0 +1,3 @@
+-- Add migration script here
+drop table webhook_delivery;
+drop table webhook;
@@ -0,0 +1,27 @@
+-- Add migration script here
+create table webhook
+(
+    id          integer primary key autoincrement,
+    url         text     not null,
+    secret      text     not null,             -- hmac-sha256 key of the X-Telegrab-Signature-256 header
+    events      text     not null default '[]', -- e.g. ["cbz.created", "task.*"], empty for every event
+    enabled     bool     not null default true,
+    description text,
+    created_at  datetime not null default (strftime('%Y-%m-%dT%H:%M:%fZ', 'now')),
+    updated_at  datetime not null default (strftime('%Y-%m-%dT%H:%M:%fZ', 'now'))
+);
+
+create table webhook_delivery
+(
+    id              integer primary key autoincrement,
+    webhook_id      int      not null references webhook (id) on delete cascade,
+    event           text     not null,
+    payload         text     not null,           -- as json
+    status          smallint not null default 0, -- 0 pending, 1 delivered, 2 failed (gave up)
+    attempts        int      not null default 0,
+    response_status int,                         -- of the last attempt
+    error           text,                        -- of the last attempt
+    next_attempt_at datetime,
+    delivered_at    datetime,
+    created_at      datetime not null default (strftime('%Y-%m-%dT%H:%M:%fZ', 'now'))
+);
@@ -6,4 +6,4 @@ sudo -u postgres psql -c "CREATE USER telegrab WITH PASSWORD 'password';"
 sudo -u postgres psql -c "CREATE DATABASE telegrab OWNER telegrab;"
 
 sqlx database create
-sqlx migrate run
\ No newline at end of file
+cargo run --bin telegrab -- migrate up
\ No newline at end of file